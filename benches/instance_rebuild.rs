@@ -0,0 +1,70 @@
+// The per frame instance rebuild (position + rotation -> model matrix) at
+// 100k instances, once with cgmath like praxis used to and once with glam.
+// `cargo bench --bench instance_rebuild`
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const INSTANCES: usize = 100_000;
+const RUNS: u32 = 50;
+
+fn time(name: &str, mut rebuild: impl FnMut() -> usize) -> Duration {
+    // warm up the caches and the allocator
+    for _ in 0..5 {
+        black_box(rebuild());
+    }
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        black_box(rebuild());
+    }
+    let per_run = start.elapsed() / RUNS;
+    println!("{:<8} {:>10.3?} per rebuild", name, per_run);
+    per_run
+}
+
+fn main() {
+    let scattered = |i: usize| {
+        let f = i as f32;
+        ([f.sin() * 50.0, f.cos() * 50.0, f * 0.001], f * 0.01)
+    };
+
+    let cgmath_instances = (0..INSTANCES)
+        .map(|i| {
+            use cgmath::Rotation3;
+            let (position, angle) = scattered(i);
+            (
+                cgmath::Vector3::from(position),
+                cgmath::Quaternion::from_angle_y(cgmath::Rad(angle)),
+            )
+        })
+        .collect::<Vec<_>>();
+    let glam_instances = (0..INSTANCES)
+        .map(|i| {
+            let (position, angle) = scattered(i);
+            (
+                glam::Vec3::from(position),
+                glam::Quat::from_rotation_y(angle),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut raw = vec![[[0.0f32; 4]; 4]; INSTANCES];
+    let cgmath_time = time("cgmath", || {
+        for (out, (position, rotation)) in raw.iter_mut().zip(&cgmath_instances) {
+            *out = (cgmath::Matrix4::from_translation(*position)
+                * cgmath::Matrix4::from(*rotation))
+            .into();
+        }
+        raw.len()
+    });
+    let glam_time = time("glam", || {
+        for (out, (position, rotation)) in raw.iter_mut().zip(&glam_instances) {
+            *out = glam::Mat4::from_rotation_translation(*rotation, *position).to_cols_array_2d();
+        }
+        raw.len()
+    });
+    println!(
+        "glam is {:.2}x cgmath",
+        cgmath_time.as_secs_f64() / glam_time.as_secs_f64()
+    );
+}
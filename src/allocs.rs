@@ -0,0 +1,109 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering::Relaxed};
+
+// What the heap's being used for, as far as App says: allocations are put
+// down to whichever `scope` the thread is in, Other outside all of them.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Subsystem {
+    Other,
+    Simulation,
+    Render,
+    Ui,
+    Audio,
+    Network,
+}
+
+const SUBSYSTEMS: usize = 6;
+const NAMES: [&str; SUBSYSTEMS] = ["other", "sim", "render", "ui", "audio", "net"];
+
+// per subsystem, allocations and bytes since the last frame() and bytes ever
+static COUNT: [AtomicU64; SUBSYSTEMS] = [const { AtomicU64::new(0) }; SUBSYSTEMS];
+static BYTES: [AtomicU64; SUBSYSTEMS] = [const { AtomicU64::new(0) }; SUBSYSTEMS];
+static TOTAL: [AtomicU64; SUBSYSTEMS] = [const { AtomicU64::new(0) }; SUBSYSTEMS];
+// the whole heap, whoever freed it
+static LIVE: AtomicI64 = AtomicI64::new(0);
+
+thread_local! {
+    // const so reading it doesn't allocate, which would come back round to here
+    static CURRENT: Cell<usize> = const { Cell::new(Subsystem::Other as usize) };
+}
+
+// The system allocator with a count kept of everything asked of it. The
+// counting is a few relaxed atomics per allocation, cheap enough to leave on,
+// so a change that starts allocating every frame shows up in F3 straight away.
+pub struct Tracking;
+
+fn record(bytes: usize) {
+    // a thread being torn down has no thread locals left, that's Other
+    let subsystem = CURRENT
+        .try_with(|c| c.get())
+        .unwrap_or(Subsystem::Other as usize);
+    COUNT[subsystem].fetch_add(1, Relaxed);
+    BYTES[subsystem].fetch_add(bytes as u64, Relaxed);
+    TOTAL[subsystem].fetch_add(bytes as u64, Relaxed);
+}
+
+unsafe impl GlobalAlloc for Tracking {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record(layout.size());
+            LIVE.fetch_add(layout.size() as i64, Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE.fetch_sub(layout.size() as i64, Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            // a Vec growing is what this is mostly for, so it counts
+            record(new_size);
+            LIVE.fetch_add(new_size as i64 - layout.size() as i64, Relaxed);
+        }
+        new
+    }
+}
+
+// Puts allocations on this thread down to `subsystem` until it's dropped,
+// then back to whatever they were before.
+pub fn scope(subsystem: Subsystem) -> Scope {
+    let previous = CURRENT.with(|c| c.replace(subsystem as usize));
+    Scope { previous }
+}
+
+pub struct Scope {
+    previous: usize,
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        CURRENT.with(|c| c.set(self.previous));
+    }
+}
+
+// one subsystem's share of a frame
+pub struct Usage {
+    pub name: &'static str,
+    pub count: u64,
+    pub bytes: u64,
+    pub total: u64,
+}
+
+// What was allocated since the last call, which starts the next frame's
+// count, and how big the heap is now. An array so that reading them doesn't
+// allocate itself.
+pub fn frame() -> ([Usage; SUBSYSTEMS], i64) {
+    let usage = std::array::from_fn(|i| Usage {
+        name: NAMES[i],
+        count: COUNT[i].swap(0, Relaxed),
+        bytes: BYTES[i].swap(0, Relaxed),
+        total: TOTAL[i].load(Relaxed),
+    });
+    (usage, LIVE.load(Relaxed))
+}
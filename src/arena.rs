@@ -0,0 +1,69 @@
+use crate::vertex::{self, ModelFormat, ModelVertex};
+use wgpu::util::DeviceExt;
+
+// Every loaded mesh's vertices and indices, packed one after another into a
+// single vertex buffer and a single index buffer. A mesh is a base vertex
+// and an index range into them (see model::Mesh), so the buffers get bound
+// once per pass with `set_mesh_arena` and drawing another model is just
+// another draw_indexed. It also leaves everything in place for multi-draw.
+pub struct MeshArena {
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub index_format: wgpu::IndexFormat,
+}
+
+// Collects meshes on the CPU while models load, `build` makes the buffers
+// once they're all in.
+pub struct MeshArenaBuilder {
+    format: ModelFormat,
+    vertex_bytes: Vec<u8>,
+    vertex_count: u32,
+    indices: Vec<u32>,
+    meshes: u32,
+}
+
+impl MeshArenaBuilder {
+    pub fn new(format: ModelFormat) -> Self {
+        Self {
+            format,
+            vertex_bytes: Vec::new(),
+            vertex_count: 0,
+            indices: Vec::new(),
+            meshes: 0,
+        }
+    }
+
+    // Puts a mesh after everything added so far and gives back its base
+    // vertex and first index. Indices stay relative to the mesh's own
+    // vertices, so they can still be 16 bit however big the arena gets.
+    pub fn add(&mut self, vertices: &[ModelVertex], indices: &[u32]) -> (i32, u32) {
+        let placed = (self.vertex_count as i32, self.indices.len() as u32);
+        self.vertex_bytes.extend(self.format.vertex_bytes(vertices));
+        self.vertex_count += vertices.len() as u32;
+        self.indices.extend_from_slice(indices);
+        self.meshes += 1;
+        placed
+    }
+
+    pub fn build(self, device: &wgpu::Device) -> MeshArena {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("mesh arena vertex buffer"),
+            contents: &self.vertex_bytes,
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let (index_buffer, index_format) =
+            vertex::create_index_buffer(device, "mesh arena index buffer", &self.indices);
+        log::info!(
+            "mesh arena: {} meshes, {} vertices, {} indices ({:?})",
+            self.meshes,
+            self.vertex_count,
+            self.indices.len(),
+            index_format
+        );
+        MeshArena {
+            vertex_buffer,
+            index_buffer,
+            index_format,
+        }
+    }
+}
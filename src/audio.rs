@@ -0,0 +1,314 @@
+use crate::math::{Point3, Vec3};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, Sample, SizedSample};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+
+pub const SAMPLE_RATE: u32 = 44100;
+// frames mixed at a time, about 12 ms
+const BLOCK: usize = 512;
+// full volume up to here, then falling off as the distance grows
+const REFERENCE_DISTANCE: f32 = 5.0;
+const ROLLOFF: f32 = 1.0;
+// past this a sound isn't mixed at all
+const MAX_DISTANCE: f32 = 80.0;
+// a new sound over this many takes the place of the oldest
+const MAX_VOICES: usize = 32;
+
+// a mono sound at SAMPLE_RATE, -1..1
+pub type Samples = Arc<[f32]>;
+
+enum Message {
+    Play {
+        samples: Samples,
+        position: Option<Point3>,
+        gain: f32,
+        rate: f32,
+    },
+    Listener {
+        position: Point3,
+        right: Vec3,
+    },
+    // how busy things are, 0..1, for the music
+    Intensity(f32),
+    Music(bool),
+}
+
+struct Voice {
+    samples: Samples,
+    // None plays it as it is, in the middle
+    position: Option<Point3>,
+    gain: f32,
+    // samples moved through per frame, above 1 plays it faster and higher
+    rate: f32,
+    cursor: f32,
+    // what each ear got at the end of the last block, ramped from so a
+    // moving listener doesn't click
+    ears: [f32; 2],
+}
+
+// Sound effects and music. The default output device's callback mixes
+// whatever's playing into stereo at SAMPLE_RATE; without a device that does
+// that praxis just stays quiet.
+//
+// A sound can be played at a place in the world, in which case it's
+// attenuated with distance from the listener (the camera) and panned
+// between the ears by which side of the camera it's on.
+pub struct Audio {
+    sender: Option<Sender<Message>>,
+    // playing until it's dropped
+    _stream: Option<cpal::Stream>,
+}
+
+impl Audio {
+    // `--mute` doesn't start anything
+    pub fn from_args() -> Self {
+        if std::env::args().any(|a| a == "--mute") {
+            return Self {
+                sender: None,
+                _stream: None,
+            };
+        }
+        let (sender, receiver) = mpsc::channel();
+        match start(Mixer::new(receiver)) {
+            Ok(stream) => Self {
+                sender: Some(sender),
+                _stream: Some(stream),
+            },
+            Err(e) => {
+                log::warn!("no sound: {:#}", e);
+                Self {
+                    sender: None,
+                    _stream: None,
+                }
+            }
+        }
+    }
+
+    pub fn set_listener(&self, position: Point3, right: Vec3) {
+        self.send(Message::Listener { position, right });
+    }
+
+    pub fn set_intensity(&self, intensity: f32) {
+        self.send(Message::Intensity(intensity.clamp(0.0, 1.0)));
+    }
+
+    pub fn set_music(&self, enabled: bool) {
+        self.send(Message::Music(enabled));
+    }
+
+    pub fn play(&self, samples: &Samples, position: Option<Point3>, gain: f32, rate: f32) {
+        self.send(Message::Play {
+            samples: samples.clone(),
+            position,
+            gain,
+            rate,
+        });
+    }
+
+    fn send(&self, message: Message) {
+        if let Some(sender) = &self.sender {
+            // the mixer's only gone if the stream is, that was logged there
+            let _ = sender.send(message);
+        }
+    }
+}
+
+// a stream on the default output, 16 bit or float, with `mixer` filling it
+fn start(mixer: Mixer) -> anyhow::Result<cpal::Stream> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or_else(|| anyhow::anyhow!("there's no output device"))?;
+    let range = device
+        .supported_output_configs()?
+        .filter(|c| {
+            matches!(
+                c.sample_format(),
+                cpal::SampleFormat::F32 | cpal::SampleFormat::I16
+            )
+        })
+        .find(|c| {
+            c.channels() == 2
+                && c.min_sample_rate().0 <= SAMPLE_RATE
+                && c.max_sample_rate().0 >= SAMPLE_RATE
+        })
+        .ok_or_else(|| anyhow::anyhow!("the output can't play {} Hz stereo", SAMPLE_RATE))?;
+    let format = range.sample_format();
+    let config = range
+        .with_sample_rate(cpal::SampleRate(SAMPLE_RATE))
+        .config();
+    let stream = match format {
+        cpal::SampleFormat::I16 => build::<i16>(&device, &config, mixer)?,
+        _ => build::<f32>(&device, &config, mixer)?,
+    };
+    stream.play()?;
+    Ok(stream)
+}
+
+fn build<T: SizedSample + FromSample<f32>>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut mixer: Mixer,
+) -> anyhow::Result<cpal::Stream> {
+    Ok(device.build_output_stream(
+        config,
+        move |out: &mut [T], _| mixer.fill(out),
+        |e| log::warn!("sound stream: {}", e),
+        None,
+    )?)
+}
+
+// what's playing, mixed a BLOCK at a time as the device asks for more
+struct Mixer {
+    receiver: Receiver<Message>,
+    voices: Vec<Voice>,
+    listener: (Point3, Vec3),
+    music: crate::music::Music,
+    block: Vec<[f32; 2]>,
+    // frames of `block` already handed out
+    played: usize,
+}
+
+impl Mixer {
+    fn new(receiver: Receiver<Message>) -> Self {
+        Self {
+            receiver,
+            voices: Vec::new(),
+            listener: (Point3::ZERO, Vec3::X),
+            music: crate::music::Music::new(),
+            block: vec![[0.0; 2]; BLOCK],
+            played: BLOCK,
+        }
+    }
+
+    fn fill<T: Sample + FromSample<f32>>(&mut self, out: &mut [T]) {
+        for frame in out.chunks_exact_mut(2) {
+            if self.played == BLOCK {
+                self.mix();
+                self.played = 0;
+            }
+            for (sample, channel) in frame.iter_mut().zip(self.block[self.played]) {
+                *sample = T::from_sample(channel.clamp(-1.0, 1.0));
+            }
+            self.played += 1;
+        }
+    }
+
+    fn mix(&mut self) {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(Message::Play {
+                    samples,
+                    position,
+                    gain,
+                    rate,
+                }) => {
+                    if self.voices.len() >= MAX_VOICES {
+                        self.voices.remove(0);
+                    }
+                    self.voices.push(Voice {
+                        samples,
+                        position,
+                        gain,
+                        rate,
+                        cursor: 0.0,
+                        ears: [f32::NAN; 2],
+                    });
+                }
+                Ok(Message::Listener { position, right }) => self.listener = (position, right),
+                Ok(Message::Intensity(intensity)) => self.music.intensity = intensity,
+                Ok(Message::Music(enabled)) => self.music.enabled = enabled,
+                // the Audio going away drops the stream too, this is just its last block
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => break,
+            }
+        }
+
+        let block = &mut self.block;
+        block.fill([0.0; 2]);
+        for voice in &mut self.voices {
+            let ears = spatialize(voice.position, voice.gain, self.listener);
+            let from = if voice.ears[0].is_nan() {
+                ears
+            } else {
+                voice.ears
+            };
+            for (i, frame) in block.iter_mut().enumerate() {
+                let Some(sample) = voice.sample() else {
+                    break;
+                };
+                let t = i as f32 / BLOCK as f32;
+                frame[0] += sample * (from[0] + (ears[0] - from[0]) * t);
+                frame[1] += sample * (from[1] + (ears[1] - from[1]) * t);
+                voice.cursor += voice.rate;
+            }
+            voice.ears = ears;
+        }
+        self.voices.retain(|v| v.sample().is_some());
+        self.music.mix(block);
+    }
+}
+
+impl Voice {
+    // where the cursor is, between the samples either side, or None once
+    // it's played out
+    fn sample(&self) -> Option<f32> {
+        let i = self.cursor as usize;
+        let a = *self.samples.get(i)?;
+        let b = self.samples.get(i + 1).copied().unwrap_or(0.0);
+        Some(a + (b - a) * self.cursor.fract())
+    }
+}
+
+// How loud a sound is in each ear. Distance rolls it off like an inverse
+// distance model clamped at REFERENCE_DISTANCE, and an equal power pan puts
+// it to the side it's on.
+fn spatialize(position: Option<Point3>, gain: f32, listener: (Point3, Vec3)) -> [f32; 2] {
+    let Some(position) = position else {
+        return [gain * std::f32::consts::FRAC_1_SQRT_2; 2];
+    };
+    let (at, right) = listener;
+    let offset = position - at;
+    let distance = offset.length();
+    if distance > MAX_DISTANCE {
+        return [0.0; 2];
+    }
+    let attenuation = REFERENCE_DISTANCE
+        / (REFERENCE_DISTANCE + ROLLOFF * (distance.max(REFERENCE_DISTANCE) - REFERENCE_DISTANCE));
+    let pan = offset.normalize_or_zero().dot(right).clamp(-1.0, 1.0);
+    let angle = (pan + 1.0) * std::f32::consts::FRAC_PI_4;
+    [
+        gain * attenuation * angle.cos(),
+        gain * attenuation * angle.sin(),
+    ]
+}
+
+///// sounds, made up rather than loaded
+
+// a sine sweeping from `from` to `to` Hz, dying away exponentially
+pub fn chirp(from: f32, to: f32, seconds: f32, decay: f32) -> Samples {
+    let count = (seconds * SAMPLE_RATE as f32) as usize;
+    let mut phase = 0.0f32;
+    (0..count)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let frequency = from + (to - from) * t / seconds;
+            phase += frequency / SAMPLE_RATE as f32 * std::f32::consts::TAU;
+            phase.sin() * (-decay * t).exp()
+        })
+        .collect()
+}
+
+// white noise through a one pole low pass, dying away exponentially. A
+// lower `cutoff` (0..1 of the way to Nyquist) makes it duller.
+pub fn burst(seconds: f32, decay: f32, cutoff: f32, seed: u64) -> Samples {
+    let count = (seconds * SAMPLE_RATE as f32) as usize;
+    let mut rng = crate::rng::Rng::new(seed);
+    let mut filtered = 0.0f32;
+    (0..count)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            filtered += (rng.f32() * 2.0 - 1.0 - filtered) * cutoff;
+            filtered * (-decay * t).exp()
+        })
+        .collect()
+}
@@ -0,0 +1,116 @@
+use crate::floor::FLOOR_Y;
+use crate::math::{Vec2, Vec3};
+use crate::saves::{Cube, Slot};
+use anyhow::{bail, Result};
+
+// texels per unit of ground
+const TEXELS_PER_UNIT: f32 = 4.0;
+const MAX_TEXELS: u32 = 1024;
+// rays per texel over the hemisphere
+const RAYS: u32 = 64;
+// anything further away than this doesn't darken the ground
+const REACH: f32 = 6.0;
+// ground kept around the outermost cubes
+const MARGIN: f32 = 6.0;
+
+// The square of ground an AO map covers, saved as an `ao` line in scene.txt.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Area {
+    pub min: Vec2,
+    pub size: f32,
+}
+
+// the cubes' shadow on the ground, what's under and between them
+fn area(cubes: &[Cube]) -> Area {
+    let (min, max) = cubes.iter().fold(
+        (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+        |(min, max), cube| {
+            let p = Vec2::new(cube.position.x, cube.position.z);
+            (min.min(p), max.max(p))
+        },
+    );
+    let size = (max - min).max_element() + 2.0 * MARGIN;
+    Area {
+        min: (min + max) / 2.0 - Vec2::splat(size / 2.0),
+        size,
+    }
+}
+
+// cosine weighted directions over the hemisphere around +y, the same
+// spiral for every texel so the map doesn't come out noisy
+fn hemisphere() -> Vec<Vec3> {
+    let golden = std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+    (0..RAYS)
+        .map(|i| {
+            let r = ((i as f32 + 0.5) / RAYS as f32).sqrt();
+            let phi = golden * i as f32;
+            Vec3::new(r * phi.cos(), (1.0 - r * r).sqrt(), r * phi.sin())
+        })
+        .collect()
+}
+
+// whether the ray hits the cube within REACH, in the cube's own space where
+// it's the -1..1 box, like picking does
+fn hits(cube: &Cube, origin: Vec3, direction: Vec3) -> bool {
+    let to_local = cube.rotation.inverse();
+    let origin = to_local * (origin - cube.position);
+    let direction = to_local * direction;
+    let mut near = 0.0f32;
+    let mut far = REACH;
+    for axis in 0..3 {
+        let t0 = (-1.0 - origin[axis]) / direction[axis];
+        let t1 = (1.0 - origin[axis]) / direction[axis];
+        near = near.max(t0.min(t1));
+        far = far.min(t0.max(t1));
+    }
+    near <= far
+}
+
+// `--bake <slot>`: traces ambient occlusion from the slot's cubes onto the
+// ground and saves it as ao.png next to its scene.txt, which praxis picks up
+// whenever the slot is loaded. Runs without a window or a GPU.
+pub fn run(name: &str) -> Result<()> {
+    let Some(mut slot) = Slot::list().into_iter().find(|slot| slot.name == name) else {
+        bail!("no save slot called {:?}", name);
+    };
+    if slot.cubes.is_empty() {
+        bail!("{} has no cubes to bake", name);
+    }
+    let area = area(&slot.cubes);
+    let texels = ((area.size * TEXELS_PER_UNIT) as u32).min(MAX_TEXELS);
+    log::info!(
+        "baking {} cubes into {}x{} texels of ground",
+        slot.cubes.len(),
+        texels,
+        texels
+    );
+
+    let rays = hemisphere();
+    // only cubes that can reach a texel get tested against it
+    let reach = REACH + 3.0f32.sqrt();
+    let mut image = image::GrayImage::new(texels, texels);
+    for y in 0..texels {
+        for x in 0..texels {
+            let ground =
+                area.min + (Vec2::new(x as f32, y as f32) + 0.5) * area.size / texels as f32;
+            let origin = Vec3::new(ground.x, FLOOR_Y + 1e-3, ground.y);
+            let near = slot
+                .cubes
+                .iter()
+                .filter(|cube| cube.position.distance(origin) < reach)
+                .collect::<Vec<_>>();
+            let blocked = rays
+                .iter()
+                .filter(|d| near.iter().any(|cube| hits(cube, origin, **d)))
+                .count();
+            let open = 1.0 - blocked as f32 / RAYS as f32;
+            image.put_pixel(x, y, image::Luma([(open * 255.0).round() as u8]));
+        }
+    }
+
+    slot.ao = Some(area);
+    slot.save(None)?;
+    image.save(slot.ao_path())?;
+    log::info!("saved {}", slot.ao_path().display());
+    Ok(())
+}
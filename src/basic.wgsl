@@ -1,3 +1,10 @@
+// The background, the reactor image showing through wisps of noise that
+// drift over the clear colour, which still shows between them.
+
+#define TIMER_GROUP 1
+#include "common.wgsl"
+#include "noise.wgsl"
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
@@ -29,6 +36,8 @@ var s_diffuse: sampler;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    let color = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    let wisps = noise_fbm(in.tex_coords * 3.0 + vec2<f32>(timer.t * 0.05, 0.0), 4u);
+    return vec4<f32>(color.rgb, smoothstep(-0.2, 0.6, wisps) * 0.35);
 }
 
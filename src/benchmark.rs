@@ -0,0 +1,141 @@
+use crate::math::{Point3, Vec3};
+use std::path::PathBuf;
+use std::time::Instant;
+
+// frames in the first seconds are still compiling and filling caches
+const WARMUP: f64 = 2.0;
+const SECONDS: f64 = 30.0;
+// the scene, the same every run
+pub const FORMATION: &str = "grid 32 32";
+pub const SEED: u64 = 728;
+
+// `--benchmark` sets up one heavy scene (a field of cubes in the rain with
+// the cloth, water and the Ultra preset), flies the camera round it on a
+// fixed path and reports the frame rate and where the GPU time went, as
+// JSON on stdout and in `--benchmark-json <path>`. With
+// `--benchmark-min-fps <fps>` the run fails, and praxis exits with 1, when
+// the 1% low is under it.
+pub struct Benchmark {
+    pub json_path: Option<PathBuf>,
+    pub min_fps: Option<f64>,
+    started: Instant,
+    last: Option<Instant>,
+    // seconds, after the warmup
+    frames: Vec<f64>,
+    // total milliseconds and how many frames had it, per label
+    passes: Vec<(&'static str, f64, u32)>,
+}
+
+impl Benchmark {
+    pub fn from_args() -> Option<Self> {
+        let args = std::env::args().collect::<Vec<_>>();
+        if !args.iter().any(|a| a == "--benchmark") {
+            return None;
+        }
+        let value = |name: &str| {
+            args.iter()
+                .position(|a| a == name)
+                .and_then(|i| args.get(i + 1))
+        };
+        Some(Self {
+            json_path: value("--benchmark-json").map(PathBuf::from),
+            min_fps: value("--benchmark-min-fps").and_then(|fps| fps.parse().ok()),
+            started: Instant::now(),
+            last: None,
+            frames: Vec::new(),
+            passes: Vec::new(),
+        })
+    }
+
+    fn elapsed(&self) -> f64 {
+        self.started.elapsed().as_secs_f64()
+    }
+
+    // Where the eye is and what it looks at along the path: once round the
+    // field over the run, swinging in and out and bobbing up and down so
+    // near cubes, far cubes and the reflections all get their turn.
+    pub fn camera(&self, center: Point3) -> (Point3, Point3) {
+        let turn = (self.elapsed() / (WARMUP + SECONDS)) as f32 * std::f32::consts::TAU;
+        let radius = 35.0 + 15.0 * (2.0 * turn).sin();
+        let height = 12.0 + 8.0 * (3.0 * turn).sin();
+        let eye = center + Vec3::new(radius * turn.cos(), height, radius * turn.sin());
+        (eye, center)
+    }
+
+    // once a frame, true when the run's over
+    pub fn frame(&mut self, gpu_times: &[(&'static str, f64)]) -> bool {
+        let now = Instant::now();
+        let last = self.last.replace(now);
+        if self.elapsed() < WARMUP {
+            return false;
+        }
+        if let Some(last) = last {
+            self.frames.push((now - last).as_secs_f64());
+        }
+        for (label, ms) in gpu_times {
+            match self.passes.iter_mut().find(|(l, ..)| l == label) {
+                Some((_, total, count)) => {
+                    *total += ms;
+                    *count += 1;
+                }
+                None => self.passes.push((label, *ms, 1)),
+            }
+        }
+        self.elapsed() >= WARMUP + SECONDS
+    }
+
+    fn fps(&self) -> (f64, f64, f64) {
+        let mut sorted = self.frames.clone();
+        sorted.sort_by(f64::total_cmp);
+        let Some(&slowest) = sorted.last() else {
+            return (0.0, 0.0, 0.0);
+        };
+        let average = sorted.len() as f64 / sorted.iter().sum::<f64>();
+        // the frame rate the slowest 1% of frames are at or under
+        let low = sorted[(sorted.len() * 99 / 100).min(sorted.len() - 1)];
+        (1.0 / slowest, average, 1.0 / low)
+    }
+
+    pub fn passed(&self) -> bool {
+        self.min_fps.is_none_or(|min| self.fps().2 >= min)
+    }
+
+    // only numbers and labels from the code go in, nothing needs escaping
+    pub fn json(&self, adapter: &str, backend: &str) -> String {
+        let (min, average, low) = self.fps();
+        let passes = self
+            .passes
+            .iter()
+            .map(|(label, total, count)| format!("\"{}\": {:.3}", label, total / *count as f64))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "{{\"adapter\": \"{}\", \"backend\": \"{}\", \"scene\": \"{}\", \
+             \"seconds\": {:.3}, \"frames\": {}, \"min_fps\": {:.3}, \"average_fps\": {:.3}, \
+             \"low_1_percent_fps\": {:.3}, \"gpu_ms\": {{{}}}, \"threshold_fps\": {}, \"passed\": {}}}\n",
+            adapter.replace(['"', '\\'], ""),
+            backend,
+            FORMATION,
+            self.frames.iter().sum::<f64>(),
+            self.frames.len(),
+            min,
+            average,
+            low,
+            passes,
+            self.min_fps
+                .map_or("null".to_string(), |fps| format!("{:.3}", fps)),
+            self.passed()
+        )
+    }
+
+    pub fn finish(&self, adapter: &str, backend: &str) {
+        let json = self.json(adapter, backend);
+        print!("{}", json);
+        if let Some(path) = &self.json_path {
+            match std::fs::write(path, &json) {
+                Ok(()) => log::info!("wrote the benchmark to {}", path.display()),
+                Err(e) => log::error!("couldn't write {}: {}", path.display(), e),
+            }
+        }
+    }
+}
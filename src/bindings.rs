@@ -0,0 +1,228 @@
+use wgpu_text::glyph_brush::{OwnedSection, Section as TextSection, Text};
+use winit::keyboard::{Key, NamedKey, SmolStr};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Action {
+    Quit,
+    SpawnCube,
+    Stats,
+    MainMenu,
+    KeyHelp,
+    Options,
+    Capture,
+    Save,
+    SaveMenu,
+    LoseDevice,
+    SwitchBackend,
+    OutlineThinner,
+    OutlineThicker,
+    Water,
+    Floor,
+    Cloth,
+    Reflections,
+    Retro,
+    Clip,
+    RestoreQuality,
+    Shatter,
+    Tweaks,
+    Materials,
+    Pause,
+    Step,
+    FirstPerson,
+}
+
+// what `bind` in the console calls each action, and what the overlay says it does
+const ACTIONS: [(Action, &str, &str); 26] = [
+    (Action::Quit, "quit", "quit"),
+    (Action::SpawnCube, "spawn", "add a cube"),
+    (Action::Stats, "stats", "frame stats"),
+    (Action::MainMenu, "menu", "main menu"),
+    (Action::KeyHelp, "keys", "this list"),
+    (Action::Options, "options", "options"),
+    (Action::Capture, "capture", "renderdoc capture"),
+    (Action::Save, "save", "quick save"),
+    (Action::SaveMenu, "saves", "save slots"),
+    (Action::LoseDevice, "lose_device", "simulate device loss"),
+    (Action::SwitchBackend, "backend", "switch backend"),
+    (Action::OutlineThinner, "thinner", "thinner outline"),
+    (Action::OutlineThicker, "thicker", "thicker outline"),
+    (Action::Water, "water", "water"),
+    (Action::Floor, "floor", "floor"),
+    (Action::Cloth, "cloth", "drop cloth"),
+    (Action::Reflections, "reflections", "dynamic reflections"),
+    (Action::Retro, "retro", "retro pixels"),
+    (Action::Clip, "clip", "gif of the last 5 seconds"),
+    (
+        Action::RestoreQuality,
+        "restore",
+        "undo automatic quality drops",
+    ),
+    (Action::Shatter, "shatter", "shatter the picked cubes"),
+    (Action::Tweaks, "tweaks", "shader tweaks"),
+    (Action::Materials, "materials", "material editor"),
+    (Action::Pause, "pause", "pause the simulation"),
+    (Action::Step, "step", "one tick while paused"),
+    (
+        Action::FirstPerson,
+        "fly",
+        "fly the camera or follow the cube",
+    ),
+];
+
+pub fn action_names() -> impl Iterator<Item = &'static str> {
+    ACTIONS.iter().map(|(_, name, _)| *name)
+}
+
+fn name(action: Action) -> &'static str {
+    ACTIONS.iter().find(|(a, ..)| *a == action).unwrap().1
+}
+
+// the few named keys worth binding to, plus any single character
+fn parse_key(name: &str) -> Option<Key> {
+    let named = match name {
+        "escape" => NamedKey::Escape,
+        "space" => NamedKey::Space,
+        "tab" => NamedKey::Tab,
+        "enter" => NamedKey::Enter,
+        "f1" => NamedKey::F1,
+        "f2" => NamedKey::F2,
+        "f3" => NamedKey::F3,
+        "f4" => NamedKey::F4,
+        "f5" => NamedKey::F5,
+        "f6" => NamedKey::F6,
+        "f7" => NamedKey::F7,
+        "f8" => NamedKey::F8,
+        "f9" => NamedKey::F9,
+        "f10" => NamedKey::F10,
+        "f11" => NamedKey::F11,
+        "f12" => NamedKey::F12,
+        _ if name.chars().count() == 1 => return Some(Key::Character(SmolStr::new(name))),
+        _ => return None,
+    };
+    Some(Key::Named(named))
+}
+
+fn key_name(key: &Key) -> String {
+    match key {
+        Key::Character(c) => c.to_string(),
+        Key::Named(named) => format!("{:?}", named),
+        _ => "?".to_string(),
+    }
+}
+
+// Which key does what, for everything that isn't movement or a menu's own
+// digits. window_event asks this instead of matching keys itself, so `bind`
+// in the console takes effect straight away, and F2 lists the current keys.
+pub struct Bindings {
+    pub help_open: bool,
+    keys: Vec<(Action, Key)>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let named = |action, key| (action, Key::Named(key));
+        let character = |action, c| (action, Key::Character(SmolStr::new(c)));
+        Self {
+            help_open: false,
+            keys: vec![
+                named(Action::Quit, NamedKey::Escape),
+                named(Action::SpawnCube, NamedKey::Space),
+                named(Action::Stats, NamedKey::F3),
+                named(Action::MainMenu, NamedKey::F1),
+                named(Action::KeyHelp, NamedKey::F2),
+                named(Action::Options, NamedKey::F7),
+                named(Action::Capture, NamedKey::F10),
+                named(Action::Save, NamedKey::F5),
+                named(Action::SaveMenu, NamedKey::F6),
+                named(Action::LoseDevice, NamedKey::F8),
+                named(Action::SwitchBackend, NamedKey::F9),
+                character(Action::OutlineThinner, "["),
+                character(Action::OutlineThicker, "]"),
+                character(Action::Water, "o"),
+                character(Action::Floor, "g"),
+                character(Action::Cloth, "c"),
+                character(Action::Reflections, "r"),
+                character(Action::Retro, "p"),
+                named(Action::Clip, NamedKey::F4),
+                named(Action::RestoreQuality, NamedKey::F12),
+                character(Action::Shatter, "x"),
+                named(Action::Tweaks, NamedKey::F11),
+                character(Action::Materials, "m"),
+                character(Action::Pause, ","),
+                character(Action::Step, "."),
+                named(Action::FirstPerson, NamedKey::Tab),
+            ],
+        }
+    }
+}
+
+impl Bindings {
+    pub fn action(&self, key: &Key) -> Option<Action> {
+        self.keys
+            .iter()
+            .find(|(_, bound)| bound == key)
+            .map(|(action, _)| *action)
+    }
+
+    // moves `action` onto `key`, whatever had that key before loses it
+    pub fn bind(&mut self, action: &str, key: &str) -> Result<String, String> {
+        let action = ACTIONS
+            .iter()
+            .find(|(_, name, _)| *name == action)
+            .map(|(action, ..)| *action)
+            .ok_or_else(|| format!("no action called {}", action))?;
+        let key = parse_key(&key.to_lowercase()).ok_or_else(|| format!("unknown key {}", key))?;
+        let message = format!("{} is on {} now", name(action), key_name(&key));
+        self.keys.retain(|(a, k)| *a != action && *k != key);
+        self.keys.push((action, key));
+        Ok(message)
+    }
+
+    // every action and the key it's on, for a profile to bind again
+    pub fn pairs(&self) -> Vec<(String, String)> {
+        self.keys
+            .iter()
+            .map(|(action, key)| (name(*action).to_string(), key_name(key)))
+            .collect()
+    }
+
+    // the key `action` is on, "-" if none
+    pub fn key_for(&self, action: Action) -> String {
+        self.keys
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map(|(_, key)| key_name(key))
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    // every action and its key in two columns, built fresh each frame
+    pub fn sections(&self, width: f32) -> Vec<OwnedSection> {
+        if !self.help_open {
+            return Vec::new();
+        }
+        let lines = ACTIONS
+            .iter()
+            .map(|(action, _, description)| format!("{:<8}{}", self.key_for(*action), description))
+            .collect::<Vec<_>>();
+        let rows = lines.len().div_ceil(2);
+        let column = |x: f32, y: f32, text: String| {
+            TextSection::default()
+                .add_text(
+                    Text::new(&text)
+                        .with_color([0.9, 1.0, 1.0, 1.0])
+                        .with_scale(20.0),
+                )
+                .with_screen_position((x, y))
+                .to_owned()
+        };
+        vec![
+            column(
+                width / 2.0 - 300.0,
+                120.0,
+                "keys (WASD or arrows move, `bind <action> <key>` in the console)".to_string(),
+            ),
+            column(width / 2.0 - 300.0, 160.0, lines[..rows].join("\n")),
+            column(width / 2.0 + 20.0, 160.0, lines[rows..].join("\n")),
+        ]
+    }
+}
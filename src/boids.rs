@@ -0,0 +1,121 @@
+use crate::math::{Point3, Vec3};
+use std::collections::HashMap;
+
+const STEP: f64 = 1.0 / 60.0;
+// a slow frame doesn't get to ask for more than this many steps at once
+const MAX_STEPS: u32 = 5;
+
+// how far a boid sees its neighbours, also the size of a grid cell
+const NEIGHBOUR_RADIUS: f32 = 4.0;
+const SEPARATION_RADIUS: f32 = 2.5;
+const SEPARATION: f32 = 8.0;
+const ALIGNMENT: f32 = 1.5;
+const COHESION: f32 = 1.0;
+// pull towards the player, stronger the further out a boid is
+const HOME: f32 = 0.05;
+const MIN_SPEED: f32 = 4.0;
+const MAX_SPEED: f32 = 12.0;
+
+// Flocking for every cube but the first, which is the player they circle
+// around. Runs on its own fixed step, and neighbours are found through a
+// grid of NEIGHBOUR_RADIUS cells so a few thousand boids stay cheap.
+#[derive(Default, Clone)]
+pub struct Boids {
+    pub enabled: bool,
+    velocities: Vec<Vec3>,
+    acc: f64,
+    grid: HashMap<[i32; 3], Vec<u32>>,
+}
+
+impl Boids {
+    pub fn velocity(&self, i: usize) -> Vec3 {
+        self.velocities[i]
+    }
+
+    // runs however many fixed steps `frame_time` covers and says how many that was
+    pub fn update(
+        &mut self,
+        frame_time: f64,
+        player: Point3,
+        positions: &mut [Point3],
+        rng: &mut crate::rng::Rng,
+    ) -> u32 {
+        // new boids start off in some random direction
+        while self.velocities.len() < positions.len() {
+            let direction = Vec3::new(rng.f32() - 0.5, rng.f32() - 0.5, rng.f32() - 0.5);
+            self.velocities
+                .push(direction.normalize_or(Vec3::X) * MIN_SPEED);
+        }
+        self.velocities.truncate(positions.len());
+
+        self.acc = (self.acc + frame_time).min(STEP * MAX_STEPS as f64);
+        let mut steps = 0;
+        while self.acc >= STEP {
+            self.acc -= STEP;
+            self.step(player, positions);
+            steps += 1;
+        }
+        steps
+    }
+
+    fn step(&mut self, player: Point3, positions: &mut [Point3]) {
+        let cell = |p: Point3| (p / NEIGHBOUR_RADIUS).floor().as_ivec3().to_array();
+        for cell in self.grid.values_mut() {
+            cell.clear();
+        }
+        for (i, p) in positions.iter().enumerate() {
+            self.grid.entry(cell(*p)).or_default().push(i as u32);
+        }
+
+        let mut velocities = self.velocities.clone();
+        for (i, p) in positions.iter().enumerate() {
+            let [x, y, z] = cell(*p);
+            let mut separation = Vec3::ZERO;
+            let mut heading = Vec3::ZERO;
+            let mut center = Vec3::ZERO;
+            let mut neighbours = 0;
+            for dz in -1..=1 {
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let Some(others) = self.grid.get(&[x + dx, y + dy, z + dz]) else {
+                            continue;
+                        };
+                        for &j in others {
+                            let offset = *p - positions[j as usize];
+                            if j as usize == i
+                                || offset.length_squared() > NEIGHBOUR_RADIUS * NEIGHBOUR_RADIUS
+                            {
+                                continue;
+                            }
+                            let distance = offset.length();
+                            if distance < SEPARATION_RADIUS {
+                                separation += offset / distance.max(0.01).powi(2);
+                            }
+                            heading += self.velocities[j as usize];
+                            center += positions[j as usize];
+                            neighbours += 1;
+                        }
+                    }
+                }
+            }
+
+            let mut steer = separation * SEPARATION + (player - *p) * HOME;
+            if neighbours > 0 {
+                let n = neighbours as f32;
+                steer += (heading / n - self.velocities[i]) * ALIGNMENT;
+                steer += (center / n - *p) * COHESION;
+            }
+            let velocity = self.velocities[i] + steer * STEP as f32;
+            if velocity.length_squared() > 0.0 {
+                velocities[i] = velocity.clamp_length(MIN_SPEED, MAX_SPEED);
+            }
+        }
+
+        for (p, v) in positions.iter_mut().zip(&velocities) {
+            *p += *v * STEP as f32;
+        }
+        self.velocities = velocities;
+        // cells nobody is in any more would pile up otherwise
+        self.grid.retain(|_, cell| !cell.is_empty());
+    }
+}
@@ -0,0 +1,237 @@
+use wgpu_text::glyph_brush::{OwnedSection, Section as TextSection, Text};
+
+// this many elements of a buffer of many are shown at once
+const PAGE: u64 = 4;
+
+#[derive(Copy, Clone, Debug)]
+pub enum Field {
+    F32,
+    U32,
+    Vec4,
+    // four columns, the way glam and WGSL keep them
+    Mat4,
+}
+
+impl Field {
+    fn size(self) -> u64 {
+        match self {
+            Field::F32 | Field::U32 => 4,
+            Field::Vec4 => 16,
+            Field::Mat4 => 64,
+        }
+    }
+}
+
+// what's in an element of each buffer, in the order of the struct it holds
+pub const CAMERA: &[(&str, Field)] = &[
+    ("view_pos", Field::Vec4),
+    ("view_proj", Field::Mat4),
+    ("inv_view_proj", Field::Mat4),
+];
+pub const INSTANCE: &[(&str, Field)] = &[
+    ("model", Field::Mat4),
+    ("flash", Field::F32),
+    ("skin", Field::U32),
+    ("fade", Field::F32),
+];
+pub const TIMER: &[(&str, Field)] = &[("t", Field::F32)];
+pub const LIGHT: &[(&str, Field)] = &[
+    ("direction", Field::Vec4),
+    ("color", Field::Vec4),
+    ("ambient", Field::Vec4),
+];
+pub const POINT_LIGHT: &[(&str, Field)] = &[("position", Field::Vec4), ("color", Field::Vec4)];
+
+// a buffer that can be inspected, how its elements are laid out and how
+// many of them are in use, which for one with room to spare is fewer than fit
+pub type Inspectable<'a> = (
+    &'static str,
+    &'a wgpu::Buffer,
+    &'static [(&'static str, Field)],
+    u64,
+);
+
+// one with every element in use
+pub fn whole<'a>(
+    name: &'static str,
+    buffer: &'a wgpu::Buffer,
+    fields: &'static [(&'static str, Field)],
+) -> Inspectable<'a> {
+    (name, buffer, fields, buffer.size() / stride(fields))
+}
+
+// the copy of one frame's contents, to be read once the frame's submitted
+pub struct Pending {
+    buffer: wgpu::Buffer,
+    name: &'static str,
+    fields: &'static [(&'static str, Field)],
+    first: u64,
+    total: u64,
+}
+
+// `buffer <name> [first]` in the console. Every frame while it's open, the
+// picked buffer (a page of it, for the instances) is copied at the end of
+// the frame into one that can be mapped, read back once the frame's done and
+// decoded field by field, matrices as their rows, so what the shaders
+// actually got can be checked against what was meant to be uploaded. The
+// wait for the read stalls the frame, it's only for looking.
+#[derive(Default)]
+pub struct BufferInspector {
+    // which of Renderer::buffers, none when it's closed
+    selected: Option<usize>,
+    // the first element shown
+    first: u64,
+    lines: Vec<String>,
+}
+
+impl BufferInspector {
+    pub fn list(buffers: &[Inspectable]) -> String {
+        buffers
+            .iter()
+            .enumerate()
+            .map(|(i, (name, buffer, fields, count))| {
+                format!(
+                    "{} {} {} bytes, {} of {} elements in use",
+                    i,
+                    name,
+                    buffer.size(),
+                    count,
+                    buffer.size() / stride(fields)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // by its number in the list or its name
+    pub fn select(&mut self, which: &str, first: u64, buffers: &[Inspectable]) -> String {
+        let found = which
+            .parse::<usize>()
+            .ok()
+            .filter(|i| *i < buffers.len())
+            .or_else(|| buffers.iter().position(|(name, ..)| *name == which));
+        match found {
+            Some(i) => {
+                self.selected = Some(i);
+                self.first = first;
+                format!("inspecting {}", buffers[i].0)
+            }
+            None => format!("no buffer {}, buffer lists them", which),
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.selected = None;
+        self.lines.clear();
+    }
+
+    pub fn record(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        buffers: &[Inspectable],
+    ) -> Option<Pending> {
+        let (name, buffer, fields, count) = *buffers.get(self.selected?)?;
+        let stride = stride(fields);
+        // nothing past what's in use, that's whatever was left there
+        let total = count.min(buffer.size() / stride);
+        if total == 0 {
+            return None;
+        }
+        let first = self.first.min(total - 1);
+        let count = PAGE.min(total - first);
+        let copy = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("buffer inspector copy"),
+            size: count * stride,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_buffer_to_buffer(buffer, first * stride, &copy, 0, count * stride);
+        Some(Pending {
+            buffer: copy,
+            name,
+            fields,
+            first,
+            total,
+        })
+    }
+
+    // blocks until the frame the copy was in is done
+    pub fn read(&mut self, device: &wgpu::Device, pending: Pending) {
+        let slice = pending.buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        if let Ok(Err(e)) | Err(e) = receiver.recv().map_err(|_| wgpu::BufferAsyncError) {
+            log::error!("couldn't read back {}: {}", pending.name, e);
+            return;
+        }
+        let data = slice.get_mapped_range();
+        let words: &[u32] = bytemuck::cast_slice(&data);
+
+        let count = data.len() as u64 / stride(pending.fields);
+        self.lines = vec![format!(
+            "{} {} to {} of {}",
+            pending.name,
+            pending.first,
+            pending.first + count - 1,
+            pending.total
+        )];
+        let mut words = words.iter().copied();
+        for i in 0..count {
+            if pending.total > 1 {
+                self.lines.push(format!("[{}]", pending.first + i));
+            }
+            for (name, field) in pending.fields {
+                let mut floats = |n: usize| {
+                    (0..n)
+                        .map(|_| format!("{:>9.3}", f32::from_bits(words.next().unwrap())))
+                        .collect::<Vec<_>>()
+                };
+                match field {
+                    Field::F32 => self
+                        .lines
+                        .push(format!("  {} {}", name, floats(1)[0].trim())),
+                    Field::U32 => self
+                        .lines
+                        .push(format!("  {} {}", name, words.next().unwrap())),
+                    Field::Vec4 => self
+                        .lines
+                        .push(format!("  {} {}", name, floats(4).join(""))),
+                    Field::Mat4 => {
+                        let columns = floats(16);
+                        self.lines.push(format!("  {}", name));
+                        for row in 0..4 {
+                            let row = (0..4).map(|c| columns[c * 4 + row].as_str());
+                            self.lines.push(format!("    {}", row.collect::<String>()));
+                        }
+                    }
+                }
+            }
+        }
+        drop(data);
+        pending.buffer.unmap();
+    }
+
+    pub fn section(&self, height: f32) -> Option<OwnedSection> {
+        self.selected?;
+        let mut text = "buffer (buffer <n|name> [first], buffer off closes)\n".to_string();
+        text += &self.lines.join("\n");
+        Some(
+            TextSection::default()
+                .add_text(
+                    Text::new(&text)
+                        .with_color([0.9, 1.0, 1.0, 1.0])
+                        .with_scale(18.0),
+                )
+                .with_screen_position((10.0, height / 4.0))
+                .to_owned(),
+        )
+    }
+}
+
+fn stride(fields: &[(&str, Field)]) -> u64 {
+    fields.iter().map(|(_, field)| field.size()).sum()
+}
@@ -1,57 +1,172 @@
-#[rustfmt::skip]
-pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
-    1.0, 0.0, 0.0, 0.0,
-    0.0, 1.0, 0.0, 0.0,
-    0.0, 0.0, 0.5, 0.5,
-    0.0, 0.0, 0.0, 1.0,
-);
+use crate::math::{self, Degrees, Mat4, Point3, Vec3, Vec4};
+
+// how far over the top or under the floor orbiting goes, short of straight
+// up or down where look_at loses track of which way is up
+const MAX_PITCH: f32 = 85.0;
+// how close to and far from the target zooming goes
+const MIN_DISTANCE: f32 = 2.0;
+const MAX_DISTANCE: f32 = 90.0;
+
+// every shader and group the camera bind group gets bound at, its layout is reflected from these
+pub const BIND_GROUP_USES: &[(&str, u32)] = &[
+    ("cube.wgsl", 1),
+    ("floor.wgsl", 0),
+    ("outline.wgsl", 0),
+    ("skybox.wgsl", 0),
+    ("water.wgsl", 0),
+    ("cloth_draw.wgsl", 0),
+    ("sprite.wgsl", 0),
+    ("velocity.wgsl", 0),
+    ("portal.wgsl", 0),
+    ("impostor.wgsl", 0),
+    ("weather_draw.wgsl", 0),
+];
+
+// What moves the camera. Following, it stays on the cube's side: WASD walk
+// the cube and the camera's only moved by orbiting, chasing or the grid.
+// FirstPerson flies the camera itself, WASD along where it's looking and the
+// mouse (held by the window) turning it.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum CameraMode {
+    #[default]
+    Follow,
+    FirstPerson,
+}
 
 pub struct Camera {
-    pub eye: cgmath::Point3<f32>,
-    pub target: cgmath::Point3<f32>,
-    pub up: cgmath::Vector3<f32>,
+    pub eye: Point3,
+    pub target: Point3,
+    pub up: Vec3,
     pub aspect: f32,
-    pub fovy: f32,
+    pub fovy: Degrees,
     pub znear: f32,
     pub zfar: f32,
 }
 
-impl Camera {
+// where it starts, up and off to the side of the cube. init_gpu sets the
+// aspect to the window's
+impl Default for Camera {
     fn default() -> Self {
-        #[rustfmt::skip]
-        return Camera {
-            eye: cgmath::Point3 { x: 0.0, y: 0.0, z: 0.0, },
-            target: cgmath::Point3 { x: 0.0, y: 0.0, z: 0.0, },
-            up: cgmath::Vector3 { x: 0.0, y: 0.0, z: 0.0, },
-            aspect: 0.0,
-            fovy: 0.0,
-            znear: 0.0,
-            zfar: 0.0,
-        };
-    }
-
-    pub fn build_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
-        let view = cgmath::Matrix4::look_at_rh(self.eye, self.target, self.up);
-        let proj = cgmath::perspective(cgmath::Deg(self.fovy), self.aspect, self.znear, self.zfar);
+        Camera {
+            eye: Point3::new(8.4, 25.0, -8.4),
+            target: Point3::ZERO,
+            up: math::UP,
+            aspect: crate::WIDTH as f32 / crate::HEIGHT as f32,
+            fovy: Degrees(90.0),
+            znear: 0.1,
+            zfar: 100.0,
+        }
+    }
+}
+
+impl Camera {
+    // already in wgpu's clip space
+    pub fn build_view_projection_matrix(&self) -> Mat4 {
+        let view = math::look_at(self.eye, self.target, self.up);
+        let proj = math::perspective(self.fovy, self.aspect, self.znear, self.zfar);
         proj * view
     }
+
+    // Swings the eye around the target, `yaw` about up and `pitch` over it,
+    // keeping its distance.
+    pub fn orbit(&mut self, yaw: Degrees, pitch: Degrees) {
+        self.eye = swing(self.target, self.eye, yaw, pitch);
+    }
+
+    // Turns the view from where the eye is, the other way round from orbit.
+    pub fn look(&mut self, yaw: Degrees, pitch: Degrees) {
+        self.target = swing(self.eye, self.target, yaw, pitch);
+    }
+
+    // eye and target both, `forward` along where it's looking and `right` across it
+    pub fn fly(&mut self, forward: f32, right: f32) {
+        let ahead = (self.target - self.eye).normalize_or(-Vec3::Z);
+        let across = ahead.cross(self.up).normalize_or(Vec3::X);
+        let step = ahead * forward + across * right;
+        self.eye += step;
+        self.target += step;
+    }
+
+    // the eye `factor` times as far from the target, within reason
+    pub fn zoom(&mut self, factor: f32) {
+        let offset = self.eye - self.target;
+        let distance = (offset.length() * factor).clamp(MIN_DISTANCE, MAX_DISTANCE);
+        self.eye = self.target + offset.normalize_or(Vec3::Z) * distance;
+    }
+}
+
+// `point` carried around `center`, `yaw` about up and `pitch` over the top,
+// at the same distance
+fn swing(center: Point3, point: Point3, yaw: Degrees, pitch: Degrees) -> Point3 {
+    let offset = point - center;
+    let distance = offset.length();
+    if distance == 0.0 {
+        return point;
+    }
+    let yaw = offset.x.atan2(offset.z) + yaw.to_radians();
+    let pitch = ((offset.y / distance).asin() + pitch.to_radians())
+        .clamp(-MAX_PITCH.to_radians(), MAX_PITCH.to_radians());
+    center
+        + Vec3::new(
+            pitch.cos() * yaw.sin(),
+            pitch.sin(),
+            pitch.cos() * yaw.cos(),
+        ) * distance
+}
+
+// The six planes of a view_proj, facing in. Made from whatever matrix a view
+// is drawn with, so a mirrored or carried camera culls with the same code.
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    pub fn from_view_proj(m: Mat4) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|i| m.row(i));
+        // depth is 0..1, so the near plane is just z
+        let planes = [w + x, w - x, w + y, w - y, z, w - z].map(|p| p / p.truncate().length());
+        Self { planes }
+    }
+
+    // for shaders that cull themselves
+    pub fn planes(&self) -> [[f32; 4]; 6] {
+        self.planes.map(|p| p.to_array())
+    }
+
+    // conservative, a box near a corner can pass without being in view
+    pub fn intersects_box(&self, min: Vec3, max: Vec3) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner furthest along the plane's normal
+            let corner = Vec3::select(plane.truncate().cmpge(Vec3::ZERO), max, min);
+            plane.dot(corner.extend(1.0)) >= 0.0
+        })
+    }
 }
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct CameraUniform {
+    view_pos: [f32; 4],
     view_proj: [[f32; 4]; 4],
+    inv_view_proj: [[f32; 4]; 4],
 }
 
 impl CameraUniform {
     pub fn new() -> Self {
-        use cgmath::SquareMatrix;
         Self {
-            view_proj: cgmath::Matrix4::identity().into(),
+            view_pos: [0.0; 4],
+            view_proj: math::mat4_array(Mat4::IDENTITY),
+            inv_view_proj: math::mat4_array(Mat4::IDENTITY),
         }
     }
 
     pub fn update_view_proj(&mut self, camera: &Camera) {
-        self.view_proj = (OPENGL_TO_WGPU_MATRIX * camera.build_view_projection_matrix()).into();
+        self.set(camera.eye, camera.build_view_projection_matrix());
+    }
+
+    pub fn set(&mut self, eye: Point3, view_proj: Mat4) {
+        self.view_pos = math::point4_array(eye);
+        self.view_proj = math::mat4_array(view_proj);
+        self.inv_view_proj = math::mat4_array(view_proj.inverse());
     }
 }
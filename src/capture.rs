@@ -0,0 +1,91 @@
+use std::time::{Duration, Instant};
+
+// RenderDoc only hooks the process if it was launched from (or injected by)
+// the RenderDoc UI, so a missing library here is the normal case.
+pub struct FrameCapture {
+    #[cfg(feature = "renderdoc")]
+    renderdoc: Option<renderdoc::RenderDoc<renderdoc::V110>>,
+    pub slow_frame_threshold: Duration,
+    pub auto_capture_cooldown: Duration,
+    last_auto_capture: Option<Instant>,
+}
+
+impl FrameCapture {
+    pub fn new() -> Self {
+        #[cfg(feature = "renderdoc")]
+        let renderdoc = match renderdoc::RenderDoc::new() {
+            Ok(rd) => {
+                log::info!("renderdoc attached, press F10 to capture a frame");
+                Some(rd)
+            }
+            Err(e) => {
+                log::info!("renderdoc not available: {}", e);
+                None
+            }
+        };
+
+        Self {
+            #[cfg(feature = "renderdoc")]
+            renderdoc,
+            slow_frame_threshold: Duration::from_millis(50),
+            auto_capture_cooldown: Duration::from_secs(5),
+            last_auto_capture: None,
+        }
+    }
+
+    // captures whatever frame gets presented next, false if nothing can
+    pub fn trigger(&mut self) -> bool {
+        #[cfg(feature = "renderdoc")]
+        {
+            match self.renderdoc.as_mut() {
+                Some(rd) => {
+                    rd.trigger_capture();
+                    log::info!("renderdoc capture triggered");
+                    true
+                }
+                None => {
+                    log::warn!("renderdoc capture requested but renderdoc is not attached");
+                    false
+                }
+            }
+        }
+        #[cfg(not(feature = "renderdoc"))]
+        {
+            log::warn!(
+                "renderdoc capture requested but praxis was built without the `renderdoc` feature"
+            );
+            false
+        }
+    }
+
+    // a hitch can't be captured after the fact, so the frame following a slow one
+    // is captured instead; it usually shows the same state that caused the spike
+    pub fn frame_finished(&mut self, frame_time: Duration) {
+        if !self.is_attached() || frame_time < self.slow_frame_threshold {
+            return;
+        }
+        let now = Instant::now();
+        if let Some(last) = self.last_auto_capture {
+            if now - last < self.auto_capture_cooldown {
+                return;
+            }
+        }
+        self.last_auto_capture = Some(now);
+        log::warn!(
+            "slow frame ({:.1} ms), capturing next frame",
+            frame_time.as_secs_f64() * 1000.0
+        );
+        self.trigger();
+    }
+
+    fn is_attached(&self) -> bool {
+        #[cfg(feature = "renderdoc")]
+        {
+            self.renderdoc.is_some()
+        }
+        #[cfg(not(feature = "renderdoc"))]
+        {
+            false
+        }
+    }
+}
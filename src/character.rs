@@ -0,0 +1,119 @@
+use crate::floor::FLOOR_Y;
+use crate::math::{Degrees, Point3, Vec3};
+use crate::physics::{Layers, World};
+
+// the player is swept as a ball the cube's half size across
+const RADIUS: f32 = 1.0;
+// kept between the ball and whatever it's up against, so the next cast
+// doesn't start touching it
+const SKIN: f32 = 0.01;
+// how far under the ball still counts as standing on something
+const GROUND_PROBE: f32 = 0.05;
+const GRAVITY: f32 = 20.0;
+// goes round the slide this many times before giving up on the rest of a move
+const SLIDES: usize = 3;
+
+// Kinematic movement for the player cube. A move is swept through the cubes
+// and slides along whatever it runs into. Surfaces up to `slope_limit` can
+// be walked up, steeper ones are walls, and a ledge up to `step_height`
+// high is stepped onto rather than stopping the move. Off the ground the
+// cube falls.
+#[derive(Clone)]
+pub struct CharacterController {
+    pub slope_limit: Degrees,
+    pub step_height: f32,
+    pub grounded: bool,
+    fall_speed: f32,
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        Self {
+            slope_limit: Degrees(45.0),
+            step_height: 0.6,
+            grounded: true,
+            fall_speed: 0.0,
+        }
+    }
+}
+
+impl CharacterController {
+    // Where the cube ends up after trying to go `wanted` this frame (only x
+    // and z are used, falling is up to the controller). `mask` is what it
+    // can't go through.
+    pub fn step(
+        &mut self,
+        dt: f32,
+        position: Point3,
+        wanted: Vec3,
+        world: World,
+        mask: Layers,
+    ) -> Point3 {
+        let wanted = Vec3::new(wanted.x, 0.0, wanted.z);
+        let start = position;
+        let mut position = self.slide(position, wanted, world, mask);
+
+        // Blocked on the ground: try the move again from step_height up and
+        // settle back down, keeping that if it got further.
+        let short = wanted.length() - (position - start).with_y(0.0).length();
+        if self.grounded && short > SKIN && wanted.length() > 0.0 {
+            let lifted = self.slide(position, Vec3::Y * self.step_height, world, mask);
+            let across = self.slide(lifted, wanted, world, mask);
+            let landed = self.slide(across, -Vec3::Y * self.step_height, world, mask);
+            let further = (across - lifted).with_y(0.0).length() > SKIN;
+            if further && self.standing(landed, world, mask) {
+                position = landed;
+            }
+        }
+
+        if self.grounded {
+            self.fall_speed = 0.0;
+        } else {
+            self.fall_speed += GRAVITY * dt;
+        }
+        // a little down even on the ground, to follow it down slopes
+        let drop = self.fall_speed * dt + GROUND_PROBE;
+        position = self.slide(position, -Vec3::Y * drop, world, mask);
+        self.grounded = self.standing(position, world, mask);
+        position
+    }
+
+    // Moves the ball by `delta`, stopping short of anything in the way and
+    // sliding along it with what's left. Nothing can be climbed that's
+    // steeper than the slope limit.
+    fn slide(&self, mut position: Point3, mut delta: Vec3, world: World, mask: Layers) -> Point3 {
+        for _ in 0..SLIDES {
+            let length = delta.length();
+            if length <= f32::EPSILON {
+                break;
+            }
+            let direction = delta / length;
+            let Some(hit) = world.sphere_cast(position, RADIUS, direction, length + SKIN, mask)
+            else {
+                position += delta;
+                break;
+            };
+            let travel = (hit.distance - SKIN).max(0.0);
+            position += direction * travel;
+            delta -= direction * travel;
+            delta -= hit.normal * delta.dot(hit.normal);
+            if !self.walkable(hit.normal) {
+                delta.y = delta.y.min(0.0);
+            }
+        }
+        position.y = position.y.max(FLOOR_Y + RADIUS);
+        position
+    }
+
+    fn walkable(&self, normal: Vec3) -> bool {
+        normal.y >= self.slope_limit.to_radians().cos()
+    }
+
+    // on the floor, or just above something flat enough to stand on
+    fn standing(&self, position: Point3, world: World, mask: Layers) -> bool {
+        position.y <= FLOOR_Y + RADIUS + GROUND_PROBE
+            || world
+                .sphere_cast(position, RADIUS, -Vec3::Y, GROUND_PROBE + SKIN, mask)
+                .is_some_and(|hit| self.walkable(hit.normal))
+    }
+}
@@ -0,0 +1,62 @@
+use crate::math::{Point3, Vec3};
+use crate::physics::{Layers, World};
+
+// how big a ball the eye is, kept this far off anything it would clip into
+const PROBE_RADIUS: f32 = 0.3;
+// rates the springs close in at, per second
+const FOLLOW_RATE: f32 = 10.0;
+const SHORTEN_RATE: f32 = 25.0;
+const LENGTHEN_RATE: f32 = 3.0;
+// what the arm pulls in for, triggers and projectiles pass through it
+pub const OBSTACLES: Layers = Layers::PLAYER.union(Layers::ENEMY).union(Layers::STATIC);
+
+// Locks the camera onto a cube on the end of a spring arm. The eye keeps
+// the offset it had when the lock started, the pivot eases after the cube,
+// and a ball the size of the eye is cast from the pivot out along the arm
+// so the arm pulls in quickly when a cube or the ground is in the way and
+// lets back out slowly once it's clear.
+#[derive(Default)]
+pub struct ChaseCamera {
+    // index into the cubes, None when the camera's free
+    pub target: Option<usize>,
+    // from the pivot to the eye at full length
+    arm: Vec3,
+    // how much of the arm is out right now
+    length: f32,
+    pivot: Point3,
+}
+
+impl ChaseCamera {
+    pub fn lock(&mut self, target: usize, at: Point3, eye: Point3) {
+        self.target = Some(target);
+        self.arm = eye - at;
+        self.length = self.arm.length();
+        self.pivot = at;
+    }
+
+    // The eye and what it looks at this frame. `world` should leave out the
+    // target itself.
+    pub fn update(&mut self, dt: f32, at: Point3, world: World) -> (Point3, Point3) {
+        self.pivot += (at - self.pivot) * (1.0 - (-FOLLOW_RATE * dt).exp());
+
+        let full = self.arm.length();
+        let direction = self.arm.normalize_or_zero();
+        // a cube the pivot is already in can't be got out of along the arm,
+        // and the cast goes through those
+        let mut clear = world
+            .sphere_cast(self.pivot, PROBE_RADIUS, direction, full, OBSTACLES)
+            .map_or(full, |hit| hit.distance);
+        let ground = crate::floor::FLOOR_Y + PROBE_RADIUS;
+        if direction.y < 0.0 && self.pivot.y > ground {
+            clear = clear.min((ground - self.pivot.y) / direction.y);
+        }
+
+        let rate = if clear < self.length {
+            SHORTEN_RATE
+        } else {
+            LENGTHEN_RATE
+        };
+        self.length += (clear - self.length) * (1.0 - (-rate * dt).exp());
+        (self.pivot + direction * self.length, self.pivot)
+    }
+}
@@ -0,0 +1,160 @@
+use crate::math::{Mat4, Point3};
+use std::time::{Duration, Instant};
+use wgpu_text::glyph_brush::{
+    HorizontalAlign, Layout, OwnedSection, Section as TextSection, Text, VerticalAlign,
+};
+use winit::event::{ElementState, KeyEvent, WindowEvent};
+use winit::keyboard::{Key, NamedKey};
+
+const MAX_LOG: usize = 8;
+// how long a message stays up once the chat's closed, and how long it takes to go
+const SHOWN: Duration = Duration::from_secs(10);
+const FADE_SECONDS: f32 = 1.0;
+const LINE_HEIGHT: f32 = 22.0;
+// how far over a cube's middle its name goes
+const TAG_HEIGHT: f32 = 1.6;
+
+// Talking to everyone else on the server. Enter opens a line at the bottom
+// left and Enter again sends it, through the server so it comes back to
+// everyone with the sender's name on. Messages stay a while and fade, and
+// are all back up whenever the line's open. Like the console, while it's
+// open it takes every key.
+#[derive(Default)]
+pub struct Chat {
+    pub open: bool,
+    line: String,
+    log: Vec<(Instant, String)>,
+}
+
+impl Chat {
+    // Returns whether the event was used, and the line when Enter finished one.
+    pub fn process_events(&mut self, event: &WindowEvent) -> (bool, Option<String>) {
+        let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    logical_key,
+                    ..
+                },
+            ..
+        } = event
+        else {
+            return (false, None);
+        };
+        match logical_key {
+            Key::Named(NamedKey::Enter) if !self.open => {
+                self.open = true;
+                (true, None)
+            }
+            _ if !self.open => (false, None),
+            Key::Named(NamedKey::Escape) => {
+                self.open = false;
+                self.line.clear();
+                (true, None)
+            }
+            Key::Named(NamedKey::Enter) => {
+                self.open = false;
+                let line = std::mem::take(&mut self.line);
+                let line = line.trim();
+                (true, (!line.is_empty()).then(|| line.to_string()))
+            }
+            Key::Named(NamedKey::Backspace) => {
+                self.line.pop();
+                (true, None)
+            }
+            Key::Named(NamedKey::Space) => {
+                self.line.push(' ');
+                (true, None)
+            }
+            Key::Character(c) => {
+                self.line += c.as_str();
+                (true, None)
+            }
+            _ => (true, None),
+        }
+    }
+
+    // `from` is empty for the server's own notices
+    pub fn receive(&mut self, from: &str, text: &str) {
+        let line = if from.is_empty() {
+            format!("* {}", text)
+        } else {
+            format!("<{}> {}", from, text)
+        };
+        log::info!("chat: {}", line);
+        self.log.push((Instant::now(), line));
+        if self.log.len() > MAX_LOG {
+            self.log.remove(0);
+        }
+    }
+
+    // bottom left, where the console goes, so not while it's open
+    pub fn sections(&self, height: f32) -> Vec<OwnedSection> {
+        let mut lines = self
+            .log
+            .iter()
+            .filter_map(|(at, line)| {
+                let left = SHOWN.as_secs_f32() - at.elapsed().as_secs_f32();
+                let alpha = if self.open {
+                    1.0
+                } else {
+                    (left / FADE_SECONDS).min(1.0)
+                };
+                (alpha > 0.0).then_some((line.clone(), [0.8, 1.0, 0.85, alpha]))
+            })
+            .collect::<Vec<_>>();
+        if self.open {
+            lines.push((format!("say: {}_", self.line), [1.0, 1.0, 1.0, 1.0]));
+        }
+        lines
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, (line, color))| {
+                TextSection::default()
+                    .add_text(Text::new(line).with_color(*color).with_scale(18.0))
+                    .with_screen_position((10.0, height - 40.0 - i as f32 * LINE_HEIGHT))
+                    .with_layout(Layout::default().v_align(VerticalAlign::Bottom))
+                    .to_owned()
+            })
+            .collect()
+    }
+}
+
+// names over the other players' cubes, left out when they're behind the camera
+pub fn name_tags<'a>(
+    view_proj: Mat4,
+    players: impl Iterator<Item = (Point3, &'a str)>,
+    width: f32,
+    height: f32,
+) -> Vec<OwnedSection> {
+    players
+        .filter_map(|(position, name)| {
+            let clip = view_proj * (position + crate::math::UP * TAG_HEIGHT).extend(1.0);
+            if clip.w <= 0.0 {
+                return None;
+            }
+            let ndc = clip.truncate() / clip.w;
+            if ndc.x.abs() > 1.0 || ndc.y.abs() > 1.0 {
+                return None;
+            }
+            let x = (ndc.x + 1.0) * 0.5 * width;
+            let y = (1.0 - ndc.y) * 0.5 * height;
+            Some(
+                TextSection::default()
+                    .add_text(
+                        Text::new(name)
+                            .with_color([1.0, 1.0, 1.0, 0.9])
+                            .with_scale(16.0),
+                    )
+                    .with_screen_position((x, y))
+                    .with_layout(
+                        Layout::default()
+                            .h_align(HorizontalAlign::Center)
+                            .v_align(VerticalAlign::Bottom),
+                    )
+                    .to_owned(),
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,61 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+const SECONDS: f64 = 5.0;
+const FPS: f64 = 10.0;
+// frames are scaled down to this wide as they come in
+const WIDTH: u32 = 320;
+
+// The last few seconds of frames, small and already quantized, so a clip
+// of whatever just happened can be written out as a GIF at any moment.
+// A frame is only read back from the GPU every 1/FPS seconds.
+#[derive(Default)]
+pub struct ClipRing {
+    frames: VecDeque<Vec<u8>>,
+    size: (u32, u32),
+    // timer seconds of the newest frame
+    last: f64,
+}
+
+impl ClipRing {
+    pub fn wants_frame(&self, now: f64) -> bool {
+        now - self.last >= 1.0 / FPS
+    }
+
+    pub fn push(&mut self, frame: &image::RgbaImage, now: f64) {
+        self.last = now;
+        let height = (frame.height() * WIDTH / frame.width().max(1)).max(1);
+        if self.size != (WIDTH, height) {
+            // the window changed size, which the older frames can't follow
+            self.frames.clear();
+            self.size = (WIDTH, height);
+        }
+        let small =
+            image::imageops::resize(frame, WIDTH, height, image::imageops::FilterType::Triangle);
+        self.frames.push_back(crate::gif::quantize(&small));
+        while self.frames.len() > (SECONDS * FPS) as usize {
+            self.frames.pop_front();
+        }
+    }
+
+    // writes what's in the ring to clips/ in the data dir, giving back the file
+    pub fn save(&self) -> anyhow::Result<PathBuf> {
+        if self.frames.is_empty() {
+            anyhow::bail!("nothing recorded yet");
+        }
+        let dir = crate::dirs::data_dir().join("clips");
+        std::fs::create_dir_all(&dir)?;
+        let path = (1..)
+            .map(|i| dir.join(format!("clip {}.gif", i)))
+            .find(|path| !path.exists())
+            .unwrap();
+        let gif = crate::gif::encode(
+            self.size.0 as u16,
+            self.size.1 as u16,
+            &self.frames,
+            (100.0 / FPS) as u16,
+        );
+        std::fs::write(&path, gif)?;
+        Ok(path)
+    }
+}
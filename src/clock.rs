@@ -1,13 +1,42 @@
+// Game time for the session: milliseconds and updates since it started (or
+// since `clock reset` in the console). The timer keeps running for the shaders,
+// this one is only for showing.
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct ClockBuffer {
     ms: f32,
+    tick: u32,
 }
 impl ClockBuffer {
     pub fn new() -> ClockBuffer {
-        ClockBuffer { ms: 0.0 }
+        ClockBuffer { ms: 0.0, tick: 0 }
     }
+    // one simulation update that took `delta` milliseconds
     pub fn update(&mut self, delta: f32) {
         self.ms += delta;
+        self.tick += 1;
+    }
+
+    pub fn seconds(&self) -> f32 {
+        self.ms / 1000.0
+    }
+
+    // hh:mm:ss.s and the tick, for the HUD
+    pub fn text(&self) -> String {
+        let tenths = (self.ms / 100.0) as u64;
+        format!(
+            "{:02}:{:02}:{:02}.{}  tick {}",
+            tenths / 36000,
+            tenths / 600 % 60,
+            tenths / 10 % 60,
+            tenths % 10,
+            self.tick
+        )
+    }
+}
+
+impl Default for ClockBuffer {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -0,0 +1,277 @@
+use wgpu::util::DeviceExt;
+
+const GRID: u32 = 32;
+const SIZE: f32 = 5.0;
+const DROP_HEIGHT: f32 = 2.5;
+// odd, so the last pass writes back into the buffer that gets drawn
+const CONSTRAINT_PASSES: u32 = 15;
+const DT: f32 = 1.0 / 60.0;
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct Particle {
+    position: [f32; 4],
+    previous: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SimUniform {
+    cube_center: [f32; 3],
+    dt: f32,
+    rest_length: f32,
+    grid: u32,
+    _padding: [u32; 2],
+}
+
+// A square of cloth simulated on the GPU and dropped onto a cube. The
+// particles live in two storage buffers that the compute passes ping-pong
+// between, and the draw pipeline reads positions straight out of them.
+pub struct Cloth {
+    particles: [wgpu::Buffer; 2],
+    sim_buffer: wgpu::Buffer,
+    // 0 reads particles[0] and writes particles[1], 1 the other way round
+    compute_bind_groups: [wgpu::BindGroup; 2],
+    integrate_pipeline: wgpu::ComputePipeline,
+    constrain_pipeline: wgpu::ComputePipeline,
+
+    draw_bind_group: wgpu::BindGroup,
+    draw_pipeline: wgpu::RenderPipeline,
+    index_buffer: wgpu::Buffer,
+    index_format: wgpu::IndexFormat,
+    num_indices: u32,
+}
+
+impl Cloth {
+    pub fn new(
+        device: &wgpu::Device,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let particles = [0, 1].map(|_| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("cloth particle buffer"),
+                size: (GRID * GRID) as u64 * std::mem::size_of::<Particle>() as u64,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+        let sim_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("cloth sim buffer"),
+            contents: bytemuck::cast_slice(&[SimUniform {
+                cube_center: [0.0; 3],
+                dt: DT,
+                rest_length: SIZE / (GRID - 1) as f32,
+                grid: GRID,
+                _padding: [0; 2],
+            }]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        ///// simulation
+        let compute_bind_group_layout = crate::reflect::bind_group_layout(
+            device,
+            "cloth compute bind group layout",
+            &[("cloth.wgsl", 0)],
+        )
+        .unwrap();
+        let compute_bind_groups = [0, 1].map(|from| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &compute_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: sim_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: particles[from].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: particles[1 - from].as_entire_binding(),
+                    },
+                ],
+                label: Some("cloth compute bind group"),
+            })
+        });
+        let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("cloth compute shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                crate::shaders::load("cloth.wgsl", &[]).unwrap().into(),
+            ),
+        });
+        let compute_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("cloth compute pipeline layout"),
+                bind_group_layouts: &[&compute_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let compute_pipeline = |label, entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&compute_pipeline_layout),
+                module: &compute_shader,
+                entry_point,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: crate::pipeline_cache::current().as_deref(),
+            })
+        };
+        let integrate_pipeline = compute_pipeline("cloth integrate pipeline", "integrate");
+        let constrain_pipeline = compute_pipeline("cloth constrain pipeline", "constrain");
+
+        ///// drawing
+        let draw_bind_group_layout = crate::reflect::bind_group_layout(
+            device,
+            "cloth draw bind group layout",
+            &[("cloth_draw.wgsl", 1)],
+        )
+        .unwrap();
+        let draw_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &draw_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: particles[0].as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: sim_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("cloth draw bind group"),
+        });
+        let draw_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("cloth draw shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                crate::shaders::load("cloth_draw.wgsl", &[]).unwrap().into(),
+            ),
+        });
+        let draw_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("cloth draw pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &draw_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let draw_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("cloth render pipeline"),
+            layout: Some(&draw_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &draw_shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &draw_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(crate::texture::Texture::depth_state(
+                true,
+                wgpu::CompareFunction::Less,
+            )),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: crate::pipeline_cache::current().as_deref(),
+        });
+
+        let indices = (0..GRID - 1)
+            .flat_map(|y| {
+                (0..GRID - 1).flat_map(move |x| {
+                    let i = y * GRID + x;
+                    [i, i + GRID, i + 1, i + 1, i + GRID, i + GRID + 1]
+                })
+            })
+            .collect::<Vec<u32>>();
+        let (index_buffer, index_format) =
+            crate::vertex::create_index_buffer(device, "cloth index buffer", &indices);
+
+        Self {
+            particles,
+            sim_buffer,
+            compute_bind_groups,
+            integrate_pipeline,
+            constrain_pipeline,
+            draw_bind_group,
+            draw_pipeline,
+            index_buffer,
+            index_format,
+            num_indices: indices.len() as u32,
+        }
+    }
+
+    // lays the cloth out flat and at rest above `center`
+    pub fn drop_over(&self, queue: &wgpu::Queue, center: crate::math::Vec3) {
+        let spacing = SIZE / (GRID - 1) as f32;
+        let particles = (0..GRID * GRID)
+            .map(|i| {
+                let position = [
+                    center.x - SIZE / 2.0 + (i % GRID) as f32 * spacing,
+                    center.y + DROP_HEIGHT,
+                    center.z - SIZE / 2.0 + (i / GRID) as f32 * spacing,
+                    1.0,
+                ];
+                Particle {
+                    position,
+                    previous: position,
+                }
+            })
+            .collect::<Vec<_>>();
+        queue.write_buffer(&self.particles[0], 0, bytemuck::cast_slice(&particles));
+    }
+
+    // advances the simulation by one fixed step, colliding with the cube at `cube_center`
+    pub fn step(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        queue: &wgpu::Queue,
+        cube_center: crate::math::Vec3,
+    ) {
+        queue.write_buffer(
+            &self.sim_buffer,
+            0,
+            bytemuck::cast_slice(&crate::math::vec3_array(cube_center)),
+        );
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("cloth pass"),
+            timestamp_writes: None,
+        });
+        let workgroups = (GRID * GRID).div_ceil(64);
+        compute_pass.set_pipeline(&self.integrate_pipeline);
+        compute_pass.set_bind_group(0, &self.compute_bind_groups[0], &[]);
+        compute_pass.dispatch_workgroups(workgroups, 1, 1);
+        compute_pass.set_pipeline(&self.constrain_pipeline);
+        for pass in 0..CONSTRAINT_PASSES {
+            let from = (pass as usize + 1) % 2;
+            compute_pass.set_bind_group(0, &self.compute_bind_groups[from], &[]);
+            compute_pass.dispatch_workgroups(workgroups, 1, 1);
+        }
+    }
+
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass, camera_bind_group: &wgpu::BindGroup) {
+        render_pass.set_pipeline(&self.draw_pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.draw_bind_group, &[]);
+        render_pass.set_index_buffer(self.index_buffer.slice(..), self.index_format);
+        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+    }
+}
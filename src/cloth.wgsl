@@ -0,0 +1,95 @@
+// Verlet cloth, one invocation per particle. `integrate` runs once a frame,
+// then `constrain` runs a few times, ping-ponging between the two buffers so
+// every invocation only reads positions from the previous pass.
+
+struct Particle {
+    position: vec4<f32>,
+    previous: vec4<f32>,
+}
+
+struct Sim {
+    cube_center: vec3<f32>,
+    dt: f32,
+    rest_length: f32,
+    grid: u32,
+}
+@group(0) @binding(0)
+var<uniform> sim: Sim;
+@group(0) @binding(1)
+var<storage, read> src: array<Particle>;
+@group(0) @binding(2)
+var<storage, read_write> dst: array<Particle>;
+
+const GRAVITY: vec3<f32> = vec3<f32>(0.0, -9.8, 0.0);
+const DAMPING: f32 = 0.99;
+// a bit bigger than the cube so the cloth doesn't clip into its faces
+const CUBE_HALF_SIZE: f32 = 1.05;
+const FRICTION: f32 = 0.6;
+
+@compute @workgroup_size(64)
+fn integrate(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= sim.grid * sim.grid {
+        return;
+    }
+    let p = src[i];
+    let velocity = (p.position.xyz - p.previous.xyz) * DAMPING;
+    let position = p.position.xyz + velocity + GRAVITY * sim.dt * sim.dt;
+    dst[i] = Particle(vec4<f32>(position, 1.0), p.position);
+}
+
+fn correction(position: vec3<f32>, x: i32, y: i32, rest: f32) -> vec3<f32> {
+    let grid = i32(sim.grid);
+    if x < 0 || y < 0 || x >= grid || y >= grid {
+        return vec3<f32>(0.0);
+    }
+    let delta = src[y * grid + x].position.xyz - position;
+    let distance = length(delta);
+    if distance == 0.0 {
+        return vec3<f32>(0.0);
+    }
+    return delta * (distance - rest) / distance;
+}
+
+@compute @workgroup_size(64)
+fn constrain(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= sim.grid * sim.grid {
+        return;
+    }
+    let x = i32(i % sim.grid);
+    let y = i32(i / sim.grid);
+    let p = src[i];
+    var position = p.position.xyz;
+    var previous = p.previous.xyz;
+
+    // structural then shear springs. Each side of a spring moves half way,
+    // averaged over the neighbours and over relaxed a little to converge faster.
+    let rest = sim.rest_length;
+    let diagonal = rest * sqrt(2.0);
+    var total = correction(position, x - 1, y, rest)
+        + correction(position, x + 1, y, rest)
+        + correction(position, x, y - 1, rest)
+        + correction(position, x, y + 1, rest)
+        + correction(position, x - 1, y - 1, diagonal)
+        + correction(position, x + 1, y - 1, diagonal)
+        + correction(position, x - 1, y + 1, diagonal)
+        + correction(position, x + 1, y + 1, diagonal);
+    position += total * 0.5 / 8.0 * 1.5;
+
+    // push out of the cube through the closest face
+    let local = position - sim.cube_center;
+    let depth = vec3<f32>(CUBE_HALF_SIZE) - abs(local);
+    if all(depth > vec3<f32>(0.0)) {
+        if depth.x < depth.y && depth.x < depth.z {
+            position.x += sign(local.x) * depth.x;
+        } else if depth.y < depth.z {
+            position.y += sign(local.y) * depth.y;
+        } else {
+            position.z += sign(local.z) * depth.z;
+        }
+        previous = mix(previous, position, FRICTION);
+    }
+
+    dst[i] = Particle(vec4<f32>(position, 1.0), vec4<f32>(previous, 1.0));
+}
@@ -0,0 +1,57 @@
+#define CAMERA_GROUP 0
+#include "common.wgsl"
+
+struct Particle {
+    position: vec4<f32>,
+    previous: vec4<f32>,
+}
+@group(1) @binding(0)
+var<storage, read> particles: array<Particle>;
+
+struct Sim {
+    cube_center: vec3<f32>,
+    dt: f32,
+    rest_length: f32,
+    grid: u32,
+}
+@group(1) @binding(1)
+var<uniform> sim: Sim;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+}
+
+fn particle(x: i32, y: i32) -> vec3<f32> {
+    let last = i32(sim.grid) - 1;
+    return particles[clamp(y, 0, last) * i32(sim.grid) + clamp(x, 0, last)].position.xyz;
+}
+
+// no vertex buffer, positions are pulled straight out of the simulation
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let x = i32(index % sim.grid);
+    let y = i32(index / sim.grid);
+    let position = particle(x, y);
+    let across = particle(x + 1, y) - particle(x - 1, y);
+    let down = particle(x, y + 1) - particle(x, y - 1);
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(position, 1.0);
+    out.normal = normalize(cross(down, across));
+    out.uv = vec2<f32>(f32(x), f32(y)) / f32(sim.grid - 1u);
+    return out;
+}
+
+const LIGHT_DIR: vec3<f32> = vec3<f32>(0.3, 0.9, -0.3);
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // checkers make the folds easier to see
+    let check = (u32(in.uv.x * 8.0) + u32(in.uv.y * 8.0)) % 2u;
+    let albedo = select(vec3<f32>(0.8, 0.2, 0.25), vec3<f32>(0.9, 0.85, 0.8), check == 0u);
+    // both sides of the cloth are visible
+    let diffuse = abs(dot(normalize(in.normal), normalize(LIGHT_DIR)));
+    return vec4<f32>(albedo * (0.25 + 0.75 * diffuse), 1.0);
+}
@@ -0,0 +1,73 @@
+// Declarations shared between shaders, pulled in with `#include "common.wgsl"`.
+// `#define CAMERA_GROUP 1` (and so on) before the include says which group a
+// binding lives in, bindings without a group are left out.
+
+struct Camera {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_view_proj: mat4x4<f32>,
+}
+#ifdef CAMERA_GROUP
+@group(CAMERA_GROUP) @binding(0)
+var<uniform> camera: Camera;
+#endif
+
+struct Timer {
+    t: f32,
+}
+#ifdef TIMER_GROUP
+@group(TIMER_GROUP) @binding(0)
+var<uniform> timer: Timer;
+#endif
+
+// one non-instanced object from the per frame ObjectArena, see objects.rs.
+// Read through current_object(), which is the slice bound at a dynamic
+// offset, or with PUSH_CONSTANTS the one the draw's push constants pick.
+struct Object {
+    model: mat4x4<f32>,
+    tint: vec4<f32>,
+}
+#ifdef OBJECT_GROUP
+#ifdef PUSH_CONSTANTS
+struct Draw {
+    object: u32,
+    flags: u32,
+}
+var<push_constant> draw: Draw;
+@group(OBJECT_GROUP) @binding(0)
+var<storage, read> objects: array<Object>;
+fn current_object() -> Object {
+    return objects[draw.object];
+}
+#else
+@group(OBJECT_GROUP) @binding(0)
+var<uniform> object: Object;
+fn current_object() -> Object {
+    return object;
+}
+#endif
+#endif
+
+// the per instance attributes from InstanceRaw
+#ifdef INSTANCED
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+    @location(9) flash: f32,
+    // layer of the cube material's diffuse array
+    @location(10) skin: u32,
+    // timer time a streamed cube started fading in, negative for out
+    @location(15) fade: f32,
+}
+
+fn model_matrix(instance: InstanceInput) -> mat4x4<f32> {
+    return mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+}
+#endif
@@ -0,0 +1,254 @@
+use wgpu_text::glyph_brush::{Layout, OwnedSection, Section as TextSection, Text, VerticalAlign};
+use winit::event::{ElementState, KeyEvent, WindowEvent};
+use winit::keyboard::{Key, NamedKey};
+
+const MAX_OUTPUT: usize = 8;
+// lines kept between runs
+const MAX_HISTORY: usize = 100;
+// every command run_command knows, for tab to complete
+const COMMANDS: &[&str] = &[
+    "bind",
+    "boids",
+    "buffer",
+    "chase",
+    "clock",
+    "collide",
+    "crt",
+    "diff",
+    "grid",
+    "help",
+    "ignore",
+    "impostors",
+    "interp",
+    "joint",
+    "joints",
+    "layer",
+    "light",
+    "mirror",
+    "music",
+    "portals",
+    "profile",
+    "quality",
+    "record",
+    "skin",
+    "snapshot",
+    "snapshots",
+    "sneak",
+    "spawn",
+    "sprint",
+    "sprite",
+    "sprites",
+    "stream",
+    "texture",
+    "textures",
+    "tilemap",
+    "tweak",
+    "visualizer",
+    "weather",
+];
+
+// A one line command prompt opened with `. While it's open it takes every key,
+// finished lines come back out of `process_events` for the app to run. Tab
+// finishes a command's name, up and down go back through the lines run
+// before, this run or earlier ones.
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    line: String,
+    output: Vec<String>,
+    // everything printed since start_capture, for a command run from elsewhere
+    captured: Option<Vec<String>>,
+    // oldest first
+    history: Vec<String>,
+    // which of the history is in the line, while going through it
+    browsing: Option<usize>,
+}
+
+impl Console {
+    // Returns whether the event was used, and the line when Enter finished one.
+    pub fn process_events(&mut self, event: &WindowEvent) -> (bool, Option<String>) {
+        let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    logical_key,
+                    ..
+                },
+            ..
+        } = event
+        else {
+            return (false, None);
+        };
+        match logical_key {
+            Key::Character(c) if c.as_str() == "`" => {
+                self.open = !self.open;
+                (true, None)
+            }
+            _ if !self.open => (false, None),
+            Key::Named(NamedKey::Escape) => {
+                self.open = false;
+                (true, None)
+            }
+            Key::Named(NamedKey::Enter) => {
+                let line = std::mem::take(&mut self.line);
+                self.browsing = None;
+                if line.trim().is_empty() {
+                    return (true, None);
+                }
+                self.print(format!("> {}", line));
+                self.remember(&line);
+                (true, Some(line))
+            }
+            Key::Named(NamedKey::Tab) => {
+                self.complete();
+                (true, None)
+            }
+            Key::Named(NamedKey::ArrowUp) => {
+                self.browse(-1);
+                (true, None)
+            }
+            Key::Named(NamedKey::ArrowDown) => {
+                self.browse(1);
+                (true, None)
+            }
+            Key::Named(NamedKey::Backspace) => {
+                self.line.pop();
+                self.browsing = None;
+                (true, None)
+            }
+            Key::Named(NamedKey::Space) => {
+                self.line.push(' ');
+                self.browsing = None;
+                (true, None)
+            }
+            Key::Character(c) => {
+                self.line += c.as_str();
+                self.browsing = None;
+                (true, None)
+            }
+            _ => (true, None),
+        }
+    }
+
+    // the first word as far as every command it could be agrees, the
+    // choices printed when there's more than one
+    fn complete(&mut self) {
+        if self.line.contains(' ') {
+            return;
+        }
+        let matches = COMMANDS
+            .iter()
+            .filter(|c| c.starts_with(self.line.as_str()))
+            .collect::<Vec<_>>();
+        match matches[..] {
+            [] => {}
+            [only] => self.line = format!("{} ", only),
+            _ => {
+                let first = matches[0];
+                let common = (self.line.len()..=first.len())
+                    .rev()
+                    .find(|n| matches.iter().all(|m| m.starts_with(&first[..*n])))
+                    .unwrap_or(self.line.len());
+                self.line = first[..common].to_string();
+                let choices = matches.iter().map(|m| **m).collect::<Vec<_>>().join("  ");
+                self.print(choices);
+            }
+        }
+    }
+
+    // `step` back (-1) or forward (1) through the history, past the newest
+    // is an empty line again
+    fn browse(&mut self, step: isize) {
+        if self.history.is_empty() {
+            return;
+        }
+        let next = match self.browsing {
+            None if step < 0 => Some(self.history.len() - 1),
+            None => return,
+            Some(i) => i
+                .checked_add_signed(step)
+                .map(|i| i.min(self.history.len()))
+                .or(Some(0)),
+        };
+        self.browsing = next.filter(|i| *i < self.history.len());
+        self.line = self
+            .browsing
+            .map(|i| self.history[i].clone())
+            .unwrap_or_default();
+    }
+
+    // the history from earlier runs, once at startup
+    pub fn load_history(&mut self) {
+        if let Ok(text) = std::fs::read_to_string(history_path()) {
+            self.history = text.lines().map(str::to_string).collect();
+        }
+    }
+
+    // written out after every line, so a crash doesn't lose it
+    fn remember(&mut self, line: &str) {
+        if self.history.last().is_some_and(|last| last == line) {
+            return;
+        }
+        self.history.push(line.to_string());
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+        let path = history_path();
+        let written = std::fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| std::fs::write(&path, self.history.join("\n") + "\n"));
+        if let Err(e) = written {
+            log::warn!(
+                "couldn't save the console history to {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+
+    pub fn print(&mut self, line: impl Into<String>) {
+        let line = line.into();
+        log::info!("console: {}", line);
+        if let Some(captured) = self.captured.as_mut() {
+            captured.push(line.clone());
+        }
+        self.output.push(line);
+        if self.output.len() > MAX_OUTPUT {
+            self.output.remove(0);
+        }
+    }
+
+    // for the HTTP API
+    #[cfg(feature = "http")]
+    pub fn start_capture(&mut self) {
+        self.captured = Some(Vec::new());
+    }
+
+    #[cfg(feature = "http")]
+    pub fn end_capture(&mut self) -> Vec<String> {
+        self.captured.take().unwrap_or_default()
+    }
+
+    // sits at the bottom of the screen, just above the clock
+    pub fn section(&self, height: f32) -> Option<OwnedSection> {
+        if !self.open {
+            return None;
+        }
+        let mut text = self.output.join("\n");
+        text += &format!("\n] {}_", self.line);
+        Some(
+            TextSection::default()
+                .add_text(
+                    Text::new(&text)
+                        .with_color([1.0, 1.0, 0.8, 1.0])
+                        .with_scale(18.0),
+                )
+                .with_screen_position((10.0, height - 40.0))
+                .with_layout(Layout::default().v_align(VerticalAlign::Bottom))
+                .to_owned(),
+        )
+    }
+}
+
+fn history_path() -> std::path::PathBuf {
+    crate::dirs::data_dir().join("console_history")
+}
@@ -1,16 +1,48 @@
+use crate::math::Degrees;
 use winit::{
-    event::{ElementState, KeyEvent, WindowEvent},
+    dpi::PhysicalPosition,
+    event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
     keyboard::{KeyCode, PhysicalKey},
 };
 
-#[derive(Default)]
+// how far the camera turns for a pixel of dragging
+const DEGREES_PER_PIXEL: f32 = 0.3;
+// how much nearer one notch of the wheel takes it
+const ZOOM_PER_NOTCH: f32 = 0.9;
+// touchpads scroll in pixels, this many to a notch
+const PIXELS_PER_NOTCH: f64 = 50.0;
+// mouse look, raw mouse counts are about pixels
+const LOOK_DEGREES_PER_COUNT: f32 = 0.15;
+// times the speed while shift or ctrl is held, `sprint` and `sneak` in the
+// console change them
+const SPRINT: f32 = 1.8;
+const SNEAK: f32 = 0.4;
+// how much wider the view gets at a full sprint
+const FOV_KICK: f32 = 8.0;
+// how far down the camera goes sneaking
+const SNEAK_DROP: f32 = 0.8;
+// how long the kick and the drop take to come and go
+const GAIT_SECONDS: f32 = 0.2;
+
 pub struct Controller {
     pub velocity: f32,
     pub is_up_pressed: bool,
     pub is_down_pressed: bool,
     pub is_left_pressed: bool,
     pub is_right_pressed: bool,
+    pub is_sprint_pressed: bool,
+    pub is_sneak_pressed: bool,
+    // speed multipliers
+    pub sprint: f32,
+    pub sneak: f32,
 }
+
+impl Default for Controller {
+    fn default() -> Self {
+        Self::new(0.0)
+    }
+}
+
 impl Controller {
     pub fn new(velocity: f32) -> Self {
         Self {
@@ -19,6 +51,22 @@ impl Controller {
             is_down_pressed: false,
             is_left_pressed: false,
             is_right_pressed: false,
+            is_sprint_pressed: false,
+            is_sneak_pressed: false,
+            sprint: SPRINT,
+            sneak: SNEAK,
+        }
+    }
+
+    // what walking, flying and the grid's tween are sped up or slowed by,
+    // sneaking wins when both are held
+    pub fn gait(&self) -> f32 {
+        if self.is_sneak_pressed {
+            self.sneak
+        } else if self.is_sprint_pressed {
+            self.sprint
+        } else {
+            1.0
         }
     }
 
@@ -51,10 +99,169 @@ impl Controller {
                         self.is_right_pressed = is_pressed;
                         true
                     }
+                    KeyCode::ShiftLeft | KeyCode::ShiftRight => {
+                        self.is_sprint_pressed = is_pressed;
+                        true
+                    }
+                    KeyCode::ControlLeft | KeyCode::ControlRight => {
+                        self.is_sneak_pressed = is_pressed;
+                        true
+                    }
                     _ => false,
                 }
             }
             _ => false,
         }
     }
+
+    // In grid mode a press moves one tile instead of setting a held direction.
+    // Same keys and the same axes as `update` in main.rs, repeats don't count.
+    pub fn grid_step(event: &WindowEvent) -> Option<crate::math::Vec3> {
+        let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key: PhysicalKey::Code(keycode),
+                    repeat: false,
+                    ..
+                },
+            ..
+        } = event
+        else {
+            return None;
+        };
+        let (x, z) = match keycode {
+            KeyCode::KeyW | KeyCode::ArrowUp => (0.0, 1.0),
+            KeyCode::KeyA | KeyCode::ArrowLeft => (-1.0, 0.0),
+            KeyCode::KeyS | KeyCode::ArrowDown => (0.0, -1.0),
+            KeyCode::KeyD | KeyCode::ArrowRight => (1.0, 0.0),
+            _ => return None,
+        };
+        Some(crate::math::Vec3::new(x, 0.0, z))
+    }
+}
+
+// The camera's side of sprinting and sneaking: the view widens a little at
+// a sprint and drops down when sneaking, both eased in and out so they
+// don't snap. Each is 0..1 of the way there, and `update` gives back how
+// much the FOV and the camera's height have to change this frame.
+#[derive(Default)]
+pub struct Gait {
+    sprinting: f32,
+    sneaking: f32,
+}
+
+impl Gait {
+    // degrees wider and how far lower, None when neither moved
+    pub fn update(&mut self, dt: f32, sprinting: bool, sneaking: bool) -> Option<(f32, f32)> {
+        let before = (self.sprinting, self.sneaking);
+        let toward = |amount: f32, on: bool| {
+            let target = if on { 1.0 } else { 0.0 };
+            let step = dt / GAIT_SECONDS;
+            amount + (target - amount).clamp(-step, step)
+        };
+        self.sprinting = toward(self.sprinting, sprinting);
+        self.sneaking = toward(self.sneaking, sneaking);
+        if (self.sprinting, self.sneaking) == before {
+            return None;
+        }
+        Some((
+            (ease(self.sprinting) - ease(before.0)) * FOV_KICK,
+            (ease(self.sneaking) - ease(before.1)) * SNEAK_DROP,
+        ))
+    }
+}
+
+fn ease(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Right drag swings the camera around what it's looking at and the wheel
+// takes it nearer or further. What the mouse did piles up between frames
+// and `take` hands it over once a frame, so the camera's only written again
+// when something moved.
+#[derive(Default)]
+pub struct OrbitController {
+    dragging: bool,
+    last: Option<PhysicalPosition<f64>>,
+    yaw: f32,
+    pitch: f32,
+    notches: f32,
+}
+
+impl OrbitController {
+    // whether the event was used, the cursor moving never is so picking
+    // still knows where it is
+    pub fn process_events(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Right,
+                ..
+            } => {
+                self.dragging = *state == ElementState::Pressed;
+                self.last = None;
+                true
+            }
+            WindowEvent::CursorMoved { position, .. } if self.dragging => {
+                if let Some(last) = self.last {
+                    self.yaw -= (position.x - last.x) as f32 * DEGREES_PER_PIXEL;
+                    self.pitch += (position.y - last.y) as f32 * DEGREES_PER_PIXEL;
+                }
+                self.last = Some(*position);
+                false
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.notches += match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(p) => (p.y / PIXELS_PER_NOTCH) as f32,
+                };
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // the turn and zoom since the last time, None if there wasn't any
+    pub fn take(&mut self) -> Option<(Degrees, Degrees, f32)> {
+        if self.yaw == 0.0 && self.pitch == 0.0 && self.notches == 0.0 {
+            return None;
+        }
+        let taken = (
+            Degrees(self.yaw),
+            Degrees(self.pitch),
+            ZOOM_PER_NOTCH.powf(self.notches),
+        );
+        self.yaw = 0.0;
+        self.pitch = 0.0;
+        self.notches = 0.0;
+        Some(taken)
+    }
+}
+
+// First person turning, from the mouse's own movement rather than the
+// cursor's, which is held in place while it's going. Piles up between
+// frames the same as the orbit does.
+#[derive(Default)]
+pub struct MouseLook {
+    yaw: f32,
+    pitch: f32,
+}
+
+impl MouseLook {
+    pub fn motion(&mut self, (x, y): (f64, f64)) {
+        // right turns right, up looks up
+        self.yaw -= x as f32 * LOOK_DEGREES_PER_COUNT;
+        self.pitch -= y as f32 * LOOK_DEGREES_PER_COUNT;
+    }
+
+    pub fn take(&mut self) -> Option<(Degrees, Degrees)> {
+        if self.yaw == 0.0 && self.pitch == 0.0 {
+            return None;
+        }
+        let taken = (Degrees(self.yaw), Degrees(self.pitch));
+        self.yaw = 0.0;
+        self.pitch = 0.0;
+        Some(taken)
+    }
 }
@@ -0,0 +1,91 @@
+// An old CRT monitor: a bent picture, scanlines, a phosphor mask and a bit of
+// flicker from the timer. It's the last post pass, so the text stays flat.
+pub struct Crt {
+    curvature: f32,
+    strength: f32,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    timer_bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Crt {
+    pub fn new(
+        device: &wgpu::Device,
+        post: &crate::post::Post,
+        timer: &crate::timer::Timer,
+    ) -> Self {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("crt uniform buffer"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout =
+            crate::reflect::bind_group_layout(device, "crt bind group layout", &[("crt.wgsl", 1)])
+                .unwrap();
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("crt bind group"),
+        });
+        // its own bind group over the timer's buffer, effects only get the render pass
+        let timer_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &timer.timer_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: timer.timer_buffer.as_entire_binding(),
+            }],
+            label: Some("crt timer bind group"),
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("crt shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                crate::shaders::load("crt.wgsl", post.defines())
+                    .unwrap()
+                    .into(),
+            ),
+        });
+        let pipeline = post.pipeline(
+            device,
+            "crt pipeline",
+            &shader,
+            &[&bind_group_layout, &timer.timer_bind_group_layout],
+        );
+
+        Self {
+            curvature: 0.0,
+            strength: 0.0,
+            uniform_buffer,
+            bind_group,
+            timer_bind_group,
+            pipeline,
+        }
+    }
+
+    pub fn set(&mut self, queue: &wgpu::Queue, curvature: f32, strength: f32) {
+        self.curvature = curvature;
+        self.strength = strength;
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[curvature, strength, 0.0, 0.0]),
+        );
+    }
+}
+
+impl crate::post::Effect for Crt {
+    fn enabled(&self) -> bool {
+        self.strength > 0.0 || self.curvature > 0.0
+    }
+
+    fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.set_bind_group(2, &self.timer_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
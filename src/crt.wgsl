@@ -0,0 +1,43 @@
+#define TIMER_GROUP 2
+#include "common.wgsl"
+#include "post.wgsl"
+
+struct Crt {
+    // how far the corners bend in, 0 is flat
+    curvature: f32,
+    // of the scanlines, mask and flicker together
+    strength: f32,
+}
+@group(1) @binding(0)
+var<uniform> crt: Crt;
+
+const PI: f32 = 3.14159265;
+// @tweak crt_scanline_depth 0..1 = 0.6
+// @tweak crt_mask_level 0..1 = 0.7
+
+@fragment
+fn fs_main(in: PostOutput) -> @location(0) vec4<f32> {
+    // barrel distortion, pulling the picture in towards the corners
+    let centred = in.uv * 2.0 - 1.0;
+    let bent = centred * (1.0 + crt.curvature * dot(centred, centred));
+    let uv = bent * 0.5 + 0.5;
+    let size = vec2<f32>(textureDimensions(t_input));
+    var color = textureSample(t_input, s_input, uv).rgb;
+    // past the bent edge of the tube
+    if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    // a dark gap between every other line of the picture
+    let scanline = 0.5 + 0.5 * cos(uv.y * size.y * PI);
+    color *= mix(1.0, scanline, crt.strength * crt_scanline_depth);
+    // an aperture grille, each column of screen pixels favours one of r, g, b
+    let column = u32(in.clip_position.x) % 3u;
+    var mask = vec3<f32>(crt_mask_level);
+    mask[column] = 1.0;
+    color *= mix(vec3<f32>(1.0), mask, crt.strength);
+    // the picture breathes a little, and darkens towards the bent edges
+    color *= 1.0 + sin(timer.t * 113.0) * 0.02 * crt.strength;
+    let edge = uv * (1.0 - uv);
+    color *= mix(1.0, saturate(pow(edge.x * edge.y * 16.0, 0.25)), saturate(crt.curvature * 4.0));
+    return vec4<f32>(color, 1.0);
+}
@@ -1,180 +1,61 @@
-use crate::texture;
-use crate::vertex::ModelVertex;
-use core::ops::Range;
-use wgpu::util::DeviceExt;
-
-pub struct Cube {
-    pub meshes: Vec<Mesh>,
-    pub materials: Vec<Material>,
-}
-pub struct Material {
-    pub name: String,
-    pub diffuse_texture: texture::Texture,
-    pub bind_group: wgpu::BindGroup,
-}
-
-pub struct Mesh {
-    pub name: String,
-    pub vertex_buffer: wgpu::Buffer,
-    pub index_buffer: wgpu::Buffer,
-    pub num_elements: u32,
-    pub material: usize,
+use crate::arena::MeshArenaBuilder;
+use crate::model::{Material, Model};
+
+// The looks a cube can spawn with, a layer each of the material's diffuse
+// array, picked per instance by InstanceRaw's `skin`. Most are the cobbles
+// tinted, multiplied in linear.
+pub const SKINS: [(&str, [f32; 3]); 5] = [
+    ("cobble", [1.0, 1.0, 1.0]),
+    ("crate", [1.0, 1.0, 1.0]),
+    ("moss", [0.55, 0.85, 0.45]),
+    ("sandstone", [1.0, 0.8, 0.55]),
+    ("slate", [0.55, 0.65, 0.85]),
+];
+
+// `crate_` is the texture the cube's MTL gives it, stretched to the cobbles' size
+fn skin_layers(crate_: &image::RgbaImage) -> anyhow::Result<Vec<image::RgbaImage>> {
+    let cobble = image::load_from_memory(include_bytes!("../res/cobble-diffuse.png"))?.to_rgba8();
+    let (width, height) = cobble.dimensions();
+    let crate_ =
+        image::imageops::resize(crate_, width, height, image::imageops::FilterType::Triangle);
+    let tinted = |tint: [f32; 3]| {
+        let mut image = cobble.clone();
+        for pixel in image.pixels_mut() {
+            for c in 0..3 {
+                let linear = (pixel[c] as f32 / 255.0).powf(2.2) * tint[c];
+                pixel[c] = (linear.powf(1.0 / 2.2) * 255.0).round() as u8;
+            }
+        }
+        image
+    };
+    Ok(SKINS
+        .iter()
+        .map(|(name, tint)| match *name {
+            "cobble" => cobble.clone(),
+            "crate" => crate_.clone(),
+            _ => tinted(*tint),
+        })
+        .collect())
 }
 
+// res/cube.obj, with the skins in place of the texture its MTL gives it, which
+// stays on as the crate skin
 pub fn load_cube(
-    file_name: &str,
     device: &wgpu::Device,
     queue: &wgpu::Queue,
     layout: &wgpu::BindGroupLayout,
-) -> anyhow::Result<Cube> {
-    let path = std::path::Path::new(env!("OUT_DIR")).join("res");
-
-    let (models, obj_materials) = tobj::load_obj(
-        path.join(file_name),
-        &tobj::LoadOptions {
-            triangulate: true,
-            single_index: true,
-            ..Default::default()
-        },
-    )?;
-
-    let (materials, material_map) = tobj::load_mtl(path.join("cube.mtl")).unwrap();
-    let material_name = materials[0].name.clone();
-
-    let diffuse_texture = texture::Texture::from_bytes(
+    arena: &mut MeshArenaBuilder,
+) -> anyhow::Result<Model> {
+    let path = std::path::Path::new(env!("OUT_DIR")).join("res/cube.obj");
+    let mut model = Model::load(&path, device, queue, layout, arena)?;
+    let loaded = &model.materials[0];
+    model.materials[0] = Material::new(
         device,
         queue,
-        include_bytes!("../res/cobble-diffuse.png"),
-        // "../res/cobble-diffuse.png"),
-        "cube texture",
-        false,
-    )?;
-
-    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
         layout,
-        entries: &[
-            wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
-            },
-            wgpu::BindGroupEntry {
-                binding: 1,
-                resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
-            },
-        ],
-        label: None,
-    });
-
-    let mut materials = Vec::new();
-    materials.push(Material {
-        name: material_name,
-        diffuse_texture,
-        bind_group,
-    });
-
-    let meshes = models
-        .into_iter()
-        .map(|m| {
-            let vertices = (0..m.mesh.positions.len() / 3)
-                .map(|i| {
-                    if m.mesh.normals.is_empty() {
-                        ModelVertex {
-                            position: [
-                                m.mesh.positions[i * 3],
-                                m.mesh.positions[i * 3 + 1],
-                                m.mesh.positions[i * 3 + 2],
-                            ],
-                            tex_coords: [
-                                m.mesh.texcoords[i * 2],
-                                1.0 - m.mesh.texcoords[i * 2 + 1],
-                            ],
-                            normal: [0.0, 0.0, 0.0],
-                        }
-                    } else {
-                        ModelVertex {
-                            position: [
-                                m.mesh.positions[i * 3],
-                                m.mesh.positions[i * 3 + 1],
-                                m.mesh.positions[i * 3 + 2],
-                            ],
-                            tex_coords: [
-                                m.mesh.texcoords[i * 2],
-                                1.0 - m.mesh.texcoords[i * 2 + 1],
-                            ],
-                            normal: [
-                                m.mesh.normals[i * 3],
-                                m.mesh.normals[i * 3 + 1],
-                                m.mesh.normals[i * 3 + 2],
-                            ],
-                        }
-                    }
-                })
-                .collect::<Vec<_>>();
-
-            let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("{:?} Vertex Buffer", file_name)),
-                contents: bytemuck::cast_slice(&vertices),
-                usage: wgpu::BufferUsages::VERTEX,
-            });
-            let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("{:?} Index Buffer", file_name)),
-                contents: bytemuck::cast_slice(&m.mesh.indices),
-                usage: wgpu::BufferUsages::INDEX,
-            });
-
-            Mesh {
-                name: file_name.to_string(),
-                vertex_buffer,
-                index_buffer,
-                num_elements: m.mesh.indices.len() as u32,
-                material: m.mesh.material_id.unwrap_or(0),
-            }
-        })
-        .collect::<Vec<_>>();
-
-    Ok(Cube { meshes, materials })
-}
-
-pub trait DrawModel<'a> {
-    fn draw_mesh(
-        &mut self,
-        mesh: &'a Mesh,
-        material: &'a Material,
-        camera_bind_group: &'a wgpu::BindGroup,
-    );
-    fn draw_mesh_instanced(
-        &mut self,
-        mesh: &'a Mesh,
-        material: &'a Material,
-        instances: Range<u32>,
-        camera_bind_group: &'a wgpu::BindGroup,
-    );
-}
-
-impl<'a, 'b> DrawModel<'b> for wgpu::RenderPass<'a>
-where
-    'b: 'a,
-{
-    fn draw_mesh(
-        &mut self,
-        mesh: &'b Mesh,
-        material: &'b Material,
-        camera_bind_group: &'b wgpu::BindGroup,
-    ) {
-        self.draw_mesh_instanced(mesh, material, 0..1, camera_bind_group);
-    }
-
-    fn draw_mesh_instanced(
-        &mut self,
-        mesh: &'b Mesh,
-        material: &'b Material,
-        instances: Range<u32>,
-        camera_bind_group: &'b wgpu::BindGroup,
-    ) {
-        self.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-        self.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-        self.set_bind_group(0, &material.bind_group, &[]);
-        self.set_bind_group(1, camera_bind_group, &[]);
-        self.draw_indexed(0..mesh.num_elements, 0, instances);
-    }
+        &loaded.name,
+        &skin_layers(&loaded.layers[0])?,
+        loaded.reflectivity,
+    )?;
+    Ok(model)
 }
@@ -1,24 +1,31 @@
+#define CAMERA_GROUP 1
+#define TIMER_GROUP 3
+#define INSTANCED
+#include "common.wgsl"
+
 // Vertex shader
-struct Camera {
-    view_proj: mat4x4<f32>,
-}
-@group(1) @binding(0)
-var<uniform> camera: Camera;
+
+const FLASH_DURATION: f32 = 0.2;
+// stream::FADE_SECONDS, how long a streamed cell takes to come and go
+const FADE: f32 = 0.5;
 
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
-}
-struct InstanceInput {
-    @location(5) model_matrix_0: vec4<f32>,
-    @location(6) model_matrix_1: vec4<f32>,
-    @location(7) model_matrix_2: vec4<f32>,
-    @location(8) model_matrix_3: vec4<f32>,
+    @location(2) normal: vec3<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
+    @location(1) world_position: vec3<f32>,
+    @location(2) world_normal: vec3<f32>,
+    @location(3) flash: f32,
+    // the light probes are picked per cube, from where its centre is
+    @location(4) @interpolate(flat) center: vec3<f32>,
+    @location(5) @interpolate(flat) skin: u32,
+    // how much of the cube is there, 1 unless it's streaming in or out
+    @location(6) @interpolate(flat) opacity: f32,
 }
 
 @vertex
@@ -26,25 +33,164 @@ fn vs_main(
     model: VertexInput,
     instance: InstanceInput,
 ) -> VertexOutput {
-    let model_matrix = mat4x4<f32>(
-        instance.model_matrix_0,
-        instance.model_matrix_1,
-        instance.model_matrix_2,
-        instance.model_matrix_3,
-    );
+    let matrix = model_matrix(instance);
+    let world_position = matrix * vec4<f32>(model.position, 1.0);
     var out: VertexOutput;
     out.tex_coords = model.tex_coords;
-    out.clip_position = camera.view_proj * model_matrix * vec4<f32>(model.position, 1.0);
+    out.world_position = world_position.xyz;
+    // instances are only rotated and translated, so no inverse transpose needed
+    out.world_normal = (matrix * vec4<f32>(model.normal, 0.0)).xyz;
+    out.clip_position = camera.view_proj * world_position;
+    let since_flash = timer.t - instance.flash;
+    out.flash = saturate(1.0 - since_flash / FLASH_DURATION);
+    out.flash *= out.flash;
+    out.center = matrix[3].xyz;
+    out.skin = instance.skin;
+    out.opacity = 1.0;
+    if instance.fade != 0.0 {
+        let faded = saturate((timer.t - abs(instance.fade)) / FADE);
+        out.opacity = select(1.0 - faded, faded, instance.fade > 0.0);
+    }
     return out;
 }
 
+const BAYER = array<f32, 16>(
+    0.0, 8.0, 2.0, 10.0,
+    12.0, 4.0, 14.0, 6.0,
+    3.0, 11.0, 1.0, 9.0,
+    15.0, 7.0, 13.0, 5.0,
+);
+
 // Fragment shader
 @group(0) @binding(0)
-var t_diffuse: texture_2d<f32>;
+var t_diffuse: texture_2d_array<f32>;
 @group(0)@binding(1)
 var s_diffuse: sampler;
 
+// one per skin, see materials.rs
+struct Skin {
+    // rgb, and roughness in w
+    tint: vec4<f32>,
+    metallic: f32,
+}
+
+struct Material {
+    reflectivity: f32,
+    skins: array<Skin, 8>,
+}
+@group(0) @binding(2)
+var<uniform> material: Material;
+
+@group(2) @binding(0)
+var t_environment: texture_cube<f32>;
+@group(2) @binding(1)
+var s_environment: sampler;
+
+struct Probes {
+    // x and z of the first probe, the spacing, probes along each side
+    grid: vec4<f32>,
+    // 9 coefficients per probe, row by row, see probes.rs
+    sh: array<vec4<f32>, 225>,
+}
+@group(2) @binding(2)
+var<uniform> probes: Probes;
+
+// the sun, see light.rs
+struct Light {
+    // the way the light travels, normalized
+    direction: vec4<f32>,
+    color: vec4<f32>,
+    // how much of the probes' light is let in, in x
+    ambient: vec4<f32>,
+}
+@group(2) @binding(3)
+var<uniform> light: Light;
+
+// light::MAX_POINT_LIGHTS, the ones not in use have no radius
+struct PointLight {
+    // xyz, and the radius it reaches out to in w
+    position: vec4<f32>,
+    color: vec4<f32>,
+}
+@group(2) @binding(4)
+var<uniform> point_lights: array<PointLight, 16>;
+
+// what the point lights give a surface at `p` facing `n`, falling off with
+// the square of the distance and smoothly to nothing at the radius
+fn point_light(p: vec3<f32>, n: vec3<f32>) -> vec3<f32> {
+    var total = vec3<f32>(0.0);
+    for (var i = 0u; i < 16u; i++) {
+        let point = point_lights[i];
+        let radius = point.position.w;
+        if radius <= 0.0 {
+            continue;
+        }
+        let to_light = point.position.xyz - p;
+        let distance = length(to_light);
+        let window = saturate(1.0 - pow(distance / radius, 4.0));
+        let falloff = window * window / (distance * distance + 1.0);
+        total += point.color.rgb * max(dot(n, to_light / max(distance, 0.0001)), 0.0) * falloff;
+    }
+    return total;
+}
+
+// what a white surface facing `n` reflects, from one probe
+fn probe_light(probe: u32, n: vec3<f32>) -> vec3<f32> {
+    let c = probe * 9u;
+    var light = probes.sh[c].rgb * 0.282095;
+    light += probes.sh[c + 1u].rgb * 0.488603 * n.y;
+    light += probes.sh[c + 2u].rgb * 0.488603 * n.z;
+    light += probes.sh[c + 3u].rgb * 0.488603 * n.x;
+    light += probes.sh[c + 4u].rgb * 1.092548 * n.x * n.y;
+    light += probes.sh[c + 5u].rgb * 1.092548 * n.y * n.z;
+    light += probes.sh[c + 6u].rgb * 0.315392 * (3.0 * n.z * n.z - 1.0);
+    light += probes.sh[c + 7u].rgb * 1.092548 * n.x * n.z;
+    light += probes.sh[c + 8u].rgb * 0.546274 * (n.x * n.x - n.y * n.y);
+    return max(light, vec3<f32>(0.0));
+}
+
+// blended between the four probes around `center`, the edge probes carry on
+// past the grid
+fn ambient(center: vec3<f32>, n: vec3<f32>) -> vec3<f32> {
+    let count = u32(probes.grid.w);
+    let cell = clamp(
+        (center.xz - probes.grid.xy) / probes.grid.z,
+        vec2<f32>(0.0),
+        vec2<f32>(f32(count - 1u)),
+    );
+    let corner = min(vec2<u32>(floor(cell)), vec2<u32>(count - 2u));
+    let f = cell - vec2<f32>(corner);
+    let i = corner.y * count + corner.x;
+    let near = mix(probe_light(i, n), probe_light(i + 1u, n), f.x);
+    let far = mix(probe_light(i + count, n), probe_light(i + count + 1u, n), f.x);
+    return mix(near, far, f.y);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    // a fading cube is dithered rather than blended, so it needs no sorting
+    let pixel = vec2<u32>(in.clip_position.xy) % 4u;
+    var bayer = BAYER;
+    if in.opacity <= (bayer[pixel.y * 4u + pixel.x] + 0.5) / 16.0 {
+        discard;
+    }
+    let diffuse = textureSample(t_diffuse, s_diffuse, in.tex_coords, in.skin);
+    let normal = normalize(in.world_normal);
+    let view_dir = normalize(in.world_position - camera.view_pos.xyz);
+    let reflected = reflect(view_dir, normal);
+    let skin = material.skins[min(in.skin, 7u)];
+    let albedo = diffuse.rgb * skin.tint.rgb;
+    // the probes are the environment blurred all the way, which is what a
+    // fully rough surface reflects
+    let sharp = textureSample(t_environment, s_environment, reflected).rgb;
+    let rough = ambient(in.center, reflected);
+    // metals are all reflection, in their own color
+    let reflection = mix(sharp, rough, skin.tint.w) * mix(vec3<f32>(1.0), albedo, skin.metallic);
+    // lambert for the sun and the point lights, and whatever the sky gives
+    // from all around
+    let sun = light.color.rgb * max(dot(normal, -light.direction.xyz), 0.0);
+    let points = point_light(in.world_position, normal);
+    let lit = albedo * (ambient(in.center, normal) * light.ambient.x + sun + points);
+    let color = mix(lit, reflection, mix(material.reflectivity, 1.0, skin.metallic));
+    return vec4<f32>(color + in.flash * vec3<f32>(1.0, 0.9, 0.7), diffuse.a);
 }
@@ -0,0 +1,58 @@
+use directories::ProjectDirs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+// Where praxis keeps things per user, worked out the way each platform
+// expects (XDG on Linux, Application Support on macOS, AppData on Windows).
+// Nothing is created until something writes there, and without a home
+// directory everything falls back to the working directory.
+//
+// `--data-dir <dir>` (or PRAXIS_DATA_DIR) puts the data, cache and logs
+// under one directory instead, `--data-dir .` is how it was before.
+
+static ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+// reads the override, before anything asks for a directory
+pub fn init_from_args() {
+    let args = std::env::args().collect::<Vec<_>>();
+    let root = args
+        .iter()
+        .position(|a| a == "--data-dir")
+        .and_then(|i| args.get(i + 1))
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("PRAXIS_DATA_DIR").map(PathBuf::from));
+    let _ = ROOT.set(root);
+}
+
+fn root() -> Option<&'static PathBuf> {
+    ROOT.get().and_then(Option::as_ref)
+}
+
+fn project() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "praxis")
+}
+
+// settings, the data dir override leaves them where they are
+pub fn config_dir() -> Option<PathBuf> {
+    project().map(|p| p.config_dir().to_path_buf())
+}
+
+// saves, clips and anything else worth keeping
+pub fn data_dir() -> PathBuf {
+    if let Some(root) = root() {
+        return root.clone();
+    }
+    project().map_or_else(|| PathBuf::from("."), |p| p.data_dir().to_path_buf())
+}
+
+// anything that can be thrown away and made again
+pub fn cache_dir() -> PathBuf {
+    if let Some(root) = root() {
+        return root.join("cache");
+    }
+    project().map_or_else(|| PathBuf::from("cache"), |p| p.cache_dir().to_path_buf())
+}
+
+pub fn log_dir() -> PathBuf {
+    data_dir().join("logs")
+}
@@ -0,0 +1,70 @@
+use std::ops::Range;
+
+// Which elements of an array changed since it was last uploaded, so only
+// those get written instead of the whole buffer.
+#[derive(Default, Clone)]
+pub struct DirtyRanges {
+    ranges: Vec<Range<usize>>,
+}
+
+impl DirtyRanges {
+    pub fn mark(&mut self, range: Range<usize>) {
+        if !range.is_empty() {
+            self.ranges.push(range);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+    }
+
+    // the marked ranges, sorted, with overlapping and adjacent ones merged
+    pub fn take(&mut self) -> Vec<Range<usize>> {
+        self.ranges.sort_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(self.ranges.len());
+        for range in self.ranges.drain(..) {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_and_adjacent_ranges_merge() {
+        let mut dirty = DirtyRanges::default();
+        dirty.mark(10..20);
+        dirty.mark(0..5);
+        dirty.mark(15..25);
+        dirty.mark(5..7);
+        dirty.mark(30..31);
+        dirty.mark(12..14);
+        assert_eq!(dirty.take(), vec![0..7, 10..25, 30..31]);
+    }
+
+    #[test]
+    fn take_empties_it() {
+        let mut dirty = DirtyRanges::default();
+        dirty.mark(0..1);
+        assert_eq!(dirty.take(), vec![0..1]);
+        assert!(dirty.take().is_empty());
+        dirty.mark(3..4);
+        dirty.clear();
+        assert!(dirty.take().is_empty());
+    }
+
+    #[test]
+    fn empty_ranges_are_ignored() {
+        let mut dirty = DirtyRanges::default();
+        dirty.mark(4..4);
+        #[allow(clippy::reversed_empty_ranges)]
+        dirty.mark(9..2);
+        assert!(dirty.take().is_empty());
+    }
+}
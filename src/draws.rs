@@ -0,0 +1,124 @@
+use crate::arena::MeshArena;
+use crate::model::{DrawModel, Material, Mesh};
+use crate::stats::Stats;
+use core::ops::Range;
+
+// What a draw takes its instances from
+#[derive(Clone)]
+pub enum Instances<'a> {
+    Direct(&'a wgpu::Buffer, Range<u32>),
+    // the count is on the GPU, in DrawIndexedIndirectArgs at `offset` in
+    // `args`, which also says which mesh
+    Indirect {
+        instances: &'a wgpu::Buffer,
+        args: &'a wgpu::Buffer,
+        offset: u64,
+    },
+}
+
+impl Instances<'_> {
+    fn buffer(&self) -> &wgpu::Buffer {
+        match self {
+            Instances::Direct(buffer, _) => buffer,
+            Instances::Indirect { instances, .. } => instances,
+        }
+    }
+}
+
+// Sorted on in this order. Pipelines, materials and meshes are numbered in
+// the order they were first pushed, so equal ones end up next to each other
+// without anything having to hand out ids.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct DrawKey {
+    pipeline: u32,
+    material: u32,
+    mesh: u32,
+    // farthest first, there's no depth buffer
+    depth: u32,
+}
+
+// Mesh draws for one pass, collected and then sorted by pipeline, material,
+// mesh and depth in `submit` so each of those only gets bound when it
+// changes. Anything the draws share past groups 0 and 1 (the environment,
+// the timer) is set on the pass before submitting.
+#[derive(Default)]
+pub struct DrawQueue<'a> {
+    pipelines: Vec<&'a wgpu::RenderPipeline>,
+    materials: Vec<&'a Material>,
+    meshes: Vec<&'a Mesh>,
+    draws: Vec<(DrawKey, Instances<'a>)>,
+}
+
+// where `item` is in `list`, going by address, adding it if it isn't
+fn number<'a, T>(list: &mut Vec<&'a T>, item: &'a T) -> u32 {
+    match list.iter().position(|known| std::ptr::eq(*known, item)) {
+        Some(i) => i as u32,
+        None => {
+            list.push(item);
+            list.len() as u32 - 1
+        }
+    }
+}
+
+impl<'a> DrawQueue<'a> {
+    // `depth` is how far the draw is from the camera, for instanced draws
+    // whatever stands in for the lot of them
+    pub fn push(
+        &mut self,
+        pipeline: &'a wgpu::RenderPipeline,
+        mesh: &'a Mesh,
+        material: &'a Material,
+        depth: f32,
+        instances: Instances<'a>,
+    ) {
+        let key = DrawKey {
+            pipeline: number(&mut self.pipelines, pipeline),
+            material: number(&mut self.materials, material),
+            mesh: number(&mut self.meshes, mesh),
+            // positive floats sort like their bits, flipped for far to near
+            depth: !depth.max(0.0).to_bits(),
+        };
+        self.draws.push((key, instances));
+    }
+
+    pub fn submit(
+        mut self,
+        render_pass: &mut wgpu::RenderPass,
+        arena: &MeshArena,
+        camera_bind_group: &wgpu::BindGroup,
+        stats: &mut Stats,
+    ) {
+        self.draws.sort_by_key(|(key, _)| *key);
+        render_pass.set_mesh_arena(arena);
+        render_pass.set_bind_group(1, camera_bind_group, &[]);
+
+        let mut last: Option<(DrawKey, &wgpu::Buffer)> = None;
+        for (key, instances) in &self.draws {
+            let buffer = instances.buffer();
+            if last.is_none_or(|(last, _)| last.pipeline != key.pipeline) {
+                render_pass.set_pipeline(self.pipelines[key.pipeline as usize]);
+                stats.pipeline_switches += 1;
+            }
+            if last.is_none_or(|(last, _)| last.material != key.material) {
+                let material = self.materials[key.material as usize];
+                render_pass.set_bind_group(0, &material.bind_group, &[]);
+                stats.bind_group_switches += 1;
+            }
+            if last.is_none_or(|(_, last)| !std::ptr::eq(last, buffer)) {
+                render_pass.set_vertex_buffer(1, buffer.slice(..));
+                stats.buffer_switches += 1;
+            }
+            match instances {
+                Instances::Direct(_, range) => {
+                    let mesh = self.meshes[key.mesh as usize];
+                    render_pass.draw_indexed(mesh.indices(), mesh.base_vertex, range.clone());
+                }
+                Instances::Indirect { args, offset, .. } => {
+                    render_pass.draw_indexed_indirect(args, *offset);
+                }
+            }
+            stats.draws += 1;
+            last = Some((*key, buffer));
+        }
+    }
+}
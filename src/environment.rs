@@ -0,0 +1,304 @@
+use crate::camera::CameraUniform;
+use crate::light::Light;
+use crate::math;
+use crate::noise::Noise;
+use crate::probes;
+use crate::texture::Texture;
+use image::imageops::FilterType;
+use image::GenericImageView;
+use wgpu::util::DeviceExt;
+
+const FACE_SIZE: u32 = 256;
+
+// cubemap faces follow the D3D/Vulkan convention, which is mirrored compared
+// to a right handed look_at, so face cameras flip x and the pipeline used to
+// draw into them has to use clockwise front faces
+#[rustfmt::skip]
+const FACE_FLIP: math::Mat4 = math::Mat4::from_cols_array(&[
+    -1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 1.0, 0.0,
+    0.0, 0.0, 0.0, 1.0,
+]);
+
+// (direction, up) for +X, -X, +Y, -Y, +Z, -Z
+const FACES: [([f32; 3], [f32; 3]); 6] = [
+    ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ([-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ([0.0, 1.0, 0.0], [0.0, 0.0, -1.0]),
+    ([0.0, -1.0, 0.0], [0.0, 0.0, 1.0]),
+    ([0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+    ([0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+];
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ProbesUniform {
+    // x and z of the first probe, the spacing, probes along each side
+    grid: [f32; 4],
+    sh: [probes::Sh9; (probes::GRID * probes::GRID) as usize],
+}
+
+// The sky, for the skybox and reflections, and the ambient light probes
+// baked from it, which both go in the same bind group.
+pub struct Environment {
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    static_bind_group: wgpu::BindGroup,
+    dynamic_bind_group: wgpu::BindGroup,
+    pub dynamic_map: Texture,
+    face_camera_buffers: Vec<wgpu::Buffer>,
+    face_camera_bind_groups: Vec<wgpu::BindGroup>,
+    skybox_pipeline: wgpu::RenderPipeline,
+
+    // when set, reflections come from a cubemap rendered at `center` every `interval` frames
+    pub dynamic: bool,
+    pub interval: u32,
+    pub center: math::Point3,
+    frame: u32,
+}
+
+impl Environment {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        noise: &Noise,
+        light: &Light,
+    ) -> Self {
+        let backdrop =
+            image::load_from_memory(include_bytes!("../res/backgrounds/reactor.png")).unwrap();
+        let faces = faces_from_backdrop(&backdrop);
+        let static_map = Texture::cube_from_faces(device, queue, &faces, "environment");
+
+        let first = probes::position(0);
+        let spacing = probes::position(1).x - first.x;
+        let probes = ProbesUniform {
+            grid: [first.x, first.z, spacing, probes::GRID as f32],
+            sh: probes::bake(&faces.map(|face| face.to_rgba8()), noise)
+                .try_into()
+                .unwrap(),
+        };
+        let probe_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("light probe buffer"),
+            contents: bytemuck::cast_slice(&[probes]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let dynamic_map =
+            Texture::create_cube_render_target(device, FACE_SIZE, format, "dynamic environment");
+
+        let bind_group_layout = crate::reflect::bind_group_layout(
+            device,
+            "environment bind group layout",
+            &[("skybox.wgsl", 1), ("cube.wgsl", 2), ("water.wgsl", 2)],
+        )
+        .unwrap();
+        let bind_group = |texture: &Texture, label| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&texture.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: probe_buffer.as_entire_binding(),
+                    },
+                    // the sun and point lights, only the cube shader has them
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: light.buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: light.points.as_entire_binding(),
+                    },
+                ],
+                label: Some(label),
+            })
+        };
+        let static_bind_group = bind_group(&static_map, "environment bind group");
+        let dynamic_bind_group = bind_group(&dynamic_map, "dynamic environment bind group");
+
+        let face_camera_buffers = (0..6)
+            .map(|_| {
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("environment face camera buffer"),
+                    contents: bytemuck::cast_slice(&[CameraUniform::new()]),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                })
+            })
+            .collect::<Vec<_>>();
+        let face_camera_bind_groups = face_camera_buffers
+            .iter()
+            .map(|buffer| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: camera_bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                    label: Some("environment face camera bind group"),
+                })
+            })
+            .collect();
+
+        let skybox_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("skybox shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                crate::shaders::load("skybox.wgsl", &[]).unwrap().into(),
+            ),
+        });
+        let skybox_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("skybox pipeline layout"),
+                bind_group_layouts: &[camera_bind_group_layout, &bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let skybox_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("skybox render pipeline"),
+            layout: Some(&skybox_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &skybox_shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &skybox_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: crate::pipeline_cache::current().as_deref(),
+        });
+
+        Self {
+            bind_group_layout,
+            static_bind_group,
+            dynamic_bind_group,
+            dynamic_map,
+            face_camera_buffers,
+            face_camera_bind_groups,
+            skybox_pipeline,
+            dynamic: false,
+            interval: 10,
+            center: (0.0, 0.0, 0.0).into(),
+            frame: 0,
+        }
+    }
+
+    // what materials should sample this frame
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        if self.dynamic {
+            &self.dynamic_bind_group
+        } else {
+            &self.static_bind_group
+        }
+    }
+
+    // counts frames and says whether the dynamic cubemap should be redrawn
+    pub fn due(&mut self) -> bool {
+        self.frame = self.frame.wrapping_add(1);
+        self.dynamic && self.frame.is_multiple_of(self.interval.max(1))
+    }
+
+    // Renders the six faces of the dynamic cubemap. `draw` gets the pass, the
+    // face camera bind group and the static environment (the dynamic map can't
+    // be sampled while it's being rendered to).
+    pub fn render_faces(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        queue: &wgpu::Queue,
+        mut draw: impl FnMut(&mut wgpu::RenderPass, &wgpu::BindGroup, &wgpu::BindGroup),
+    ) {
+        let projection = FACE_FLIP * math::perspective(math::Degrees(90.0), 1.0, 0.1, 100.0);
+
+        for (face, (direction, up)) in FACES.iter().enumerate() {
+            let view = math::look_at(
+                self.center,
+                self.center + math::Vec3::from(*direction),
+                math::Vec3::from(*up),
+            );
+            let mut uniform = CameraUniform::new();
+            uniform.set(self.center, projection * view);
+            queue.write_buffer(
+                &self.face_camera_buffers[face],
+                0,
+                bytemuck::cast_slice(&[uniform]),
+            );
+
+            let face_view = self.dynamic_map.face_view(face as u32);
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("environment face pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &face_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&self.skybox_pipeline);
+            render_pass.set_bind_group(0, &self.face_camera_bind_groups[face], &[]);
+            render_pass.set_bind_group(1, &self.static_bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+
+            draw(
+                &mut render_pass,
+                &self.face_camera_bind_groups[face],
+                &self.static_bind_group,
+            );
+        }
+    }
+}
+
+// there is no real skybox art, so the backdrop is cut up into something
+// cube shaped: four slices around the horizon plus its top and bottom strips
+fn faces_from_backdrop(backdrop: &image::DynamicImage) -> [image::DynamicImage; 6] {
+    let (width, height) = backdrop.dimensions();
+    let slice = width / 4;
+    let strip = height / 4;
+    let face = |x, y, w, h| {
+        backdrop
+            .crop_imm(x, y, w, h)
+            .resize_exact(FACE_SIZE, FACE_SIZE, FilterType::Triangle)
+    };
+    [
+        face(0, 0, slice, height),
+        face(slice * 2, 0, slice, height),
+        face(0, 0, width, strip),
+        face(0, height - strip, width, strip),
+        face(slice, 0, slice, height),
+        face(slice * 3, 0, slice, height),
+    ]
+}
@@ -0,0 +1,73 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use wgpu_text::glyph_brush::{OwnedSection, Section as TextSection, Text};
+
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+const MAX_TOASTS: usize = 5;
+
+// Collects wgpu errors from error scopes and the uncaptured error handler so
+// they end up in the log and on screen instead of panicking inside wgpu.
+#[derive(Clone, Default)]
+pub struct GpuErrors {
+    recent: Arc<Mutex<Vec<(Instant, String)>>>,
+}
+
+impl GpuErrors {
+    pub fn install(&self, device: &wgpu::Device) {
+        let errors = self.clone();
+        device.on_uncaptured_error(Box::new(move |error| {
+            errors.report("uncaptured", &error);
+        }));
+    }
+
+    pub fn push_scope(&self, device: &wgpu::Device) {
+        device.push_error_scope(wgpu::ErrorFilter::OutOfMemory);
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+    }
+
+    // pops the two scopes pushed by push_scope, `label` names what was created in between
+    pub fn pop_scope(&self, device: &wgpu::Device, label: &str) {
+        for _ in 0..2 {
+            if let Some(error) = pollster::block_on(device.pop_error_scope()) {
+                self.report(label, &error);
+            }
+        }
+    }
+
+    pub fn report(&self, label: &str, error: &wgpu::Error) {
+        log::error!("wgpu error in {}: {}", label, error);
+        let message = error.to_string();
+        let summary = message.lines().next().unwrap_or_default();
+
+        let mut recent = self.recent.lock().unwrap();
+        recent.push((Instant::now(), format!("{}: {}", label, summary)));
+        if recent.len() > MAX_TOASTS {
+            recent.remove(0);
+        }
+    }
+
+    pub fn toast_section(&self, width: f32, height: f32) -> Option<OwnedSection> {
+        let mut recent = self.recent.lock().unwrap();
+        recent.retain(|(at, _)| at.elapsed() < TOAST_DURATION);
+        if recent.is_empty() {
+            return None;
+        }
+
+        let text = recent
+            .iter()
+            .map(|(_, message)| message.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        Some(
+            TextSection::default()
+                .add_text(
+                    Text::new(&text)
+                        .with_color([1.0, 0.35, 0.3, 1.0])
+                        .with_scale(18.0),
+                )
+                .with_screen_position((10.0, 10.0))
+                .with_bounds((width - 20.0, height - 20.0))
+                .to_owned(),
+        )
+    }
+}
@@ -0,0 +1,220 @@
+use crate::rng::Rng;
+use anyhow::{anyhow, bail, Result};
+
+// A console command's arguments: plain values in order, and `name=value`
+// ones anywhere among them, so `spawn grid 10 10 spacing=2.5 skin=rand`
+// and `spawn grid rows=10 columns=10` mean the same thing.
+pub struct Args<'a> {
+    pub positional: Vec<&'a str>,
+    named: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Args<'a> {
+    pub fn parse(words: &[&'a str]) -> Self {
+        let mut args = Self {
+            positional: Vec::new(),
+            named: Vec::new(),
+        };
+        for word in words {
+            match word.split_once('=') {
+                Some((name, value)) => args.named.push((name, value)),
+                None => args.positional.push(word),
+            }
+        }
+        args
+    }
+
+    // the value given for `name`, which it then doesn't have any more
+    pub fn take(&mut self, name: &str) -> Option<&'a str> {
+        let i = self.named.iter().position(|(n, _)| *n == name)?;
+        Some(self.named.remove(i).1)
+    }
+
+    // an error naming anything `name=` no one took
+    pub fn finish(&self, command: &str) -> Result<()> {
+        match self.named.first() {
+            Some((name, _)) => bail!("{} has no {}", command, name),
+            None => Ok(()),
+        }
+    }
+}
+
+// Works out a number written as a small expression: + - * / and brackets
+// over numbers (`1e-3` too), `pi`, and `rand`, which is a new number from 0
+// to 1 each time it's read. No spaces, those split arguments. Anything
+// that comes out infinite or NaN, `1/0` say, is an error.
+pub fn eval(text: &str, rng: &mut Rng) -> Result<f32> {
+    let mut parser = Parser {
+        text,
+        at: 0,
+        depth: 0,
+        rng,
+    };
+    let value = parser.sum()?;
+    if parser.at < text.len() {
+        bail!("{} doesn't make sense from {}", text, &text[parser.at..]);
+    }
+    if !value.is_finite() {
+        bail!("{} comes out as {}", text, value);
+    }
+    Ok(value)
+}
+
+// how many brackets and minuses deep an expression can go, each is a call
+// further down the stack
+const MAX_DEPTH: usize = 64;
+
+struct Parser<'a> {
+    text: &'a str,
+    at: usize,
+    depth: usize,
+    rng: &'a mut Rng,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.at..].chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.at += c.len_utf8();
+        }
+        found
+    }
+
+    fn sum(&mut self) -> Result<f32> {
+        let mut value = self.product()?;
+        loop {
+            if self.eat('+') {
+                value += self.product()?;
+            } else if self.eat('-') {
+                value -= self.product()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<f32> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat('*') {
+                value *= self.unary()?;
+            } else if self.eat('/') {
+                value /= self.unary()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<f32> {
+        if self.depth == MAX_DEPTH {
+            bail!("{} goes too deep", self.text);
+        }
+        self.depth += 1;
+        let value = self.operand();
+        self.depth -= 1;
+        value
+    }
+
+    fn operand(&mut self) -> Result<f32> {
+        if self.eat('-') {
+            return Ok(-self.unary()?);
+        }
+        if self.eat('(') {
+            let value = self.sum()?;
+            if !self.eat(')') {
+                bail!("{} is missing a )", self.text);
+            }
+            return Ok(value);
+        }
+        let rest = &self.text[self.at..];
+        let word_end = |from: usize| {
+            rest[from..]
+                .find(|c: char| !(c.is_alphanumeric() || c == '.' || c == '_'))
+                .map_or(rest.len(), |i| from + i)
+        };
+        let mut end = word_end(0);
+        // an exponent's sign, `1e-3`, is part of the number and not a minus
+        let number = rest.starts_with(|c: char| c.is_ascii_digit() || c == '.');
+        if number && rest[..end].ends_with(['e', 'E']) && rest[end..].starts_with(['+', '-']) {
+            end = word_end(end + 1);
+        }
+        let word = &rest[..end];
+        self.at += end;
+        match word {
+            "" => Err(anyhow!("{} is missing a number", self.text)),
+            "rand" => Ok(self.rng.f32()),
+            "pi" => Ok(std::f32::consts::PI),
+            _ => word
+                .parse::<f32>()
+                .map_err(|_| anyhow!("{} isn't a number", word)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(text: &str) -> Result<f32> {
+        eval(text, &mut Rng::new(1))
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(value("1+2*3").unwrap(), 7.0);
+        assert_eq!(value("(1+2)*3").unwrap(), 9.0);
+        assert_eq!(value("8/4/2").unwrap(), 1.0);
+        assert_eq!(value("2-3-4").unwrap(), -5.0);
+        assert_eq!(value("--2").unwrap(), 2.0);
+        assert_eq!(value("1e-3*1e3").unwrap(), 1.0);
+        assert_eq!(value("2*pi").unwrap(), std::f32::consts::TAU);
+    }
+
+    #[test]
+    fn rand_is_new_each_read() {
+        let mut rng = Rng::new(1);
+        let a = eval("rand", &mut rng).unwrap();
+        let b = eval("rand", &mut rng).unwrap();
+        assert!((0.0..1.0).contains(&a) && (0.0..1.0).contains(&b));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn nonsense_is_an_error() {
+        for text in ["", "1+", "(1", "1)", "two", "1..2", "1 2", "*3"] {
+            assert!(value(text).is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn infinite_and_nan_are_errors() {
+        for text in ["1/0", "-1/0", "0/0", "1e30*1e30"] {
+            assert!(value(text).is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error_not_an_overflow() {
+        let deep = "(".repeat(10_000) + "1" + &")".repeat(10_000);
+        assert!(value(&deep).is_err());
+        assert!(value(&"-".repeat(10_000)).is_err());
+        let shallow = "(".repeat(MAX_DEPTH / 2) + "1" + &")".repeat(MAX_DEPTH / 2);
+        assert_eq!(value(&shallow).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn args_by_name_or_in_order() {
+        let mut args = Args::parse(&["10", "spacing=2", "5"]);
+        assert_eq!(args.positional, vec!["10", "5"]);
+        assert_eq!(args.take("spacing"), Some("2"));
+        assert_eq!(args.take("spacing"), None);
+        assert!(args.finish("grid").is_ok());
+        let args = Args::parse(&["colour=red"]);
+        assert!(args.finish("grid").is_err());
+    }
+}
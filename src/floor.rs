@@ -0,0 +1,419 @@
+use crate::camera::{Camera, CameraUniform, Frustum};
+use crate::math::{Mat4, Vec3};
+use crate::noise::Noise;
+use crate::texture::Texture;
+use std::collections::HashMap;
+use wgpu::util::DeviceExt;
+
+// the same as in floor.wgsl
+pub const FLOOR_Y: f32 = -2.0;
+const CHUNK_SIZE: f32 = 16.0;
+// chunks kept in each direction around the player's chunk
+const RADIUS: i32 = 4;
+const MAX_CHUNKS: usize = ((2 * RADIUS + 1) * (2 * RADIUS + 1)) as usize;
+
+fn chunk_tint(noise: &Noise, (x, z): (i32, i32)) -> f32 {
+    noise.simplex(x as f32 * 0.3, z as f32 * 0.3)
+}
+
+// what floor.wgsl draws at (x, z) before the mirror, for baking the probes
+pub fn ground_color(noise: &Noise, x: f32, z: f32) -> Vec3 {
+    let chunk = (
+        (x / CHUNK_SIZE).floor() as i32,
+        (z / CHUNK_SIZE).floor() as i32,
+    );
+    let check = ((x / 2.0).floor() as i32 + (z / 2.0).floor() as i32) & 1;
+    let base = Vec3::new(0.22, 0.24, 0.2).lerp(Vec3::new(0.3, 0.32, 0.27), check as f32);
+    base * (0.85 + 0.3 * chunk_tint(noise, chunk))
+}
+
+// one white texel
+fn no_ao(device: &wgpu::Device, queue: &wgpu::Queue) -> Texture {
+    let white = image::RgbaImage::from_pixel(1, 1, image::Rgba([255; 4]));
+    Texture::from_image(device, queue, &white.into(), Some("no floor ao"), true).unwrap()
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ChunkRaw {
+    offset: [f32; 2],
+    tint: f32,
+}
+
+// Ground that goes on forever: one plane mesh drawn once per chunk, with the
+// chunks around the player created and dropped as it moves. Only the chunks
+// in the view's frustum are drawn.
+//
+// As a mirror, what's above it is drawn once more into `reflection` with the
+// camera mirrored through the floor, the same way the water does it, and
+// blended in by fresnel. The mirror pass is skipped when no chunk is in view.
+pub struct Floor {
+    chunks: HashMap<(i32, i32), ChunkRaw>,
+    // the chunks in the order they are in the instance buffer
+    uploaded: Vec<(i32, i32)>,
+    center: Option<(i32, i32)>,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    instance_buffer: wgpu::Buffer,
+    pub reflection: Texture,
+    reflection_camera_buffer: wgpu::Buffer,
+    reflection_camera_bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+    // white when there's no baked AO
+    pub ao: Texture,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Floor {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let plane: [[f32; 2]; 4] = [
+            [0.0, 0.0],
+            [CHUNK_SIZE, 0.0],
+            [CHUNK_SIZE, CHUNK_SIZE],
+            [0.0, CHUNK_SIZE],
+        ];
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("floor vertex buffer"),
+            contents: bytemuck::cast_slice(&plane),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("floor index buffer"),
+            contents: bytemuck::cast_slice::<u16, u8>(&[0, 1, 2, 0, 2, 3]),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("floor chunk buffer"),
+            size: (MAX_CHUNKS * std::mem::size_of::<ChunkRaw>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let reflection =
+            Texture::create_render_target(device, width, height, format, "floor reflection");
+        let reflection_camera_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("floor reflection camera buffer"),
+                contents: bytemuck::cast_slice(&[CameraUniform::new()]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+        let reflection_camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: reflection_camera_buffer.as_entire_binding(),
+            }],
+            label: Some("floor reflection camera bind group"),
+        });
+        // mirror strength, then the AO map's size and corner, then the wetness
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("floor uniform buffer"),
+            contents: bytemuck::cast_slice(&[0.0f32, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let ao = no_ao(device, queue);
+        let bind_group_layout = crate::reflect::bind_group_layout(
+            device,
+            "floor bind group layout",
+            &[("floor.wgsl", 1)],
+        )
+        .unwrap();
+        let bind_group = Self::create_bind_group(
+            device,
+            &bind_group_layout,
+            &reflection,
+            &uniform_buffer,
+            &ao,
+        );
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("floor shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                crate::shaders::load("floor.wgsl", &[]).unwrap().into(),
+            ),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("floor pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("floor render pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[
+                    wgpu::VertexBufferLayout {
+                        array_stride: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &[wgpu::VertexAttribute {
+                            offset: 0,
+                            shader_location: 0,
+                            format: wgpu::VertexFormat::Float32x2,
+                        }],
+                    },
+                    wgpu::VertexBufferLayout {
+                        array_stride: std::mem::size_of::<ChunkRaw>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Instance,
+                        attributes: &[
+                            wgpu::VertexAttribute {
+                                offset: 0,
+                                shader_location: 1,
+                                format: wgpu::VertexFormat::Float32x2,
+                            },
+                            wgpu::VertexAttribute {
+                                offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                                shader_location: 2,
+                                format: wgpu::VertexFormat::Float32,
+                            },
+                        ],
+                    },
+                ],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(Texture::depth_state(true, wgpu::CompareFunction::Less)),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: crate::pipeline_cache::current().as_deref(),
+        });
+
+        Self {
+            chunks: HashMap::new(),
+            uploaded: Vec::new(),
+            center: None,
+            vertex_buffer,
+            index_buffer,
+            instance_buffer,
+            reflection,
+            reflection_camera_buffer,
+            reflection_camera_bind_group,
+            uniform_buffer,
+            ao,
+            bind_group_layout,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        reflection: &Texture,
+        uniform_buffer: &wgpu::Buffer,
+        ao: &Texture,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&reflection.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&reflection.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&ao.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(&ao.sampler),
+                },
+            ],
+            label: Some("floor bind group"),
+        })
+    }
+
+    // the reflection is looked up by screen position, so it's the frame's size
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        let format = self.reflection.texture.format();
+        self.reflection =
+            Texture::create_render_target(device, width, height, format, "floor reflection");
+        self.bind_group = Self::create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.reflection,
+            &self.uniform_buffer,
+            &self.ao,
+        );
+    }
+
+    // a map from `--bake` over `area`, None goes back to no AO
+    pub fn set_ao(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        baked: Option<(crate::bake::Area, image::DynamicImage)>,
+    ) {
+        let (ao, area) = match baked.and_then(|(area, image)| {
+            Texture::from_image(device, queue, &image, Some("floor ao"), true)
+                .map_err(|e| log::warn!("couldn't upload the AO map: {}", e))
+                .ok()
+                .map(|texture| (texture, area))
+        }) {
+            Some((texture, area)) => (texture, [area.size, area.min.x, area.min.y]),
+            None => (no_ao(device, queue), [1.0, 0.0, 0.0]),
+        };
+        queue.write_buffer(&self.uniform_buffer, 4, bytemuck::cast_slice(&area));
+        self.ao = ao;
+        self.bind_group = Self::create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.reflection,
+            &self.uniform_buffer,
+            &self.ao,
+        );
+    }
+
+    // how much of the mirror shows, 0 is plain ground
+    pub fn set_mirror(&self, queue: &wgpu::Queue, strength: f32) {
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[strength]));
+    }
+
+    // 0 is dry, 1 soaked through: darker, with a sheen of sky on it
+    pub fn set_wetness(&self, queue: &wgpu::Queue, wetness: f32) {
+        queue.write_buffer(&self.uniform_buffer, 16, bytemuck::cast_slice(&[wetness]));
+    }
+
+    fn chunk_visible(&self, frustum: &Frustum, (x, z): (i32, i32)) -> bool {
+        let min = Vec3::new(x as f32 * CHUNK_SIZE, FLOOR_Y, z as f32 * CHUNK_SIZE);
+        frustum.intersects_box(min, min + Vec3::new(CHUNK_SIZE, 0.0, CHUNK_SIZE))
+    }
+
+    pub fn in_view(&self, frustum: &Frustum) -> bool {
+        self.uploaded
+            .iter()
+            .any(|chunk| self.chunk_visible(frustum, *chunk))
+    }
+
+    // Draws the mirrored scene, `draw` should use a clockwise pipeline like
+    // the water's reflection. Cleared to transparent so only what's drawn
+    // shows up in the floor.
+    pub fn render_reflection(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        queue: &wgpu::Queue,
+        camera: &Camera,
+        mut draw: impl FnMut(&mut wgpu::RenderPass, &wgpu::BindGroup),
+    ) {
+        let mirror = Mat4::from_translation(Vec3::new(0.0, 2.0 * FLOOR_Y, 0.0))
+            * Mat4::from_scale(Vec3::new(1.0, -1.0, 1.0));
+        let mut eye = camera.eye;
+        eye.y = 2.0 * FLOOR_Y - eye.y;
+        let mut uniform = CameraUniform::new();
+        uniform.set(eye, camera.build_view_projection_matrix() * mirror);
+        queue.write_buffer(
+            &self.reflection_camera_buffer,
+            0,
+            bytemuck::cast_slice(&[uniform]),
+        );
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("floor reflection pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.reflection.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        draw(&mut render_pass, &self.reflection_camera_bind_group);
+    }
+
+    // streams chunks in and out around `player`, only touching the GPU when
+    // the player crosses into another chunk
+    pub fn update(&mut self, queue: &wgpu::Queue, noise: &Noise, player: crate::math::Vec3) {
+        let center = (
+            (player.x / CHUNK_SIZE).floor() as i32,
+            (player.z / CHUNK_SIZE).floor() as i32,
+        );
+        if self.center == Some(center) {
+            return;
+        }
+        self.center = Some(center);
+
+        let in_range =
+            |(x, z): (i32, i32)| (x - center.0).abs() <= RADIUS && (z - center.1).abs() <= RADIUS;
+        self.chunks.retain(|key, _| in_range(*key));
+        for x in center.0 - RADIUS..=center.0 + RADIUS {
+            for z in center.1 - RADIUS..=center.1 + RADIUS {
+                self.chunks.entry((x, z)).or_insert_with(|| ChunkRaw {
+                    offset: [x as f32 * CHUNK_SIZE, z as f32 * CHUNK_SIZE],
+                    tint: chunk_tint(noise, (x, z)),
+                });
+            }
+        }
+
+        self.uploaded = self.chunks.keys().copied().collect();
+        let chunks = self
+            .uploaded
+            .iter()
+            .map(|key| self.chunks[key])
+            .collect::<Vec<_>>();
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&chunks));
+    }
+
+    // `frustum` is the one the camera bind group was made from
+    pub fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        camera_bind_group: &wgpu::BindGroup,
+        frustum: &Frustum,
+    ) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        for (i, chunk) in self.uploaded.iter().enumerate() {
+            if self.chunk_visible(frustum, *chunk) {
+                render_pass.draw_indexed(0..6, 0, i as u32..i as u32 + 1);
+            }
+        }
+    }
+}
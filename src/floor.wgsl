@@ -0,0 +1,74 @@
+#define CAMERA_GROUP 0
+#include "common.wgsl"
+
+// just under the bottom of a cube sitting at y = -1
+const FLOOR_Y: f32 = -2.0;
+// what wet ground reflects when there's no mirror
+const SKY_SHEEN: vec3<f32> = vec3<f32>(0.55, 0.6, 0.68);
+
+struct FloorUniform {
+    mirror: f32,
+    // the square of ground the baked AO covers
+    ao_size: f32,
+    ao_min: vec2<f32>,
+    // from the weather, 0 dry to 1 soaked
+    wetness: f32,
+}
+// what's above, drawn with the camera mirrored through the floor
+@group(1) @binding(0)
+var t_reflection: texture_2d<f32>;
+@group(1) @binding(1)
+var s_reflection: sampler;
+@group(1) @binding(2)
+var<uniform> floor_uniform: FloorUniform;
+// from `--bake`, white when the scene has none
+@group(1) @binding(3)
+var t_ao: texture_2d<f32>;
+@group(1) @binding(4)
+var s_ao: sampler;
+
+struct ChunkInput {
+    @location(1) offset: vec2<f32>,
+    @location(2) tint: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world: vec2<f32>,
+    @location(1) tint: f32,
+}
+
+@vertex
+fn vs_main(@location(0) position: vec2<f32>, chunk: ChunkInput) -> VertexOutput {
+    let world = position + chunk.offset;
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(world.x, FLOOR_Y, world.y, 1.0);
+    out.world = world;
+    out.tint = chunk.tint;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let tile = vec2<i32>(floor(in.world / 2.0));
+    let check = f32((tile.x + tile.y) & 1);
+    let base = mix(vec3<f32>(0.22, 0.24, 0.2), vec3<f32>(0.3, 0.32, 0.27), check);
+    let ao_uv = (in.world - floor_uniform.ao_min) / floor_uniform.ao_size;
+    let ao = textureSample(t_ao, s_ao, ao_uv).r;
+    var ground = base * (0.85 + 0.3 * in.tint) * ao;
+
+    // Schlick, with enough at head on that it still reads as a mirror from above
+    let view_dir = normalize(vec3<f32>(in.world.x, FLOOR_Y, in.world.y) - camera.view_pos.xyz);
+    let cos_theta = saturate(-view_dir.y);
+
+    // wet ground is darker and shines with the sky at grazing angles, the
+    // lighter chunks (higher, drier) take longer to soak
+    let wet = saturate(floor_uniform.wetness * (1.3 - 0.6 * in.tint));
+    let sheen = (0.04 + 0.96 * pow(1.0 - cos_theta, 5.0)) * wet;
+    ground = mix(ground * mix(1.0, 0.5, wet), SKY_SHEEN, sheen);
+    let fresnel = 0.25 + 0.75 * pow(1.0 - cos_theta, 5.0);
+    let screen_uv = in.clip_position.xy / vec2<f32>(textureDimensions(t_reflection));
+    let reflection = textureSample(t_reflection, s_reflection, screen_uv);
+    let amount = reflection.a * fresnel * floor_uniform.mirror;
+    return vec4<f32>(mix(ground, reflection.rgb, amount), 1.0);
+}
@@ -0,0 +1,233 @@
+use crate::instance_buffer::MAX_INSTANCES;
+use crate::math::{Point3, Vec3};
+
+// Shapes cubes can be spawned in all at once, from the console with
+// `spawn grid 10 10 3` and so on. Spacings and radii are in cube half sizes,
+// and every formation lies flat on the xz plane around its center apart
+// from the sphere.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Formation {
+    Grid {
+        columns: u32,
+        rows: u32,
+        spacing: f32,
+    },
+    Ring {
+        count: u32,
+        radius: f32,
+    },
+    // a sunflower spiral, evenly packed however many there are
+    Spiral {
+        count: u32,
+        spacing: f32,
+    },
+    // uniformly spread through the volume, not just on the surface
+    Sphere {
+        count: u32,
+        radius: f32,
+    },
+}
+
+impl Formation {
+    pub fn count(&self) -> usize {
+        match *self {
+            Formation::Grid { columns, rows, .. } => columns as usize * rows as usize,
+            Formation::Ring { count, .. }
+            | Formation::Spiral { count, .. }
+            | Formation::Sphere { count, .. } => count as usize,
+        }
+    }
+
+    pub fn positions(&self, center: Point3, rng: &mut crate::rng::Rng) -> Vec<Point3> {
+        let mut positions = Vec::with_capacity(self.count());
+        match *self {
+            Formation::Grid {
+                columns,
+                rows,
+                spacing,
+            } => {
+                let offset =
+                    Vec3::new(columns as f32 - 1.0, 0.0, rows as f32 - 1.0) * spacing / 2.0;
+                for z in 0..rows {
+                    for x in 0..columns {
+                        let cell = Vec3::new(x as f32, 0.0, z as f32) * spacing;
+                        positions.push(center + cell - offset);
+                    }
+                }
+            }
+            Formation::Ring { count, radius } => {
+                for i in 0..count {
+                    let angle = i as f32 / count as f32 * std::f32::consts::TAU;
+                    positions.push(center + Vec3::new(angle.cos(), 0.0, angle.sin()) * radius);
+                }
+            }
+            Formation::Spiral { count, spacing } => {
+                let golden_angle = std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+                for i in 0..count {
+                    let angle = i as f32 * golden_angle;
+                    let radius = spacing * (i as f32).sqrt();
+                    positions.push(center + Vec3::new(angle.cos(), 0.0, angle.sin()) * radius);
+                }
+            }
+            Formation::Sphere { count, radius } => {
+                while positions.len() < count as usize {
+                    let p = Vec3::new(rng.f32(), rng.f32(), rng.f32()) * 2.0 - Vec3::ONE;
+                    if p.length_squared() <= 1.0 {
+                        positions.push(center + p * radius);
+                    }
+                }
+            }
+        }
+        positions
+    }
+}
+
+impl Formation {
+    // `grid <columns> <rows> [spacing]`, `ring <count> [radius]`,
+    // `spiral <count> [spacing]` or `sphere <count> [radius]`, where any
+    // of them can be given by name instead (`ring 12 radius=4*2`) and be an
+    // expression, see expr.rs
+    pub fn parse(text: &str, rng: &mut crate::rng::Rng) -> anyhow::Result<Self> {
+        let words = text.split_whitespace().collect::<Vec<_>>();
+        let Some((kind, rest)) = words.split_first() else {
+            anyhow::bail!("expected grid, ring, spiral or sphere");
+        };
+        Self::from_args(kind, crate::expr::Args::parse(rest), rng)
+    }
+
+    pub fn from_args(
+        kind: &str,
+        mut args: crate::expr::Args,
+        rng: &mut crate::rng::Rng,
+    ) -> anyhow::Result<Self> {
+        let names: &[&str] = match kind {
+            "grid" => &["columns", "rows", "spacing"],
+            "ring" | "sphere" => &["count", "radius"],
+            "spiral" => &["count", "spacing"],
+            _ => anyhow::bail!("expected grid, ring, spiral or sphere, not {}", kind),
+        };
+        // each by name, or failing that the next plain value
+        let mut positional = args.positional.clone().into_iter();
+        let mut values = Vec::new();
+        for name in names {
+            let value = args.take(name).or_else(|| positional.next());
+            values.push(value.map(|v| crate::expr::eval(v, rng)).transpose()?);
+        }
+        if let Some(extra) = positional.next() {
+            anyhow::bail!(
+                "{} takes {}, {} is one too many",
+                kind,
+                names.join(", "),
+                extra
+            );
+        }
+        args.finish(kind)?;
+        // counts come in as floats too, they just can't have a fraction
+        let count = |i: usize| match values[i] {
+            Some(v) if v >= 1.0 && v.fract() == 0.0 => Ok(v as u32),
+            Some(v) => Err(anyhow::anyhow!("{} isn't a count", v)),
+            None => Err(anyhow::anyhow!("{} needs {}", kind, names[i])),
+        };
+        let or = |i: usize, default: f32| values[i].unwrap_or(default);
+        let formation = match kind {
+            "grid" => Formation::Grid {
+                columns: count(0)?,
+                rows: count(1)?,
+                spacing: or(2, 3.0),
+            },
+            "ring" => Formation::Ring {
+                count: count(0)?,
+                radius: or(1, 10.0),
+            },
+            "spiral" => Formation::Spiral {
+                count: count(0)?,
+                spacing: or(1, 3.0),
+            },
+            _ => Formation::Sphere {
+                count: count(0)?,
+                radius: or(1, 10.0),
+            },
+        };
+        // positions() makes room for the lot up front
+        let total = match formation {
+            Formation::Grid { columns, rows, .. } => columns.checked_mul(rows),
+            _ => Some(formation.count() as u32),
+        };
+        match total {
+            Some(total) if total as usize <= MAX_INSTANCES => Ok(formation),
+            _ => anyhow::bail!("that's more than the {} cubes there can be", MAX_INSTANCES),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    fn parse(text: &str) -> anyhow::Result<Formation> {
+        Formation::parse(text, &mut Rng::new(1))
+    }
+
+    #[test]
+    fn parses_in_order_or_by_name() {
+        let grid = Formation::Grid {
+            columns: 4,
+            rows: 2,
+            spacing: 3.0,
+        };
+        assert_eq!(parse("grid 4 2").unwrap(), grid);
+        assert_eq!(parse("grid rows=2 columns=4").unwrap(), grid);
+        assert_eq!(
+            parse("ring 12 radius=4*2").unwrap(),
+            Formation::Ring {
+                count: 12,
+                radius: 8.0
+            }
+        );
+    }
+
+    #[test]
+    fn bad_counts_are_errors() {
+        for text in [
+            "grid",
+            "grid 4",
+            "ring 0",
+            "ring 2.5",
+            "ring -3",
+            "ring 3 4 5",
+            "ring 3 spacing=1",
+            "cone 3",
+        ] {
+            assert!(parse(text).is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn counts_past_the_cap_are_errors() {
+        // the product overflows a u32
+        assert!(parse("grid 65536 65536").is_err());
+        assert!(parse("grid 2048 1024").is_err());
+        assert!(parse(&format!("sphere {}", MAX_INSTANCES + 1)).is_err());
+        assert!(parse("grid 1024 1024").is_ok());
+    }
+
+    #[test]
+    fn positions_match_the_count() {
+        let center = Point3::new(1.0, 2.0, 3.0);
+        for text in ["grid 5 3", "ring 12", "spiral 40", "sphere 30 radius=2"] {
+            let formation = parse(text).unwrap();
+            let positions = formation.positions(center, &mut Rng::new(1));
+            assert_eq!(positions.len(), formation.count(), "{}", text);
+        }
+        let sphere = parse("sphere 200 radius=2").unwrap();
+        for p in sphere.positions(center, &mut Rng::new(1)) {
+            assert!(p.distance(center) <= 2.0 + 1e-4);
+        }
+        // the grid's centered on where it's spawned
+        let grid = parse("grid 3 3")
+            .unwrap()
+            .positions(center, &mut Rng::new(1));
+        assert_eq!(grid[4], center);
+    }
+}
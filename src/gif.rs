@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+// A small GIF89a writer, just enough for looping clips: one global palette,
+// full frames, a fixed delay. Colors go through a fixed 6x7x6 cube (green
+// gets the extra step, the eye is keenest on it) with ordered dithering, so
+// there's no palette to work out per clip.
+
+const LEVELS: [u32; 3] = [6, 7, 6];
+// compressed codes start one bit wider than the 8 bit indices
+const MIN_CODE_SIZE: u8 = 8;
+const CLEAR: u16 = 1 << MIN_CODE_SIZE;
+const END: u16 = CLEAR + 1;
+const MAX_CODE: u16 = 4095;
+
+const BAYER: [[f32; 4]; 4] = [
+    [0.0, 8.0, 2.0, 10.0],
+    [12.0, 4.0, 14.0, 6.0],
+    [3.0, 11.0, 1.0, 9.0],
+    [15.0, 7.0, 13.0, 5.0],
+];
+
+fn palette() -> Vec<u8> {
+    let mut palette = Vec::with_capacity(256 * 3);
+    for r in 0..LEVELS[0] {
+        for g in 0..LEVELS[1] {
+            for b in 0..LEVELS[2] {
+                for (c, levels) in [r, g, b].into_iter().zip(LEVELS) {
+                    palette.push((c * 255 / (levels - 1)) as u8);
+                }
+            }
+        }
+    }
+    // the table has to be a power of two long
+    palette.resize(256 * 3, 0);
+    palette
+}
+
+// palette indices for `image`, a byte a pixel
+pub fn quantize(image: &image::RgbaImage) -> Vec<u8> {
+    image
+        .enumerate_pixels()
+        .map(|(x, y, p)| {
+            let threshold = BAYER[(y % 4) as usize][(x % 4) as usize] / 16.0 - 0.5;
+            let [r, g, b] = [0, 1, 2].map(|c| {
+                let steps = (LEVELS[c] - 1) as f32;
+                (p[c] as f32 / 255.0 * steps + threshold)
+                    .round()
+                    .clamp(0.0, steps) as u32
+            });
+            ((r * LEVELS[1] + g) * LEVELS[2] + b) as u8
+        })
+        .collect()
+}
+
+// codes packed least significant bit first
+#[derive(Default)]
+struct Bits {
+    bytes: Vec<u8>,
+    buffer: u32,
+    count: u32,
+}
+
+impl Bits {
+    fn write(&mut self, code: u16, size: u32) {
+        self.buffer |= (code as u32) << self.count;
+        self.count += size;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+fn compress(indices: &[u8]) -> Vec<u8> {
+    let mut bits = Bits::default();
+    let mut size = MIN_CODE_SIZE as u32 + 1;
+    bits.write(CLEAR, size);
+    let Some((&first, rest)) = indices.split_first() else {
+        bits.write(END, size);
+        return bits.finish();
+    };
+    let mut codes = HashMap::<(u16, u8), u16>::new();
+    let mut last = END;
+    let mut prefix = first as u16;
+    for &index in rest {
+        if let Some(&code) = codes.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+        bits.write(prefix, size);
+        last += 1;
+        codes.insert((prefix, index), last);
+        if last >= 1 << size {
+            size += 1;
+        }
+        // the table's full, start it over
+        if last == MAX_CODE {
+            bits.write(CLEAR, size);
+            codes.clear();
+            size = MIN_CODE_SIZE as u32 + 1;
+            last = END;
+        }
+        prefix = index as u16;
+    }
+    bits.write(prefix, size);
+    bits.write(END, size);
+    bits.finish()
+}
+
+// `frames` are quantized, `delay` is in hundredths of a second
+pub fn encode<'a>(
+    width: u16,
+    height: u16,
+    frames: impl IntoIterator<Item = &'a Vec<u8>>,
+    delay: u16,
+) -> Vec<u8> {
+    let mut out = b"GIF89a".to_vec();
+    out.extend(width.to_le_bytes());
+    out.extend(height.to_le_bytes());
+    // a global table of 2^(7 + 1) colors
+    out.extend([0xf7, 0, 0]);
+    out.extend(palette());
+    // loop forever
+    out.extend([0x21, 0xff, 0x0b]);
+    out.extend(b"NETSCAPE2.0");
+    out.extend([0x03, 0x01, 0x00, 0x00, 0x00]);
+
+    for frame in frames {
+        out.extend([0x21, 0xf9, 0x04, 0x00]);
+        out.extend(delay.to_le_bytes());
+        out.extend([0x00, 0x00]);
+        out.extend([0x2c, 0, 0, 0, 0]);
+        out.extend(width.to_le_bytes());
+        out.extend(height.to_le_bytes());
+        out.push(0x00);
+        out.push(MIN_CODE_SIZE);
+        for block in compress(frame).chunks(255) {
+            out.push(block.len() as u8);
+            out.extend(block);
+        }
+        out.push(0x00);
+    }
+    out.push(0x3b);
+    out
+}
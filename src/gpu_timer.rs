@@ -0,0 +1,116 @@
+// at most this many marks a frame
+const MARKS: u32 = 8;
+const WANTED: wgpu::Features =
+    wgpu::Features::TIMESTAMP_QUERY.union(wgpu::Features::TIMESTAMP_QUERY_INSIDE_ENCODERS);
+
+// How long the GPU spends on each part of a frame. A timestamp goes into the
+// encoder at each mark, and the time between two marks is charged to the
+// later one's label. Reading them back waits for the whole frame, so this
+// is only made for the benchmark.
+pub struct GpuTimer {
+    queries: wgpu::QuerySet,
+    resolve: wgpu::Buffer,
+    readback: wgpu::Buffer,
+    labels: Vec<&'static str>,
+    // nanoseconds a tick
+    period: f32,
+}
+
+impl GpuTimer {
+    // what the device has to be asked for, if the adapter can do it at all
+    pub fn features(adapter: &wgpu::Adapter) -> wgpu::Features {
+        if adapter.features().contains(WANTED) {
+            WANTED
+        } else {
+            wgpu::Features::empty()
+        }
+    }
+
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(WANTED) {
+            log::warn!("no timestamp queries on this adapter, so no GPU times");
+            return None;
+        }
+        let queries = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("gpu timer queries"),
+            ty: wgpu::QueryType::Timestamp,
+            count: MARKS,
+        });
+        let size = MARKS as u64 * std::mem::size_of::<u64>() as u64;
+        let resolve = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("gpu timer resolve buffer"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("gpu timer readback buffer"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Some(Self {
+            queries,
+            resolve,
+            readback,
+            labels: Vec::new(),
+            period: queue.get_timestamp_period(),
+        })
+    }
+
+    // the first mark of a frame only starts the clock, its label isn't used
+    pub fn mark(&mut self, encoder: &mut wgpu::CommandEncoder, label: &'static str) {
+        if self.labels.len() as u32 == MARKS {
+            return;
+        }
+        encoder.write_timestamp(&self.queries, self.labels.len() as u32);
+        self.labels.push(label);
+    }
+
+    // at the end of the frame's encoder, after the last mark
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        let count = self.labels.len() as u32;
+        if count == 0 {
+            return;
+        }
+        encoder.resolve_query_set(&self.queries, 0..count, &self.resolve, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve,
+            0,
+            &self.readback,
+            0,
+            count as u64 * std::mem::size_of::<u64>() as u64,
+        );
+    }
+
+    // After the submit, waits for the GPU and gives each label the
+    // milliseconds since the mark before it.
+    pub fn read(&mut self, device: &wgpu::Device) -> Vec<(&'static str, f64)> {
+        let labels = std::mem::take(&mut self.labels);
+        if labels.len() < 2 {
+            return Vec::new();
+        }
+        let slice = self
+            .readback
+            .slice(..labels.len() as u64 * std::mem::size_of::<u64>() as u64);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        if let Ok(Err(e)) = receiver.recv() {
+            log::error!("couldn't read the GPU times: {}", e);
+            return Vec::new();
+        }
+        let ticks = bytemuck::cast_slice::<u8, u64>(&slice.get_mapped_range()).to_vec();
+        self.readback.unmap();
+        ticks
+            .windows(2)
+            .zip(&labels[1..])
+            .map(|(pair, label)| {
+                let nanoseconds = pair[1].saturating_sub(pair[0]) as f64 * self.period as f64;
+                (*label, nanoseconds / 1_000_000.0)
+            })
+            .collect()
+    }
+}
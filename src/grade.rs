@@ -0,0 +1,194 @@
+use anyhow::bail;
+
+// entries along each side, the usual size for LUTs saved as strips
+pub const LUT_SIZE: u32 = 32;
+
+pub struct Lut {
+    pub name: String,
+    // rgba8, red fastest then green then blue
+    data: Vec<u8>,
+}
+
+impl Lut {
+    // A 32x32x32 LUT saved as one 1024x32 strip: 32 squares side by side, blue
+    // picks the square, red goes across it and green down it.
+    pub fn from_strip(name: &str, bytes: &[u8]) -> anyhow::Result<Self> {
+        let image = image::load_from_memory(bytes)?.to_rgba8();
+        if image.dimensions() != (LUT_SIZE * LUT_SIZE, LUT_SIZE) {
+            bail!(
+                "{} is {}x{}, a LUT strip is {}x{}",
+                name,
+                image.width(),
+                image.height(),
+                LUT_SIZE * LUT_SIZE,
+                LUT_SIZE
+            );
+        }
+        let mut data = Vec::with_capacity((LUT_SIZE * LUT_SIZE * LUT_SIZE * 4) as usize);
+        for b in 0..LUT_SIZE {
+            for g in 0..LUT_SIZE {
+                for r in 0..LUT_SIZE {
+                    data.extend_from_slice(&image.get_pixel(b * LUT_SIZE + r, g).0);
+                }
+            }
+        }
+        Ok(Self {
+            name: name.to_string(),
+            data,
+        })
+    }
+
+    // the strips in res/luts
+    pub fn built_in() -> Vec<Self> {
+        [
+            (
+                "teal orange",
+                &include_bytes!("../res/luts/teal_orange.png")[..],
+            ),
+            ("sepia", &include_bytes!("../res/luts/sepia.png")[..]),
+        ]
+        .into_iter()
+        .map(|(name, bytes)| Self::from_strip(name, bytes).unwrap())
+        .collect()
+    }
+}
+
+// Color grading through a 3D LUT, the last thing done to the scene. There's
+// no HDR target or tonemapping yet, so it grades the scene colors as drawn.
+// Which LUT and how strongly it applies is set from the options menu.
+pub struct ColorGrade {
+    pub luts: Vec<Lut>,
+    // into luts, None is off
+    current: Option<usize>,
+    strength: f32,
+    texture: wgpu::Texture,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl ColorGrade {
+    pub fn new(device: &wgpu::Device, post: &crate::post::Post, luts: Vec<Lut>) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("grade lut"),
+            size: wgpu::Extent3d {
+                width: LUT_SIZE,
+                height: LUT_SIZE,
+                depth_or_array_layers: LUT_SIZE,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("grade lut sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("grade uniform buffer"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = crate::reflect::bind_group_layout(
+            device,
+            "grade bind group layout",
+            &[("grade.wgsl", 1)],
+        )
+        .unwrap();
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some("grade bind group"),
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("grade shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                crate::shaders::load("grade.wgsl", post.defines())
+                    .unwrap()
+                    .into(),
+            ),
+        });
+        let pipeline = post.pipeline(device, "grade pipeline", &shader, &[&bind_group_layout]);
+
+        Self {
+            luts,
+            current: None,
+            strength: 1.0,
+            texture,
+            uniform_buffer,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    // uploads the chosen LUT, only when it's a different one
+    pub fn set(&mut self, queue: &wgpu::Queue, lut: Option<usize>, strength: f32) {
+        let lut = lut.filter(|i| *i < self.luts.len());
+        if lut != self.current {
+            if let Some(i) = lut {
+                queue.write_texture(
+                    wgpu::ImageCopyTexture {
+                        texture: &self.texture,
+                        mip_level: 0,
+                        origin: wgpu::Origin3d::ZERO,
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    &self.luts[i].data,
+                    wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(4 * LUT_SIZE),
+                        rows_per_image: Some(LUT_SIZE),
+                    },
+                    wgpu::Extent3d {
+                        width: LUT_SIZE,
+                        height: LUT_SIZE,
+                        depth_or_array_layers: LUT_SIZE,
+                    },
+                );
+            }
+        }
+        self.current = lut;
+        self.strength = strength;
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[strength, LUT_SIZE as f32, 0.0, 0.0]),
+        );
+    }
+}
+
+impl crate::post::Effect for ColorGrade {
+    fn enabled(&self) -> bool {
+        self.current.is_some() && self.strength > 0.0
+    }
+
+    fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
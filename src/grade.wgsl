@@ -0,0 +1,29 @@
+#include "post.wgsl"
+
+struct Grade {
+    strength: f32,
+    // entries along each side of the LUT
+    size: f32,
+}
+@group(1) @binding(0)
+var<uniform> grade: Grade;
+@group(1) @binding(1)
+var t_lut: texture_3d<f32>;
+@group(1) @binding(2)
+var s_lut: sampler;
+
+// @tweak grade_tint color = 1 1 1
+// @tweak grade_saturation 0..2 = 1
+
+@fragment
+fn fs_main(in: PostOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_input, s_input, in.uv);
+    // LUTs are made for the colors as they're shown, so grade those
+    let display = shown(color.rgb);
+    // through texel centres, so 0 and 1 land on the first and last entries
+    let lookup = display * (grade.size - 1.0) / grade.size + 0.5 / grade.size;
+    var graded = mix(display, textureSample(t_lut, s_lut, lookup).rgb, grade.strength);
+    let luma = dot(graded, vec3<f32>(0.2126, 0.7152, 0.0722));
+    graded = saturate(mix(vec3<f32>(luma), graded, grade_saturation) * grade_tint);
+    return vec4<f32>(stored(graded), color.a);
+}
@@ -0,0 +1,58 @@
+use crate::math::{Point3, Vec3};
+
+// a cube is 2 across, so one tile is one cube
+pub const TILE: f32 = 2.0;
+const TWEEN_SECONDS: f32 = 0.15;
+
+// Turn based movement: every key press moves the player cube exactly one
+// tile, eased over TWEEN_SECONDS. A press during a move is remembered and
+// taken as soon as that move ends, anything after it is dropped.
+#[derive(Default)]
+pub struct GridMove {
+    pub enabled: bool,
+    from: Point3,
+    to: Point3,
+    // 0..1 through the current move, 1 when standing still
+    progress: f32,
+    queued: Option<Vec3>,
+}
+
+impl GridMove {
+    // switches the mode on, easing the cube onto the nearest tile
+    pub fn enable(&mut self, at: Point3) {
+        self.enabled = true;
+        self.from = at;
+        self.to = (at / TILE).round() * TILE;
+        self.to.y = at.y;
+        self.progress = 0.0;
+        self.queued = None;
+    }
+
+    // `direction` is in tiles, only x and z are used
+    pub fn step(&mut self, direction: Vec3) {
+        if self.progress < 1.0 {
+            self.queued = Some(direction);
+            return;
+        }
+        self.from = self.to;
+        self.to += Vec3::new(direction.x, 0.0, direction.z) * TILE;
+        self.progress = 0.0;
+    }
+
+    // where the cube is this frame, None when it's standing still
+    pub fn update(&mut self, dt: f32) -> Option<Point3> {
+        if !self.enabled || self.progress >= 1.0 {
+            return None;
+        }
+        self.progress = (self.progress + dt / TWEEN_SECONDS).min(1.0);
+        let t = self.progress;
+        let eased = t * t * (3.0 - 2.0 * t);
+        let position = self.from.lerp(self.to, eased);
+        if self.progress >= 1.0 {
+            if let Some(direction) = self.queued.take() {
+                self.step(direction);
+            }
+        }
+        Some(position)
+    }
+}
@@ -0,0 +1,112 @@
+use winit::event::WindowEvent;
+use winit::window::Window;
+
+// egui over everything else, for the panels that want real widgets rather
+// than text and arrow keys (the material editor so far). It only runs on
+// frames where one of them is open, in a pass of its own after the text.
+#[derive(Default)]
+pub struct Gui {
+    ctx: egui::Context,
+    gpu: Option<GuiGpu>,
+}
+
+struct GuiGpu {
+    state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+}
+
+// a frame's worth of egui, tessellated and waiting for `draw`
+pub struct Frame {
+    jobs: Vec<egui::ClippedPrimitive>,
+    textures: egui::TexturesDelta,
+    pixels_per_point: f32,
+}
+
+impl Gui {
+    pub fn init_gpu(
+        &mut self,
+        window: &Window,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+    ) {
+        let state = egui_winit::State::new(
+            self.ctx.clone(),
+            egui::ViewportId::ROOT,
+            window,
+            Some(window.scale_factor() as f32),
+            None,
+            Some(device.limits().max_texture_dimension_2d as usize),
+        );
+        let renderer = egui_wgpu::Renderer::new(device, format, None, 1, false);
+        self.gpu = Some(GuiGpu { state, renderer });
+    }
+
+    // whether egui used the event, which it only gets while something's open
+    pub fn process_events(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        match &mut self.gpu {
+            Some(gpu) => gpu.state.on_window_event(window, event).consumed,
+            None => false,
+        }
+    }
+
+    // runs `ui` for this frame
+    pub fn run(&mut self, window: &Window, ui: impl FnMut(&egui::Context)) -> Option<Frame> {
+        let gpu = self.gpu.as_mut()?;
+        let input = gpu.state.take_egui_input(window);
+        let output = self.ctx.run(input, ui);
+        gpu.state
+            .handle_platform_output(window, output.platform_output);
+        Some(Frame {
+            jobs: self.ctx.tessellate(output.shapes, output.pixels_per_point),
+            textures: output.textures_delta,
+            pixels_per_point: output.pixels_per_point,
+        })
+    }
+
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        size: (u32, u32),
+        frame: Frame,
+    ) {
+        let Some(gpu) = &mut self.gpu else {
+            return;
+        };
+        let screen = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [size.0, size.1],
+            pixels_per_point: frame.pixels_per_point,
+        };
+        for (id, delta) in &frame.textures.set {
+            gpu.renderer.update_texture(device, queue, *id, delta);
+        }
+        // what it gives back is from paint callbacks, there aren't any
+        let _ = gpu
+            .renderer
+            .update_buffers(device, queue, encoder, &frame.jobs, &screen);
+        {
+            let mut render_pass = encoder
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("gui pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                })
+                .forget_lifetime();
+            gpu.renderer.render(&mut render_pass, &frame.jobs, &screen);
+        }
+        for id in &frame.textures.free {
+            gpu.renderer.free_texture(id);
+        }
+    }
+}
@@ -0,0 +1,370 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+// what's listened on without `--http <address>`'s host
+const DEFAULT_HOST: &str = "127.0.0.1";
+// a command is a line, nothing posted needs to be bigger
+const MAX_BODY: usize = 4096;
+// for the request line and each header, and how many headers there can be
+const MAX_LINE: usize = 1024;
+const MAX_HEADERS: usize = 32;
+// POST /command has to say it's this, which a browser can't send from a page
+// without asking first, and nothing here answers the asking
+const COMMAND_TYPE: &str = "application/x-praxis-command";
+// the console commands that can come over HTTP. `bind` and `record` write
+// files, `visualizer` opens the microphone and `profile` says where things
+// are on disk, so they're left out
+const COMMANDS: [&str; 34] = [
+    "boids",
+    "buffer",
+    "chase",
+    "clock",
+    "collide",
+    "crt",
+    "diff",
+    "grid",
+    "help",
+    "ignore",
+    "impostors",
+    "interp",
+    "joint",
+    "joints",
+    "layer",
+    "light",
+    "mirror",
+    "music",
+    "portals",
+    "quality",
+    "skin",
+    "snapshot",
+    "snapshots",
+    "sneak",
+    "spawn",
+    "sprint",
+    "sprite",
+    "sprites",
+    "stream",
+    "texture",
+    "textures",
+    "tilemap",
+    "tweak",
+    "weather",
+];
+// how long a request waits on the frame loop before giving up
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+pub enum Route {
+    Stats,
+    Screenshot,
+    // a console line
+    Command(String),
+}
+
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn json(body: String) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json",
+            body: body.into_bytes(),
+        }
+    }
+
+    pub fn text(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: body.into().into_bytes(),
+        }
+    }
+}
+
+// a request waiting for the frame loop, which answers through `reply`
+pub struct Request {
+    pub route: Route,
+    pub reply: Sender<Response>,
+}
+
+// A small HTTP server for scripts and monitoring, only built with `cargo
+// build --features http` and only started with `--http <port>`. It listens
+// on localhost unless given a whole address. GET /stats is the last frame's
+// numbers as JSON, GET /screenshot the next frame as a PNG, and POST
+// /command runs its body as a console line and gives back what the console
+// printed.
+//
+// Only what looks like it came from a script on this machine is answered:
+// anything with an Origin (so from a browser page) or a Host that isn't
+// 127.0.0.1 or localhost at the port listened on is turned away, and so is
+// a command posted without COMMAND_TYPE or that isn't one of the COMMANDS.
+//
+// Connections are taken one at a time on a thread of their own, which
+// hands each request to the frame loop and waits for the answer, so
+// nothing touches the app from off the main thread.
+pub struct HttpApi {
+    requests: Receiver<Request>,
+}
+
+impl HttpApi {
+    pub fn from_args() -> Option<Self> {
+        let args = std::env::args().collect::<Vec<_>>();
+        let address = args
+            .iter()
+            .position(|a| a == "--http")
+            .and_then(|i| args.get(i + 1))?;
+        let address = if address.contains(':') {
+            address.clone()
+        } else {
+            format!("{}:{}", DEFAULT_HOST, address)
+        };
+        let listener = match TcpListener::bind(&address) {
+            Ok(listener) => listener,
+            Err(e) => {
+                log::error!("no HTTP API, couldn't listen on {}: {}", address, e);
+                return None;
+            }
+        };
+        if !address.starts_with(DEFAULT_HOST) && !address.starts_with("localhost") {
+            log::warn!(
+                "the HTTP API on {} can be reached from other machines",
+                address
+            );
+        }
+        log::info!("HTTP API on http://{}", address);
+        let port = listener.local_addr().map_or(0, |a| a.port());
+        let (sender, requests) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("http api".into())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    if let Err(e) = serve(stream, port, &sender) {
+                        log::warn!("HTTP API: {:#}", e);
+                    }
+                }
+            })
+            .unwrap();
+        Some(Self { requests })
+    }
+
+    // everything that's come in since the last poll, to be answered
+    pub fn poll(&self) -> Vec<Request> {
+        self.requests.try_iter().collect()
+    }
+}
+
+// the request line and the headers that matter here
+#[derive(Debug, Default)]
+struct Head {
+    method: String,
+    path: String,
+    length: usize,
+    host: Option<String>,
+    origin: bool,
+    content_type: Option<String>,
+}
+
+// one line, or an error once it's longer than MAX_LINE
+fn read_line(reader: &mut impl BufRead) -> anyhow::Result<String> {
+    let mut line = String::new();
+    reader.take(MAX_LINE as u64).read_line(&mut line)?;
+    anyhow::ensure!(line.ends_with('\n'), "a line over {} bytes", MAX_LINE);
+    Ok(line)
+}
+
+// None if it's not HTTP or goes on too long
+fn read_head(reader: &mut impl BufRead) -> Option<Head> {
+    let request_line = read_line(reader).ok()?;
+    let [method, path, _] = request_line.split_whitespace().collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let mut head = Head {
+        method: method.to_string(),
+        path: path.to_string(),
+        ..Default::default()
+    };
+    for _ in 0..=MAX_HEADERS {
+        let header = read_line(reader).ok()?;
+        let header = header.trim();
+        if header.is_empty() {
+            return Some(head);
+        }
+        let (name, value) = header.split_once(':')?;
+        let value = value.trim();
+        match name.to_ascii_lowercase().as_str() {
+            "content-length" => head.length = value.parse().ok()?,
+            "host" => head.host = Some(value.to_ascii_lowercase()),
+            "origin" => head.origin = true,
+            "content-type" => head.content_type = Some(value.to_ascii_lowercase()),
+            _ => {}
+        }
+    }
+    None
+}
+
+// turned away unless it's from a script here, see HttpApi
+fn check(head: &Head, port: u16) -> Result<(), Response> {
+    let local = [format!("127.0.0.1:{}", port), format!("localhost:{}", port)];
+    if head.origin || !head.host.as_ref().is_some_and(|h| local.contains(h)) {
+        return Err(Response::text(403, "only from scripts on this machine\n"));
+    }
+    if head.method == "POST" && head.content_type.as_deref() != Some(COMMAND_TYPE) {
+        return Err(Response::text(
+            415,
+            format!("commands are posted as {}\n", COMMAND_TYPE),
+        ));
+    }
+    Ok(())
+}
+
+// whether a console line can be run from here
+fn allowed(line: &str) -> bool {
+    line.split_whitespace()
+        .next()
+        .is_some_and(|command| COMMANDS.contains(&command))
+}
+
+fn serve(stream: TcpStream, port: u16, requests: &Sender<Request>) -> anyhow::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let response = match read_head(&mut reader) {
+        Some(head) => match check(&head, port) {
+            Err(response) => response,
+            Ok(()) => {
+                let route = match (head.method.as_str(), head.path.as_str()) {
+                    ("GET", "/stats") => Some(Route::Stats),
+                    ("GET", "/screenshot") => Some(Route::Screenshot),
+                    ("POST", "/command") if head.length > MAX_BODY => None,
+                    ("POST", "/command") => {
+                        let mut body = vec![0; head.length];
+                        reader.read_exact(&mut body)?;
+                        Some(Route::Command(String::from_utf8(body)?.trim().to_string()))
+                    }
+                    _ => None,
+                };
+                match route {
+                    Some(Route::Command(line)) if !allowed(&line) => Response::text(
+                        403,
+                        format!("only these commands: {}\n", COMMANDS.join(", ")),
+                    ),
+                    Some(route) => ask(requests, route),
+                    None if head.length > MAX_BODY => {
+                        Response::text(413, "commands are one line\n")
+                    }
+                    None => Response::text(404, "GET /stats, GET /screenshot or POST /command\n"),
+                }
+            }
+        },
+        None => Response::text(400, "not HTTP, or headers too long\n"),
+    };
+
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len()
+    )?;
+    stream.write_all(&response.body)?;
+    Ok(())
+}
+
+fn ask(requests: &Sender<Request>, route: Route) -> Response {
+    let (reply, answer) = mpsc::channel();
+    if requests.send(Request { route, reply }).is_err() {
+        return Response::text(503, "praxis is closing\n");
+    }
+    answer
+        .recv_timeout(TIMEOUT)
+        .unwrap_or_else(|_| Response::text(503, "no frame came round to answer\n"))
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn head(request: &str) -> Option<Head> {
+        read_head(&mut request.as_bytes())
+    }
+
+    #[test]
+    fn reads_the_head() {
+        let head = head("POST /command HTTP/1.1\r\nHost: LocalHost:8080\r\nContent-Length: 5\r\nContent-Type: application/x-praxis-command\r\n\r\ngrid\n").unwrap();
+        assert_eq!(
+            (head.method.as_str(), head.path.as_str()),
+            ("POST", "/command")
+        );
+        assert_eq!(head.length, 5);
+        assert_eq!(head.host.as_deref(), Some("localhost:8080"));
+        assert!(check(&head, 8080).is_ok());
+    }
+
+    #[test]
+    fn long_lines_and_many_headers_arent_read() {
+        let long = format!("GET /stats HTTP/1.1\r\nX: {}\r\n\r\n", "a".repeat(MAX_LINE));
+        assert!(head(&long).is_none());
+        let many = format!(
+            "GET /stats HTTP/1.1\r\n{}\r\n",
+            "X: a\r\n".repeat(MAX_HEADERS + 1)
+        );
+        assert!(head(&many).is_none());
+        assert!(head("nonsense\r\n\r\n").is_none());
+    }
+
+    #[test]
+    fn only_scripts_here() {
+        let status = |request: &str| check(&head(request).unwrap(), 8080).err().map(|r| r.status);
+        assert_eq!(
+            status("GET /stats HTTP/1.1\r\nHost: 127.0.0.1:8080\r\n\r\n"),
+            None
+        );
+        assert_eq!(status("GET /stats HTTP/1.1\r\n\r\n"), Some(403));
+        assert_eq!(
+            status("GET /stats HTTP/1.1\r\nHost: evil.example:8080\r\n\r\n"),
+            Some(403)
+        );
+        assert_eq!(
+            status("GET /stats HTTP/1.1\r\nHost: localhost:9090\r\n\r\n"),
+            Some(403)
+        );
+        assert_eq!(
+            status("GET /stats HTTP/1.1\r\nHost: localhost:8080\r\nOrigin: http://localhost:8080\r\n\r\n"),
+            Some(403)
+        );
+        assert_eq!(
+            status("POST /command HTTP/1.1\r\nHost: localhost:8080\r\nContent-Type: text/plain\r\n\r\n"),
+            Some(415)
+        );
+    }
+
+    #[test]
+    fn only_safe_commands() {
+        assert!(allowed("spawn ring 12"));
+        assert!(allowed("weather rain"));
+        assert!(!allowed("record /tmp/out.y4m"));
+        assert!(!allowed("bind quit q"));
+        assert!(!allowed("visualizer"));
+        assert!(!allowed(""));
+    }
+}
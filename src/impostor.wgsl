@@ -0,0 +1,67 @@
+#define CAMERA_GROUP 0
+#define INSTANCED
+#include "common.wgsl"
+
+// the cube seen from YAW_TILES directions around it (columns) at
+// PITCH_TILES heights (rows), drawn once at startup by lod.rs
+@group(1) @binding(0)
+var t_atlas: texture_2d<f32>;
+@group(1) @binding(1)
+var s_atlas: sampler;
+
+const YAW_TILES: f32 = 8.0;
+const PITCH_TILES: f32 = 4.0;
+const PITCH_STEP: f32 = 0.43633231;
+const TAU: f32 = 6.2831853;
+// what the atlas cameras framed
+const RADIUS: f32 = 1.7320508;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, instance: InstanceInput) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, -1.0), vec2<f32>(-1.0, 1.0),
+        vec2<f32>(-1.0, 1.0), vec2<f32>(1.0, -1.0), vec2<f32>(1.0, 1.0),
+    );
+    let corner = corners[index];
+    let model = model_matrix(instance);
+    let center = model[3].xyz;
+    let rotation = mat3x3<f32>(model[0].xyz, model[1].xyz, model[2].xyz);
+    let to_eye = normalize(camera.view_pos.xyz - center);
+
+    // which way the eye is from the cube in the cube's own space, picks the tile
+    let local = transpose(rotation) * to_eye;
+    let yaw = atan2(local.x, local.z);
+    let pitch = asin(clamp(local.y, -1.0, 1.0));
+    let column = (round(yaw / (TAU / YAW_TILES)) + YAW_TILES) % YAW_TILES;
+    let row = clamp(round(pitch / PITCH_STEP), 0.0, PITCH_TILES - 1.0);
+
+    // turned to the eye, with up as close to the cube's own up as it gets,
+    // like the atlas cameras
+    var right = cross(rotation[1], to_eye);
+    if length(right) < 1e-4 {
+        right = rotation[0];
+    }
+    right = normalize(right);
+    let up = cross(to_eye, right);
+
+    var out: VertexOutput;
+    let world = center + (corner.x * right + corner.y * up) * RADIUS;
+    out.clip_position = camera.view_proj * vec4<f32>(world, 1.0);
+    let tile_uv = corner * vec2<f32>(0.5, -0.5) + 0.5;
+    out.uv = (vec2<f32>(column, row) + tile_uv) / vec2<f32>(YAW_TILES, PITCH_TILES);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_atlas, s_atlas, in.uv);
+    if color.a < 0.5 {
+        discard;
+    }
+    return vec4<f32>(color.rgb, 1.0);
+}
@@ -0,0 +1,71 @@
+use crate::InstanceRaw;
+
+// never smaller than this many instances
+const MIN_CAPACITY: usize = 1024;
+// and never asked to hold more cubes than this, a gigabyte of instances is
+// a typo and not a scene
+pub const MAX_INSTANCES: usize = 1 << 20;
+
+// The cubes' instances on the GPU, in a buffer with room to spare. Adding a
+// cube used to make the whole buffer again; now it's only made again when
+// the cubes outgrow it, at the next power of two up, and otherwise what
+// changed is written into the one there is. Past the cubes it's whatever
+// was left there, so anything reading it goes by the sim's count and not
+// the buffer's size.
+pub struct InstanceBuffer {
+    pub buffer: wgpu::Buffer,
+    // in instances
+    capacity: usize,
+}
+
+impl InstanceBuffer {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, instances: &[InstanceRaw]) -> Self {
+        let capacity = capacity_for(instances.len());
+        let mut instance_buffer = Self {
+            buffer: create(device, capacity),
+            capacity,
+        };
+        instance_buffer.upload(device, queue, instances);
+        instance_buffer
+    }
+
+    // Makes sure `count` fit, with a new and empty buffer if they don't.
+    // Gives back whether it had to, the caller then has to upload again.
+    pub fn reserve(&mut self, device: &wgpu::Device, count: usize) -> bool {
+        if count <= self.capacity {
+            return false;
+        }
+        self.capacity = capacity_for(count);
+        self.buffer = create(device, self.capacity);
+        log::debug!("instance buffer grown to {}", self.capacity);
+        true
+    }
+
+    // all of `instances` from the start, growing first if they don't fit
+    pub fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        instances: &[InstanceRaw],
+    ) {
+        self.reserve(device, instances.len());
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(instances));
+    }
+}
+
+fn capacity_for(count: usize) -> usize {
+    count.next_power_of_two().max(MIN_CAPACITY)
+}
+
+// the lod cull pass and the buffer inspector read it too
+fn create(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("cube instance buffer"),
+        size: (capacity * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX
+            | wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_DST
+            | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    })
+}
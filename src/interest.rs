@@ -0,0 +1,83 @@
+use crate::math::Vec3;
+use crate::Instance;
+
+// cubes further than this from a client's player aren't sent to it at all
+pub const RADIUS: f32 = 80.0;
+// how much a cube's importance halves over, distance wise
+const FALLOFF: f32 = 10.0;
+// a cube moving at 1 unit a tick counts for this many times a still one
+const MOVING: f32 = 8.0;
+// where a cube the client has never been sent starts, so it's sent soon
+const NEW: f32 = 1000.0;
+// the most that can be saved up for a burst, in seconds of the cap
+const BURST: f32 = 0.25;
+
+// What one client gets sent of the cubes. Every cube in range gains
+// priority each snapshot, faster the nearer and quicker it is, and the
+// ones with the most go out until the client's bandwidth cap is used up,
+// their priority starting again from nothing. Far or still cubes still get
+// their turn, just less often, and the client's own cube goes every time.
+// Out of range ones are sent once, so the client knows they're there, and
+// then stay wherever it last saw them.
+pub struct Interest {
+    priority: Vec<f32>,
+    // bytes that can go out, topped up with the cap as time goes by
+    allowance: f32,
+}
+
+impl Interest {
+    pub fn new() -> Self {
+        Self {
+            priority: Vec::new(),
+            allowance: 0.0,
+        }
+    }
+
+    // the cubes were renumbered, so what the client has can't be trusted
+    // and everything goes again
+    pub fn forget(&mut self) {
+        self.priority.clear();
+    }
+
+    // The cubes to send this snapshot, most important first, `dt` seconds
+    // after the last. `velocities` are in units a tick and `cap` is in
+    // bytes a second.
+    pub fn choose(
+        &mut self,
+        dt: f32,
+        player: usize,
+        cubes: &[Instance],
+        velocities: &[Vec3],
+        cap: f32,
+    ) -> Vec<usize> {
+        self.priority.resize(cubes.len(), NEW);
+        self.allowance = (self.allowance + cap * dt).min(cap * BURST);
+        let center = cubes[player].position;
+        let mut wanted = Vec::new();
+        for (i, cube) in cubes.iter().enumerate() {
+            let distance = cube.position.distance(center);
+            let unseen = self.priority[i] >= NEW;
+            if i == player || (distance > RADIUS && !unseen) {
+                continue;
+            }
+            let speed = velocities.get(i).map_or(0.0, |v| v.length());
+            self.priority[i] += dt * (1.0 + speed * MOVING) / (1.0 + distance / FALLOFF);
+            wanted.push(i);
+        }
+        wanted.sort_by(|a, b| self.priority[*b].total_cmp(&self.priority[*a]));
+
+        let mut chosen = vec![player];
+        for i in wanted {
+            if crate::net::snapshot_bytes(chosen.len() + 1) as f32 > self.allowance {
+                break;
+            }
+            chosen.push(i);
+        }
+        for &i in &chosen {
+            self.priority[i] = 0.0;
+        }
+        // the player's own cube goes even over the cap, it just borrows
+        self.allowance -= crate::net::snapshot_bytes(chosen.len()) as f32;
+        chosen
+    }
+}
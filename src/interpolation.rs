@@ -0,0 +1,143 @@
+use crate::math::{Point3, Quat};
+use std::collections::VecDeque;
+use std::time::Instant;
+
+// how far behind the server other cubes are shown without `--interp-delay <ms>`,
+// two snapshots' worth
+const DELAY: f64 = 0.1;
+// a cube whose snapshots have stopped coming goes on this long at the speed
+// it was going, then waits
+const MAX_EXTRAPOLATION: f64 = 0.25;
+// a cube keeps at most this many snapshots, more than any delay needs
+const MAX_SAMPLES: usize = 32;
+// the server clock seeming to jump more than this is a new server, not lag
+const RESYNC: f64 = 1.0;
+
+struct Sample {
+    // server time, in seconds
+    time: f64,
+    position: Point3,
+    rotation: Quat,
+}
+
+// Other cubes drawn from the snapshots that have come in for them, a
+// little in the past: `delay` behind the server's clock, so there's nearly
+// always a snapshot either side to blend between and a late or lost packet
+// doesn't show. When they run out anyway the cube carries on at the speed
+// it was going for a moment rather than stopping dead. The player's own
+// cube isn't buffered, it goes where the server says straight away.
+pub struct Interpolation {
+    // seconds
+    pub delay: f64,
+    cubes: Vec<VecDeque<Sample>>,
+    // the server's clock less ours, from when snapshots arrive
+    offset: Option<f64>,
+    started: Instant,
+}
+
+impl Default for Interpolation {
+    fn default() -> Self {
+        Self {
+            delay: DELAY,
+            cubes: Vec::new(),
+            offset: None,
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Interpolation {
+    pub fn from_args() -> Self {
+        let args = std::env::args().collect::<Vec<_>>();
+        let delay = args
+            .iter()
+            .position(|a| a == "--interp-delay")
+            .and_then(|i| args.get(i + 1))
+            .and_then(|ms| ms.parse::<f64>().ok())
+            .map_or(DELAY, |ms| ms / 1000.0);
+        Self {
+            delay,
+            ..Default::default()
+        }
+    }
+
+    // the cubes were renumbered, nothing buffered is any use
+    pub fn reset(&mut self, count: usize) {
+        self.cubes.clear();
+        self.cubes.resize_with(count, VecDeque::new);
+    }
+
+    // cube `i` as of server tick `tick`, after `arrived` for its snapshot
+    pub fn push(&mut self, i: usize, tick: u32, position: Point3, rotation: Quat) {
+        let time = server_time(tick);
+        let Some(samples) = self.cubes.get_mut(i) else {
+            return;
+        };
+        if samples.back().is_some_and(|s| s.time >= time) {
+            return;
+        }
+        samples.push_back(Sample {
+            time,
+            position,
+            rotation,
+        });
+        if samples.len() > MAX_SAMPLES {
+            samples.pop_front();
+        }
+    }
+
+    // Where cube `i` should be drawn now, None if nothing's come in for it.
+    pub fn sample(&mut self, i: usize) -> Option<(Point3, Quat)> {
+        let now = self.now()? - self.delay;
+        let samples = self.cubes.get_mut(i)?;
+        // only the last one from before now is needed to blend from
+        while samples.len() > 2 && samples[1].time <= now {
+            samples.pop_front();
+        }
+        let newest = samples.back()?;
+        match samples.iter().position(|s| s.time > now) {
+            Some(0) => {
+                let oldest = &samples[0];
+                Some((oldest.position, oldest.rotation))
+            }
+            Some(next) => {
+                let (a, b) = (&samples[next - 1], &samples[next]);
+                let t = ((now - a.time) / (b.time - a.time)) as f32;
+                Some((
+                    a.position.lerp(b.position, t),
+                    a.rotation.slerp(b.rotation, t),
+                ))
+            }
+            None if samples.len() < 2 => Some((newest.position, newest.rotation)),
+            None => {
+                let previous = &samples[samples.len() - 2];
+                let velocity =
+                    (newest.position - previous.position) / (newest.time - previous.time) as f32;
+                let ahead = (now - newest.time).min(MAX_EXTRAPOLATION) as f32;
+                Some((newest.position + velocity * ahead, newest.rotation))
+            }
+        }
+    }
+
+    // A snapshot from server tick `tick` came in, which says what the
+    // server's clock is now, going by the quickest snapshots to arrive.
+    // One coming in sooner than expected moves the estimate straight up to
+    // it; slower ones only pull it back a little, in case the lag has grown.
+    pub fn arrived(&mut self, tick: u32) {
+        let seen = server_time(tick) - self.started.elapsed().as_secs_f64();
+        self.offset = Some(match self.offset {
+            Some(offset) if (seen - offset).abs() > RESYNC => seen,
+            Some(offset) if seen > offset => seen,
+            Some(offset) => offset + (seen - offset) * 0.01,
+            None => seen,
+        });
+    }
+
+    fn now(&self) -> Option<f64> {
+        Some(self.started.elapsed().as_secs_f64() + self.offset?)
+    }
+}
+
+fn server_time(tick: u32) -> f64 {
+    tick as f64 * crate::net::TICK.as_secs_f64()
+}
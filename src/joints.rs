@@ -0,0 +1,248 @@
+use crate::math::{Point3, Quat, Vec3};
+use crate::Instance;
+use std::collections::{HashMap, HashSet};
+
+const STEP: f64 = 1.0 / 60.0;
+// a slow frame doesn't get to ask for more than this many steps at once
+const MAX_STEPS: u32 = 5;
+const GRAVITY: f32 = 9.8;
+// rounds of pulling every joint back into shape per step, more is stiffer
+const ITERATIONS: usize = 8;
+// the share of its speed a moving cube loses per second
+const DRAG: f32 = 0.2;
+// per unit of stretch and per unit of stretching speed, every cube weighs the same
+const SPRING_STIFFNESS: f32 = 40.0;
+const SPRING_DAMPING: f32 = 2.0;
+// where a cube's center is when it sits on the floor
+const GROUND: f32 = crate::floor::FLOOR_Y + 1.0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Kind {
+    // b keeps its place and turn relative to a
+    Fixed,
+    // b swings around a at the distance it was connected at, in the
+    // vertical plane through both
+    Hinge,
+    // b is pulled back towards the distance it was connected at, and
+    // bounces around it
+    Spring,
+}
+
+impl Kind {
+    pub const NAMES: [(&'static str, Kind); 3] = [
+        ("fixed", Kind::Fixed),
+        ("hinge", Kind::Hinge),
+        ("spring", Kind::Spring),
+    ];
+
+    pub fn parse(name: &str) -> Option<Kind> {
+        Self::NAMES
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, k)| *k)
+    }
+
+    pub fn name(self) -> &'static str {
+        Self::NAMES.iter().find(|(_, k)| *k == self).unwrap().0
+    }
+}
+
+#[derive(Clone)]
+pub struct Joint {
+    pub kind: Kind,
+    // indices into the cubes
+    pub a: usize,
+    pub b: usize,
+    // b's place and turn in a's frame when they were connected
+    offset: Vec3,
+    relative: Quat,
+    rest: f32,
+    // the hinge's axis in world space
+    axis: Vec3,
+    // from a to b and b's turn when they were connected, so b can turn
+    // with the swing
+    direction: Vec3,
+    rotation: Quat,
+}
+
+// Cubes connected into contraptions: pendulums, chains, stacks that sag.
+// Every cube in a joint falls and is pulled back into shape by its joints
+// on a fixed step, position based so long chains stay stable; a pinned
+// cube holds still and the others hang off it. The first cube of a new
+// contraption is pinned where it is.
+#[derive(Default, Clone)]
+pub struct Joints {
+    pub joints: Vec<Joint>,
+    pinned: HashSet<usize>,
+    // where each moving cube was a step ago, which is its velocity
+    previous: HashMap<usize, Point3>,
+    acc: f64,
+}
+
+impl Joints {
+    pub fn connect(&mut self, kind: Kind, a: usize, b: usize, cubes: &[Instance]) {
+        if !self.joints.iter().any(|j| j.a == a || j.b == a) {
+            self.pinned.insert(a);
+        }
+        let (a_cube, b_cube) = (&cubes[a], &cubes[b]);
+        let direction = (b_cube.position - a_cube.position).normalize_or(-Vec3::Y);
+        self.joints.push(Joint {
+            kind,
+            a,
+            b,
+            offset: a_cube.rotation.inverse() * (b_cube.position - a_cube.position),
+            relative: a_cube.rotation.inverse() * b_cube.rotation,
+            rest: a_cube.position.distance(b_cube.position),
+            axis: direction.cross(crate::math::UP).normalize_or(Vec3::X),
+            direction,
+            rotation: b_cube.rotation,
+        });
+    }
+
+    // pins a cube where it is, or lets it go again, and says which
+    pub fn toggle_pin(&mut self, i: usize) -> bool {
+        if !self.pinned.remove(&i) {
+            self.pinned.insert(i);
+            return true;
+        }
+        false
+    }
+
+    pub fn clear(&mut self) {
+        self.joints.clear();
+        self.pinned.clear();
+        self.previous.clear();
+    }
+
+    // cube `i` is gone and the ones after it moved down one
+    pub fn removed(&mut self, i: usize) {
+        self.joints.retain(|j| j.a != i && j.b != i);
+        let shift = |j: usize| if j > i { j - 1 } else { j };
+        for joint in &mut self.joints {
+            joint.a = shift(joint.a);
+            joint.b = shift(joint.b);
+        }
+        self.pinned = self
+            .pinned
+            .iter()
+            .filter(|j| **j != i)
+            .map(|j| shift(*j))
+            .collect();
+        self.previous = self
+            .previous
+            .iter()
+            .filter(|(j, _)| **j != i)
+            .map(|(j, p)| (shift(*j), *p))
+            .collect();
+    }
+
+    // the cubes the joints move
+    pub fn moving(&self) -> impl Iterator<Item = usize> + '_ {
+        let ends = self.joints.iter().flat_map(|j| [j.a, j.b]);
+        ends.filter(|i| !self.pinned.contains(i))
+    }
+
+    // runs however many fixed steps `frame_time` covers and says how many that was
+    pub fn update(&mut self, frame_time: f64, cubes: &mut [Instance]) -> u32 {
+        // cubes can go away underneath the joints
+        self.joints
+            .retain(|j| j.a < cubes.len() && j.b < cubes.len());
+        if self.joints.is_empty() {
+            return 0;
+        }
+        self.acc = (self.acc + frame_time).min(STEP * MAX_STEPS as f64);
+        let mut steps = 0;
+        while self.acc >= STEP {
+            self.acc -= STEP;
+            self.step(cubes);
+            steps += 1;
+        }
+        steps
+    }
+
+    fn weight(&self, i: usize) -> f32 {
+        if self.pinned.contains(&i) {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    fn step(&mut self, cubes: &mut [Instance]) {
+        let dt = STEP as f32;
+        let moving = self.moving().collect::<HashSet<_>>();
+        let mut velocities = moving
+            .iter()
+            .map(|&i| {
+                let previous = self.previous.get(&i).copied().unwrap_or(cubes[i].position);
+                (i, (cubes[i].position - previous) / dt * (1.0 - DRAG * dt))
+            })
+            .collect::<HashMap<_, _>>();
+
+        // springs push on the velocities, everything else is a hard constraint
+        for joint in self.joints.iter().filter(|j| j.kind == Kind::Spring) {
+            let (wa, wb) = (self.weight(joint.a), self.weight(joint.b));
+            if wa + wb == 0.0 {
+                continue;
+            }
+            let delta = cubes[joint.b].position - cubes[joint.a].position;
+            let direction = delta.normalize_or(joint.direction);
+            let va = velocities.get(&joint.a).copied().unwrap_or_default();
+            let vb = velocities.get(&joint.b).copied().unwrap_or_default();
+            let stretch = delta.length() - joint.rest;
+            let stretching = (vb - va).dot(direction);
+            let pull = direction * (SPRING_STIFFNESS * stretch + SPRING_DAMPING * stretching) * dt;
+            if let Some(v) = velocities.get_mut(&joint.a) {
+                *v += pull * wa / (wa + wb) * 2.0;
+            }
+            if let Some(v) = velocities.get_mut(&joint.b) {
+                *v -= pull * wb / (wa + wb) * 2.0;
+            }
+        }
+
+        for (&i, velocity) in &mut velocities {
+            velocity.y -= GRAVITY * dt;
+            self.previous.insert(i, cubes[i].position);
+            cubes[i].position += *velocity * dt;
+        }
+
+        for _ in 0..ITERATIONS {
+            for joint in &self.joints {
+                let (wa, wb) = (self.weight(joint.a), self.weight(joint.b));
+                if wa + wb == 0.0 {
+                    continue;
+                }
+                let (a, b) = (&cubes[joint.a], &cubes[joint.b]);
+                let target = match joint.kind {
+                    Kind::Fixed => a.position + a.rotation * joint.offset,
+                    Kind::Hinge => {
+                        let delta = b.position - a.position;
+                        let across = delta - joint.axis * delta.dot(joint.axis);
+                        a.position + across.normalize_or(joint.direction) * joint.rest
+                    }
+                    Kind::Spring => continue,
+                };
+                let correction = target - b.position;
+                cubes[joint.a].position -= correction * wa / (wa + wb);
+                cubes[joint.b].position += correction * wb / (wa + wb);
+            }
+            for &i in &moving {
+                cubes[i].position.y = cubes[i].position.y.max(GROUND);
+            }
+        }
+
+        for joint in &self.joints {
+            if !moving.contains(&joint.b) {
+                continue;
+            }
+            let (a, b) = (&cubes[joint.a], &cubes[joint.b]);
+            cubes[joint.b].rotation = match joint.kind {
+                Kind::Fixed => a.rotation * joint.relative,
+                Kind::Hinge | Kind::Spring => {
+                    let direction = (b.position - a.position).normalize_or(joint.direction);
+                    Quat::from_rotation_arc(joint.direction, direction) * joint.rotation
+                }
+            };
+        }
+    }
+}
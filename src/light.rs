@@ -0,0 +1,173 @@
+use crate::math::Vec3;
+
+// The sun: one directional light over everything the cube shader draws,
+// diffuse on top of the ambient the light probes give. App keeps these and
+// hands them to Light, so they live through a renderer rebuild; a scenario
+// can bring its own (Scenario::lighting) and `light` in the console changes
+// them.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Lighting {
+    // the way the light travels, it needn't be normalized
+    pub direction: Vec3,
+    // rgb, brighter than 1 is fine
+    pub color: Vec3,
+    // how much of the probes' light is let in
+    pub ambient: f32,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            direction: Vec3::new(-0.4, -1.0, -0.3),
+            color: Vec3::new(0.65, 0.6, 0.55),
+            ambient: 0.8,
+        }
+    }
+}
+
+impl Lighting {
+    pub fn describe(&self) -> String {
+        let d = self.direction.normalize_or(Vec3::NEG_Y);
+        format!(
+            "light from {:.2} {:.2} {:.2}, color {:.2} {:.2} {:.2}, ambient {:.2}",
+            d.x, d.y, d.z, self.color.x, self.color.y, self.color.z, self.ambient
+        )
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct LightUniform {
+    direction: [f32; 4],
+    color: [f32; 4],
+    // in x, then unused
+    ambient: [f32; 4],
+}
+
+impl From<&Lighting> for LightUniform {
+    fn from(lighting: &Lighting) -> Self {
+        Self {
+            direction: lighting
+                .direction
+                .normalize_or(Vec3::NEG_Y)
+                .extend(0.0)
+                .into(),
+            color: lighting.color.extend(1.0).into(),
+            ambient: [lighting.ambient, 0.0, 0.0, 0.0],
+        }
+    }
+}
+
+// cube.wgsl's array is this long
+pub const MAX_POINT_LIGHTS: usize = 16;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PointLight {
+    pub position: Vec3,
+    pub color: Vec3,
+    // nothing past this is lit by it at all
+    pub radius: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct PointLightRaw {
+    // the radius in w, none means it's off
+    position: [f32; 4],
+    color: [f32; 4],
+}
+
+// The point lights, next to the sun. Ones that are put down (`light point`
+// in the console) stay until cleared; the rest are added in App::update
+// every frame they're wanted and gone the frame after, so whatever follows
+// something moving just adds it again where it is now. Past
+// MAX_POINT_LIGHTS the rest are dropped, the placed ones first in line.
+#[derive(Default)]
+pub struct LightManager {
+    placed: Vec<PointLight>,
+    frame: Vec<PointLight>,
+}
+
+impl LightManager {
+    // for this frame only, false when there's no room left
+    pub fn add_point_light(&mut self, light: PointLight) -> bool {
+        if self.placed.len() + self.frame.len() >= MAX_POINT_LIGHTS {
+            return false;
+        }
+        self.frame.push(light);
+        true
+    }
+
+    pub fn place(&mut self, light: PointLight) -> bool {
+        if self.placed.len() >= MAX_POINT_LIGHTS {
+            return false;
+        }
+        self.placed.push(light);
+        true
+    }
+
+    pub fn clear_placed(&mut self) {
+        self.placed.clear();
+    }
+
+    pub fn placed(&self) -> &[PointLight] {
+        &self.placed
+    }
+
+    // before App::update adds this frame's
+    pub fn begin_frame(&mut self) {
+        self.frame.clear();
+    }
+
+    fn raw(&self) -> [PointLightRaw; MAX_POINT_LIGHTS] {
+        let mut raw = [PointLightRaw::default(); MAX_POINT_LIGHTS];
+        let lights = self.placed.iter().chain(&self.frame);
+        for (raw, light) in raw.iter_mut().zip(lights) {
+            raw.position = light.position.extend(light.radius.max(0.0)).into();
+            raw.color = light.color.extend(1.0).into();
+        }
+        raw
+    }
+}
+
+// the GPU side. The buffers go in the environment's bind group, which the
+// cube pipeline has as group 2, so it fits in the default four
+pub struct Light {
+    pub buffer: wgpu::Buffer,
+    // written every frame, from the LightManager
+    pub points: wgpu::Buffer,
+}
+
+impl Light {
+    pub fn new(device: &wgpu::Device, lighting: &Lighting) -> Self {
+        use wgpu::util::DeviceExt;
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("light buffer"),
+            contents: bytemuck::cast_slice(&[LightUniform::from(lighting)]),
+            usage: wgpu::BufferUsages::UNIFORM
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+        });
+        let points = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("point light buffer"),
+            size: std::mem::size_of::<[PointLightRaw; MAX_POINT_LIGHTS]>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        Self { buffer, points }
+    }
+
+    pub fn set(&self, queue: &wgpu::Queue, lighting: &Lighting) {
+        queue.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::cast_slice(&[LightUniform::from(lighting)]),
+        );
+    }
+
+    pub fn set_points(&self, queue: &wgpu::Queue, lights: &LightManager) {
+        queue.write_buffer(&self.points, 0, bytemuck::cast_slice(&lights.raw()));
+    }
+}
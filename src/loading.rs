@@ -0,0 +1,552 @@
+use crate::errors::GpuErrors;
+use crate::model::DrawModel;
+use crate::renderer::Kept;
+use crate::splash::Splash;
+use crate::texture::Texture;
+use crate::timer::Timer;
+use crate::vertex::{BasicVertex, Vertex};
+use crate::{
+    arena, camera, cloth, crt, cube, environment, floor, gpu_timer, grade, light, lod, model,
+    motion, noise, objects, outline, pipeline_cache, portal, post, reflect, retro, shaders,
+    shatter, sprite, staging, tilemap, tweaks, vertex, water, weather, InstanceRaw,
+    BACKGROUND_QUAD, BACKGROUND_QUAD_INDICES, EFFECT_INDICES, EFFECT_VERTS,
+};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use wgpu::util::DeviceExt;
+use wgpu_text::glyph_brush::ab_glyph::FontRef;
+use wgpu_text::TextBrush;
+
+// What App::init_gpu makes itself, straight after the device: the surface
+// and what the splash draws with, and what's cheap enough not to bother
+// the worker with. It all goes into the Renderer once the worker's done.
+pub struct Early {
+    pub instance: wgpu::Instance,
+    pub surface: wgpu::Surface<'static>,
+    pub adapter_info: wgpu::AdapterInfo,
+    pub config: wgpu::SurfaceConfiguration,
+    pub depth_texture: Texture,
+    pub brush: TextBrush<FontRef<'static>>,
+    pub camera_buffer: wgpu::Buffer,
+    pub camera_bind_group: wgpu::BindGroup,
+    pub gpu_timer: Option<gpu_timer::GpuTimer>,
+    pub pipeline_cache: Option<pipeline_cache::PipelineCache>,
+    // from the renderer this one replaces, if there was one
+    pub kept: Option<Kept>,
+    pub started: std::time::Instant,
+}
+
+// What the worker goes by, copied out of App so it can take it along.
+// Settings that are only a uniform write (grading, retro, the CRT, the
+// mirror, the sun) go in after, as they are by then.
+pub struct Settings {
+    pub texture_format: wgpu::TextureFormat,
+    pub width: u32,
+    pub height: u32,
+    pub model_format: vertex::ModelFormat,
+    pub noise: noise::Noise,
+    pub push_constants: bool,
+    pub lighting: light::Lighting,
+    pub lut_path: Option<PathBuf>,
+    pub tilemap_path: Option<PathBuf>,
+}
+
+// What the worker made: the shaders, pipelines and passes, and the assets
+// they're drawn with. What was kept from an old renderer isn't put in yet,
+// that's App::finish_gpu's.
+pub struct Made {
+    pub timer: Timer,
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub tweak_buffer: wgpu::Buffer,
+    pub lut_names: Vec<String>,
+    pub environment: environment::Environment,
+    pub outline: outline::Outline,
+    pub post: post::Post,
+    pub grade: grade::ColorGrade,
+    pub motion_blur: motion::MotionBlur,
+    pub retro: retro::Retro,
+    pub crt: crt::Crt,
+    pub floor: floor::Floor,
+    pub staging: staging::StagingRing,
+    pub objects: objects::ObjectArena,
+    pub cloth: cloth::Cloth,
+    pub water: water::Water,
+    pub portals: portal::Portals,
+    pub sprites: Option<sprite::SpriteLayer>,
+    pub weather: Option<weather::Weather>,
+    pub tilemap: Option<tilemap::TileLayer>,
+    pub light: light::Light,
+    pub background_render_pipeline: wgpu::RenderPipeline,
+    pub background_texture_bind_group: wgpu::BindGroup,
+    pub background_vertex_buffer: wgpu::Buffer,
+    pub background_index_buffer: wgpu::Buffer,
+    pub cube_pipeline: wgpu::RenderPipeline,
+    pub cube_env_pipeline: wgpu::RenderPipeline,
+    pub cube_model: model::Model,
+    pub mesh_arena: arena::MeshArena,
+    pub lod: lod::Lod,
+    pub shatter: shatter::Shatter,
+}
+
+// The renderer on its way. A worker thread compiles the shaders, builds the
+// pipelines and loads the models and textures while the main thread keeps
+// drawing the splash from `show`, once a frame, with the worker's latest
+// stage. The device and queue are shared with the worker until it's done.
+pub struct Loading {
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    pub early: Early,
+    splash: Splash,
+    // the surface as it's configured now, the window may have been resized
+    // since the worker started making everything at `early.config`'s size
+    shown: wgpu::SurfaceConfiguration,
+    progress: (f32, &'static str),
+    stages: Receiver<(f32, &'static str)>,
+    worker: JoinHandle<Made>,
+}
+
+impl Loading {
+    pub fn start(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        early: Early,
+        settings: Settings,
+        errors: GpuErrors,
+    ) -> Self {
+        let device = Arc::new(device);
+        let queue = Arc::new(queue);
+        let splash = Splash::new(&device, settings.texture_format);
+        let (sender, stages) = mpsc::channel();
+        let worker = {
+            let (device, queue) = (device.clone(), queue.clone());
+            std::thread::Builder::new()
+                .name("loading".into())
+                .spawn(move || {
+                    make(&device, &queue, &settings, &errors, |progress, label| {
+                        log::debug!("loading {}", label);
+                        // nobody's listening if the load was abandoned
+                        let _ = sender.send((progress, label));
+                    })
+                })
+                .unwrap()
+        };
+        Self {
+            device,
+            queue,
+            shown: early.config.clone(),
+            early,
+            splash,
+            progress: (0.0, "starting up"),
+            stages,
+            worker,
+        }
+    }
+
+    pub fn done(&self) -> bool {
+        self.worker.is_finished()
+    }
+
+    // a frame of the splash at the last stage the worker got to
+    pub fn show(&mut self) {
+        while let Ok(progress) = self.stages.try_recv() {
+            self.progress = progress;
+        }
+        let (progress, label) = self.progress;
+        self.splash.show(
+            &self.device,
+            &self.queue,
+            &self.early.surface,
+            &self.shown,
+            &mut self.early.brush,
+            progress,
+            label,
+        );
+    }
+
+    // only the surface and the text, everything else gets resized when
+    // it's a renderer
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.shown.width = width;
+        self.shown.height = height;
+        self.early.surface.configure(&self.device, &self.shown);
+        self.early
+            .brush
+            .resize_view(width as f32, height as f32, &self.queue);
+    }
+
+    // waits for the worker, once `done` says it's finished that's no wait
+    pub fn finish(self) -> (wgpu::Device, wgpu::Queue, Early, Made) {
+        let made = match self.worker.join() {
+            Ok(made) => made,
+            Err(panic) => std::panic::resume_unwind(panic),
+        };
+        // the worker's copies went when it returned
+        let device = Arc::into_inner(self.device).expect("the worker still has the device");
+        let queue = Arc::into_inner(self.queue).expect("the worker still has the queue");
+        (device, queue, self.early, made)
+    }
+}
+
+// everything slow, on the worker, `stage` says how far along it is
+fn make(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    settings: &Settings,
+    errors: &GpuErrors,
+    stage: impl Fn(f32, &'static str),
+) -> Made {
+    let texture_format = settings.texture_format;
+    let (width, height) = (settings.width, settings.height);
+
+    //// uniform buffer
+    errors.push_scope(device);
+    let timer = Timer::new(device);
+    let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(EFFECT_VERTS),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+    // index buffer
+    let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Index Buffer"),
+        contents: bytemuck::cast_slice(EFFECT_INDICES),
+        usage: wgpu::BufferUsages::INDEX,
+    });
+    errors.pop_scope(device, "timer and effect buffers");
+
+    errors.push_scope(device);
+    let cube_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Shader"),
+        source: wgpu::ShaderSource::Wgsl(shaders::load("cube.wgsl", &[]).unwrap().into()),
+    });
+    let cube_bind_group_layout =
+        &reflect::bind_group_layout(device, "cube bind group layout", &[("cube.wgsl", 0)]).unwrap();
+    let camera_bind_group_layout =
+        &reflect::bind_group_layout(device, "camera_bind_group_layout", camera::BIND_GROUP_USES)
+            .unwrap();
+
+    stage(0.15, "environment and probes");
+    let light = light::Light::new(device, &settings.lighting);
+    let environment = environment::Environment::new(
+        device,
+        queue,
+        camera_bind_group_layout,
+        texture_format,
+        &settings.noise,
+        &light,
+    );
+
+    let outline = outline::Outline::new(
+        device,
+        camera_bind_group_layout,
+        settings.model_format,
+        texture_format,
+        width,
+        height,
+    );
+    stage(0.3, "post processing");
+    let tweak_buffer = tweaks::Tweaks::create_buffer(device);
+    let post = post::Post::new(device, width, height, texture_format, &tweak_buffer);
+    let mut luts = grade::Lut::built_in();
+    if let Some(path) = &settings.lut_path {
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+        match std::fs::read(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| grade::Lut::from_strip(&name, &bytes))
+        {
+            Ok(lut) => luts.push(lut),
+            Err(e) => log::error!("ignoring --lut {}: {}", path.display(), e),
+        }
+    }
+    let lut_names = luts.iter().map(|lut| lut.name.clone()).collect();
+    let grade = grade::ColorGrade::new(device, &post, luts);
+    let motion_blur = motion::MotionBlur::new(
+        device,
+        &post,
+        camera_bind_group_layout,
+        settings.model_format,
+        width,
+        height,
+    );
+    let retro = retro::Retro::new(device, &post);
+    let crt = crt::Crt::new(device, &post, &timer);
+
+    stage(0.45, "floor, cloth and water");
+    let floor = floor::Floor::new(
+        device,
+        queue,
+        camera_bind_group_layout,
+        texture_format,
+        width,
+        height,
+    );
+    let staging = staging::StagingRing::new(device);
+    let objects = objects::ObjectArena::new(device, settings.push_constants);
+    let cloth = cloth::Cloth::new(device, camera_bind_group_layout, texture_format);
+    let water = water::Water::new(
+        device,
+        [
+            camera_bind_group_layout,
+            &environment.bind_group_layout,
+            &timer.timer_bind_group_layout,
+        ],
+        &settings.noise,
+        texture_format,
+        width,
+        height,
+    );
+    let portals = portal::Portals::new(
+        device,
+        camera_bind_group_layout,
+        &objects,
+        texture_format,
+        width,
+        height,
+    );
+
+    stage(0.6, "sprites, weather and tiles");
+    let sprites = sprite::SpriteLayer::new(device, queue, camera_bind_group_layout, texture_format)
+        .inspect_err(|e| log::error!("no sprites: {:#}", e))
+        .ok();
+    let weather = weather::Weather::new(
+        device,
+        camera_bind_group_layout,
+        texture_format,
+        &settings.noise,
+    )
+    .inspect_err(|e| log::error!("no weather: {:#}", e))
+    .ok();
+    let tilemap = match &settings.tilemap_path {
+        Some(path) => tilemap::TileLayer::load(device, queue, texture_format, path, width, height),
+        None => tilemap::TileLayer::demo(device, queue, texture_format, width, height),
+    }
+    .inspect_err(|e| log::error!("no tilemap: {:#}", e))
+    .ok();
+
+    stage(0.7, "pipelines");
+    let cube_render_pipeline_layout =
+        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("cube pipeline layout"),
+            bind_group_layouts: &[
+                cube_bind_group_layout,
+                camera_bind_group_layout,
+                &environment.bind_group_layout,
+                &timer.timer_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+    ///// shader time
+    let basic_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Shader"),
+        source: wgpu::ShaderSource::Wgsl(settings.noise.shader("basic.wgsl").unwrap().into()),
+    });
+    let background_texture_bind_group_layout = &reflect::bind_group_layout(
+        device,
+        "background texture bind group layout",
+        &[("basic.wgsl", 0)],
+    )
+    .unwrap();
+    let background_render_pipeline_layout =
+        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("cube pipeline layout"),
+            bind_group_layouts: &[
+                background_texture_bind_group_layout,
+                &timer.timer_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+    let background_render_pipeline =
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("background render pipeline"),
+            layout: Some(&background_render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &basic_shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[BasicVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &basic_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: texture_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Cw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(Texture::depth_state(false, wgpu::CompareFunction::Always)),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: pipeline_cache::current().as_deref(),
+        });
+    let background_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("background vertex buffer"),
+        contents: bytemuck::cast_slice(BACKGROUND_QUAD.to_vec().as_slice()),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+    let background_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("background index buffer"),
+        contents: bytemuck::cast_slice(BACKGROUND_QUAD_INDICES),
+        usage: wgpu::BufferUsages::INDEX,
+    });
+
+    let background_diffuse_bytes = include_bytes!("../res/backgrounds/reactor.png");
+    let background_diffuse_texture = Texture::from_bytes(
+        device,
+        queue,
+        background_diffuse_bytes,
+        "background image",
+        false,
+    )
+    .unwrap();
+    let background_texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: background_texture_bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&background_diffuse_texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&background_diffuse_texture.sampler),
+            },
+        ],
+        label: Some("backgroundd texture bind group"),
+    });
+    errors.pop_scope(device, "background pipeline and texture");
+
+    errors.push_scope(device);
+    // the reflection passes have no depth texture, the mirrored cubes
+    // there are few and far enough not to need one
+    let create_cube_pipeline = |label, front_face, depth: bool| {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&cube_render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &cube_shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[settings.model_format.desc(), InstanceRaw::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &cube_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: texture_format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: depth.then(|| Texture::depth_state(true, wgpu::CompareFunction::Less)),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: pipeline_cache::current().as_deref(),
+        })
+    };
+    let cube_pipeline = create_cube_pipeline("cube render pipeline", wgpu::FrontFace::Ccw, true);
+    let cube_env_pipeline = create_cube_pipeline(
+        "cube environment render pipeline",
+        wgpu::FrontFace::Cw,
+        false,
+    );
+    errors.pop_scope(device, "cube pipeline");
+
+    stage(0.8, "models");
+    errors.push_scope(device);
+    // every model loaded from here on goes in too
+    let mut arena = arena::MeshArenaBuilder::new(settings.model_format);
+    let cube_model = cube::load_cube(device, queue, cube_bind_group_layout, &mut arena).unwrap();
+    let mesh_arena = arena.build(device);
+    errors.pop_scope(device, "cube model");
+
+    stage(0.9, "impostor atlas");
+    errors.push_scope(device);
+    let lod = lod::Lod::new(device, camera_bind_group_layout, texture_format);
+    lod.render_atlas(
+        device,
+        queue,
+        camera_bind_group_layout,
+        |render_pass, camera_bind_group, instance_buffer| {
+            render_pass.set_pipeline(&cube_pipeline);
+            render_pass.set_mesh_arena(&mesh_arena);
+            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+            render_pass.set_bind_group(2, environment.bind_group(), &[]);
+            render_pass.set_bind_group(3, &timer.timer_bind_group, &[]);
+            render_pass.draw_model_instanced(&cube_model, 0, 0..1, camera_bind_group);
+        },
+    );
+    errors.pop_scope(device, "lod and impostor atlas");
+    // pieces in the air are lost with the old buffer, they'd be gone soon anyway
+    let shatter = shatter::Shatter::new(device);
+    stage(1.0, "ready");
+
+    Made {
+        timer,
+        vertex_buffer,
+        index_buffer,
+        tweak_buffer,
+        lut_names,
+        environment,
+        outline,
+        post,
+        grade,
+        motion_blur,
+        retro,
+        crt,
+        floor,
+        staging,
+        objects,
+        cloth,
+        water,
+        portals,
+        sprites,
+        weather,
+        tilemap,
+        light,
+        background_render_pipeline,
+        background_texture_bind_group,
+        background_vertex_buffer,
+        background_index_buffer,
+        cube_pipeline,
+        cube_env_pipeline,
+        cube_model,
+        mesh_arena,
+        lod,
+        shatter,
+    }
+}
@@ -0,0 +1,434 @@
+use crate::camera::{Camera, CameraUniform, Frustum};
+use crate::draws::Instances;
+use crate::math::{self, Point3, Vec3};
+use crate::model::Mesh;
+use crate::texture::Texture;
+use crate::vertex::Vertex;
+use crate::InstanceRaw;
+use wgpu::util::DeviceExt;
+
+// the atlas: the cube from 8 directions around it at 4 heights, 0 to 75
+// degrees up, in tiles of 64x64. impostor.wgsl has the same layout.
+const YAW_TILES: u32 = 8;
+const PITCH_TILES: u32 = 4;
+const PITCH_STEP: f32 = 25.0;
+const TILE: u32 = 64;
+// half the cube's diagonal, what each tile frames so any turn fits
+const RADIUS: f32 = 1.732_050_8;
+// a cube covering fewer pixels than this across is drawn as an impostor
+const IMPOSTOR_PIXELS: f32 = 24.0;
+const WORKGROUP: u32 = 64;
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct CullUniform {
+    planes: [[f32; 4]; 6],
+    // xyz, w is the instance count
+    eye: [f32; 4],
+    // pixels per unit one unit away, then IMPOSTOR_PIXELS
+    screen: [f32; 4],
+}
+
+// Level of detail for the cubes, picked on the GPU. A compute pass goes
+// over every instance once a frame, drops the ones outside the frustum and
+// sorts the rest into the cubes close enough to be worth their triangles
+// and the ones drawn as a single camera facing quad out of an atlas of the
+// cube rendered at startup. Both are drawn indirectly, with the counts the
+// pass wrote, so nothing comes back to the CPU. `impostors` in the console
+// turns it off, back to drawing every cube in full.
+pub struct Lod {
+    pub enabled: bool,
+    // instances the near and far buffers have room for
+    capacity: u32,
+    near_buffer: wgpu::Buffer,
+    far_buffer: wgpu::Buffer,
+    // a DrawIndexedIndirectArgs for the near cubes, then a DrawIndirectArgs
+    // for the impostors
+    args_buffer: wgpu::Buffer,
+    // a DrawIndexedIndirectArgs per mesh of the model, each with the near
+    // count copied in from args_buffer after culling
+    mesh_args_buffer: wgpu::Buffer,
+    cull_buffer: wgpu::Buffer,
+    cull_bind_group_layout: wgpu::BindGroupLayout,
+    cull_pipeline: wgpu::ComputePipeline,
+
+    pub atlas: Texture,
+    atlas_bind_group: wgpu::BindGroup,
+    impostor_pipeline: wgpu::RenderPipeline,
+}
+
+fn mesh_args_buffer(device: &wgpu::Device, meshes: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("lod mesh args buffer"),
+        size: (meshes.max(1) * MESH_ARGS) as u64,
+        usage: wgpu::BufferUsages::INDIRECT | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+// the size of a DrawIndexedIndirectArgs
+const MESH_ARGS: usize = 5 * 4;
+
+fn instance_buffers(device: &wgpu::Device, capacity: u32) -> [wgpu::Buffer; 2] {
+    ["lod near buffer", "lod far buffer"].map(|label| {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: capacity as u64 * std::mem::size_of::<InstanceRaw>() as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+            mapped_at_creation: false,
+        })
+    })
+}
+
+impl Lod {
+    pub fn new(
+        device: &wgpu::Device,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let capacity = 1024;
+        let [near_buffer, far_buffer] = instance_buffers(device, capacity);
+        let args_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("lod args buffer"),
+            size: 9 * 4,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mesh_args_buffer = mesh_args_buffer(device, 1);
+        let cull_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("lod cull buffer"),
+            size: std::mem::size_of::<CullUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        ///// culling
+        let cull_bind_group_layout = crate::reflect::bind_group_layout(
+            device,
+            "lod cull bind group layout",
+            &[("lod_cull.wgsl", 0)],
+        )
+        .unwrap();
+        let cull_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("lod cull shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                crate::shaders::load("lod_cull.wgsl", &[]).unwrap().into(),
+            ),
+        });
+        let cull_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("lod cull pipeline layout"),
+            bind_group_layouts: &[&cull_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let cull_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("lod cull pipeline"),
+            layout: Some(&cull_pipeline_layout),
+            module: &cull_shader,
+            entry_point: "cull_main",
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            cache: crate::pipeline_cache::current().as_deref(),
+        });
+
+        ///// impostors
+        let atlas = Texture::create_render_target(
+            device,
+            YAW_TILES * TILE,
+            PITCH_TILES * TILE,
+            format,
+            "impostor atlas",
+        );
+        let atlas_bind_group_layout = crate::reflect::bind_group_layout(
+            device,
+            "impostor bind group layout",
+            &[("impostor.wgsl", 1)],
+        )
+        .unwrap();
+        let atlas_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &atlas_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&atlas.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&atlas.sampler),
+                },
+            ],
+            label: Some("impostor bind group"),
+        });
+        let impostor_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("impostor shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                crate::shaders::load("impostor.wgsl", &[]).unwrap().into(),
+            ),
+        });
+        let impostor_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("impostor pipeline layout"),
+                bind_group_layouts: &[camera_bind_group_layout, &atlas_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let impostor_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("impostor render pipeline"),
+            layout: Some(&impostor_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &impostor_shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[InstanceRaw::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &impostor_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(Texture::depth_state(true, wgpu::CompareFunction::Less)),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: crate::pipeline_cache::current().as_deref(),
+        });
+
+        Self {
+            enabled: true,
+            capacity,
+            near_buffer,
+            far_buffer,
+            args_buffer,
+            mesh_args_buffer,
+            cull_buffer,
+            cull_bind_group_layout,
+            cull_pipeline,
+            atlas,
+            atlas_bind_group,
+            impostor_pipeline,
+        }
+    }
+
+    // Renders the atlas, once after the cube model is loaded. `draw` gets a
+    // pass with the viewport on one tile, that tile's camera and a buffer
+    // with a single cube at the origin to draw as instance 0.
+    pub fn render_atlas(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        mut draw: impl FnMut(&mut wgpu::RenderPass, &wgpu::BindGroup, &wgpu::Buffer),
+    ) {
+        let origin = crate::Instance {
+            position: Point3::ZERO,
+            rotation: math::Quat::IDENTITY,
+            flash: crate::NO_FLASH,
+            skin: 0,
+            layer: crate::physics::Layers::STATIC,
+        };
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("impostor atlas instance buffer"),
+            contents: bytemuck::cast_slice(&[origin.to_raw()]),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let projection = math::orthographic(RADIUS, RADIUS, 0.1, 10.0);
+        // every tile's camera has to exist at once, they're all drawn in one pass
+        let camera_bind_groups = (0..YAW_TILES * PITCH_TILES)
+            .map(|i| {
+                let yaw = (i % YAW_TILES) as f32 * std::f32::consts::TAU / YAW_TILES as f32;
+                let pitch = math::Degrees((i / YAW_TILES) as f32 * PITCH_STEP).to_radians();
+                let eye = Point3::new(
+                    yaw.sin() * pitch.cos(),
+                    pitch.sin(),
+                    yaw.cos() * pitch.cos(),
+                ) * 5.0;
+                let mut uniform = CameraUniform::new();
+                uniform.set(eye, projection * math::look_at(eye, Point3::ZERO, Vec3::Y));
+                let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("impostor atlas camera buffer"),
+                    contents: bytemuck::cast_slice(&[uniform]),
+                    usage: wgpu::BufferUsages::UNIFORM,
+                });
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: camera_bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                    label: Some("impostor atlas camera bind group"),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let size = self.atlas.texture.size();
+        let depth =
+            Texture::create_depth_target(device, size.width, size.height, "impostor atlas depth");
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("impostor atlas encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("impostor atlas pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.atlas.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            for (i, camera_bind_group) in camera_bind_groups.iter().enumerate() {
+                let (column, row) = (i as u32 % YAW_TILES, i as u32 / YAW_TILES);
+                render_pass.set_viewport(
+                    (column * TILE) as f32,
+                    (row * TILE) as f32,
+                    TILE as f32,
+                    TILE as f32,
+                    0.0,
+                    1.0,
+                );
+                draw(&mut render_pass, camera_bind_group, &instance_buffer);
+            }
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+
+    // Sorts this frame's `count` instances into near cubes and impostors,
+    // the near ones to be drawn as each of `meshes`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn cull(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        instance_buffer: &wgpu::Buffer,
+        count: u32,
+        meshes: &[Mesh],
+        camera: &Camera,
+        height: u32,
+    ) {
+        if count > self.capacity {
+            self.capacity = count.next_power_of_two();
+            [self.near_buffer, self.far_buffer] = instance_buffers(device, self.capacity);
+        }
+
+        let frustum = Frustum::from_view_proj(camera.build_view_projection_matrix());
+        let pixels_per_unit = height as f32 / (2.0 * (camera.fovy.to_radians() / 2.0).tan());
+        let uniform = CullUniform {
+            planes: frustum.planes(),
+            eye: camera.eye.extend(count as f32).to_array(),
+            screen: [pixels_per_unit, IMPOSTOR_PIXELS, 0.0, 0.0],
+        };
+        queue.write_buffer(&self.cull_buffer, 0, bytemuck::cast_slice(&[uniform]));
+        // the instance counts start at 0 and the pass counts them up
+        let args: [u32; 9] = [0, 0, 0, 0, 0, 6, 0, 0, 0];
+        queue.write_buffer(&self.args_buffer, 0, bytemuck::cast_slice(&args));
+        if self.mesh_args_buffer.size() < (meshes.len() * MESH_ARGS) as u64 {
+            self.mesh_args_buffer = mesh_args_buffer(device, meshes.len());
+        }
+        let mesh_args = meshes
+            .iter()
+            .flat_map(|mesh| {
+                [
+                    mesh.num_elements,
+                    0,
+                    mesh.first_index,
+                    mesh.base_vertex as u32,
+                    0,
+                ]
+            })
+            .collect::<Vec<_>>();
+        queue.write_buffer(&self.mesh_args_buffer, 0, bytemuck::cast_slice(&mesh_args));
+
+        // the instance buffer is made again whenever the cubes outgrow it
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.cull_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.cull_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: instance_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.near_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.far_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: self.args_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("lod cull bind group"),
+        });
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("lod cull pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.cull_pipeline);
+        compute_pass.set_bind_group(0, &bind_group, &[]);
+        compute_pass.dispatch_workgroups(count.div_ceil(WORKGROUP), 1, 1);
+        drop(compute_pass);
+        for i in 0..meshes.len() {
+            encoder.copy_buffer_to_buffer(
+                &self.args_buffer,
+                4,
+                &self.mesh_args_buffer,
+                (i * MESH_ARGS + 4) as u64,
+                4,
+            );
+        }
+    }
+
+    // the near cubes as mesh `mesh` of the model, to draw with the cube pipeline
+    pub fn near_instances(&self, mesh: usize) -> Instances<'_> {
+        Instances::Indirect {
+            instances: &self.near_buffer,
+            args: &self.mesh_args_buffer,
+            offset: (mesh * MESH_ARGS) as u64,
+        }
+    }
+
+    // there's no depth buffer, so these go before the near cubes
+    pub fn draw_impostors(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        camera_bind_group: &wgpu::BindGroup,
+    ) {
+        render_pass.set_pipeline(&self.impostor_pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.atlas_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.far_buffer.slice(..));
+        render_pass.draw_indirect(&self.args_buffer, 5 * 4);
+    }
+}
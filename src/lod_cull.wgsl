@@ -0,0 +1,66 @@
+// One thread per cube: cubes outside the frustum are dropped, the rest go to
+// the near list if they'd cover enough of the screen and the impostor list
+// if not. The lists are counted straight into the indirect draws' instance
+// counts, see lod.rs.
+
+struct Cull {
+    // facing in, from camera::Frustum
+    planes: array<vec4<f32>, 6>,
+    // xyz, w is how many cubes there are
+    eye: vec4<f32>,
+    // pixels a unit covers one unit away, then the size in pixels below
+    // which a cube is drawn as an impostor
+    screen: vec4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> cull: Cull;
+// InstanceRaw as plain words, it isn't laid out like a WGSL struct. Copied
+// as u32 so the skin index goes through untouched.
+@group(0) @binding(1)
+var<storage, read> instances: array<u32>;
+@group(0) @binding(2)
+var<storage, read_write> near: array<u32>;
+@group(0) @binding(3)
+var<storage, read_write> far: array<u32>;
+// a draw_indexed_indirect for the near cubes then a draw_indirect for the
+// impostors, only the instance counts (1 and 6) are written here
+@group(0) @binding(4)
+var<storage, read_write> args: array<atomic<u32>, 9>;
+
+const STRIDE: u32 = 19u;
+// half the diagonal of the cube model
+const RADIUS: f32 = 1.7320508;
+
+@compute @workgroup_size(64)
+fn cull_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= u32(cull.eye.w) {
+        return;
+    }
+    let source = i * STRIDE;
+    let center = bitcast<vec3<f32>>(vec3<u32>(
+        instances[source + 12u],
+        instances[source + 13u],
+        instances[source + 14u],
+    ));
+    for (var p = 0; p < 6; p++) {
+        let plane = cull.planes[p];
+        if dot(plane.xyz, center) + plane.w < -RADIUS {
+            return;
+        }
+    }
+
+    let distance = max(distance(center, cull.eye.xyz), 1e-3);
+    let pixels = 2.0 * RADIUS * cull.screen.x / distance;
+    if pixels >= cull.screen.y {
+        let to = atomicAdd(&args[1], 1u) * STRIDE;
+        for (var k = 0u; k < STRIDE; k++) {
+            near[to + k] = instances[source + k];
+        }
+    } else {
+        let to = atomicAdd(&args[6], 1u) * STRIDE;
+        for (var k = 0u; k < STRIDE; k++) {
+            far[to + k] = instances[source + k];
+        }
+    }
+}
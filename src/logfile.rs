@@ -0,0 +1,50 @@
+use std::fs::File;
+use std::io::Write;
+
+// Whatever gets logged (RUST_LOG picks what, as always) goes to stderr and
+// also into logs/praxis.log in the data directory, with the run before
+// kept as praxis.old.log so a crash's log survives the restart.
+struct Tee {
+    file: Option<File>,
+}
+
+impl Write for Tee {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        std::io::stderr().write_all(buf)?;
+        if let Some(file) = &mut self.file {
+            // a full disk shouldn't take the logging down with it
+            if file.write_all(buf).is_err() {
+                self.file = None;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if let Some(file) = &mut self.file {
+            file.flush()?;
+        }
+        std::io::stderr().flush()
+    }
+}
+
+pub fn init() {
+    let dir = crate::dirs::log_dir();
+    let path = dir.join("praxis.log");
+    let file = std::fs::create_dir_all(&dir).and_then(|_| {
+        match std::fs::rename(&path, dir.join("praxis.old.log")) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => File::create(&path),
+        }
+    });
+    let (file, error) = match file {
+        Ok(file) => (Some(file), None),
+        Err(e) => (None, Some(e)),
+    };
+    env_logger::Builder::from_default_env()
+        .target(env_logger::Target::Pipe(Box::new(Tee { file })))
+        .init();
+    if let Some(e) = error {
+        log::warn!("not logging to {}: {}", path.display(), e);
+    }
+}
@@ -1,78 +1,171 @@
 use camera::Camera;
 use camera::CameraUniform;
-use cgmath::InnerSpace;
-use cgmath::Zero;
-use cube::{Cube, DrawModel};
+use model::DrawModel;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use timer::Timer;
 use vertex::{BasicVertex, EffectVertex, Vertex};
 use wgpu::util::DeviceExt;
-use wgpu::Surface;
-use wgpu_text::glyph_brush::ab_glyph::FontRef;
 use wgpu_text::glyph_brush::{OwnedSection, Section as TextSection, Text};
-use wgpu_text::TextBrush;
 use winit::application::ApplicationHandler;
-use winit::event::{KeyEvent, WindowEvent};
+use winit::event::{DeviceEvent, DeviceId, ElementState, KeyEvent, MouseButton, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
-use winit::keyboard::{Key, NamedKey};
-use winit::window::{Window, WindowId};
-
+use winit::keyboard::Key;
+use winit::window::{CursorGrabMode, Window, WindowId};
+
+mod allocs;
+mod arena;
+mod audio;
+mod bake;
+mod benchmark;
+mod bindings;
+mod boids;
+mod buffer_inspector;
 mod camera;
+mod capture;
+mod character;
+mod chase;
+mod chat;
+mod clip;
+mod clock;
+mod cloth;
+mod console;
 mod controller;
+mod crt;
 mod cube;
+mod dirs;
+mod dirty;
+mod draws;
+mod environment;
+mod errors;
+mod expr;
+mod floor;
+mod formation;
+mod gif;
+mod gpu_timer;
+mod grade;
+mod grid;
+mod gui;
+#[cfg(feature = "http")]
+mod http;
+mod instance_buffer;
+mod interest;
+mod interpolation;
+mod joints;
+mod light;
+mod loading;
+mod lod;
+mod logfile;
+mod materials;
+mod math;
+mod meshopt;
+mod model;
+mod motion;
+mod music;
+mod net;
+mod noise;
+mod objects;
+mod options;
+mod outline;
+mod physics;
+mod pipeline_cache;
+mod portal;
+mod post;
+mod probes;
+mod profile;
+mod quality;
+mod reflect;
+mod remote;
+mod renderer;
+mod retro;
+mod rng;
+mod rollback;
+mod saves;
+mod scaling;
+mod scenario;
+mod screenshot;
+mod server;
+mod session;
+mod sfx;
+mod shaders;
+mod shatter;
+mod simulation;
+mod snapshot;
+mod splash;
+mod sprite;
+mod staging;
+mod stats;
+mod stepping;
+mod stream;
 mod texture;
+mod texture_viewer;
+mod tilemap;
 mod timer;
+mod toasts;
+mod tweaks;
+mod ui;
 mod vertex;
-
+mod video;
+mod visualizer;
+mod watchdog;
+mod water;
+mod weather;
+
+// every allocation is counted, F3 shows them
+#[global_allocator]
+static ALLOCATOR: allocs::Tracking = allocs::Tracking;
+
+// far enough in the past that the flash has long faded out
+const NO_FLASH: f32 = -1000.0;
+// a flashing cube lights up what's around it for as long as the flash shows
+const GLOW_SECONDS: f32 = 0.2;
+const GLOW_RADIUS: f32 = 12.0;
+// how far above its spot a new cube is dropped from
+const SPAWN_DROP: f32 = 50.0;
+// first person, units a second
+const FLY_SPEED: f32 = 20.0;
+
+#[derive(Clone)]
 struct Instance {
-    position: cgmath::Vector3<f32>,
-    rotation: cgmath::Quaternion<f32>,
+    position: math::Vec3,
+    rotation: math::Quat,
+    // timer time the last flash started at
+    flash: f32,
+    // which of cube::SKINS it wears
+    skin: u32,
+    layer: physics::Layers,
 }
 impl Instance {
     fn to_raw(&self) -> InstanceRaw {
+        self.scaled_raw(1.0)
+    }
+
+    // bigger or smaller than it is, only for show
+    fn scaled_raw(&self, scale: f32) -> InstanceRaw {
+        let scaling = math::Mat4::from_scale(math::Vec3::splat(scale));
         InstanceRaw {
-            model: (cgmath::Matrix4::from_translation(self.position)
-                * cgmath::Matrix4::from(self.rotation))
-            .into(),
+            model: math::mat4_array(math::model_matrix(self.position, self.rotation) * scaling),
+            flash: self.flash,
+            skin: self.skin,
+            fade: 0.0,
         }
     }
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Vertex)]
+#[vertex(instance)]
 struct InstanceRaw {
+    // a column per location, 5 to 8
+    #[vertex(location = 5)]
     model: [[f32; 4]; 4],
-}
-impl InstanceRaw {
-    fn desc() -> wgpu::VertexBufferLayout<'static> {
-        use std::mem;
-        wgpu::VertexBufferLayout {
-            array_stride: mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Instance,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 5,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-                wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
-                    shader_location: 6,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-                wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
-                    shader_location: 7,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-                wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
-                    shader_location: 8,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-            ],
-        }
-    }
+    #[vertex(location = 9)]
+    flash: f32,
+    #[vertex(location = 10)]
+    skin: u32,
+    // when a streamed cube started fading in, or out when it's negative, 0
+    // for cubes that are just there. After the motion blur's 11 to 14.
+    #[vertex(location = 15)]
+    fade: f32,
 }
 
 const EFFECT_VERTS: &[EffectVertex] = &[
@@ -117,43 +210,153 @@ const BACKGROUND_QUAD_INDICES: &[u16] = &[0, 1, 2, 0, 2, 3];
 #[derive(Default)]
 struct App {
     window: Option<Arc<Window>>,
-    instance: Option<wgpu::Instance>,
-    surface: Option<Surface<'static>>,
-    device: Option<wgpu::Device>,
-    queue: Option<wgpu::Queue>,
-
-    vertex_buffer: Option<wgpu::Buffer>,
-    index_buffer: Option<wgpu::Buffer>,
-    timer: Option<Timer>,
+    // everything on the GPU, None until the window's made and while it's being made again
+    gfx: Option<renderer::Renderer>,
+    // the renderer while it's being made, with the splash up
+    loading: Option<loading::Loading>,
+
+    clock: clock::ClockBuffer,
+    toasts: toasts::Toasts,
+    // --lut, an extra strip next to the built in ones
+    lut_path: Option<std::path::PathBuf>,
+    bindings: bindings::Bindings,
+    ui: ui::Ui,
+    clock_text: Option<ui::NodeId>,
+    cubes_text: Option<ui::NodeId>,
 
-    brush: Option<TextBrush<FontRef<'static>>>,
     text_section: Option<OwnedSection>,
 
     // camera
-    camera: Option<Camera>,
-    camera_buffer: Option<wgpu::Buffer>,
-    camera_bind_group: Option<wgpu::BindGroup>,
-
-    // background texture
-    background_render_pipeline: Option<wgpu::RenderPipeline>,
-    background_texture_bind_group: Option<wgpu::BindGroup>,
-    background_vertex_buffer: Option<wgpu::Buffer>,
-    background_index_buffer: Option<wgpu::Buffer>,
-
-    // cube
-    cube_pipeline: Option<wgpu::RenderPipeline>,
-    cube_bind_group: Option<wgpu::BindGroup>,
-    cube_vertex_buf: Option<wgpu::Buffer>,
-    cube_index_buf: Option<wgpu::Buffer>,
-    cube_instances: Vec<Instance>,
-    cube_instance_buffer: Option<wgpu::Buffer>,
-    cube_model: Option<cube::Cube>,
+    camera: Camera,
+
+    stream_name: Option<String>,
+    // whether the device was asked for push constants
+    push_constants: bool,
+    stats: stats::Stats,
+    // the cubes and what moves them
+    sim: simulation::Simulation,
+    // sound effects, heard from the camera
+    audio: Option<audio::Audio>,
+    // which sound each event makes
+    sfx: sfx::Sfx,
+    // `music` in the console
+    music_off: bool,
+    // `visualizer` in the console, cubes and background moving to the mic
+    visualizer: Option<visualizer::Visualizer>,
+    // `--osc <port>`, driven from outside
+    remote: Option<remote::RemoteControl>,
+    // `--connect <address>`, playing on someone's `--server`
+    client: Option<net::Client>,
+    // where the other cubes are drawn from, between the snapshots for them
+    interpolation: interpolation::Interpolation,
+    // a `--server --rollback` session's own simulation, once it's started
+    rollback: Option<rollback::Rollback>,
+    // Enter while connected, and the names over everyone's cubes
+    chat: chat::Chat,
+    // , and . pause and step one tick at a time
+    stepping: stepping::Stepping,
+    // `snapshot` and `diff` in the console
+    snapshots: snapshot::Snapshots,
+    // `textures`, what's in the renderer's textures drawn over the frame
+    texture_viewer: texture_viewer::TextureViewer,
+    // the sun, `light` in the console and whatever the scenario wants
+    lighting: light::Lighting,
+    // point lights, `light point` ones and whatever update adds each frame
+    lights: light::LightManager,
+    // `buffer`, what's in the camera and instance buffers as the GPU has them
+    buffer_inspector: buffer_inspector::BufferInspector,
+    // F11, the values shaders' `// @tweak` comments ask for
+    tweaks: tweaks::Tweaks,
+    // M, the cube skins' colors and textures
+    materials: materials::MaterialEditor,
+    // egui, for the material editor
+    gui: gui::Gui,
+    // `--http <port>`, and the screenshots it's waiting on the next frame for
+    #[cfg(feature = "http")]
+    http: Option<http::HttpApi>,
+    #[cfg(feature = "http")]
+    http_screenshots: Vec<std::sync::mpsc::Sender<http::Response>>,
+    // demos from the F1 menu, `scenario` is the one running
+    main_menu: bool,
+    scenarios: Vec<Box<dyn scenario::Scenario>>,
+    scenario: Option<usize>,
+    session: session::Session,
+    options: options::Options,
+
+    // selection, indices into cube_instances
+    selected: Vec<u32>,
+    cursor_position: winit::dpi::PhysicalPosition<f64>,
+
+    // cloth demo, dropped onto the first cube
+    cloth_enabled: bool,
+
+    tilemap_path: Option<std::path::PathBuf>,
+    water_enabled: bool,
+
+    rng: rng::Rng,
+    noise: noise::Noise,
+
+    floor_hidden: bool,
+    // `mirror` in the console
+    floor_mirror: bool,
+
+    // save slots, the menu is open while this holds the listed slots
+    pending_save: bool,
+    save_menu: Option<Vec<saves::Slot>>,
+    autosave: saves::Autosave,
+    // the autosave offered back after a crash, until answered with Y or N
+    restore_prompt: Option<saves::Slot>,
 
     // player
-    cube_position: Option<cgmath::Vector3<f32>>,
+    cube_position: Option<math::Vec3>,
+    // how new cubes are turned, `--spawn-rotation "euler 0 45 0"`
+    spawn_rotation: math::Rotation,
+    // the skin new cubes wear, `skin <name>` in the console, random if none
+    spawn_skin: Option<u32>,
+    // `--packed-vertices` shrinks model vertices
+    model_format: vertex::ModelFormat,
 
     // controller
     controller: controller::Controller,
+    // right drag and the wheel, the camera around what it's looking at
+    orbit: controller::OrbitController,
+    // Tab, flying the camera with WASD and the mouse instead, and where it
+    // was before so it can go back
+    camera_mode: camera::CameraMode,
+    mouse_look: controller::MouseLook,
+    // the FOV kick sprinting and the drop sneaking
+    gait: controller::Gait,
+    followed_from: Option<(math::Point3, math::Point3)>,
+    // `chase` in the console, the camera following a cube on a spring arm
+    chase: chase::ChaseCamera,
+    // one tile per key press instead, with the camera following
+    grid: grid::GridMove,
+    console: console::Console,
+
+    // renderdoc
+    capture: Option<capture::FrameCapture>,
+    // `--record <file>` or `record <file>` in the console
+    video: Option<video::VideoRecorder>,
+    // `--profile <name>`, where the options and bindings are kept
+    profile: Option<profile::Profile>,
+    // the last few seconds, for F4
+    clips: clip::ClipRing,
+    // turns quality down when frames run long
+    watchdog: watchdog::Watchdog,
+    // --benchmark
+    benchmark: Option<benchmark::Benchmark>,
+    // --scaling
+    scaling: Option<scaling::Scaling>,
+
+    // wgpu validation errors
+    errors: errors::GpuErrors,
+
+    device_lost: Arc<AtomicBool>,
+
+    // backend switching, None means wgpu picks from the primary backends
+    backends: Option<wgpu::Backends>,
+    // what the last renderer kept, until a new one's started with it
+    kept: Option<renderer::Kept>,
 }
 
 const WIDTH: u32 = 1024;
@@ -174,700 +377,3291 @@ impl ApplicationHandler for App {
                 .unwrap(),
         ));
 
-        self.instance = Some(wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::PRIMARY,
-            flags: wgpu::InstanceFlags::empty(),
-            ..Default::default()
-        }));
-        self.surface = Some(
-            self.instance
-                .as_ref()
-                .unwrap()
-                .create_surface(self.window.clone().unwrap())
-                .unwrap(),
-        );
-
-        let adapter = pollster::block_on(self.instance.as_ref().unwrap().request_adapter(
-            &wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: self.surface.as_ref(),
-                force_fallback_adapter: false,
-            },
-        ))
-        .unwrap();
-        let device_queue = pollster::block_on(adapter.request_device(
-            &wgpu::DeviceDescriptor {
-                label: Some("device-descriptor"),
-                required_features: wgpu::Features::empty(),
-                required_limits: wgpu::Limits::default(),
-                ..Default::default()
-            },
-            None,
-        ))
-        .unwrap();
-
-        self.device = Some(device_queue.0);
-        self.queue = Some(device_queue.1);
-
-        let texture_format = wgpu::TextureFormat::Bgra8UnormSrgb;
-
-        self.camera = Some(Camera {
-            eye: (8.4, 25.0, -8.4).into(),
-            target: (0.0, 0.0, 0.0).into(),
-            up: (0.0, 1.0, 0.0).into(),
-            aspect: WIDTH as f32 / HEIGHT as f32,
-            fovy: 90.0,
-            znear: 0.1,
-            zfar: 100.0,
-        });
-
-        let mut camera_uniform = CameraUniform::new();
-        camera_uniform.update_view_proj(&self.camera.as_ref().unwrap());
-
-        self.camera_buffer = Some(self.device.as_ref().unwrap().create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Camera Buffer"),
-                contents: bytemuck::cast_slice(&[camera_uniform]),
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            },
-        ));
-
-        let cube_bind_group_layout = &self.device.as_ref().unwrap().create_bind_group_layout(
-            &wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        // This should match the filterable field of the
-                        // corresponding Texture entry above.
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                ],
-                label: Some("cube bind group layout"),
-            },
-        );
-
-        let camera_bind_group_layout = self.device.as_ref().unwrap().create_bind_group_layout(
-            &wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-                label: Some("camera_bind_group_layout"),
-            },
-        );
-
-        self.camera_bind_group = Some(self.device.as_ref().unwrap().create_bind_group(
-            &wgpu::BindGroupDescriptor {
-                layout: &camera_bind_group_layout,
-                entries: &[wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: self.camera_buffer.as_ref().unwrap().as_entire_binding(),
-                }],
-                label: Some("camera_bind_group"),
-            },
-        ));
-
-        let size = self.window.as_ref().unwrap().inner_size();
-        self.surface.as_ref().unwrap().configure(
-            &self.device.as_ref().unwrap(),
-            &wgpu::SurfaceConfiguration {
-                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-                // not really sure what the TextureFormat is
-                format: texture_format,
-                width: size.width,
-                height: size.height,
-                present_mode: wgpu::PresentMode::Fifo,
-                desired_maximum_frame_latency: 1,
-                alpha_mode: wgpu::CompositeAlphaMode::PostMultiplied,
-                // alpha_mode: wgpu::CompositeAlphaMode::Opaque,
-                view_formats: vec![wgpu::TextureFormat::Bgra8UnormSrgb],
-            },
-        );
-
-        ////// controller
-        self.controller.velocity = 0.5; // = controller::Controller::new(0.5);
-
-        /////// brush stuff
-        let font = include_bytes!("../res/fonts/Fira_Code_v6.2/ttf/FiraCode-Light.ttf") as &[u8];
-        self.brush = Some(
-            wgpu_text::BrushBuilder::using_font_bytes(font)
-                .unwrap()
-                .build(self.device.as_ref().unwrap(), WIDTH, HEIGHT, texture_format),
-        );
-
-        self.text_section = Some(
-            TextSection::default()
-                .add_text(Text::new("Hello!  はじめまして!").with_color([0.9, 1.0, 1.0, 1.0]))
-                .with_bounds((WIDTH as f32, HEIGHT as f32))
-                .with_layout(
-                    wgpu_text::glyph_brush::Layout::default()
-                        .v_align(wgpu_text::glyph_brush::VerticalAlign::Center),
-                )
-                // .with_screen_position((0.0, 0.0))
-                .to_owned(),
-        );
-        ////
-
-        //// uniform buffer
-        self.timer = Some(Timer::new(self.device.as_ref().unwrap()));
-
-        self.vertex_buffer = Some(self.device.as_ref().unwrap().create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Vertex Buffer"),
-                contents: bytemuck::cast_slice(EFFECT_VERTS),
-                usage: wgpu::BufferUsages::VERTEX,
-            },
-        ));
-        // index buffer
-        self.index_buffer = Some(self.device.as_ref().unwrap().create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Index Buffer"),
-                contents: bytemuck::cast_slice(EFFECT_INDICES),
-                usage: wgpu::BufferUsages::INDEX,
-            },
-        ));
-
-        // camera stuff
-        let mut camera_uniform = CameraUniform::new();
-        camera_uniform.update_view_proj(&self.camera.as_ref().unwrap());
-
-        let cube_shader =
-            self.device
-                .as_ref()
-                .unwrap()
-                .create_shader_module(wgpu::ShaderModuleDescriptor {
-                    label: Some("Shader"),
-                    source: wgpu::ShaderSource::Wgsl(include_str!("cube.wgsl").into()),
-                });
-
-        let camera_bind_group_layout = &self.device.as_ref().unwrap().create_bind_group_layout(
-            &wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-                label: Some("camera_bind_group_layout"),
-            },
-        );
-
-        let cube_render_pipeline_layout =
-            self.device
-                .as_ref()
-                .unwrap()
-                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("cube pipeline layout"),
-                    bind_group_layouts: &[cube_bind_group_layout, &camera_bind_group_layout],
-                    push_constant_ranges: &[],
-                });
-
-        ///// shader time
-        let basic_shader =
-            self.device
-                .as_ref()
-                .unwrap()
-                .create_shader_module(wgpu::ShaderModuleDescriptor {
-                    label: Some("Shader"),
-                    source: wgpu::ShaderSource::Wgsl(include_str!("basic.wgsl").into()),
-                });
-        let background_texture_bind_group_layout =
-            &self.device.as_ref().unwrap().create_bind_group_layout(
-                &wgpu::BindGroupLayoutDescriptor {
-                    entries: &[
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 0,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Texture {
-                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                                view_dimension: wgpu::TextureViewDimension::D2,
-                                multisampled: false,
-                            },
-                            count: None,
-                        },
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 1,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                            count: None,
-                        },
-                    ],
-                    label: Some("background texture bind group layout"),
-                },
-            );
-        let background_render_pipeline_layout = self
-            .device
-            .as_ref()
-            .unwrap()
-            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("cube pipeline layout"),
-                bind_group_layouts: &[background_texture_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-        self.background_render_pipeline =
-            Some(self.device.as_ref().unwrap().create_render_pipeline(
-                &wgpu::RenderPipelineDescriptor {
-                    label: Some("background render pipeline"),
-                    layout: Some(&background_render_pipeline_layout),
-                    vertex: wgpu::VertexState {
-                        module: &basic_shader,
-                        entry_point: "vs_main",
-                        compilation_options: wgpu::PipelineCompilationOptions::default(),
-                        buffers: &[BasicVertex::desc()],
-                    },
-                    fragment: Some(wgpu::FragmentState {
-                        module: &basic_shader,
-                        entry_point: "fs_main",
-                        targets: &[Some(wgpu::ColorTargetState {
-                            format: texture_format,
-                            blend: Some(wgpu::BlendState::REPLACE),
-                            write_mask: wgpu::ColorWrites::ALL,
-                        })],
-                        compilation_options: wgpu::PipelineCompilationOptions::default(),
-                    }),
-                    primitive: wgpu::PrimitiveState {
-                        topology: wgpu::PrimitiveTopology::TriangleList,
-                        strip_index_format: None,
-                        front_face: wgpu::FrontFace::Cw,
-                        cull_mode: Some(wgpu::Face::Back),
-                        polygon_mode: wgpu::PolygonMode::Fill,
-                        unclipped_depth: false,
-                        conservative: false,
-                    },
-                    depth_stencil: None,
-                    multisample: wgpu::MultisampleState {
-                        count: 1,
-                        mask: !0,
-                        alpha_to_coverage_enabled: false,
-                    },
-                    multiview: None,
-                    cache: None,
-                },
-            ));
-        self.background_vertex_buffer = Some(self.device.as_ref().unwrap().create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("background vertex buffer"),
-                contents: bytemuck::cast_slice(BACKGROUND_QUAD.to_vec().as_slice()),
-                usage: wgpu::BufferUsages::VERTEX,
-            },
-        ));
-        self.background_index_buffer = Some(self.device.as_ref().unwrap().create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("background index buffer"),
-                contents: bytemuck::cast_slice(BACKGROUND_QUAD_INDICES),
-                usage: wgpu::BufferUsages::INDEX,
-            },
-        ));
-
-        let background_diffuse_bytes = include_bytes!("../res/backgrounds/reactor.png");
-        let background_diffuse_texture = texture::Texture::from_bytes(
-            &self.device.as_ref().unwrap(),
-            &self.queue.as_ref().unwrap(),
-            background_diffuse_bytes,
-            "background image",
-            false,
-        )
-        .unwrap();
-        self.background_texture_bind_group = Some(self.device.as_ref().unwrap().create_bind_group(
-            &wgpu::BindGroupDescriptor {
-                layout: &background_texture_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(
-                            &background_diffuse_texture.view,
-                        ),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(
-                            &background_diffuse_texture.sampler,
-                        ),
-                    },
-                ],
-                label: Some("backgroundd texture bind group"),
-            },
-        ));
-
-        self.cube_pipeline = Some(self.device.as_ref().unwrap().create_render_pipeline(
-            &wgpu::RenderPipelineDescriptor {
-                label: Some("cube render pipeline"),
-                layout: Some(&cube_render_pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &cube_shader,
-                    entry_point: "vs_main",
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
-                    buffers: &[vertex::ModelVertex::desc(), InstanceRaw::desc()],
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &basic_shader,
-                    entry_point: "fs_main",
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: texture_format,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    unclipped_depth: false,
-                    conservative: false,
-                },
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                multiview: None,
-                cache: None,
-            },
-        ));
-
-        self.cube_model = Some(
-            cube::load_cube(
-                "cube.obj",
-                &self.device.as_ref().unwrap(),
-                &self.queue.as_ref().unwrap(),
-                cube_bind_group_layout,
-            )
-            .unwrap(),
-        );
+        self.capture = Some(capture::FrameCapture::new());
+        self.scenarios = scenario::all();
+        self.audio = Some(audio::Audio::from_args());
+        self.sfx = sfx::Sfx::from_args();
+        self.remote = remote::RemoteControl::from_args();
+        self.client = net::Client::from_args();
+        self.interpolation = interpolation::Interpolation::from_args();
+        self.console.load_history();
+        self.materials = materials::MaterialEditor::from_args();
+        #[cfg(feature = "http")]
+        {
+            self.http = http::HttpApi::from_args();
+        }
+        self.rng = rng::Rng::from_args();
+        // noise only takes 32 bits, which is plenty for picking a field
+        self.noise = noise::Noise {
+            seed: self.rng.seed() as u32,
+        };
+        let args = std::env::args().collect::<Vec<_>>();
+        if let Some(path) = args
+            .iter()
+            .position(|a| a == "--tilemap")
+            .and_then(|i| args.get(i + 1))
+        {
+            self.tilemap_path = Some(path.into());
+        }
+        self.stream_name = args
+            .iter()
+            .position(|a| a == "--stream")
+            .and_then(|i| args.get(i + 1))
+            .cloned();
+        if let Some(path) = args
+            .iter()
+            .position(|a| a == "--lut")
+            .and_then(|i| args.get(i + 1))
+        {
+            self.lut_path = Some(path.into());
+        }
+        if let Some(path) = args
+            .iter()
+            .position(|a| a == "--session-json")
+            .and_then(|i| args.get(i + 1))
+        {
+            self.session.json_path = Some(path.into());
+        }
+        if args.iter().any(|a| a == "--packed-vertices") {
+            self.model_format = vertex::ModelFormat::Packed;
+        }
+        if let Some(arg) = args
+            .iter()
+            .position(|a| a == "--spawn-rotation")
+            .and_then(|i| args.get(i + 1))
+        {
+            match arg.parse() {
+                Ok(rotation) => self.spawn_rotation = rotation,
+                Err(e) => log::error!("ignoring --spawn-rotation: {}", e),
+            }
+        }
+        if let Some(arg) = args
+            .iter()
+            .position(|a| a == "--frame-budget")
+            .and_then(|i| args.get(i + 1))
+        {
+            match arg.parse::<f64>() {
+                Ok(ms) if ms > 0.0 => self.watchdog.budget = ms / 1000.0,
+                _ => log::error!("ignoring --frame-budget {}, it takes milliseconds", arg),
+            }
+        }
+        if let Some(path) = args
+            .iter()
+            .position(|a| a == "--record")
+            .and_then(|i| args.get(i + 1))
+        {
+            let fps = args
+                .iter()
+                .position(|a| a == "--record-fps")
+                .and_then(|i| args.get(i + 1))
+                .and_then(|fps| fps.parse().ok())
+                .filter(|fps| *fps > 0)
+                .unwrap_or(60);
+            match video::VideoRecorder::start(path, fps) {
+                Ok(video) => self.video = Some(video),
+                Err(e) => log::error!("not recording: {:#}", e),
+            }
+        }
+        match profile::Profile::from_args() {
+            Ok(profile) => {
+                profile.load(&mut self.options, &mut self.bindings);
+                self.profile = Some(profile);
+            }
+            Err(e) => log::error!("not using a profile: {:#}", e),
+        }
+        self.options.quality_from_args();
+        self.benchmark = benchmark::Benchmark::from_args();
+        if self.benchmark.is_some() {
+            // the same scene and settings whatever the profile says
+            self.rng = rng::Rng::new(benchmark::SEED);
+            self.noise = noise::Noise {
+                seed: benchmark::SEED as u32,
+            };
+            self.options.quality = quality::Preset::Ultra;
+            self.options.overrides = Default::default();
+            // set_available falls back to Fifo where the surface can't
+            self.options.present_mode = wgpu::PresentMode::Immediate;
+            self.water_enabled = true;
+            self.cloth_enabled = true;
+            self.watchdog.enabled = false;
+        }
+        self.scaling = scaling::Scaling::from_args();
+        if self.scaling.is_some() {
+            // unsynced, and nothing turned down when it gets slow
+            self.options.present_mode = wgpu::PresentMode::Immediate;
+            self.watchdog.enabled = false;
+        }
+        saves::warn_about_old_saves();
+        if saves::lock() {
+            log::warn!("praxis didn't shut down cleanly last time");
+            self.restore_prompt = saves::Slot::latest_autosave();
+        }
 
-        self.cube_position = Some(cgmath::Vector3 {
-            x: -1.0,
-            y: -1.0,
-            z: -1.0,
-        });
+        self.init_scene();
+        self.restart_gpu(event_loop, None);
 
-        use cgmath::prelude::*;
-        const SPACE_BETWEEN: f32 = 3.0;
-        const NUM_INSTANCES_PER_ROW: i32 = 5;
-
-        self.cube_instances = vec![Instance {
-            position: self.cube_position.unwrap(),
-            rotation: cgmath::Quaternion::zero(),
-            // cgmath::Quaternion::from_axis_angle(
-            //     (16.6,50.0,-16.6).into(),
-            // cgmath::Deg(45.0)
-            // ),
-        }];
-
-        // self.cube_instances = (0..NUM_INSTANCES_PER_ROW)
-        //     .flat_map(|y| {
-        //         (0..NUM_INSTANCES_PER_ROW).map(move |x| {
-        //             let x = SPACE_BETWEEN * (x as f32 - NUM_INSTANCES_PER_ROW as f32 / 2.0);
-        //             let y = SPACE_BETWEEN * (y as f32 - NUM_INSTANCES_PER_ROW as f32 / 2.0);
-
-        //             let mut position = cgmath::Vector3 { x, y, z: 0.1 };
-        //             // position.x = self.cube_position.as_ref().unwrap().clone().x;
-        //             // position.y = self.cube_position[1].into();
-        //             // position.z = self.cube_position[2].into();
-        //             // let position = &self.cube_position.unwrap();
-
-        //             let rotation = if position.is_zero() {
-        //                 cgmath::Quaternion::from_axis_angle(
-        //                     cgmath::Vector3::unit_z(),
-        //                     cgmath::Deg(0.0),
-        //                 )
-        //             } else {
-        //                 cgmath::Quaternion::from_axis_angle(position.normalize(), cgmath::Deg(45.0))
-        //             };
-
-        //             Instance { position, rotation }
-        //         })
-        //     })
-        //     .collect::<Vec<_>>();
-
-        let instance_data = self
-            .cube_instances
+        // `--formation "grid 10 10"` starts with a field of cubes, same syntax as `spawn`
+        if let Some(arg) = args
             .iter()
-            .map(Instance::to_raw)
-            .collect::<Vec<_>>();
-
-        self.cube_instance_buffer = Some(self.device.as_ref().unwrap().create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("cube instance buffer"),
-                contents: bytemuck::cast_slice(&instance_data),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            },
-        ));
+            .position(|a| a == "--formation")
+            .and_then(|i| args.get(i + 1))
+        {
+            match formation::Formation::parse(arg, &mut self.rng) {
+                Ok(formation) => self.spawn_formation(formation),
+                Err(e) => log::error!("ignoring --formation: {}", e),
+            }
+        }
 
-        //////
-        // in new() after creating `camera`
-        let mut camera_uniform = CameraUniform::new();
-        camera_uniform.update_view_proj(&self.camera.as_ref().unwrap());
+        if self.benchmark.is_some() {
+            let formation =
+                formation::Formation::parse(benchmark::FORMATION, &mut self.rng).unwrap();
+            // and rain, which finish_gpu starts once there's a renderer
+            self.spawn_formation(formation);
+            log::info!("benchmarking {}", benchmark::FORMATION);
+        }
+        if let Some(count) = self.scaling.as_ref().map(|s| s.count()) {
+            self.set_cube_count(count);
+        }
 
         // initial redraw request
-        self.window.as_ref().unwrap().request_redraw();
+        self.request_redraw();
     }
 
-    fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
-        if self.controller.process_events(&event) {
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        if matches!(
+            event,
+            WindowEvent::KeyboardInput { .. } | WindowEvent::MouseInput { .. }
+        ) {
+            self.stats.input();
+        }
+        let (used, line) = self.console.process_events(&event);
+        if let Some(line) = line {
+            self.run_command(&line);
+        }
+        if used {
+            return;
+        }
+        if let Some(client) = &self.client {
+            let (used, line) = self.chat.process_events(&event);
+            if let Some(line) = line {
+                client.say(&line);
+            }
+            if used {
+                return;
+            }
+        }
+        if self.tweaks.process_events(&event) {
+            return;
+        }
+        if let (true, Some(window)) = (self.materials.open, &self.window) {
+            if self.gui.process_events(window, &event) {
+                return;
+            }
+        }
+        if self.grid.enabled {
+            if let Some(direction) = controller::Controller::grid_step(&event) {
+                self.grid.step(direction);
+                return;
+            }
+        }
+        if self.controller.process_events(&event) || self.orbit.process_events(&event) {
             return;
         }
         match event {
-            WindowEvent::CloseRequested
-            | WindowEvent::KeyboardInput {
+            WindowEvent::CloseRequested => self.run_action(bindings::Action::Quit, event_loop),
+            WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
                         state: winit::event::ElementState::Pressed,
-                        logical_key: Key::Named(NamedKey::Escape),
+                        logical_key: Key::Character(ref c),
                         ..
                     },
                 ..
-            } => {
-                println!("The close button was pressed; stopping");
-                event_loop.exit();
+            } if self.restore_prompt.is_some() && (c.as_str() == "y" || c.as_str() == "n") => {
+                let slot = self.restore_prompt.take().unwrap();
+                if c.as_str() == "y" {
+                    self.load_slot(&slot);
+                }
+            }
+
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: winit::event::ElementState::Pressed,
+                        logical_key: Key::Character(ref c),
+                        ..
+                    },
+                ..
+            } if self.main_menu && c.parse::<usize>().is_ok_and(|n| n <= self.scenarios.len()) => {
+                self.main_menu = false;
+                match c.parse::<usize>().unwrap() {
+                    0 => {
+                        self.scenario = None;
+                        self.set_lighting(light::Lighting::default());
+                    }
+                    n => self.start_scenario(n - 1),
+                }
+            }
+
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: winit::event::ElementState::Pressed,
+                        logical_key: Key::Character(ref c),
+                        ..
+                    },
+                ..
+            } if self.options.open
+                && c.parse::<u32>()
+                    .is_ok_and(|n| (0..=options::ITEMS).contains(&n)) =>
+            {
+                self.option_key(c.parse().unwrap())
+            }
+
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: winit::event::ElementState::Pressed,
+                        logical_key: Key::Character(ref c),
+                        ..
+                    },
+                ..
+            } if self.save_menu.is_some() && c.parse::<usize>().is_ok() => {
+                let slots = self.save_menu.take().unwrap();
+                match slots.get(c.parse::<usize>().unwrap().wrapping_sub(1)) {
+                    Some(slot) => self.load_slot(slot),
+                    None => {
+                        log::warn!("no save slot {}", c.as_str());
+                        self.save_menu = Some(slots);
+                    }
+                }
             }
+
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
                         state: winit::event::ElementState::Pressed,
-                        logical_key: Key::Named(NamedKey::Space),
+                        ref logical_key,
                         ..
                     },
                 ..
-            } => self.add_cube(),
+            } if self.bindings.action(logical_key).is_some() => {
+                let action = self.bindings.action(logical_key).unwrap();
+                self.run_action(action, event_loop);
+            }
+
+            WindowEvent::CursorMoved { position, .. } => self.cursor_position = position,
+            WindowEvent::Resized(size) => self.resize(size),
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => self.select_cube(),
 
             WindowEvent::RedrawRequested => {
+                if self.device_lost.swap(false, Ordering::SeqCst) {
+                    log::warn!("rebuilding gpu resources after device loss");
+                    self.restart_gpu(event_loop, None);
+                }
+                if self.loading.is_some() {
+                    self.poll_loading();
+                    return;
+                }
+
                 self.update();
-                let output = self
-                    .surface
-                    .as_ref()
-                    .unwrap()
-                    .get_current_texture()
-                    .unwrap();
+                // worked out before the renderer's borrowed for the rest of the frame
+                let (width, height) = self.screen_size();
+                let tags = self.name_tags();
+                let wants_frame = self.http_wants_frame();
+                let Some(gfx) = &mut self.gfx else {
+                    return;
+                };
+                self.tweaks.upload(&gfx.queue);
+                self.materials
+                    .apply(&gfx.queue, &gfx.cube_model.materials[0]);
+                let output = match gfx.surface.get_current_texture() {
+                    Ok(output) => output,
+                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                        gfx.surface.configure(&gfx.device, &gfx.config);
+                        self.request_redraw();
+                        return;
+                    }
+                    Err(wgpu::SurfaceError::Timeout) => {
+                        self.request_redraw();
+                        return;
+                    }
+                    Err(wgpu::SurfaceError::OutOfMemory) => {
+                        log::error!("surface out of memory, rebuilding gpu resources");
+                        self.restart_gpu(event_loop, None);
+                        self.request_redraw();
+                        return;
+                    }
+                };
 
                 let view = output
                     .texture
                     .create_view(&wgpu::TextureViewDescriptor::default());
-                let mut encoder = self.device.as_ref().unwrap().create_command_encoder(
-                    &wgpu::CommandEncoderDescriptor {
-                        label: Some("render encoder"),
-                    },
+                let ui = allocs::scope(allocs::Subsystem::Ui);
+                let toast = self.errors.toast_section(width, height);
+                let toasts = self.toasts.sections(width);
+                let keys = self.bindings.sections(width);
+                let menu = self
+                    .save_menu
+                    .as_ref()
+                    .map(|slots| save_menu_section(slots, height));
+                let prompt = self
+                    .restore_prompt
+                    .as_ref()
+                    .map(|slot| restore_prompt_section(slot, height));
+                let options = self.options.section(height);
+                let tweaks = self.tweaks.section(height);
+                let main_menu = self
+                    .main_menu
+                    .then(|| main_menu_section(&self.scenarios, self.scenario, height));
+                if let (Some(clock_text), Some(cubes_text)) = (self.clock_text, self.cubes_text) {
+                    self.ui.set_text(clock_text, &self.clock.text());
+                    self.ui
+                        .set_text(cubes_text, &format!("{} cubes", self.sim.cubes.len()));
+                }
+                self.ui.layout(&gfx.queue, &mut gfx.brush);
+                let console = self.console.section(height);
+                let chat = if self.console.open {
+                    Vec::new()
+                } else {
+                    self.chat.sections(height)
+                };
+                self.stats.staging_fallback_bytes += gfx.staging.take_fallback_bytes();
+                let stats = self.stats.section(width);
+                let texture_list = self.texture_viewer.section(&gfx.textures(), height);
+                let inspected = self.buffer_inspector.section(height);
+                let paused = self.stepping.section(
+                    self.sim.tick,
+                    width,
+                    &self.bindings.key_for(bindings::Action::Pause),
+                    &self.bindings.key_for(bindings::Action::Step),
                 );
+                gfx.brush
+                    .queue(
+                        &gfx.device,
+                        &gfx.queue,
+                        toast
+                            .iter()
+                            .chain(toasts.iter())
+                            .chain(menu.iter())
+                            .chain(prompt.iter())
+                            .chain(options.iter())
+                            .chain(tweaks.iter())
+                            .chain(main_menu.iter())
+                            .chain(keys.iter())
+                            .chain(self.ui.sections())
+                            .chain(tags.iter())
+                            .chain(chat.iter())
+                            .chain(console.iter())
+                            .chain(stats.iter())
+                            .chain(texture_list.iter())
+                            .chain(inspected.iter())
+                            .chain(paused.iter()),
+                    )
+                    .unwrap();
+                drop(ui);
+                let _alloc = allocs::scope(allocs::Subsystem::Render);
+
+                let mut encoder =
+                    gfx.device
+                        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: Some("render encoder"),
+                        });
+                if let Some(gpu_timer) = gfx.gpu_timer.as_mut() {
+                    gpu_timer.mark(&mut encoder, "start");
+                }
+                if let Some(sprites) = &gfx.sprites {
+                    sprites.update(&gfx.queue, self.clock.seconds());
+                }
+                let tilemap = gfx.tilemap.as_ref().filter(|t| t.order.is_some());
+                if let Some(tilemap) = tilemap {
+                    tilemap.update(&gfx.queue);
+                }
+
+                // everything drawn one at a time this frame gets its slice
+                // of the object arena here, before any pass binds it
+                let objects = &mut gfx.objects;
+                let portal_offsets = gfx.portals.objects().map(|o| objects.push(o));
+                objects.upload(&gfx.device, &gfx.queue);
+                let objects = &gfx.objects;
+
+                // what the main view culls against
+                let frustum =
+                    camera::Frustum::from_view_proj(self.camera.build_view_projection_matrix());
+
+                ///////
+                // reflections, drawn before the main pass. Both the cubemap faces
+                // and the water's mirrored camera flip winding, hence the Cw pipeline.
+                // They're small enough for the coarser LOD.
+                let model = &gfx.cube_model;
+                let pipeline = &gfx.cube_env_pipeline;
+                let instance_buffer = &gfx.cube_instances.buffer;
+                let instance_count = self.sim.cubes.len() as u32;
+                let arena = &gfx.mesh_arena;
+                let timer_bind_group = &gfx.timer.timer_bind_group;
+                let draw_mirrored_cubes =
+                    |render_pass: &mut wgpu::RenderPass,
+                     camera_bind_group: &wgpu::BindGroup,
+                     environment_bind_group: &wgpu::BindGroup| {
+                        render_pass.set_pipeline(pipeline);
+                        render_pass.set_mesh_arena(arena);
+                        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+                        render_pass.set_bind_group(2, environment_bind_group, &[]);
+                        render_pass.set_bind_group(3, timer_bind_group, &[]);
+                        render_pass.draw_model_instanced(
+                            model,
+                            1,
+                            0..instance_count,
+                            camera_bind_group,
+                        );
+                    };
+
+                let environment = &mut gfx.environment;
+                if environment.due() {
+                    environment.render_faces(&mut encoder, &gfx.queue, &draw_mirrored_cubes);
+                }
+                if self.water_enabled {
+                    gfx.water.render_reflection(
+                        &mut encoder,
+                        &gfx.queue,
+                        &self.camera,
+                        |render_pass, camera_bind_group| {
+                            draw_mirrored_cubes(
+                                render_pass,
+                                camera_bind_group,
+                                environment.bind_group(),
+                            )
+                        },
+                    );
+                }
+
+                let floor = &gfx.floor;
+                if self.floor_mirror && !self.floor_hidden && floor.in_view(&frustum) {
+                    floor.render_reflection(
+                        &mut encoder,
+                        &gfx.queue,
+                        &self.camera,
+                        |render_pass, camera_bind_group| {
+                            draw_mirrored_cubes(
+                                render_pass,
+                                camera_bind_group,
+                                environment.bind_group(),
+                            )
+                        },
+                    );
+                }
+
+                let portals = &gfx.portals;
+                if portals.enabled {
+                    let floor = (!self.floor_hidden).then_some(&gfx.floor);
+                    let pipeline = &gfx.cube_pipeline;
+                    portals.render_views(
+                        &mut encoder,
+                        &gfx.queue,
+                        &self.camera,
+                        |render_pass, camera_bind_group, frustum| {
+                            if let Some(floor) = floor {
+                                floor.draw(render_pass, camera_bind_group, frustum);
+                            }
+                            render_pass.set_pipeline(pipeline);
+                            render_pass.set_mesh_arena(arena);
+                            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+                            render_pass.set_bind_group(2, environment.bind_group(), &[]);
+                            render_pass.set_bind_group(3, timer_bind_group, &[]);
+                            render_pass.draw_model_instanced(
+                                model,
+                                0,
+                                0..instance_count,
+                                camera_bind_group,
+                            );
+                        },
+                    );
+                }
+
+                if let Some(gpu_timer) = gfx.gpu_timer.as_mut() {
+                    gpu_timer.mark(&mut encoder, "reflections");
+                }
+                let lod = &mut gfx.lod;
+                if lod.enabled {
+                    lod.cull(
+                        &gfx.device,
+                        &gfx.queue,
+                        &mut encoder,
+                        instance_buffer,
+                        instance_count,
+                        &model.meshes,
+                        &self.camera,
+                        gfx.config.height,
+                    );
+                }
+                let lod = &gfx.lod;
+
+                if self.cloth_enabled {
+                    gfx.cloth
+                        .step(&mut encoder, &gfx.queue, self.sim.cubes[0].position);
+                }
+                if let Some(weather) = &mut gfx.weather {
+                    weather.step(
+                        &mut encoder,
+                        &gfx.queue,
+                        self.camera.eye,
+                        self.clock.seconds(),
+                    );
+                    gfx.floor.set_wetness(&gfx.queue, weather.wetness);
+                }
 
+                if let Some(gpu_timer) = gfx.gpu_timer.as_mut() {
+                    gpu_timer.mark(&mut encoder, "compute");
+                }
+                gfx.motion_blur.update(
+                    &gfx.device,
+                    &gfx.queue,
+                    self.camera.build_view_projection_matrix(),
+                    &self.sim.cubes,
+                );
+                // blur first, then the retro blocks and their palette, then
+                // grading, and the crt is the screen all of that is shown on
+                let effects: [&dyn post::Effect; 4] =
+                    [&gfx.motion_blur, &gfx.retro, &gfx.grade, &gfx.crt];
+                let post = &gfx.post;
+                let scene_view = post.scene_view(&effects, &view);
+                // the background goes with the visualizer's bands, treble red to bass blue
+                let levels = self.visualizer.as_ref().map_or([0.0; 4], |v| v.levels);
                 {
                     let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                         label: Some("render pass"),
                         color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &view,
+                            view: scene_view,
                             resolve_target: None,
                             ops: wgpu::Operations {
                                 load: wgpu::LoadOp::Clear(wgpu::Color {
-                                    r: 0.1,
-                                    g: 0.2,
-                                    b: 0.3,
+                                    r: 0.1 + levels[3] as f64 * 0.4,
+                                    g: 0.2 + (levels[1] + levels[2]) as f64 * 0.15,
+                                    b: 0.3 + levels[0] as f64 * 0.5,
                                     a: 1.0,
                                 }),
                                 store: wgpu::StoreOp::Store,
                             },
                         })],
-                        depth_stencil_attachment: None,
+                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                            view: &gfx.depth_texture.view,
+                            depth_ops: Some(wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(1.0),
+                                store: wgpu::StoreOp::Store,
+                            }),
+                            stencil_ops: None,
+                        }),
                         timestamp_writes: None,
                         occlusion_query_set: None,
                     });
 
                     //////
                     // draw background
-                    render_pass.set_pipeline(&self.background_render_pipeline.as_ref().unwrap());
-                    render_pass.set_bind_group(
-                        0,
-                        &self.background_texture_bind_group.as_ref().unwrap(),
-                        &[],
-                    );
-                    render_pass.set_bind_group(1, self.camera_bind_group.as_ref().unwrap(), &[]);
-                    render_pass.set_vertex_buffer(
-                        0,
-                        self.background_vertex_buffer.as_ref().unwrap().slice(..),
-                    );
+                    render_pass.set_pipeline(&gfx.background_render_pipeline);
+                    render_pass.set_bind_group(0, &gfx.background_texture_bind_group, &[]);
+                    render_pass.set_bind_group(1, &gfx.timer.timer_bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, gfx.background_vertex_buffer.slice(..));
                     render_pass.set_index_buffer(
-                        self.background_index_buffer.as_ref().unwrap().slice(..),
+                        gfx.background_index_buffer.slice(..),
                         wgpu::IndexFormat::Uint16,
                     );
-                    // render_pass.draw_indexed(0..BACKGROUND_QUAD_INDICES.len() as u32, 0, 0..1);
+                    render_pass.draw_indexed(0..BACKGROUND_QUAD_INDICES.len() as u32, 0, 0..1);
+
+                    // the tilemap ignores depth, so behind just means drawn first
+                    if let Some(tilemap) =
+                        tilemap.filter(|t| t.order == Some(tilemap::Order::Behind))
+                    {
+                        tilemap.draw(&mut render_pass);
+                    }
+
+                    if !self.floor_hidden {
+                        gfx.floor
+                            .draw(&mut render_pass, &gfx.camera_bind_group, &frustum);
+                    }
+                    if self.water_enabled {
+                        gfx.water.draw(
+                            &mut render_pass,
+                            &gfx.camera_bind_group,
+                            gfx.environment.bind_group(),
+                            &gfx.timer.timer_bind_group,
+                        );
+                    }
+
+                    if portals.enabled {
+                        portals.draw(
+                            &mut render_pass,
+                            &gfx.camera_bind_group,
+                            objects,
+                            portal_offsets,
+                        );
+                    }
+
+                    if lod.enabled {
+                        lod.draw_impostors(&mut render_pass, &gfx.camera_bind_group);
+                    }
 
                     ///////
                     // cube
-                    render_pass.set_pipeline(&self.cube_pipeline.as_ref().unwrap());
-                    // render_pass.set_vertex_buffer(
-                    //     0,
-                    //     self.cube_model.as_ref().unwrap().meshes[0]
-                    //         .vertex_buffer
-                    //         .slice(..),
-                    // );
-                    // let material = &self.cube_model.as_ref().unwrap().materials[0].bind_group;
-                    // render_pass.set_bind_group(0, &material, &[]);
-                    // render_pass.set_index_buffer(
-                    //     self.cube_model.as_ref().unwrap().meshes[0]
-                    //         .index_buffer
-                    //         .slice(..),
-                    //     wgpu::IndexFormat::Uint16,
-                    // );
-                    // render_pass.draw_indexed(0..8, 0, 0..1);
-                    // /////////////
-                    render_pass.set_vertex_buffer(
-                        1,
-                        self.cube_instance_buffer.as_ref().unwrap().slice(..),
-                    );
-                    let mesh = &self.cube_model.as_ref().unwrap().meshes[0];
-                    let material = &self.cube_model.as_ref().unwrap().materials[0];
-                    render_pass.set_bind_group(0, &material.bind_group, &[]);
-                    render_pass.draw_mesh_instanced(
-                        mesh,
-                        material,
-                        0..self.cube_instances.len() as u32,
-                        self.camera_bind_group.as_ref().unwrap(),
+                    let mut queue = draws::DrawQueue::default();
+                    let depth = self.camera.eye.distance(self.sim.cubes[0].position);
+                    for (i, mesh) in model.meshes.iter().enumerate() {
+                        let material = &model.materials[mesh.material];
+                        let instances = if lod.enabled {
+                            lod.near_instances(i)
+                        } else {
+                            draws::Instances::Direct(
+                                &gfx.cube_instances.buffer,
+                                0..self.sim.cubes.len() as u32,
+                            )
+                        };
+                        queue.push(&gfx.cube_pipeline, mesh, material, depth, instances);
+                        let shatter = &gfx.shatter;
+                        if shatter.len() > 0 {
+                            queue.push(
+                                &gfx.cube_pipeline,
+                                mesh,
+                                material,
+                                0.0,
+                                draws::Instances::Direct(shatter.buffer(), 0..shatter.len() as u32),
+                            );
+                        }
+                        if let Some(stream) = &gfx.stream {
+                            queue.push(
+                                &gfx.cube_pipeline,
+                                mesh,
+                                material,
+                                0.0,
+                                draws::Instances::Direct(stream.buffer(), 0..stream.len() as u32),
+                            );
+                        }
+                    }
+                    render_pass.set_bind_group(2, gfx.environment.bind_group(), &[]);
+                    render_pass.set_bind_group(3, &gfx.timer.timer_bind_group, &[]);
+                    queue.submit(
+                        &mut render_pass,
+                        arena,
+                        &gfx.camera_bind_group,
+                        &mut self.stats,
                     );
 
-                    self.brush.as_ref().unwrap().draw(&mut render_pass);
+                    if self.cloth_enabled {
+                        gfx.cloth.draw(&mut render_pass, &gfx.camera_bind_group);
+                    }
+                    if let Some(sprites) = &gfx.sprites {
+                        sprites.draw(&mut render_pass, &gfx.camera_bind_group);
+                    }
+                    if let Some(weather) = &gfx.weather {
+                        weather.draw(&mut render_pass, &gfx.camera_bind_group);
+                    }
+                    if let Some(tilemap) =
+                        tilemap.filter(|t| t.order == Some(tilemap::Order::Above))
+                    {
+                        tilemap.draw(&mut render_pass);
+                    }
+                }
+                if let Some(gpu_timer) = gfx.gpu_timer.as_mut() {
+                    gpu_timer.mark(&mut encoder, "scene");
                 }
 
-                // submit will accept anything that implements IntoIter
-                self.queue
-                    .as_ref()
-                    .unwrap()
-                    .submit(std::iter::once(encoder.finish()));
-                output.present();
-                self.window.as_ref().unwrap().request_redraw();
-            }
-            _ => (),
-        }
-    }
-}
-impl App {
-    fn update(&mut self) {
-        // Update the cube's position
-        let mut x = 0.0;
-        let mut y = 0.0;
-        let mut z = 0.0;
-        if self.controller.is_up_pressed {
+                gfx.outline.render(
+                    &mut encoder,
+                    scene_view,
+                    &gfx.camera_bind_group,
+                    arena,
+                    &model.meshes,
+                    &gfx.cube_instances.buffer,
+                    &self.selected,
+                );
+                gfx.motion_blur.render_velocity(
+                    &mut encoder,
+                    &gfx.camera_bind_group,
+                    arena,
+                    &model.meshes,
+                    &gfx.cube_instances.buffer,
+                    self.sim.cubes.len() as u32,
+                );
+                post.run(&mut encoder, &effects, &view);
+                if let Some(gpu_timer) = gfx.gpu_timer.as_mut() {
+                    gpu_timer.mark(&mut encoder, "post");
+                }
+
+                ///////
+                // text goes on top of everything
+                {
+                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("text pass"),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                            view: &view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: wgpu::StoreOp::Store,
+                            },
+                        })],
+                        depth_stencil_attachment: None,
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                    });
+                    self.texture_viewer.draw(
+                        &gfx.device,
+                        &gfx.queue,
+                        &mut render_pass,
+                        &gfx.textures(),
+                        (width, height),
+                    );
+                    self.ui.draw(&mut render_pass);
+                    gfx.brush.draw(&mut render_pass);
+                }
+                if let (true, Some(window)) = (self.materials.open, &self.window) {
+                    let materials = &mut self.materials;
+                    let mut message = None;
+                    if let Some(frame) = self.gui.run(window, |ctx| message = materials.ui(ctx)) {
+                        self.gui.draw(
+                            &gfx.device,
+                            &gfx.queue,
+                            &mut encoder,
+                            &view,
+                            (gfx.config.width, gfx.config.height),
+                            frame,
+                        );
+                    }
+                    if let Some(message) = message {
+                        self.toasts.push(message);
+                    }
+                }
+                if let Some(gpu_timer) = gfx.gpu_timer.as_mut() {
+                    gpu_timer.mark(&mut encoder, "text");
+                    gpu_timer.resolve(&mut encoder);
+                }
+
+                let inspecting = self.buffer_inspector.record(
+                    &gfx.device,
+                    &mut encoder,
+                    &gfx.buffers(self.sim.cubes.len()),
+                );
+
+                // the thumbnail is whatever was on screen when saving
+                let now = gfx.timer.elapsed;
+                let readback = ((self.pending_save
+                    || self.video.is_some()
+                    || self.clips.wants_frame(now)
+                    || wants_frame)
+                    && gfx.config.usage.contains(wgpu::TextureUsages::COPY_SRC))
+                .then(|| screenshot::Readback::record(&gfx.device, &mut encoder, &output.texture));
+
+                // staged copies go first so the frame sees this frame's data
+                let staged = gfx.staging.finish();
+                gfx.queue
+                    .submit(staged.into_iter().chain(std::iter::once(encoder.finish())));
+                gfx.staging.recall(&gfx.device);
+                if let Some(inspecting) = inspecting {
+                    self.buffer_inspector.read(&gfx.device, inspecting);
+                }
+
+                let gpu_times = gfx
+                    .gpu_timer
+                    .as_mut()
+                    .map(|t| t.read(&gfx.device))
+                    .unwrap_or_default();
+                let frame = readback.and_then(|r| r.read(&gfx.device));
+                if let (Some(video), Some(frame)) = (self.video.as_mut(), frame.as_ref()) {
+                    if let Err(e) = video.push(frame) {
+                        log::error!("stopped recording: {:#}", e);
+                        self.video = None;
+                        self.toasts.push("recording failed");
+                    }
+                }
+                #[cfg(feature = "http")]
+                for reply in self.http_screenshots.drain(..) {
+                    let mut png = std::io::Cursor::new(Vec::new());
+                    let response = match frame
+                        .as_ref()
+                        .map(|f| f.write_to(&mut png, image::ImageFormat::Png))
+                    {
+                        Some(Ok(())) => http::Response {
+                            status: 200,
+                            content_type: "image/png",
+                            body: png.into_inner(),
+                        },
+                        _ => http::Response::text(503, "the frame couldn't be read back\n"),
+                    };
+                    let _ = reply.send(response);
+                }
+                if let Some(frame) = frame.as_ref().filter(|_| self.clips.wants_frame(now)) {
+                    self.clips.push(frame, now);
+                }
+                if self.pending_save {
+                    self.pending_save = false;
+                    self.save_slot(saves::Slot::next_name(), frame.as_ref());
+                }
+                output.present();
+                let finished = self.benchmark.as_mut().is_some_and(|b| b.frame(&gpu_times));
+                if let Some(benchmark) = self.benchmark.as_ref().filter(|_| finished) {
+                    let (adapter, backend) = self.adapter();
+                    benchmark.finish(&adapter, &backend);
+                    saves::unlock();
+                    event_loop.exit();
+                }
+                match self.scaling.as_mut().map(|s| s.frame()) {
+                    Some(scaling::Step::Set(count)) => self.set_cube_count(count),
+                    Some(scaling::Step::Done) => {
+                        let (adapter, backend) = self.adapter();
+                        if let Some(scaling) = &self.scaling {
+                            scaling.finish(&adapter, &backend);
+                        }
+                        saves::unlock();
+                        event_loop.exit();
+                    }
+                    _ => {}
+                }
+                let refresh = self
+                    .window
+                    .as_ref()
+                    .unwrap()
+                    .current_monitor()
+                    .and_then(|m| m.refresh_rate_millihertz())
+                    .unwrap_or(60_000);
+                self.stats.presented(
+                    self.options.queued_refreshes(),
+                    std::time::Duration::from_secs_f64(1000.0 / refresh as f64),
+                );
+                self.request_redraw();
+            }
+            _ => (),
+        }
+    }
+
+    // the mouse's own movement, for looking around while flying
+    fn device_event(&mut self, _event_loop: &ActiveEventLoop, _id: DeviceId, event: DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta } = event {
+            if self.camera_mode == camera::CameraMode::FirstPerson {
+                self.mouse_look.motion(delta);
+            }
+        }
+    }
+}
+impl App {
+    // init_gpu, and if that fails after a backend switch, init_gpu again on
+    // the backend it was switched from. With nothing to go back to, or if
+    // that fails too, there's nothing to draw with and praxis stops
+    fn restart_gpu(&mut self, event_loop: &ActiveEventLoop, previous: Option<wgpu::Backends>) {
+        let Err(e) = self.init_gpu() else {
+            return;
+        };
+        log::error!("couldn't start the renderer: {:#}", e);
+        if let Some(previous) = previous.filter(|p| Some(*p) != self.backends) {
+            self.console.print(format!(
+                "{:?} didn't start ({:#}), going back to {:?}",
+                self.backends.unwrap_or(wgpu::Backends::PRIMARY),
+                e,
+                previous
+            ));
+            self.backends = Some(previous);
+            match self.init_gpu() {
+                Ok(()) => return,
+                Err(e) => log::error!("couldn't go back to {:?} either: {:#}", previous, e),
+            }
+        }
+        event_loop.exit();
+    }
+
+    // The CPU side of the scene: the player's cube, the HUD's nodes and
+    // the controller. Only made the first time, a rebuild of the renderer
+    // leaves them as they are.
+    fn init_scene(&mut self) {
+        ////// controller
+        self.controller.velocity = 0.5; // = controller::Controller::new(0.5);
+
+        if self.clock_text.is_none() {
+            let text = |ui: &mut ui::Ui| {
+                ui.add(ui::Node {
+                    content: ui::Content::Text {
+                        text: String::new(),
+                        scale: 18.0,
+                        color: [0.9, 1.0, 1.0, 0.8],
+                    },
+                    padding: 0.0,
+                    background: None,
+                })
+            };
+            let clock_text = text(&mut self.ui);
+            let cubes_text = text(&mut self.ui);
+            let lines = self.ui.add(ui::Node {
+                content: ui::Content::Stack {
+                    axis: ui::Axis::Vertical,
+                    spacing: 2.0,
+                    children: vec![clock_text, cubes_text],
+                },
+                padding: 0.0,
+                background: None,
+            });
+            // a thin accent bar down the left of the panel
+            let bar = self.ui.add(ui::Node {
+                content: ui::Content::Panel { size: (3.0, 1.0) },
+                padding: 0.0,
+                background: Some([0.3, 0.8, 1.0, 0.8]),
+            });
+            let hud = self.ui.add(ui::Node {
+                content: ui::Content::Stack {
+                    axis: ui::Axis::Horizontal,
+                    spacing: 6.0,
+                    children: vec![bar, lines],
+                },
+                padding: 6.0,
+                background: Some([0.0, 0.0, 0.0, 0.35]),
+            });
+            self.ui.add_root(hud, ui::Anchor::BottomLeft, (10.0, 10.0));
+            self.clock_text = Some(clock_text);
+            self.cubes_text = Some(cubes_text);
+        }
+
+        if self.cube_position.is_none() {
+            self.cube_position = Some(math::Vec3::new(-1.0, -1.0, -1.0));
+        }
+
+        // instances are plain CPU data, so a rebuild after device loss keeps
+        // them. the player's is always first
+        if self.sim.cubes.is_empty() {
+            self.sim.cubes = vec![Instance {
+                position: self.cube_position.unwrap(),
+                rotation: math::Quat::IDENTITY,
+                flash: NO_FLASH,
+                skin: 0,
+                layer: physics::Layers::PLAYER,
+                // math::Quat::from_axis_angle(
+                //     math::Vec3::new(16.6, 50.0, -16.6).normalize(),
+                //     math::Degrees(45.0).to_radians(),
+                // ),
+            }];
+        }
+    }
+
+    // Creates the device and every GPU resource from CPU-side state. Called on
+    // startup and again whenever the device is lost, so it must not reset
+    // anything the player has done (camera, instances).
+    fn init_gpu(&mut self) -> anyhow::Result<()> {
+        let backends = self.backends.unwrap_or(wgpu::Backends::PRIMARY);
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends,
+            flags: wgpu::InstanceFlags::empty(),
+            ..Default::default()
+        });
+        // the old surface has to go before a new one can be made for the same
+        // window, and so does a half made one if this is starting over. what
+        // they kept waits in App in case this doesn't get as far as using it
+        if let Some(kept) = self
+            .gfx
+            .take()
+            .map(renderer::Renderer::keep)
+            .or_else(|| self.loading.take().and_then(|loading| loading.early.kept))
+        {
+            self.kept = Some(kept);
+        }
+        let window = self
+            .window
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("no window to draw in yet"))?;
+        let surface = instance.create_surface(window.clone())?;
+
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: Some(&surface),
+            force_fallback_adapter: false,
+        }))
+        .ok_or_else(|| anyhow::anyhow!("no {:?} adapter for the window", backends))?;
+        let adapter_info = adapter.get_info();
+        log::info!("using {} ({:?})", adapter_info.name, adapter_info.backend);
+
+        // per draw data goes in push constants where there are any, the
+        // objects they index are read from the vertex shader
+        self.push_constants = adapter.features().contains(wgpu::Features::PUSH_CONSTANTS)
+            && adapter
+                .get_downlevel_capabilities()
+                .flags
+                .contains(wgpu::DownlevelFlags::VERTEX_STORAGE);
+        log::info!(
+            "per draw data through {}",
+            if self.push_constants {
+                "push constants"
+            } else {
+                "dynamic uniform offsets"
+            }
+        );
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("device-descriptor"),
+                required_features: pipeline_cache::PipelineCache::features(&adapter)
+                    | if self.benchmark.is_some() {
+                        gpu_timer::GpuTimer::features(&adapter)
+                    } else {
+                        wgpu::Features::empty()
+                    }
+                    | if self.push_constants {
+                        wgpu::Features::PUSH_CONSTANTS
+                    } else {
+                        wgpu::Features::empty()
+                    },
+                required_limits: wgpu::Limits {
+                    max_push_constant_size: if self.push_constants {
+                        objects::PUSH_CONSTANT_SIZE
+                    } else {
+                        0
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            None,
+        ))?;
+
+        self.errors.install(&device);
+        let gpu_timer = self
+            .benchmark
+            .as_ref()
+            .and_then(|_| gpu_timer::GpuTimer::new(&device, &queue));
+        let pipelines_started = std::time::Instant::now();
+        let pipeline_cache = pipeline_cache::PipelineCache::load(&device, &adapter_info);
+
+        let device_lost = self.device_lost.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            // Dropped/ReplacedCallback fire when we replace the device ourselves
+            if matches!(
+                reason,
+                wgpu::DeviceLostReason::Unknown
+                    | wgpu::DeviceLostReason::Destroyed
+                    | wgpu::DeviceLostReason::DeviceInvalid
+            ) {
+                log::error!("device lost ({:?}): {}", reason, message);
+                device_lost.store(true, Ordering::SeqCst);
+            }
+        });
+
+        // GL surfaces usually don't offer Bgra8, so pick from what the adapter supports
+        let capabilities = surface.get_capabilities(&adapter);
+        let texture_format = capabilities
+            .formats
+            .iter()
+            .copied()
+            .find(|f| *f == wgpu::TextureFormat::Bgra8UnormSrgb)
+            .or_else(|| capabilities.formats.iter().copied().find(|f| f.is_srgb()))
+            .unwrap_or(capabilities.formats[0]);
+        let alpha_mode = if capabilities
+            .alpha_modes
+            .contains(&wgpu::CompositeAlphaMode::PostMultiplied)
+        {
+            wgpu::CompositeAlphaMode::PostMultiplied
+        } else {
+            capabilities.alpha_modes[0]
+        };
+
+        let size = window.inner_size();
+        self.camera.aspect = size.width as f32 / size.height.max(1) as f32;
+
+        let mut camera_uniform = CameraUniform::new();
+        camera_uniform.update_view_proj(&self.camera);
+
+        self.errors.push_scope(&device);
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::cast_slice(&[camera_uniform]),
+            // COPY_SRC for the buffer inspector
+            usage: wgpu::BufferUsages::UNIFORM
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+        });
+
+        let camera_bind_group_layout = reflect::bind_group_layout(
+            &device,
+            "camera_bind_group_layout",
+            camera::BIND_GROUP_USES,
+        )?;
+
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+            label: Some("camera_bind_group"),
+        });
+        self.errors.pop_scope(&device, "camera bind group");
+
+        self.options.set_available(&capabilities.present_modes);
+        let config = wgpu::SurfaceConfiguration {
+            // COPY_SRC lets save slots grab a thumbnail of the frame
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | (capabilities.usages & wgpu::TextureUsages::COPY_SRC),
+            // not really sure what the TextureFormat is
+            format: texture_format,
+            width: size.width,
+            height: size.height,
+            present_mode: self.options.present_mode,
+            desired_maximum_frame_latency: self.options.frame_latency,
+            alpha_mode,
+            // alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![texture_format],
+        };
+        surface.configure(&device, &config);
+        let depth_texture =
+            texture::Texture::create_depth_texture(&device, &config, "depth texture");
+
+        /////// brush stuff
+        let font = include_bytes!("../res/fonts/Fira_Code_v6.2/ttf/FiraCode-Light.ttf") as &[u8];
+        let brush = wgpu_text::BrushBuilder::using_font_bytes(font)
+            .unwrap()
+            .build(&device, config.width, config.height, texture_format);
+
+        self.text_section = Some(
+            TextSection::default()
+                .add_text(Text::new("Hello!  はじめまして!").with_color([0.9, 1.0, 1.0, 1.0]))
+                .with_bounds((config.width as f32, config.height as f32))
+                .with_layout(
+                    wgpu_text::glyph_brush::Layout::default()
+                        .v_align(wgpu_text::glyph_brush::VerticalAlign::Center),
+                )
+                // .with_screen_position((0.0, 0.0))
+                .to_owned(),
+        );
+        ////
+
+        /////// hud
+        self.ui.init_gpu(&device, texture_format);
+        self.texture_viewer.init_gpu(&device, texture_format);
+        self.gui.init_gpu(&window, &device, texture_format);
+        // the rest is slow, so it's made on a worker with the splash up
+        let settings = loading::Settings {
+            texture_format,
+            width: config.width,
+            height: config.height,
+            model_format: self.model_format,
+            noise: self.noise,
+            push_constants: self.push_constants,
+            lighting: self.lighting,
+            lut_path: self.lut_path.clone(),
+            tilemap_path: self.tilemap_path.clone(),
+        };
+        let early = loading::Early {
+            instance,
+            surface,
+            adapter_info,
+            config,
+            depth_texture,
+            brush,
+            camera_buffer,
+            camera_bind_group,
+            gpu_timer,
+            pipeline_cache,
+            kept: self.kept.take(),
+            started: pipelines_started,
+        };
+        self.loading = Some(loading::Loading::start(
+            device,
+            queue,
+            early,
+            settings,
+            self.errors.clone(),
+        ));
+        Ok(())
+    }
+
+    // another frame of the splash while the worker's still going, and the
+    // renderer once it's done
+    fn poll_loading(&mut self) {
+        match self.loading.take_if(|loading| loading.done()) {
+            Some(loading) => self.finish_gpu(loading),
+            None => {
+                if let Some(loading) = &mut self.loading {
+                    loading.show();
+                }
+            }
+        }
+        self.request_redraw();
+    }
+
+    // puts what the worker made together with what init_gpu did and what
+    // the old renderer kept
+    fn finish_gpu(&mut self, loading: loading::Loading) {
+        let (device, queue, early, made) = loading.finish();
+        let loading::Early {
+            instance,
+            surface,
+            adapter_info,
+            config,
+            depth_texture,
+            brush,
+            camera_buffer,
+            camera_bind_group,
+            gpu_timer,
+            pipeline_cache,
+            mut kept,
+            started,
+        } = early;
+        let loading::Made {
+            mut timer,
+            vertex_buffer,
+            index_buffer,
+            tweak_buffer,
+            lut_names,
+            environment,
+            outline,
+            post,
+            mut grade,
+            motion_blur,
+            mut retro,
+            mut crt,
+            floor,
+            staging,
+            objects,
+            cloth,
+            water,
+            mut portals,
+            mut sprites,
+            mut weather,
+            mut tilemap,
+            light,
+            background_render_pipeline,
+            background_texture_bind_group,
+            background_vertex_buffer,
+            background_index_buffer,
+            cube_pipeline,
+            cube_env_pipeline,
+            cube_model,
+            mesh_arena,
+            lod,
+            shatter,
+        } = made;
+
+        if let Some(old) = kept.as_ref().map(|k| &k.timer) {
+            timer.start = old.start;
+            timer.elapsed = old.elapsed;
+            timer.last = old.last;
+            timer.acc = old.acc;
+            timer.offset = old.offset;
+        }
+        self.tweaks.init_gpu(tweak_buffer);
+        self.options.set_luts(lut_names);
+        // as they are now, they could have changed while the worker was going
+        grade.set(&queue, self.options.lut, self.options.lut_strength);
+        retro.set(&queue, self.options.retro);
+        crt.set(
+            &queue,
+            self.options.crt_curvature,
+            self.options.crt_strength,
+        );
+        floor.set_mirror(&queue, if self.floor_mirror { 1.0 } else { 0.0 });
+        light.set(&queue, &self.lighting);
+        portals.enabled = kept.as_ref().is_some_and(|k| k.portals_enabled);
+        if let Some(old) = kept.as_mut().and_then(|k| k.sprites.take()) {
+            if let Some(sprites) = &mut sprites {
+                sprites.sprites = old.sprites;
+            }
+        }
+        if let Some(old) = kept.as_mut().and_then(|k| k.weather.take()) {
+            if let Some(weather) = &mut weather {
+                weather.set(&queue, old.kind);
+                weather.wetness = old.wetness;
+            }
+        }
+        if let Some(old) = kept.as_mut().and_then(|k| k.tilemap.take()) {
+            if let Some(tilemap) = &mut tilemap {
+                tilemap.order = old.order;
+                tilemap.position = old.position;
+                tilemap.zoom = old.zoom;
+            }
+        }
+        self.materials.reloaded();
+
+        let instance_data = self.instance_data();
+        let cube_instances = instance_buffer::InstanceBuffer::new(&device, &queue, &instance_data);
+        self.sim.dirty.clear();
+        let stream = if let Some(stream) = kept.as_mut().and_then(|k| k.stream.take()) {
+            Some(stream.restart(&device))
+        } else if let Some(name) = self.stream_name.take() {
+            let generator = stream::by_name(&name, self.rng.seed());
+            if generator.is_none() {
+                log::error!("ignoring --stream {}, try city", name);
+            }
+            generator.map(|generator| stream::InstanceStream::new(&device, generator))
+        } else {
+            None
+        };
+
+        // the simulation state was on the old device, so start the drop over
+        if self.cloth_enabled {
+            cloth.drop_over(&queue, self.sim.cubes[0].position);
+        }
+
+        self.gfx = Some(renderer::Renderer {
+            instance,
+            surface,
+            adapter_info,
+            device,
+            queue,
+            config,
+            depth_texture,
+            brush,
+            timer,
+            vertex_buffer,
+            index_buffer,
+            camera_buffer,
+            camera_bind_group,
+            background_render_pipeline,
+            background_texture_bind_group,
+            background_vertex_buffer,
+            background_index_buffer,
+            cube_pipeline,
+            cube_env_pipeline,
+            cube_instances,
+            cube_model,
+            mesh_arena,
+            staging,
+            objects,
+            environment,
+            outline,
+            post,
+            grade,
+            motion_blur,
+            retro,
+            crt,
+            floor,
+            water,
+            cloth,
+            portals,
+            lod,
+            shatter,
+            light,
+            sprites,
+            weather,
+            tilemap,
+            stream,
+            gpu_timer,
+            pipeline_cache,
+        });
+
+        // a rebuild starts from what's wanted, not what the watchdog left
+        self.apply_graphics();
+        // the worker made everything at the size the window was when it started
+        if let Some(window) = self.window.clone() {
+            self.resize(window.inner_size());
+        }
+        // the benchmark's scene has rain
+        if self.benchmark.is_some() {
+            if let Some(gfx) = &mut self.gfx {
+                if let Some(weather) = gfx.weather.as_mut() {
+                    weather.set(&gfx.queue, Some(weather::Kind::Rain));
+                }
+            }
+        }
+
+        log::info!("gpu resources and pipelines took {:?}", started.elapsed());
+        if let Some(cache) = self.gfx.as_ref().and_then(|g| g.pipeline_cache.as_ref()) {
+            cache.save();
+        }
+    }
+
+    fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>) {
+        self.ui.resize(size.width, size.height);
+        if let Some(loading) = &mut self.loading {
+            loading.resize(size.width, size.height);
+        }
+        let Some(gfx) = &mut self.gfx else {
+            return;
+        };
+        if gfx.resize(size.width, size.height, self.tweaks.buffer()) {
+            self.camera.aspect = size.width as f32 / size.height as f32;
+            self.write_camera();
+        }
+    }
+
+    fn request_redraw(&self) {
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+    }
+
+    fn window_size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.window
+            .as_ref()
+            .map_or(winit::dpi::PhysicalSize::new(WIDTH, HEIGHT), |w| {
+                w.inner_size()
+            })
+    }
+
+    // the surface's, which is the window's
+    fn screen_size(&self) -> (f32, f32) {
+        self.gfx
+            .as_ref()
+            .map_or((WIDTH as f32, HEIGHT as f32), |g| g.screen_size())
+    }
+
+    // the adapter's name and backend, for the benchmark and scaling reports
+    fn adapter(&self) -> (String, String) {
+        self.gfx.as_ref().map_or_else(Default::default, |g| {
+            let info = &g.adapter_info;
+            (info.name.clone(), format!("{:?}", info.backend))
+        })
+    }
+
+    // cycles Vulkan -> GL -> DX12 -> Metal, skipping backends with no adapter
+    fn switch_backend(&mut self, event_loop: &ActiveEventLoop) {
+        const ORDER: [wgpu::Backends; 4] = [
+            wgpu::Backends::VULKAN,
+            wgpu::Backends::GL,
+            wgpu::Backends::DX12,
+            wgpu::Backends::METAL,
+        ];
+        let Some(gfx) = &self.gfx else {
+            return;
+        };
+        let current = gfx.adapter_info.backend;
+        let start = ORDER
+            .iter()
+            .position(|b| *b == wgpu::Backends::from(current))
+            .unwrap_or(0);
+
+        let enabled = wgpu::Instance::enabled_backend_features();
+        let next = (1..ORDER.len())
+            .map(|i| ORDER[(start + i) % ORDER.len()])
+            .filter(|b| enabled.contains(*b))
+            .find(|b| {
+                let probe = wgpu::Instance::new(wgpu::InstanceDescriptor {
+                    backends: *b,
+                    ..Default::default()
+                });
+                !probe.enumerate_adapters(*b).is_empty()
+            });
+
+        match next {
+            Some(backends) => {
+                log::warn!("switching from {:?} to {:?}", current, backends);
+                let previous = wgpu::Backends::from(current);
+                self.backends = Some(backends);
+                self.restart_gpu(event_loop, Some(previous));
+            }
+            None => log::warn!("no other backend available besides {:?}", current),
+        }
+    }
+
+    fn update(&mut self) {
+        // Update the cube's position
+        let mut x = 0.0;
+        let y = 0.0;
+        let mut z = 0.0;
+        if self.controller.is_up_pressed {
             z += 1.0;
         }
-        if self.controller.is_down_pressed {
-            z -= 1.0;
+        if self.controller.is_down_pressed {
+            z -= 1.0;
+        }
+        if self.controller.is_left_pressed {
+            x -= 1.0;
+        }
+        if self.controller.is_right_pressed {
+            x += 1.0;
+        }
+        // flying, the keys are the camera's and the cube stands still
+        let flying = self.camera_mode == camera::CameraMode::FirstPerson;
+        let fly = math::Vec3::new(x, y, z).normalize_or_zero();
+        if flying {
+            (x, z) = (0.0, 0.0);
+        }
+        let gait = self.controller.gait();
+        let move_vector =
+            math::Vec3::new(x, y, z).normalize_or_zero() * self.controller.velocity * gait;
+        // the kick's only for going somewhere fast, not for holding shift
+        let sprinting = gait > 1.0 && (fly != math::Vec3::ZERO || move_vector != math::Vec3::ZERO);
+
+        let stepped = self.stepping.frame();
+        let step = stepped.or_else(|| self.video.as_ref().map(|v| v.step()));
+        let Some(timer) = self.gfx.as_mut().map(|g| &mut g.timer) else {
+            return;
+        };
+        let frame_time = timer.now(step) - timer.last;
+        self.sim.players[0].wanted = (!self.grid.enabled).then_some(move_vector);
+        // joined to a server, which does the stepping for everyone, unless
+        // it's a rollback session where everyone does it for themselves
+        let events = if let Some(client) = &mut self.client {
+            let _alloc = allocs::scope(allocs::Subsystem::Network);
+            let direction = math::Vec3::new(x, y, z).normalize_or_zero();
+            if self.rollback.is_none() {
+                client.send_input(direction);
+            }
+            let received = client.poll();
+            self.sim.players[0].moved = math::Vec3::ZERO;
+            for message in received {
+                self.apply_server(message);
+            }
+            if self.rollback.is_some() {
+                self.run_rollback(frame_time, direction);
+            } else {
+                self.interpolate();
+            }
+            Vec::new()
+        } else if stepped == Some(0.0) {
+            Vec::new()
+        } else {
+            let _alloc = allocs::scope(allocs::Subsystem::Simulation);
+            self.sim.step(frame_time, &mut self.rng)
+        };
+        for event in events {
+            match event {
+                simulation::Event::Landed(at) => self.play(sfx::Event::Impact, Some(at)),
+                simulation::Event::Triggered(i) => {
+                    log::info!("player went into trigger cube {}", i);
+                    self.flash_cube(i);
+                }
+            }
+        }
+        let moved = self.sim.players[0].moved;
+        self.session.distance += moved.length();
+        let speed = moved.with_y(0.0).length() / self.controller.velocity;
+        self.audio
+            .as_ref()
+            .unwrap()
+            .set_intensity(music::intensity(&self.sim.cubes, speed));
+        self.session.frame(self.sim.cubes.len());
+        let received = self.remote.as_mut().map(|r| r.poll()).unwrap_or_default();
+        for message in received {
+            self.apply_remote(message);
+        }
+        #[cfg(feature = "http")]
+        self.serve_http();
+        if let Some(position) = self.grid.update(frame_time as f32 * gait) {
+            let step = position - self.sim.cubes[0].position;
+            self.sim.cubes[0].position = position;
+            self.sim.dirty.mark(0..1);
+            self.session.distance += step.length();
+            let camera = &mut self.camera;
+            camera.eye += step;
+            camera.target += step;
+            self.write_camera();
+        }
+        let player = &self.sim.cubes[0];
+        if let Some((position, rotation)) = self
+            .gfx
+            .as_mut()
+            .unwrap()
+            .portals
+            .cross(player.position, player.rotation)
+        {
+            let jump = position - self.sim.cubes[0].position;
+            self.sim.cubes[0].position = position;
+            self.sim.cubes[0].rotation = rotation;
+            self.sim.dirty.mark(0..1);
+            if self.grid.enabled {
+                // the camera follows in grid mode, so it goes through too
+                self.grid.enable(position);
+                let camera = &mut self.camera;
+                camera.eye += jump;
+                camera.target += jump;
+                self.write_camera();
+            }
+        }
+        if self.chase.target.is_some_and(|i| i >= self.sim.cubes.len()) {
+            self.chase.target = None;
+        }
+        if let Some((yaw, pitch, zoom)) = self.orbit.take().filter(|_| !flying) {
+            let camera = &mut self.camera;
+            camera.orbit(yaw, pitch);
+            camera.zoom(zoom);
+            self.write_camera();
+        }
+        let look = self.mouse_look.take();
+        if flying && (look.is_some() || fly != math::Vec3::ZERO) {
+            let camera = &mut self.camera;
+            if let Some((yaw, pitch)) = look {
+                camera.look(yaw, pitch);
+            }
+            let distance = FLY_SPEED * gait * frame_time as f32;
+            camera.fly(fly.z * distance, fly.x * distance);
+            self.write_camera();
+        }
+        let sneaking = self.controller.is_sneak_pressed;
+        if let Some((wider, lower)) = self.gait.update(frame_time as f32, sprinting, sneaking) {
+            let camera = &mut self.camera;
+            camera.fovy.0 += wider;
+            camera.eye.y -= lower;
+            camera.target.y -= lower;
+            self.write_camera();
+        }
+        if let Some(benchmark) = &self.benchmark {
+            let (eye, target) = benchmark.camera(self.sim.cubes[0].position);
+            let camera = &mut self.camera;
+            camera.eye = eye;
+            camera.target = target;
+            self.write_camera();
+        } else if let Some(target) = self.chase.target {
+            let at = self.sim.cubes[target].position;
+            let world = physics::World::new(&self.sim.cubes).ignoring(target);
+            let (eye, target) = self.chase.update(frame_time as f32, at, world);
+            let camera = &mut self.camera;
+            camera.eye = eye;
+            camera.target = target;
+            self.write_camera();
+        }
+        if let Some(i) = self.scenario.filter(|i| !self.scenarios[*i].still()) {
+            self.scenarios[i].update(frame_time as f32, &mut self.sim.cubes);
+            self.sim.dirty.mark(1..self.sim.cubes.len());
+        }
+        let camera = &self.camera;
+        let right = (camera.target - camera.eye)
+            .cross(camera.up)
+            .normalize_or(math::Vec3::X);
+        {
+            let _alloc = allocs::scope(allocs::Subsystem::Audio);
+            self.audio.as_ref().unwrap().set_listener(camera.eye, right);
+            self.sfx.poll();
+        }
+
+        if let Some(name) = self.autosave.due() {
+            self.save_slot(name, None);
+        }
+
+        let scale = self.cube_scale();
+        let Some(gfx) = &mut self.gfx else {
+            return;
+        };
+        gfx.floor
+            .update(&gfx.queue, &self.noise, self.sim.cubes[0].position);
+
+        let now = gfx.timer.elapsed as f32;
+        if let Some(stream) = gfx.stream.as_mut() {
+            stream.update(self.camera.eye, now);
+            self.stats.instance_bytes += stream.upload(&gfx.device, &gfx.queue, &mut gfx.staging);
+        }
+
+        gfx.shatter.update(frame_time as f32, now);
+        self.stats.instance_bytes += gfx
+            .shatter
+            .upload(&gfx.device, &gfx.queue, &mut gfx.staging);
+
+        if let Some(visualizer) = self.visualizer.as_mut() {
+            if visualizer.update(frame_time as f32) {
+                for cube in &mut self.sim.cubes {
+                    cube.flash = now;
+                }
+            }
+            // the scale goes with the bass, so everything changes every frame
+            self.sim.dirty.mark(0..self.sim.cubes.len());
+        }
+
+        self.lights.begin_frame();
+        for cube in &self.sim.cubes {
+            let glow = 1.0 - (now - cube.flash) / GLOW_SECONDS;
+            if glow <= 0.0 {
+                continue;
+            }
+            let light = light::PointLight {
+                position: cube.position,
+                color: math::Vec3::new(1.0, 0.9, 0.7) * 4.0 * glow,
+                radius: GLOW_RADIUS,
+            };
+            if !self.lights.add_point_light(light) {
+                break;
+            }
+        }
+        gfx.light.set_points(&gfx.queue, &self.lights);
+
+        // only what changed goes to the GPU, one write per run of changed instances
+        for range in self.sim.dirty.take() {
+            let instance_data = self.sim.cubes[range.clone()]
+                .iter()
+                .map(|cube| cube.scaled_raw(scale))
+                .collect::<Vec<_>>();
+            let bytes: &[u8] = bytemuck::cast_slice(&instance_data);
+            gfx.staging.write(
+                &gfx.device,
+                &gfx.queue,
+                &gfx.cube_instances.buffer,
+                (range.start * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress,
+                bytes,
+            );
+            self.stats.instance_bytes += bytes.len();
+        }
+
+        let timer = &mut gfx.timer;
+        timer.elapsed = timer.now(step);
+        let frame_time = timer.elapsed - timer.last;
+        // recording runs as slow as it has to, that's not something to fix
+        let over_budget = self.video.is_none() && self.watchdog.frame(frame_time);
+        timer.acc += frame_time;
+        self.clock.update((frame_time * 1000.0) as f32);
+        self.stats.frame_ms = frame_time * 1000.0;
+        timer.last = timer.elapsed;
+        if let Some(capture) = self.capture.as_mut() {
+            capture.frame_finished(std::time::Duration::from_secs_f64(frame_time));
+        }
+        // framerate stuff goes here?
+        timer.timer_uniform.t = timer.elapsed as f32;
+        gfx.queue
+            .write_buffer(&timer.timer_buffer, 0, &timer.timer_uniform.t.to_le_bytes());
+        if over_budget {
+            self.downgrade_quality();
+        }
+    }
+
+    // Turns down the first thing that's still on, most expensive first. There's
+    // no render scale or shadows to give up, so it's the optional passes.
+    fn downgrade_quality(&mut self) {
+        let Some(gfx) = &mut self.gfx else {
+            return;
+        };
+        let weather_density = gfx.weather.as_ref().map_or(0.0, |w| w.density);
+        let downgrade = if gfx.environment.dynamic {
+            gfx.environment.dynamic = false;
+            watchdog::Downgrade::Reflections
+        } else if self.floor_mirror {
+            self.floor_mirror = false;
+            gfx.floor.set_mirror(&gfx.queue, 0.0);
+            watchdog::Downgrade::Mirror
+        } else if gfx.motion_blur.shutter_angle > 0.0 {
+            gfx.motion_blur.shutter_angle = 0.0;
+            watchdog::Downgrade::MotionBlur
+        } else if let Some(weather) = gfx
+            .weather
+            .as_mut()
+            .filter(|_| weather_density > watchdog::MIN_DENSITY)
+        {
+            weather.density = weather_density / 2.0;
+            watchdog::Downgrade::Particles
+        } else if !gfx.lod.enabled {
+            gfx.lod.enabled = true;
+            watchdog::Downgrade::Impostors
+        } else {
+            log::warn!("frames are still over budget with everything turned down");
+            self.watchdog.enabled = false;
+            return;
+        };
+        self.watchdog.took(downgrade);
+        let restore = self.bindings.key_for(bindings::Action::RestoreQuality);
+        self.toasts.push(format!(
+            "running slow: {} ({} puts it back)",
+            downgrade.describe(),
+            restore
+        ));
+    }
+
+    // Sets everything the presets cover to what the options say. This also
+    // puts back anything the watchdog turned down.
+    fn apply_graphics(&mut self) {
+        let graphics = self.options.graphics();
+        self.floor_mirror = graphics.mirror;
+        if let Some(gfx) = &mut self.gfx {
+            gfx.environment.dynamic = graphics.reflections;
+            gfx.floor
+                .set_mirror(&gfx.queue, if graphics.mirror { 1.0 } else { 0.0 });
+            gfx.motion_blur.shutter_angle = graphics.shutter_angle;
+            if let Some(weather) = gfx.weather.as_mut() {
+                weather.density = graphics.particles;
+            }
+            gfx.lod.enabled = graphics.impostors;
+        }
+        self.watchdog.taken.clear();
+        self.watchdog.reset();
+    }
+
+    // undoes everything the watchdog turned down, giving back how many
+    fn restore_quality(&mut self) -> usize {
+        let taken = self.watchdog.taken.len();
+        self.apply_graphics();
+        taken
+    }
+
+    // Picks the nearest cube under the cursor and toggles its selection;
+    // clicking empty space clears the selection.
+    fn select_cube(&mut self) {
+        let size = self.window_size();
+        let ndc_x = (self.cursor_position.x / size.width as f64 * 2.0 - 1.0) as f32;
+        let ndc_y = (1.0 - self.cursor_position.y / size.height as f64 * 2.0) as f32;
+        let inv_view_proj = self.camera.build_view_projection_matrix().inverse();
+        let unproject = |z: f32| inv_view_proj.project_point3(math::Vec3::new(ndc_x, ndc_y, z));
+        let origin = unproject(0.0);
+        let direction = (unproject(1.0) - origin).normalize();
+
+        let hit =
+            physics::World::new(&self.sim.cubes).raycast(origin, direction, physics::Layers::ALL);
+
+        if let Some(hit) = hit {
+            log::info!(
+                "picked cube {} at {:.2} on its {:.1} face",
+                hit.index,
+                hit.point,
+                hit.normal
+            );
+        }
+        match hit.map(|hit| hit.index as u32) {
+            Some(i) => match self.selected.iter().position(|s| *s == i) {
+                Some(existing) => {
+                    self.selected.remove(existing);
+                }
+                None => {
+                    self.selected.push(i);
+                    self.flash_cube(i as usize);
+                }
+            },
+            None => self.selected.clear(),
+        }
+        log::info!("selected cubes {:?}", self.selected);
+    }
+
+    fn apply_remote(&mut self, message: remote::Remote) {
+        match message {
+            remote::Remote::CameraEye(eye) => {
+                self.chase.target = None;
+                self.camera.eye = eye;
+                self.write_camera();
+            }
+            remote::Remote::CameraTarget(target) => {
+                self.chase.target = None;
+                self.camera.target = target;
+                self.write_camera();
+            }
+            remote::Remote::Spawn(None) => self.add_cube(),
+            remote::Remote::Spawn(Some(formation)) => {
+                self.run_command(&format!("spawn {}", formation))
+            }
+            // not the player's
+            remote::Remote::Shatter(i) if i > 0 && i < self.sim.cubes.len() => {
+                self.selected.clear();
+                self.shatter_cube(i);
+            }
+            remote::Remote::Flash(i) if i < self.sim.cubes.len() => self.flash_cube(i),
+            remote::Remote::Shatter(i) | remote::Remote::Flash(i) => {
+                log::warn!("remote control: there's no cube {} to do that to", i)
+            }
+            remote::Remote::Weather(kind) => self.run_command(&format!("weather {}", kind)),
+        }
+    }
+
+    #[cfg(feature = "http")]
+    fn serve_http(&mut self) {
+        let requests = self.http.as_ref().map(|h| h.poll()).unwrap_or_default();
+        for request in requests {
+            let response = match request.route {
+                http::Route::Stats => http::Response::json(
+                    self.stats.json(
+                        self.sim.cubes.len(),
+                        self.gfx
+                            .as_ref()
+                            .and_then(|g| g.stream.as_ref())
+                            .map_or(0, |s| s.len()),
+                    ),
+                ),
+                // answered once the next frame's been read back
+                http::Route::Screenshot => {
+                    self.http_screenshots.push(request.reply);
+                    continue;
+                }
+                http::Route::Command(line) => {
+                    self.console.start_capture();
+                    self.console.print(format!("> {}", line));
+                    self.run_command(&line);
+                    let mut printed = self.console.end_capture().join("\n");
+                    printed.push('\n');
+                    http::Response::text(200, printed)
+                }
+            };
+            let _ = request.reply.send(response);
+        }
+    }
+
+    // whether a frame has to be read back for anyone over HTTP
+    fn http_wants_frame(&self) -> bool {
+        #[cfg(feature = "http")]
+        return !self.http_screenshots.is_empty();
+        #[cfg(not(feature = "http"))]
+        return false;
+    }
+
+    fn write_camera(&mut self) {
+        let Some(gfx) = &self.gfx else {
+            return;
+        };
+        let mut camera_uniform = CameraUniform::new();
+        camera_uniform.update_view_proj(&self.camera);
+        gfx.queue.write_buffer(
+            &gfx.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[camera_uniform]),
+        );
+    }
+
+    // Between following the cube and flying the camera. Flying holds the
+    // cursor in the window and hides it, going back puts the camera where
+    // it was.
+    fn toggle_first_person(&mut self) {
+        let Some(window) = &self.window else {
+            return;
+        };
+        let camera = &mut self.camera;
+        self.camera_mode = match self.camera_mode {
+            camera::CameraMode::Follow => {
+                self.chase.target = None;
+                self.followed_from = Some((camera.eye, camera.target));
+                // not every platform can lock the cursor, confining it does too
+                if let Err(e) = window
+                    .set_cursor_grab(CursorGrabMode::Locked)
+                    .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))
+                {
+                    log::warn!("can't hold the cursor: {}", e);
+                }
+                window.set_cursor_visible(false);
+                camera::CameraMode::FirstPerson
+            }
+            camera::CameraMode::FirstPerson => {
+                if let Some((eye, target)) = self.followed_from.take() {
+                    camera.eye = eye;
+                    camera.target = target;
+                }
+                let _ = window.set_cursor_grab(CursorGrabMode::None);
+                window.set_cursor_visible(true);
+                camera::CameraMode::Follow
+            }
+        };
+        self.write_camera();
+        self.toasts.push(match self.camera_mode {
+            camera::CameraMode::Follow => "following the cube",
+            camera::CameraMode::FirstPerson => "flying, WASD and the mouse",
+        });
+    }
+
+    // seconds since startup, what flashes and the shaders go by
+    fn now(&self) -> f32 {
+        self.gfx.as_ref().map_or(0.0, |g| g.timer.elapsed as f32)
+    }
+
+    // short emissive pulse as feedback, the shader does the fading
+    fn flash_cube(&mut self, i: usize) {
+        self.sim.cubes[i].flash = self.now();
+        self.sim.dirty.mark(i..i + 1);
+    }
+
+    fn run_command(&mut self, line: &str) {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words[..] {
+            // the ones that work on what's on the GPU, before there is any
+            [
+                "buffer" | "impostors" | "portals" | "sprite" | "sprites" | "stream"
+                | "texture" | "textures" | "tilemap" | "weather",
+                ..,
+            ] if self.gfx.is_none() => self.console.print("the renderer isn't up yet"),
+            ["clock", "reset"] => {
+                self.clock = clock::ClockBuffer::new();
+                let sprites = self.gfx.iter_mut().filter_map(|g| g.sprites.as_mut());
+                for sprite in sprites.flat_map(|s| s.sprites.iter_mut()) {
+                    sprite.started = 0.0;
+                }
+                self.console.print("clock reset");
+            }
+            ["spawn"] => self.console.print(
+                "spawn grid <columns> <rows> [spacing], ring|sphere <count> [radius], spiral <count> [spacing]; any by name too, skin=<name|rand>, numbers can be sums like 4*2+rand",
+            ),
+            ["spawn", kind, ref rest @ ..] => {
+                let mut args = expr::Args::parse(rest);
+                // `skin=<name>` or `skin=rand` for just these
+                let skin = match args.take("skin") {
+                    Some("rand" | "random") => Ok(None),
+                    Some(name) => match cube::SKINS.iter().position(|(n, _)| *n == name) {
+                        Some(i) => Ok(Some(i as u32)),
+                        None => Err(format!("no skin called {}", name)),
+                    },
+                    None => Ok(self.spawn_skin),
+                };
+                match (
+                    skin,
+                    formation::Formation::from_args(kind, args, &mut self.rng),
+                ) {
+                    (Ok(skin), Ok(formation)) => {
+                        let before = std::mem::replace(&mut self.spawn_skin, skin);
+                        self.spawn_formation(formation);
+                        self.spawn_skin = before;
+                    }
+                    (Err(e), _) => self.console.print(e),
+                    (_, Err(e)) => self.console.print(format!("{}", e)),
+                }
+            }
+            ["boids"] => {
+                self.sim.boids.enabled = !self.sim.boids.enabled;
+                self.console.print(format!(
+                    "boids {}",
+                    if self.sim.boids.enabled { "on" } else { "off" }
+                ));
+            }
+            ["grid"] => {
+                if self.grid.enabled {
+                    self.grid.enabled = false;
+                } else {
+                    self.grid.enable(self.sim.cubes[0].position);
+                }
+                self.console.print(format!(
+                    "grid movement {}",
+                    if self.grid.enabled { "on" } else { "off" }
+                ));
+            }
+            ["music"] => {
+                self.music_off = !self.music_off;
+                self.audio.as_ref().unwrap().set_music(!self.music_off);
+                self.console.print(format!(
+                    "music {}",
+                    if self.music_off { "off" } else { "on" }
+                ));
+            }
+            ["visualizer"] if self.visualizer.is_some() => {
+                self.visualizer = None;
+                self.sim.dirty.mark(0..self.sim.cubes.len());
+                self.console.print("visualizer off");
+            }
+            ["visualizer"] => match visualizer::Visualizer::start() {
+                Ok(visualizer) => {
+                    self.visualizer = Some(visualizer);
+                    self.console.print("visualizer on, listening to the default input");
+                }
+                Err(e) => self.console.print(format!("no visualizer: {:#}", e)),
+            },
+            ["mirror"] => {
+                self.options.overrides.mirror = Some(!self.floor_mirror);
+                self.apply_graphics();
+                self.console.print(format!(
+                    "mirror floor {}",
+                    if self.floor_mirror { "on" } else { "off" }
+                ));
+            }
+            ["skin"] => {
+                let names = cube::SKINS.map(|(name, _)| name).join(", ");
+                self.console
+                    .print(format!("skins: {}, random", names));
+            }
+            ["skin", name] => {
+                if name == "random" {
+                    self.spawn_skin = None;
+                    self.console.print("new cubes get a random skin");
+                } else if let Some(i) = cube::SKINS.iter().position(|(n, _)| *n == name) {
+                    self.spawn_skin = Some(i as u32);
+                    self.console.print(format!("new cubes wear {}", name));
+                } else {
+                    self.console.print(format!("no skin called {}", name));
+                }
+            }
+            ["layer"] => self.console.print(format!(
+                "layers: {}",
+                physics::Layers::NAMED.map(|(name, _)| name).join(", ")
+            )),
+            ["layer", name] => match physics::Layers::parse(name) {
+                Some(_) if self.selected.is_empty() => self.console.print("pick some cubes first"),
+                Some(layer) => {
+                    for &i in &self.selected {
+                        self.sim.cubes[i as usize].layer = layer;
+                    }
+                    self.console.print(format!(
+                        "{} cubes on the {} layer",
+                        self.selected.len(),
+                        name
+                    ));
+                }
+                None => self.console.print(format!("no layer called {}", name)),
+            },
+            [key @ ("collide" | "ignore"), a, b] => {
+                match (physics::Layers::parse(a), physics::Layers::parse(b)) {
+                    (Some(a), Some(b)) => self.sim.filter.set(a, b, key == "collide"),
+                    _ => self.console.print(format!("{} <layer> <layer>, try layer", key)),
+                }
+            }
+            ["joint", "pin"] => {
+                for &i in &self.selected {
+                    let pinned = self.sim.joints.toggle_pin(i as usize);
+                    self.console.print(format!(
+                        "cube {} {}",
+                        i,
+                        if pinned { "pinned" } else { "let go" }
+                    ));
+                }
+            }
+            ["joint", kind] => match (joints::Kind::parse(kind), &self.selected[..]) {
+                (None, _) => self.console.print(format!("no joint called {}", kind)),
+                (Some(kind), [.., a, b]) if *a != 0 && *b != 0 => {
+                    self.sim.joints
+                        .connect(kind, *a as usize, *b as usize, &self.sim.cubes);
+                    self.console
+                        .print(format!("{} joint from cube {} to {}", kind.name(), a, b));
+                }
+                _ => self
+                    .console
+                    .print("pick two cubes to join first, not the player's"),
+            },
+            ["joints"] => {
+                let joints = self
+                    .sim
+                    .joints
+                    .joints
+                    .iter()
+                    .map(|j| format!("{} {}-{}", j.kind.name(), j.a, j.b))
+                    .collect::<Vec<_>>();
+                self.console.print(if joints.is_empty() {
+                    "no joints, pick two cubes and joint <fixed|hinge|spring>".to_string()
+                } else {
+                    format!("joints: {}", joints.join(", "))
+                });
+            }
+            ["joints", "clear"] => {
+                self.sim.joints.clear();
+                self.console.print("joints cleared");
+            }
+            ["impostors"] => {
+                let Some(gfx) = &self.gfx else {
+                    return;
+                };
+                let enabled = !gfx.lod.enabled;
+                self.options.overrides.impostors = Some(enabled);
+                self.apply_graphics();
+                self.console.print(format!(
+                    "impostors {}",
+                    if enabled { "on" } else { "off" }
+                ));
+            }
+            ["quality"] => {
+                let taken = self
+                    .watchdog
+                    .taken
+                    .iter()
+                    .map(|d| d.describe())
+                    .collect::<Vec<_>>();
+                self.console.print(format!(
+                    "{:?} quality, set by hand: {}, watchdog {}, budget {:.1} ms, turned down: {}",
+                    self.options.quality,
+                    match self.options.overrides.names().as_slice() {
+                        [] => "nothing".to_string(),
+                        names => names.join(", "),
+                    },
+                    if self.watchdog.enabled { "on" } else { "off" },
+                    self.watchdog.budget * 1000.0,
+                    if taken.is_empty() {
+                        "nothing".to_string()
+                    } else {
+                        taken.join(", ")
+                    }
+                ));
+            }
+            ["quality", "restore"] => {
+                let n = self.restore_quality();
+                self.console.print(format!("put back {} quality settings", n));
+            }
+            ["quality", "reset"] => {
+                self.options.overrides = Default::default();
+                self.apply_graphics();
+                self.console
+                    .print(format!("back to {:?} quality", self.options.quality));
+            }
+            ["quality", "set", name, value] => match self.options.overrides.set(name, value) {
+                Ok(()) => {
+                    self.apply_graphics();
+                    self.console.print(format!("{} set to {}", name, value));
+                }
+                Err(e) => self.console.print(e),
+            },
+            ["quality", "auto"] => {
+                self.watchdog.enabled = !self.watchdog.enabled;
+                self.watchdog.reset();
+                self.console.print(format!(
+                    "watchdog {}",
+                    if self.watchdog.enabled { "on" } else { "off" }
+                ));
+            }
+            ["quality", "budget", ms] => match ms.parse::<f64>() {
+                Ok(ms) if ms > 0.0 => {
+                    self.watchdog.budget = ms / 1000.0;
+                    self.watchdog.reset();
+                    self.console.print(format!("frame budget {} ms", ms));
+                }
+                _ => self.console.print(format!("{} isn't milliseconds", ms)),
+            },
+            ["quality", name] => match quality::Preset::parse(name) {
+                Some(preset) => {
+                    self.options.quality = preset;
+                    self.apply_graphics();
+                    self.console.print(format!("{:?} quality", preset));
+                }
+                None => self.console.print(format!("no preset called {}", name)),
+            },
+            ["stream"] => self.console.print(match self.gfx.as_ref().and_then(|g| g.stream.as_ref()) {
+                Some(stream) => format!(
+                    "streaming {}: {} cells, {} cubes",
+                    stream.name(),
+                    stream.cells(),
+                    stream.len()
+                ),
+                None => "not streaming, try stream city".to_string(),
+            }),
+            ["stream", "off"] => {
+                if let Some(gfx) = &mut self.gfx {
+                    gfx.stream = None;
+                }
+                self.console.print("stream off");
+            }
+            ["stream", name] => match stream::by_name(name, self.rng.seed()) {
+                Some(generator) => {
+                    let Some(gfx) = &mut self.gfx else {
+                        return;
+                    };
+                    gfx.stream = Some(stream::InstanceStream::new(&gfx.device, generator));
+                    self.console.print(format!("streaming {}", name));
+                }
+                None => self.console.print(format!("no generator called {}", name)),
+            },
+            ["profile"] => self.console.print(match &self.profile {
+                Some(profile) => format!("profile {} in {}", profile.name, profile.path.display()),
+                None => "no profile".to_string(),
+            }),
+            ["record"] => match self.video.take() {
+                Some(video) => {
+                    let message = format!("{} frames in {}", video.frames, video.path.display());
+                    drop(video);
+                    self.console.print(message);
+                }
+                None => self.console.print("not recording, record <file.mp4|file.y4m> starts"),
+            },
+            ["record", path] => match video::VideoRecorder::start(path, 60) {
+                Ok(video) => {
+                    self.video = Some(video);
+                    self.console.print(format!("recording {} at 60 fps", path));
+                }
+                Err(e) => self.console.print(format!("{:#}", e)),
+            },
+            ["chase"] if self.chase.target.is_some() => {
+                self.chase.target = None;
+                self.console.print("chase off");
+            }
+            ["chase"] => {
+                // the last cube picked, or the player's
+                let target = self.selected.last().map_or(0, |i| *i as usize);
+                let camera = &self.camera;
+                self.chase
+                    .lock(target, self.sim.cubes[target].position, camera.eye);
+                self.console.print(format!("chasing cube {}", target));
+            }
+            ["portals"] => {
+                let Some(gfx) = &mut self.gfx else {
+                    return;
+                };
+                let portals = &mut gfx.portals;
+                portals.enabled = !portals.enabled;
+                self.console.print(format!(
+                    "portals {}",
+                    if portals.enabled { "on" } else { "off" }
+                ));
+            }
+            ["sprite" | "sprites", ..] if self.gfx.as_ref().is_some_and(|g| g.sprites.is_none()) => {
+                self.console.print("no sprites loaded")
+            }
+            ["sprite", animation] => {
+                // somewhere above the first cube
+                let offset = math::Vec3::new(self.rng.f32() - 0.5, 0.0, self.rng.f32() - 0.5) * 8.0;
+                let position = self.sim.cubes[0].position + offset + math::Vec3::Y * 3.0;
+                let now = self.clock.seconds();
+                let Some(sprites) = self.gfx.as_mut().and_then(|g| g.sprites.as_mut()) else {
+                    return;
+                };
+                if let Err(e) = sprites.spawn(animation, position, now)
+                {
+                    self.console.print(format!("{}", e));
+                }
+            }
+            ["sprites"] => {
+                let Some(sprites) = self.gfx.as_ref().and_then(|g| g.sprites.as_ref()) else {
+                    return;
+                };
+                let names = sprites.set.animations.keys();
+                let names = names.map(String::as_str).collect::<Vec<_>>().join(", ");
+                self.console.print(format!("animations: {}", names));
+            }
+            ["sprites", "clear"] => {
+                if let Some(sprites) = self.gfx.as_mut().and_then(|g| g.sprites.as_mut()) {
+                    sprites.sprites.clear();
+                }
+            }
+            ["weather", ..] if self.gfx.as_ref().is_some_and(|g| g.weather.is_none()) => {
+                self.console.print("no weather loaded")
+            }
+            ["weather", kind @ ("rain" | "snow" | "off")] => {
+                let kind = match kind {
+                    "rain" => Some(weather::Kind::Rain),
+                    "snow" => Some(weather::Kind::Snow),
+                    _ => None,
+                };
+                let Some(gfx) = &mut self.gfx else {
+                    return;
+                };
+                let Some(weather) = gfx.weather.as_mut() else {
+                    return;
+                };
+                weather.set(&gfx.queue, kind);
+                let (x, _, z) = weather.config.wind;
+                self.console.print(match kind {
+                    Some(kind) => format!("{:?}, wind {} {}", kind, x, z).to_lowercase(),
+                    None => "weather off".to_string(),
+                });
+            }
+            ["tilemap", ..] if self.gfx.as_ref().is_some_and(|g| g.tilemap.is_none()) => {
+                self.console.print("no tilemap loaded")
+            }
+            ["tilemap", order @ ("behind" | "above" | "off")] => {
+                let Some(tilemap) = self.gfx.as_mut().and_then(|g| g.tilemap.as_mut()) else {
+                    return;
+                };
+                tilemap.order = match order {
+                    "behind" => Some(tilemap::Order::Behind),
+                    "above" => Some(tilemap::Order::Above),
+                    _ => None,
+                };
+                self.console.print(format!("tilemap {}", order));
+            }
+            ["tilemap", "zoom", zoom] => match zoom.parse::<f32>() {
+                Ok(zoom) if zoom > 0.0 => {
+                    if let Some(tilemap) = self.gfx.as_mut().and_then(|g| g.tilemap.as_mut()) {
+                        tilemap.zoom = zoom;
+                    }
+                }
+                _ => self.console.print(format!("{} isn't a zoom", zoom)),
+            },
+            ["tilemap", "pan", x, y] => match (x.parse(), y.parse()) {
+                (Ok(x), Ok(y)) => {
+                    if let Some(tilemap) = self.gfx.as_mut().and_then(|g| g.tilemap.as_mut()) {
+                        tilemap.position = math::Vec2::new(x, y);
+                    }
+                }
+                _ => self.console.print("tilemap pan takes map pixels"),
+            },
+            ["tweak"] => self.console.print(self.tweaks.list()),
+            ["tweak", "reset"] => self.tweaks.reset(),
+            ["tweak", name, ref values @ ..] => {
+                match values.iter().map(|v| v.parse::<f32>()).collect::<Result<Vec<_>, _>>() {
+                    Ok(values) => match self.tweaks.set(name, &values) {
+                        Ok(message) | Err(message) => self.console.print(message),
+                    },
+                    Err(_) => self.console.print("tweak <name> <value> or <r> <g> <b>"),
+                }
+            }
+            ["snapshot"] | ["snapshot", _] => {
+                let name = self.snapshots.take(words.get(1).copied(), &self.sim);
+                self.console
+                    .print(format!("snapshot {} at tick {}", name, self.sim.tick));
+            }
+            ["snapshots"] => self.console.print(self.snapshots.list()),
+            ["snapshots", "clear"] => {
+                self.snapshots.clear();
+                self.console.print("snapshots cleared");
+            }
+            // the second left out is now
+            ["diff", a, ref rest @ ..] if rest.len() <= 1 => {
+                let Some(before) = self.snapshots.get(a) else {
+                    self.console.print(format!("no snapshot called {}", a));
+                    return;
+                };
+                let lines = match rest {
+                    [b] => match self.snapshots.get(b) {
+                        Some(after) => snapshot::diff(before, &after.name, &after.state),
+                        None => vec![format!("no snapshot called {}", b)],
+                    },
+                    _ => snapshot::diff(before, "now", &self.sim),
+                };
+                for line in lines {
+                    self.console.print(line);
+                }
+            }
+            ["diff", ..] => self
+                .console
+                .print("diff <snapshot> [snapshot], the second left out is now"),
+            ["interp"] => self.console.print(format!(
+                "other cubes are drawn {} ms behind the server",
+                self.interpolation.delay * 1000.0
+            )),
+            ["interp", ms] => match ms.parse::<f64>() {
+                Ok(ms) if ms >= 0.0 => {
+                    self.interpolation.delay = ms / 1000.0;
+                    self.console
+                        .print(format!("drawing other cubes {} ms behind the server", ms));
+                }
+                _ => self.console.print(format!("{} isn't milliseconds", ms)),
+            },
+            ["textures"] => {
+                self.texture_viewer.open = !self.texture_viewer.open;
+                if let Some(gfx) = &self.gfx {
+                    self.console.print(self.texture_viewer.list(&gfx.textures()));
+                }
+            }
+            ["texture", "channel", channel] => match texture_viewer::Channel::parse(channel) {
+                Some(channel) => self.texture_viewer.channel = channel,
+                None => self.console.print("texture channel <rgba|r|g|b|a>"),
+            },
+            ["texture", "mip", mip] => match mip.parse() {
+                Ok(mip) => self.texture_viewer.mip = mip,
+                Err(_) => self.console.print(format!("{} isn't a mip level", mip)),
+            },
+            ["texture", "layer", layer] => match layer.parse() {
+                Ok(layer) => self.texture_viewer.layer = layer,
+                Err(_) => self.console.print(format!("{} isn't a layer", layer)),
+            },
+            ["texture", which] => {
+                let Some(gfx) = &self.gfx else {
+                    return;
+                };
+                let message = self.texture_viewer.select(which, &gfx.textures());
+                self.texture_viewer.open = true;
+                self.console.print(message);
+            }
+            ["buffer"] => {
+                if let Some(gfx) = &self.gfx {
+                    let list = buffer_inspector::BufferInspector::list(&gfx.buffers(self.sim.cubes.len()));
+                    self.console.print(list);
+                }
+            }
+            ["buffer", "off"] => self.buffer_inspector.close(),
+            ["buffer", which] | ["buffer", which, _] => {
+                let Some(gfx) = &self.gfx else {
+                    return;
+                };
+                let first = match words.get(2).map(|n| n.parse::<u64>()) {
+                    Some(Ok(first)) => first,
+                    Some(Err(_)) => {
+                        self.console.print("buffer <n|name> [first element]");
+                        return;
+                    }
+                    None => 0,
+                };
+                let message = self.buffer_inspector.select(which, first, &gfx.buffers(self.sim.cubes.len()));
+                self.console.print(message);
+            }
+            ["sprint" | "sneak"] => self.console.print(format!(
+                "sprint {}x, sneak {}x",
+                self.controller.sprint, self.controller.sneak
+            )),
+            [which @ ("sprint" | "sneak"), multiplier] => {
+                match multiplier.parse::<f32>() {
+                    Ok(m) if m > 0.0 => {
+                        if which == "sprint" {
+                            self.controller.sprint = m;
+                        } else {
+                            self.controller.sneak = m;
+                        }
+                        self.console.print(format!("{} {}x", which, m));
+                    }
+                    _ => self.console.print(format!("{} isn't a speed multiplier", multiplier)),
+                }
+            }
+            ["light"] => self.console.print(self.lighting.describe()),
+            ["light", "reset"] => {
+                self.set_lighting(light::Lighting::default());
+                self.console.print(self.lighting.describe());
+            }
+            ["light", which @ ("direction" | "color"), x, y, z] => {
+                let Ok(value) = [x, y, z].map(str::parse::<f32>).into_iter().collect::<Result<Vec<_>, _>>() else {
+                    self.console.print(format!("light {} <x> <y> <z>", which));
+                    return;
+                };
+                let value = math::Vec3::from_slice(&value);
+                let mut lighting = self.lighting;
+                if which == "direction" {
+                    lighting.direction = value;
+                } else {
+                    lighting.color = value.max(math::Vec3::ZERO);
+                }
+                self.set_lighting(lighting);
+                self.console.print(self.lighting.describe());
+            }
+            ["light", "ambient", ambient] => match ambient.parse::<f32>() {
+                Ok(ambient) if ambient >= 0.0 => {
+                    self.set_lighting(light::Lighting {
+                        ambient,
+                        ..self.lighting
+                    });
+                    self.console.print(self.lighting.describe());
+                }
+                _ => self.console.print(format!("{} isn't an amount of ambient", ambient)),
+            },
+            ["light", "point", "clear"] => {
+                self.lights.clear_placed();
+                self.console.print("point lights cleared");
+            }
+            ["light", "point", ref rest @ ..] if rest.len() <= 1 => {
+                let Some(radius) = rest.first().map_or(Some(20.0), |r| {
+                    r.parse::<f32>().ok().filter(|r| *r > 0.0)
+                }) else {
+                    self.console.print("light point [radius]");
+                    return;
+                };
+                // a little over the player, so the ground around it's lit too
+                let light = light::PointLight {
+                    position: self.sim.cubes[0].position + math::Vec3::Y * 3.0,
+                    color: math::Vec3::new(1.0, 0.85, 0.6) * 6.0,
+                    radius,
+                };
+                if self.lights.place(light) {
+                    self.console.print(format!(
+                        "{} point lights placed",
+                        self.lights.placed().len()
+                    ));
+                } else {
+                    self.console.print(format!(
+                        "no room, there can be {}",
+                        light::MAX_POINT_LIGHTS
+                    ));
+                }
+            }
+            ["help"] => self.console.print(
+                "commands: bind <action> <key>, boids, buffer [n|name [first]|off], clock reset, chase, collide|ignore <layer> <layer>, crt <curvature> <strength>, diff <snapshot> [snapshot], grid, impostors, interp [ms], joint <fixed|hinge|spring|pin>, joints [clear], layer [name], light [direction x y z|color r g b|ambient a|point [radius]|point clear|reset], mirror, music, portals, profile, quality [low|medium|high|ultra|set name value|reset|restore|auto|budget ms], record [file], skin [name|random], snapshot [name], snapshots [clear], sneak [multiplier], spawn <grid|ring|spiral|sphere> ... [name=value] [skin=name|rand], \
+                 sprint [multiplier], sprite <animation>, sprites [clear], stream [city|off], \
+                 texture <n|name|channel rgba|r|g|b|a|mip n|layer n>, textures, \
+                 tilemap <behind|above|off|zoom z|pan x y>, tweak [name value|reset], visualizer, weather <rain|snow|off>, help",
+            ),
+            ["crt", curvature, strength] => {
+                match (curvature.parse::<f32>(), strength.parse::<f32>()) {
+                    (Ok(curvature), Ok(strength)) => {
+                        self.options.crt_curvature = curvature.clamp(0.0, 1.0);
+                        self.options.crt_strength = strength.clamp(0.0, 1.0);
+                        if let Some(gfx) = &mut self.gfx {
+                            gfx.crt.set(
+                                &gfx.queue,
+                                self.options.crt_curvature,
+                                self.options.crt_strength,
+                            );
+                        }
+                    }
+                    _ => self.console.print("crt <curvature> <strength>, both 0 to 1"),
+                }
+            }
+            ["bind", action, key] => match self.bindings.bind(action, key) {
+                Ok(message) | Err(message) => self.console.print(message),
+            },
+            ["bind"] => self.console.print(format!(
+                "actions: {}",
+                bindings::action_names().collect::<Vec<_>>().join(", ")
+            )),
+            _ => self.console.print(format!("unknown command: {}", line)),
+        }
+    }
+
+    // one of the numbered items in the F7 menu
+    fn option_key(&mut self, item: u32) {
+        match item {
+            1 | 2 => {
+                if item == 1 {
+                    self.options.cycle_present_mode();
+                } else {
+                    self.options.cycle_frame_latency();
+                }
+                if let Some(gfx) = &mut self.gfx {
+                    self.options.apply(&mut gfx.config);
+                    gfx.surface.configure(&gfx.device, &gfx.config);
+                }
+                log::info!(
+                    "presenting with {:?}, frame latency {}",
+                    self.options.present_mode,
+                    self.options.frame_latency
+                );
+            }
+            3 | 4 => {
+                if item == 3 {
+                    self.options.cycle_lut();
+                } else {
+                    self.options.cycle_lut_strength();
+                }
+                if let Some(gfx) = &mut self.gfx {
+                    gfx.grade
+                        .set(&gfx.queue, self.options.lut, self.options.lut_strength);
+                }
+            }
+            0 => {
+                self.options.quality = self.options.quality.next();
+                self.apply_graphics();
+            }
+            5 => {
+                self.options.cycle_shutter_angle();
+                self.apply_graphics();
+            }
+            9 => {
+                self.options.cycle_crt();
+                if let Some(gfx) = &mut self.gfx {
+                    gfx.crt.set(
+                        &gfx.queue,
+                        self.options.crt_curvature,
+                        self.options.crt_strength,
+                    );
+                }
+            }
+            _ => {
+                match item {
+                    6 => self.options.retro.cycle_palette(),
+                    7 => self.options.retro.cycle_pixel_size(),
+                    _ => self.options.retro.dither = !self.options.retro.dither,
+                }
+                if let Some(gfx) = &mut self.gfx {
+                    gfx.retro.set(&gfx.queue, self.options.retro);
+                }
+            }
         }
-        if self.controller.is_left_pressed {
-            x -= 1.0;
+    }
+
+    fn run_action(&mut self, action: bindings::Action, event_loop: &ActiveEventLoop) {
+        use bindings::Action;
+        match action {
+            Action::Quit => {
+                println!("The close button was pressed; stopping");
+                self.session.finish();
+                // finishes the file
+                self.video = None;
+                if let Some(profile) = &self.profile {
+                    if let Err(e) = profile.save(&self.options, &self.bindings) {
+                        log::error!("couldn't save profile {}: {:#}", profile.name, e);
+                    }
+                }
+                saves::unlock();
+                event_loop.exit();
+            }
+            Action::SpawnCube => self.add_cube(),
+            Action::Shatter => {
+                let mut picked = std::mem::take(&mut self.selected);
+                // highest first, so removing one doesn't move the rest
+                picked.sort_unstable_by(|a, b| b.cmp(a));
+                for i in picked.into_iter().filter(|i| *i != 0) {
+                    self.shatter_cube(i as usize);
+                }
+            }
+            Action::Stats => self.stats.visible = !self.stats.visible,
+            Action::MainMenu => self.main_menu = !self.main_menu,
+            Action::KeyHelp => self.bindings.help_open = !self.bindings.help_open,
+            Action::Options => self.options.open = !self.options.open,
+            Action::Tweaks => self.tweaks.open = !self.tweaks.open,
+            Action::Materials => self.materials.open = !self.materials.open,
+            Action::Pause => self.stepping.toggle(),
+            Action::Step => self.stepping.step(),
+            Action::FirstPerson => self.toggle_first_person(),
+            Action::Capture => {
+                if let Some(capture) = self.capture.as_mut() {
+                    if capture.trigger() {
+                        self.toasts.push("capturing the next frame");
+                    } else {
+                        self.toasts.push("renderdoc isn't attached");
+                    }
+                }
+            }
+            Action::Save => self.pending_save = true,
+            Action::SaveMenu => {
+                self.save_menu = match self.save_menu {
+                    Some(_) => None,
+                    None => Some(saves::Slot::list()),
+                }
+            }
+            Action::LoseDevice => {
+                // simulate a driver reset to exercise the recovery path
+                log::warn!("destroying device");
+                if let Some(gfx) = &self.gfx {
+                    gfx.device.destroy();
+                }
+            }
+            Action::SwitchBackend => self.switch_backend(event_loop),
+            Action::OutlineThinner | Action::OutlineThicker => {
+                let Some(gfx) = &mut self.gfx else {
+                    return;
+                };
+                let step = if action == Action::OutlineThicker {
+                    1.0
+                } else {
+                    -1.0
+                };
+                let thickness = gfx.outline.thickness() + step;
+                let color = gfx.outline.color();
+                gfx.outline.set_style(&gfx.queue, color, thickness);
+            }
+            Action::Water => self.water_enabled = !self.water_enabled,
+            Action::Floor => self.floor_hidden = !self.floor_hidden,
+            Action::Cloth => {
+                self.cloth_enabled = !self.cloth_enabled;
+                if let Some(gfx) = self.gfx.as_ref().filter(|_| self.cloth_enabled) {
+                    gfx.cloth.drop_over(&gfx.queue, self.sim.cubes[0].position);
+                }
+            }
+            Action::Retro => {
+                self.options.retro.enabled = !self.options.retro.enabled;
+                if let Some(gfx) = &mut self.gfx {
+                    gfx.retro.set(&gfx.queue, self.options.retro);
+                }
+            }
+            Action::RestoreQuality => match self.restore_quality() {
+                0 => self.toasts.push("nothing was turned down"),
+                n => self.toasts.push(format!("put back {} quality settings", n)),
+            },
+            Action::Clip => match self.clips.save() {
+                Ok(path) => self.toasts.push(format!("saved {}", path.display())),
+                Err(e) => {
+                    log::error!("couldn't save a clip: {:#}", e);
+                    self.toasts.push("couldn't save the clip");
+                }
+            },
+            Action::Reflections => {
+                let Some(gfx) = &self.gfx else {
+                    return;
+                };
+                let dynamic = !gfx.environment.dynamic;
+                self.options.overrides.reflections = Some(dynamic);
+                self.apply_graphics();
+                log::info!("dynamic reflections {}", if dynamic { "on" } else { "off" });
+            }
         }
-        if self.controller.is_right_pressed {
-            x += 1.0;
+    }
+
+    fn save_slot(&mut self, name: String, thumbnail: Option<&image::RgbaImage>) {
+        let slot = saves::Slot {
+            name,
+            saved_at: std::time::SystemTime::now(),
+            cubes: self
+                .sim
+                .cubes
+                .iter()
+                .map(|c| saves::Cube {
+                    position: c.position,
+                    rotation: c.rotation,
+                    skin: c.skin,
+                    layer: c.layer,
+                })
+                .collect(),
+            ao: None,
+            filter: self.sim.filter,
+        };
+        match slot.save(thumbnail) {
+            Ok(()) => self.toasts.push(format!("saved {}", slot.name)),
+            Err(e) => {
+                log::error!("couldn't save {}: {}", slot.name, e);
+                self.toasts.push(format!("couldn't save {}", slot.name));
+            }
         }
-        let mut move_vector = cgmath::Vector3::new(x, y, z);
-        if move_vector.magnitude() != 0.0 {
-            move_vector = move_vector.normalize();
+        if self.save_menu.is_some() {
+            self.save_menu = Some(saves::Slot::list());
         }
-        move_vector *= self.controller.velocity;
+    }
 
-        for c in self.cube_instances.iter_mut() {
-            c.position += move_vector;
+    fn load_slot(&mut self, slot: &saves::Slot) {
+        if slot.cubes.is_empty() {
+            log::warn!("{} has no cubes, not loading it", slot.name);
+            self.toasts.push(format!("{} is empty", slot.name));
+            return;
         }
-        // self.cube_instances[0].position += move_vector;
-
-        // Map the instance data to `InstanceRaw` format
-        let instance_data = self
-            .cube_instances
+        self.sim.cubes = slot
+            .cubes
             .iter()
-            .map(Instance::to_raw)
-            .collect::<Vec<_>>();
+            .map(|cube| Instance {
+                position: cube.position,
+                rotation: cube.rotation,
+                flash: NO_FLASH,
+                skin: cube.skin,
+                layer: cube.layer,
+            })
+            .collect();
+        self.sim.filter = slot.filter;
+        self.sim.replaced();
+        self.selected.clear();
+        self.scenario = None;
+        self.upload_instances();
+        if let Some(gfx) = &mut self.gfx {
+            gfx.floor
+                .set_ao(&gfx.device, &gfx.queue, slot.ao.zip(slot.ao_image()));
+        }
+        self.toasts.push(format!("loaded {}", slot.name));
+    }
 
-        // Re-upload the updated instance data to the GPU
-        self.queue.as_ref().unwrap().write_buffer(
-            self.cube_instance_buffer.as_ref().unwrap(),
-            0,
-            bytemuck::cast_slice(&instance_data),
-        );
+    // everything but the player's cube makes way for the scenario's
+    fn start_scenario(&mut self, i: usize) {
+        self.sim.cubes.truncate(1);
+        self.sim.joints.clear();
+        self.selected.clear();
+        self.sim.boids.enabled = false;
+        self.scenarios[i].setup(&mut self.sim.cubes, &mut self.rng);
+        self.scenario = Some(i);
+        self.set_lighting(self.scenarios[i].lighting());
+        self.upload_instances();
+        log::info!("started {}", self.scenarios[i].name());
+    }
 
-        match self.timer.as_mut() {
-            Some(timer) => {
-                let target_fps = 1.0 / 60.0 as f64;
-                timer.elapsed = timer.start.elapsed().as_secs_f64();
-                timer.acc += timer.elapsed - timer.last;
-                timer.last = timer.elapsed;
-                // framerate stuff goes here?
-                timer.timer_uniform.t = timer.elapsed as f32;
-                self.queue.as_ref().unwrap().write_buffer(
-                    &timer.timer_buffer,
-                    0,
-                    &timer.timer_uniform.t.to_le_bytes(),
-                );
+    fn set_lighting(&mut self, lighting: light::Lighting) {
+        self.lighting = lighting;
+        if let Some(gfx) = &self.gfx {
+            gfx.light.set(&gfx.queue, &self.lighting);
+        }
+    }
+
+    // the other players' names over their cubes, while connected
+    fn name_tags(&self) -> Vec<OwnedSection> {
+        let Some(client) = &self.client else {
+            return Vec::new();
+        };
+        let Some(mine) = client.cube else {
+            return Vec::new();
+        };
+        let players = client.players.iter().filter_map(|(cube, name)| {
+            let i = net::local(*cube, mine);
+            (i != 0 && i < self.sim.cubes.len())
+                .then(|| (self.sim.cubes[i].position, name.as_str()))
+        });
+        let (width, height) = self.screen_size();
+        chat::name_tags(
+            self.camera.build_view_projection_matrix(),
+            players,
+            width,
+            height,
+        )
+    }
+
+    // What the server sent, put into our numbering (see net::local).
+    fn apply_server(&mut self, message: net::ToClient) {
+        let (tick, mine, count, cubes) = match message {
+            net::ToClient::Snapshot {
+                tick,
+                yours,
+                count,
+                cubes,
+            } => (tick, yours as usize, count as usize, cubes),
+            net::ToClient::Chat { from, text } => {
+                self.chat.receive(&from, &text);
+                return;
+            }
+            net::ToClient::Start {
+                slot,
+                players,
+                seed,
+            } => {
+                // the start's sent again whenever a hello crosses it, and one
+                // that can't be right is dropped
+                let (slot, players) = (slot as usize, players as usize);
+                if players > rollback::MAX_PLAYERS || slot >= players {
+                    log::warn!(
+                        "ignoring a rollback start for player {} of {}",
+                        slot + 1,
+                        players
+                    );
+                    return;
+                }
+                if self.rollback.is_none() {
+                    log::info!(
+                        "rollback session starting, player {} of {}",
+                        slot + 1,
+                        players
+                    );
+                    let rollback = rollback::Rollback::new(slot, players, seed);
+                    self.sim.cubes = rollback.cubes().to_vec();
+                    self.sim.replaced();
+                    self.selected.clear();
+                    self.upload_instances();
+                    self.rollback = Some(rollback);
+                }
+                return;
+            }
+            net::ToClient::Inputs {
+                slot,
+                tick,
+                directions,
+            } => {
+                if let Some(rollback) = &mut self.rollback {
+                    rollback.receive(slot as usize, tick, &directions);
+                }
+                return;
+            }
+            _ => return,
+        };
+        if mine >= count {
+            return;
+        }
+        let resized = count != self.sim.cubes.len();
+        if resized {
+            self.sim.cubes.resize_with(count, || Instance {
+                position: math::Vec3::ZERO,
+                rotation: math::Quat::IDENTITY,
+                flash: NO_FLASH,
+                skin: 0,
+                layer: physics::Layers::NONE,
+            });
+            self.sim.replaced();
+            self.selected.retain(|&i| (i as usize) < count);
+            self.interpolation.reset(count);
+        }
+        self.interpolation.arrived(tick);
+        for (index, mut cube) in cubes {
+            let i = net::local(index as usize, mine);
+            if i >= count {
+                continue;
+            }
+            // flashes are only for show, they stay ours
+            cube.flash = self.sim.cubes[i].flash;
+            if i == 0 {
+                if !resized {
+                    self.sim.players[0].moved += cube.position - self.sim.cubes[0].position;
+                }
+            } else {
+                // everyone else's go in when interpolate says
+                self.interpolation
+                    .push(i, tick, cube.position, cube.rotation);
+                cube.position = self.sim.cubes[i].position;
+                cube.rotation = self.sim.cubes[i].rotation;
+            }
+            self.sim.cubes[i] = cube;
+            self.sim.dirty.mark(i..i + 1);
+        }
+        if resized {
+            self.upload_instances();
+        }
+    }
+
+    // our inputs out, and the session's cubes into ours (see net::local)
+    fn run_rollback(&mut self, frame_time: f64, direction: math::Vec3) {
+        let rollback = self.rollback.as_mut().unwrap();
+        if let Some((tick, directions)) = rollback.update(frame_time, direction) {
+            self.client.as_ref().unwrap().send_inputs(tick, &directions);
+        }
+        let mine = rollback.slot + 1;
+        for (index, cube) in rollback.cubes().iter().enumerate() {
+            let i = net::local(index, mine);
+            if i == 0 {
+                self.sim.players[0].moved += cube.position - self.sim.cubes[0].position;
+            }
+            self.sim.cubes[i] = Instance {
+                flash: self.sim.cubes[i].flash,
+                ..cube.clone()
+            };
+            self.sim.dirty.mark(i..i + 1);
+        }
+        self.stats.rollback = Some(rollback.shown);
+    }
+
+    // the other cubes where they were a little while ago on the server
+    fn interpolate(&mut self) {
+        for i in 1..self.sim.cubes.len() {
+            let Some((position, rotation)) = self.interpolation.sample(i) else {
+                continue;
+            };
+            let cube = &mut self.sim.cubes[i];
+            if cube.position != position || cube.rotation != rotation {
+                cube.position = position;
+                cube.rotation = rotation;
+                self.sim.dirty.mark(i..i + 1);
             }
-            None => {}
+        }
+    }
+
+    // the instance count changed, so everything goes up again, into a bigger
+    // buffer if it doesn't fit
+    fn upload_instances(&mut self) {
+        let _alloc = allocs::scope(allocs::Subsystem::Render);
+        let instance_data = self.instance_data();
+        let Some(gfx) = &mut self.gfx else {
+            return;
+        };
+        self.errors.push_scope(&gfx.device);
+        gfx.cube_instances
+            .upload(&gfx.device, &gfx.queue, &instance_data);
+        self.errors.pop_scope(&gfx.device, "cube instance buffer");
+        self.sim.dirty.clear();
+        self.stats.instance_bytes += std::mem::size_of_val(&instance_data[..]);
+    }
+
+    // cubes pushed onto the end from `first` on: they go up with the rest of
+    // the frame's changes, unless there's no room for them and it all does
+    fn upload_added(&mut self, first: usize) {
+        let Some(gfx) = &mut self.gfx else {
+            return;
         };
+        if gfx
+            .cube_instances
+            .reserve(&gfx.device, self.sim.cubes.len())
+        {
+            self.upload_instances();
+        } else {
+            self.sim.dirty.mark(first..self.sim.cubes.len());
+        }
+    }
+
+    fn instance_data(&self) -> Vec<InstanceRaw> {
+        let scale = self.cube_scale();
+        self.sim
+            .cubes
+            .iter()
+            .map(|cube| cube.scaled_raw(scale))
+            .collect()
+    }
+
+    // how big the cubes are drawn, bigger with the bass while the visualizer's on
+    fn cube_scale(&self) -> f32 {
+        self.visualizer
+            .as_ref()
+            .map_or(1.0, |v| 1.0 + v.levels[0] * 0.4)
     }
 
     fn add_cube(&mut self) {
-        let x: f32 = rand::random::<f32>() * 10.0;
-        let y: f32 = rand::random::<f32>() * 10.0;
-        let z: f32 = rand::random::<f32>() * 10.0;
-        let position = (x, y, z).into();
+        let x: f32 = self.rng.f32() * 10.0;
+        let z: f32 = self.rng.f32() * 10.0;
+        // rolling hills rather than a uniform box, so the seed shapes the pile
+        let y = (self.noise.fbm(x * 0.2, z * 0.2, 4) + 1.0) * 5.0;
+        // dropped in from above, so one landing where a cube already is
+        // stacks on top of it instead
+        let above = math::Vec3::new(x, y + SPAWN_DROP, z);
+        let y = physics::World::new(&self.sim.cubes)
+            .box_cast(
+                physics::Aabb {
+                    min: above - math::Vec3::ONE,
+                    max: above + math::Vec3::ONE,
+                },
+                -math::UP,
+                SPAWN_DROP,
+                physics::Layers::ALL,
+            )
+            .map_or(y, |hit| hit.point.y + 1.0);
+        let position = math::Vec3::new(x, y, z);
+        let rotation = self.rotation_at(position);
+        let skin = self.next_skin();
 
-        self.cube_instances.push(Instance {
+        self.sim.cubes.push(Instance {
             position,
-            rotation: cgmath::Quaternion::zero(),
+            rotation,
+            flash: NO_FLASH,
+            skin,
+            layer: physics::Layers::STATIC,
         });
+        self.flash_cube(self.sim.cubes.len() - 1);
+        self.upload_added(self.sim.cubes.len() - 1);
+        self.session.cubes_spawned += 1;
+        self.play(sfx::Event::Spawn, Some(position));
+    }
 
-        let instance_data = self
-            .cube_instances
-            .iter()
-            .map(Instance::to_raw)
-            .collect::<Vec<_>>();
-
-        self.cube_instance_buffer = Some(self.device.as_ref().unwrap().create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("cube instance buffer"),
-                contents: bytemuck::cast_slice(&instance_data),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            },
-        ));
+    // Takes the cube away and leaves its pieces flying, pushed away from the
+    // player's cube a little.
+    fn shatter_cube(&mut self, i: usize) {
+        let cube = self.sim.cubes.remove(i);
+        let away = (cube.position - self.sim.cubes[0].position).normalize_or_zero();
+        let now = self.now();
+        if let Some(gfx) = &mut self.gfx {
+            gfx.shatter
+                .shatter(&cube, away * 2.0 + math::UP * 2.0, now, &mut self.rng);
+        }
+        self.play(sfx::Event::Shatter, Some(cube.position));
+        self.sim.removed(i);
+        self.chase.target = match self.chase.target {
+            Some(t) if t == i => None,
+            Some(t) if t > i => Some(t - 1),
+            t => t,
+        };
+        self.upload_instances();
+    }
 
-        // // Map the instance data to `InstanceRaw` format
-        // let instance_data = self
-        //     .cube_instances
-        //     .iter()
-        //     .map(Instance::to_raw)
-        //     .collect::<Vec<_>>();
+    // the sound for `event`, from `at` in the world or from nowhere in particular
+    fn play(&mut self, event: sfx::Event, at: Option<math::Point3>) {
+        let _alloc = allocs::scope(allocs::Subsystem::Audio);
+        if let Some((samples, gain, rate)) = self.sfx.pick(event) {
+            self.audio.as_ref().unwrap().play(&samples, at, gain, rate);
+        }
+    }
 
-        // // Re-upload the updated instance data to the GPU
-        self.queue.as_ref().unwrap().write_buffer(
-            self.cube_instance_buffer.as_ref().unwrap(),
-            0,
-            bytemuck::cast_slice(&instance_data),
+    fn next_skin(&mut self) -> u32 {
+        self.spawn_skin
+            .unwrap_or_else(|| self.rng.below(cube::SKINS.len() as u32))
+    }
+
+    // how a cube spawned at `position` is turned
+    fn rotation_at(&self, position: math::Vec3) -> math::Quat {
+        self.spawn_rotation
+            .placed_at(position)
+            .to_quat()
+            .unwrap_or_else(|e| {
+                log::warn!("spawning unrotated: {}", e);
+                math::Quat::IDENTITY
+            })
+    }
+
+    // --scaling's cubes, a spiral out from the player's and nothing else
+    fn set_cube_count(&mut self, count: usize) {
+        let spiral = formation::Formation::Spiral {
+            count: count as u32,
+            spacing: scaling::SPACING,
+        };
+        let positions = spiral.positions(self.sim.cubes[0].position, &mut self.rng);
+        self.sim.cubes.truncate(1);
+        // the spiral's first is right where the player is
+        for (i, position) in positions.into_iter().enumerate().skip(1) {
+            self.sim.cubes.push(Instance {
+                position,
+                rotation: math::Quat::IDENTITY,
+                flash: NO_FLASH,
+                skin: (i % cube::SKINS.len()) as u32,
+                layer: physics::Layers::STATIC,
+            });
+        }
+        self.sim.replaced();
+        self.selected.clear();
+        self.upload_instances();
+    }
+
+    // a whole formation around the first cube, uploaded once at the end
+    fn spawn_formation(&mut self, formation: formation::Formation) {
+        let mut positions = formation.positions(self.sim.cubes[0].position, &mut self.rng);
+        // whatever fits under the cap, the rest is left out
+        let room = instance_buffer::MAX_INSTANCES.saturating_sub(self.sim.cubes.len());
+        positions.truncate(room);
+        let spawned = positions.len();
+        // one sound for the lot, from the middle of them
+        let center = positions.iter().sum::<math::Vec3>() / positions.len().max(1) as f32;
+        let first = self.sim.cubes.len();
+        self.sim.cubes.reserve(positions.len());
+        for position in positions {
+            let rotation = self.rotation_at(position);
+            let skin = self.next_skin();
+            self.sim.cubes.push(Instance {
+                position,
+                rotation,
+                flash: NO_FLASH,
+                skin,
+                layer: physics::Layers::STATIC,
+            });
+        }
+        self.upload_added(first);
+        self.session.cubes_spawned += spawned as u32;
+        self.play(sfx::Event::Spawn, Some(center));
+        self.console.print(format!(
+            "spawned {} cubes, {} in total",
+            spawned,
+            self.sim.cubes.len()
+        ));
+        if spawned < formation.count() {
+            self.console.print(format!(
+                "{} left out, that's as many cubes as there can be",
+                formation.count() - spawned
+            ));
+        }
+    }
+}
+
+fn save_menu_section(slots: &[saves::Slot], height: f32) -> OwnedSection {
+    let mut text = String::from("load which slot? (F6 closes, F5 saves)\n");
+    if slots.is_empty() {
+        text += "no saves yet\n";
+    }
+    for (i, slot) in slots.iter().take(9).enumerate() {
+        text += &format!(
+            "{}  {}  {}  {} cubes\n",
+            i + 1,
+            slot.name,
+            slot.age(),
+            slot.cubes.len()
         );
     }
+    TextSection::default()
+        .add_text(
+            Text::new(&text)
+                .with_color([0.9, 1.0, 1.0, 1.0])
+                .with_scale(20.0),
+        )
+        .with_screen_position((10.0, height / 2.0))
+        .to_owned()
+}
+
+fn main_menu_section(
+    scenarios: &[Box<dyn scenario::Scenario>],
+    running: Option<usize>,
+    height: f32,
+) -> OwnedSection {
+    let mut text = String::from("praxis (F1 closes)\n");
+    for (i, scenario) in scenarios.iter().enumerate() {
+        let marker = if running == Some(i) {
+            "  (running)"
+        } else {
+            ""
+        };
+        text += &format!("{}  {}{}\n", i + 1, scenario.name(), marker);
+    }
+    text += "0  stop the scenario\nF6 saves, F7 options, ` console\n";
+    TextSection::default()
+        .add_text(
+            Text::new(&text)
+                .with_color([0.9, 1.0, 1.0, 1.0])
+                .with_scale(20.0),
+        )
+        .with_screen_position((10.0, height / 4.0))
+        .to_owned()
+}
+
+fn restore_prompt_section(slot: &saves::Slot, height: f32) -> OwnedSection {
+    TextSection::default()
+        .add_text(
+            Text::new(&format!(
+                "praxis didn't close properly last time.\nrestore {} from {}? (Y/N)",
+                slot.name,
+                slot.age()
+            ))
+            .with_color([1.0, 0.85, 0.4, 1.0])
+            .with_scale(24.0),
+        )
+        .with_screen_position((10.0, height / 3.0))
+        .to_owned()
 }
 
 fn main() {
+    dirs::init_from_args();
+    logfile::init();
+    let args = std::env::args().collect::<Vec<_>>();
+    if let Some(i) = args.iter().position(|a| a == "--bake") {
+        let Some(name) = args.get(i + 1) else {
+            log::error!("--bake takes the name of a save slot");
+            std::process::exit(2);
+        };
+        if let Err(e) = bake::run(name) {
+            log::error!("couldn't bake {}: {:#}", name, e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(i) = args.iter().position(|a| a == "--server") {
+        let address = args
+            .get(i + 1)
+            .filter(|a| !a.starts_with("--"))
+            .map_or(net::DEFAULT_PORT.to_string(), |a| a.clone());
+        if let Err(e) = server::run(&address) {
+            log::error!("couldn't serve on {}: {:#}", address, e);
+            std::process::exit(1);
+        }
+        return;
+    }
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(ControlFlow::Wait);
     let mut app = App::default();
     let _ = event_loop.run_app(&mut app);
+    if app.benchmark.is_some_and(|b| !b.passed()) {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // everything but drawing is on the CPU, so it all works before there's a
+    // renderer, or while one's being made
+    #[test]
+    fn runs_without_a_renderer() {
+        let mut app = App::default();
+        app.init_scene();
+        assert_eq!(app.sim.cubes.len(), 1);
+        assert_eq!(app.camera.aspect, WIDTH as f32 / HEIGHT as f32);
+        app.run_command("spawn ring 12");
+        assert_eq!(app.sim.cubes.len(), 13);
+        app.run_command("impostors");
+        app.console.open = true;
+        let shown = app.console.section(HEIGHT as f32).unwrap();
+        assert!(shown.text[0].text.contains("the renderer isn't up yet"));
+        app.resize(winit::dpi::PhysicalSize::new(800, 600));
+        app.write_camera();
+        app.upload_instances();
+        assert_eq!(app.screen_size(), (WIDTH as f32, HEIGHT as f32));
+        assert_eq!(
+            app.window_size(),
+            winit::dpi::PhysicalSize::new(WIDTH, HEIGHT)
+        );
+    }
 }
@@ -0,0 +1,272 @@
+use crate::cube::SKINS;
+use crate::model::{SkinRaw, MAX_SKINS};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+const BUILT_IN: &str = include_str!("../res/materials.ron");
+
+// one skin's entry in res/materials.ron
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct MaterialSettings {
+    pub name: String,
+    pub tint: [f32; 3],
+    pub roughness: f32,
+    pub metallic: f32,
+    // a file in res/ by name, anywhere else by its whole path, None for the
+    // built in skin
+    pub texture: Option<String>,
+}
+
+impl Default for MaterialSettings {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            tint: [1.0; 3],
+            roughness: 0.0,
+            metallic: 0.0,
+            texture: None,
+        }
+    }
+}
+
+impl MaterialSettings {
+    fn raw(&self) -> SkinRaw {
+        let [r, g, b] = self.tint;
+        SkinRaw {
+            tint: [r, g, b, self.roughness],
+            metallic: self.metallic,
+            ..Default::default()
+        }
+    }
+}
+
+fn res_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("res")
+}
+
+// one per skin, by name, whatever order or gaps the file has
+fn parse(text: &str) -> Result<Vec<MaterialSettings>> {
+    let found: Vec<MaterialSettings> = ron::from_str(text)?;
+    Ok(SKINS
+        .iter()
+        .map(|(name, _)| {
+            found
+                .iter()
+                .find(|m| m.name == *name)
+                .cloned()
+                .unwrap_or_else(|| MaterialSettings {
+                    name: name.to_string(),
+                    ..Default::default()
+                })
+        })
+        .collect())
+}
+
+// The M material editor, an egui window over the cube skins from
+// res/materials.ron (or `--materials <file>`). Colors, roughness and
+// metallic are a uniform write so show on the next frame; a texture is
+// picked with the system's file dialog and goes straight into the skin's
+// layer of the diffuse array. Save writes them all back to the file. The
+// impostors of far cubes were drawn at startup, so keep the old look until
+// the next run.
+pub struct MaterialEditor {
+    pub open: bool,
+    materials: Vec<MaterialSettings>,
+    // as last loaded or saved, what revert goes back to
+    saved: Vec<MaterialSettings>,
+    path: PathBuf,
+    selected: usize,
+    // the uniform needs writing
+    dirty: bool,
+    // skins whose texture changed since the last frame
+    swapped: Vec<usize>,
+}
+
+impl Default for MaterialEditor {
+    fn default() -> Self {
+        let materials = parse(BUILT_IN).unwrap();
+        Self {
+            open: false,
+            saved: materials.clone(),
+            materials,
+            path: res_dir().join("materials.ron"),
+            selected: 0,
+            dirty: true,
+            swapped: Vec::new(),
+        }
+    }
+}
+
+impl MaterialEditor {
+    pub fn from_args() -> Self {
+        let mut editor = Self::default();
+        let args = std::env::args().collect::<Vec<_>>();
+        if let Some(path) = args
+            .iter()
+            .position(|a| a == "--materials")
+            .and_then(|i| args.get(i + 1))
+        {
+            editor.path = PathBuf::from(path);
+        }
+        if editor.path.exists() {
+            match std::fs::read_to_string(&editor.path)
+                .map_err(anyhow::Error::from)
+                .and_then(|text| parse(&text))
+            {
+                Ok(materials) => {
+                    editor.saved = materials.clone();
+                    editor.materials = materials;
+                }
+                Err(e) => log::error!(
+                    "{} can't be read, using the built in materials: {:#}",
+                    editor.path.display(),
+                    e
+                ),
+            }
+        }
+        editor
+    }
+
+    // the GPU side was made again from scratch, so everything goes again
+    pub fn reloaded(&mut self) {
+        self.dirty = true;
+        self.swapped = (0..self.materials.len())
+            .filter(|i| self.materials[*i].texture.is_some())
+            .collect();
+    }
+
+    // once a frame, for whatever changed since the last
+    pub fn apply(&mut self, queue: &wgpu::Queue, material: &crate::model::Material) {
+        if self.dirty {
+            let mut skins = [SkinRaw::default(); MAX_SKINS];
+            for (skin, settings) in skins.iter_mut().zip(&self.materials) {
+                *skin = settings.raw();
+            }
+            material.set_skins(queue, &skins);
+            self.dirty = false;
+        }
+        for i in std::mem::take(&mut self.swapped) {
+            match self.texture(i, material) {
+                Ok(image) => material.set_skin(queue, i, &image),
+                Err(e) => log::error!("no texture for {}: {:#}", self.materials[i].name, e),
+            }
+        }
+    }
+
+    fn texture(&self, i: usize, material: &crate::model::Material) -> Result<image::DynamicImage> {
+        match &self.materials[i].texture {
+            Some(file) => {
+                let path = res_dir().join(file);
+                image::open(&path).with_context(|| path.display().to_string())
+            }
+            None => Ok(image::DynamicImage::ImageRgba8(material.layers[i].clone())),
+        }
+    }
+
+    // The window, on frames it's open. Gives back anything worth a toast.
+    pub fn ui(&mut self, ctx: &egui::Context) -> Option<String> {
+        let mut message = None;
+        let mut open = self.open;
+        egui::Window::new("materials")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for (i, material) in self.materials.iter().enumerate() {
+                        let changed = if *material != self.saved[i] { " *" } else { "" };
+                        ui.selectable_value(
+                            &mut self.selected,
+                            i,
+                            format!("{}{}", material.name, changed),
+                        );
+                    }
+                });
+                ui.separator();
+
+                let i = self.selected;
+                let material = &mut self.materials[i];
+                egui::Grid::new("material").num_columns(2).show(ui, |ui| {
+                    ui.label("tint");
+                    self.dirty |= ui.color_edit_button_rgb(&mut material.tint).changed();
+                    ui.end_row();
+                    ui.label("roughness");
+                    self.dirty |= ui
+                        .add(egui::Slider::new(&mut material.roughness, 0.0..=1.0))
+                        .changed();
+                    ui.end_row();
+                    ui.label("metallic");
+                    self.dirty |= ui
+                        .add(egui::Slider::new(&mut material.metallic, 0.0..=1.0))
+                        .changed();
+                    ui.end_row();
+                    ui.label("texture");
+                    ui.horizontal(|ui| {
+                        ui.label(material.texture.as_deref().unwrap_or("built in"));
+                        if ui.button("pick…").clicked() {
+                            if let Some(texture) = pick_texture(&material.name) {
+                                material.texture = Some(texture);
+                                self.swapped.push(i);
+                            }
+                        }
+                        if material.texture.is_some() && ui.button("built in").clicked() {
+                            material.texture = None;
+                            self.swapped.push(i);
+                        }
+                    });
+                    ui.end_row();
+                });
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui.button("save").clicked() {
+                        message = Some(match self.save() {
+                            Ok(()) => format!("materials saved to {}", self.path.display()),
+                            Err(e) => {
+                                log::error!("saving materials: {:#}", e);
+                                "couldn't save the materials".to_string()
+                            }
+                        });
+                    }
+                    if ui.button("revert").clicked() {
+                        let saved = self.saved[i].clone();
+                        if saved.texture != self.materials[i].texture {
+                            self.swapped.push(i);
+                        }
+                        self.materials[i] = saved;
+                        self.dirty = true;
+                    }
+                    ui.label(self.path.display().to_string());
+                });
+            });
+        self.open = open;
+        message
+    }
+
+    fn save(&mut self) -> Result<()> {
+        // the comment at the top of the built in file goes back on
+        let header = BUILT_IN
+            .lines()
+            .take_while(|line| line.starts_with("//"))
+            .map(|line| format!("{}\n", line))
+            .collect::<String>();
+        let text = ron::ser::to_string_pretty(&self.materials, ron::ser::PrettyConfig::default())?;
+        std::fs::write(&self.path, header + &text + "\n")?;
+        self.saved = self.materials.clone();
+        Ok(())
+    }
+}
+
+// The file dialog, starting in res/. What's in there is kept by name so the
+// materials file still works from another checkout.
+fn pick_texture(skin: &str) -> Option<String> {
+    let path = rfd::FileDialog::new()
+        .set_title(format!("a texture for {}", skin))
+        .set_directory(res_dir())
+        .add_filter("images", &["png", "jpg", "jpeg"])
+        .pick_file()?;
+    Some(match path.strip_prefix(res_dir()) {
+        Ok(name) => name.display().to_string(),
+        Err(_) => path.display().to_string(),
+    })
+}
@@ -0,0 +1,214 @@
+// Conventions, so every feature doesn't have to work them out again:
+// - right handed, +y is up, cameras look down -z in view space
+// - clip space depth is 0..1 like wgpu wants, which is what glam's `_rh`
+//   projections give
+// - angles are authored in degrees (`Degrees`) and only turned into radians
+//   where they're handed to glam
+// - one unit is the half size of the cube model, which spans -1..1
+// - matrices are column major in both glam and WGSL, so the array helpers
+//   below are plain copies
+//
+// glam rather than cgmath for the SIMD Mat4/Quat, see benches/instance_rebuild.rs
+
+pub type Vec2 = glam::Vec2;
+pub type Vec3 = glam::Vec3;
+pub type Vec4 = glam::Vec4;
+// glam has no separate point type, positions are just vectors
+pub type Point3 = glam::Vec3;
+pub type Mat4 = glam::Mat4;
+pub type Quat = glam::Quat;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Degrees(pub f32);
+
+impl Degrees {
+    pub fn to_radians(self) -> f32 {
+        self.0.to_radians()
+    }
+}
+
+pub const UP: Vec3 = Vec3::Y;
+
+// a projection straight into wgpu's clip space, `fovy` is the vertical field of view
+pub fn perspective(fovy: Degrees, aspect: f32, znear: f32, zfar: f32) -> Mat4 {
+    Mat4::perspective_rh(fovy.to_radians(), aspect, znear, zfar)
+}
+
+// a box `half_width` by `half_height` around the view axis, also into wgpu's clip space
+pub fn orthographic(half_width: f32, half_height: f32, znear: f32, zfar: f32) -> Mat4 {
+    Mat4::orthographic_rh(
+        -half_width,
+        half_width,
+        -half_height,
+        half_height,
+        znear,
+        zfar,
+    )
+}
+
+pub fn look_at(eye: Point3, target: Point3, up: Vec3) -> Mat4 {
+    Mat4::look_at_rh(eye, target, up)
+}
+
+// translation after rotation, what instances use
+pub fn model_matrix(position: Vec3, rotation: Quat) -> Mat4 {
+    Mat4::from_rotation_translation(rotation, position)
+}
+
+pub fn mat4_array(m: Mat4) -> [[f32; 4]; 4] {
+    m.to_cols_array_2d()
+}
+
+pub fn vec3_array(v: Vec3) -> [f32; 3] {
+    v.to_array()
+}
+
+// as a position, w = 1
+pub fn point4_array(p: Point3) -> [f32; 4] {
+    p.extend(1.0).to_array()
+}
+
+// How authored content says which way something faces, so scene files and
+// spawn code never have to write raw quaternions.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Rotation {
+    // yaw around +y, then pitch around +x, then roll around +z
+    Euler {
+        yaw: Degrees,
+        pitch: Degrees,
+        roll: Degrees,
+    },
+    // the axis doesn't have to be normalized, it just can't be zero
+    AxisAngle {
+        axis: Vec3,
+        angle: Degrees,
+    },
+    // turns -z (the way cameras and models face) towards `target` from `from`
+    LookAt {
+        from: Point3,
+        target: Point3,
+    },
+}
+
+impl Rotation {
+    pub const IDENTITY: Rotation = Rotation::Euler {
+        yaw: Degrees(0.0),
+        pitch: Degrees(0.0),
+        roll: Degrees(0.0),
+    };
+
+    pub fn to_quat(self) -> anyhow::Result<Quat> {
+        let rotation = match self {
+            Rotation::Euler { yaw, pitch, roll } => Quat::from_euler(
+                glam::EulerRot::YXZ,
+                yaw.to_radians(),
+                pitch.to_radians(),
+                roll.to_radians(),
+            ),
+            Rotation::AxisAngle { axis, angle } => {
+                let Some(axis) = axis.try_normalize() else {
+                    anyhow::bail!("rotation axis {} has no direction", axis);
+                };
+                Quat::from_axis_angle(axis, angle.to_radians())
+            }
+            Rotation::LookAt { from, target } => look_rotation(target - from, UP)?,
+        };
+        if !rotation.is_finite() {
+            anyhow::bail!("{:?} isn't a rotation", self);
+        }
+        Ok(rotation.normalize())
+    }
+
+    // how `LookAt` rotations written relative to the origin get placed
+    pub fn placed_at(self, position: Point3) -> Rotation {
+        match self {
+            Rotation::LookAt { target, .. } => Rotation::LookAt {
+                from: position,
+                target,
+            },
+            rotation => rotation,
+        }
+    }
+
+    // back to yaw/pitch/roll, how rotations get written out
+    pub fn euler(rotation: Quat) -> Rotation {
+        let (yaw, pitch, roll) = rotation.to_euler(glam::EulerRot::YXZ);
+        Rotation::Euler {
+            yaw: Degrees(yaw.to_degrees()),
+            pitch: Degrees(pitch.to_degrees()),
+            roll: Degrees(roll.to_degrees()),
+        }
+    }
+}
+
+// the rotation that turns -z towards `forward` and keeps +y as close to `up`
+// as it can, picking another up when `forward` points straight along it
+pub fn look_rotation(forward: Vec3, up: Vec3) -> anyhow::Result<Quat> {
+    let Some(forward) = forward.try_normalize() else {
+        anyhow::bail!("can't look along {}", forward);
+    };
+    let up = if forward.cross(up).length_squared() < 1e-6 {
+        Vec3::Z
+    } else {
+        up
+    };
+    let right = forward.cross(up).normalize();
+    let up = right.cross(forward);
+    Ok(Quat::from_mat3(&glam::Mat3::from_cols(right, up, -forward)))
+}
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Rotation::IDENTITY
+    }
+}
+
+// `euler <yaw> <pitch> <roll>`, `axis <x> <y> <z> <angle>` or `look_at <x> <y> <z>`,
+// angles in degrees. `look_at` looks from the origin until it's `placed_at`.
+impl std::str::FromStr for Rotation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim();
+        let (kind, values) = s.split_once(' ').unwrap_or((s, ""));
+        let values = values
+            .split_whitespace()
+            .map(|v| v.parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let rotation = match (kind, &values[..]) {
+            ("euler", &[yaw, pitch, roll]) => Rotation::Euler {
+                yaw: Degrees(yaw),
+                pitch: Degrees(pitch),
+                roll: Degrees(roll),
+            },
+            ("axis", &[x, y, z, angle]) => Rotation::AxisAngle {
+                axis: Vec3::new(x, y, z),
+                angle: Degrees(angle),
+            },
+            ("look_at", &[x, y, z]) => Rotation::LookAt {
+                from: Point3::ZERO,
+                target: Point3::new(x, y, z),
+            },
+            _ => anyhow::bail!("expected euler, axis or look_at with their numbers: {}", s),
+        };
+        // catches a zero axis or looking at itself now rather than at spawn
+        rotation.to_quat()?;
+        Ok(rotation)
+    }
+}
+
+impl std::fmt::Display for Rotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Rotation::Euler { yaw, pitch, roll } => {
+                write!(f, "euler {} {} {}", yaw.0, pitch.0, roll.0)
+            }
+            Rotation::AxisAngle { axis, angle } => {
+                write!(f, "axis {} {} {} {}", axis.x, axis.y, axis.z, angle.0)
+            }
+            Rotation::LookAt { target, .. } => {
+                write!(f, "look_at {} {} {}", target.x, target.y, target.z)
+            }
+        }
+    }
+}
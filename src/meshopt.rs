@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+// what the vertex cache is assumed to hold when ordering triangles
+const CACHE_SIZE: usize = 32;
+// what ACMR is measured with, closer to older hardware so gains show up
+const FIFO_SIZE: usize = 16;
+
+// Post-processing for meshes as they're loaded, the same steps meshoptimizer
+// does: merge identical vertices, order triangles so vertices get reused out
+// of the post-transform cache, then order vertices by first use so fetches
+// walk memory forwards. LODs, when asked for, go after the full detail
+// indices in the same index buffer.
+pub struct Optimized<V> {
+    pub vertices: Vec<V>,
+    pub indices: Vec<u32>,
+    // index ranges, full detail first
+    pub lods: Vec<Range<u32>>,
+}
+
+pub fn optimize<V: bytemuck::Pod>(
+    label: &str,
+    vertices: &[V],
+    indices: &[u32],
+    lod_levels: usize,
+    position: impl Fn(&V) -> [f32; 3],
+) -> Optimized<V> {
+    let acmr_before = acmr(indices);
+    let (vertices_deduped, indices_deduped) = dedup(vertices, indices);
+    let ordered = optimize_vertex_cache(&indices_deduped, vertices_deduped.len());
+    let (vertices_out, mut indices_out) = optimize_vertex_fetch(&vertices_deduped, &ordered);
+
+    let mut lods = Vec::with_capacity(lod_levels + 1);
+    lods.push(0..indices_out.len() as u32);
+    let mut lod_counts = Vec::new();
+    for level in 1..=lod_levels {
+        let lod = optimize_vertex_cache(
+            &simplify(
+                &vertices_out,
+                &indices_out[..lods[0].end as usize],
+                level,
+                &position,
+            ),
+            vertices_out.len(),
+        );
+        // nothing left, or nothing gained over the last level
+        let last = &lods[lods.len() - 1];
+        if lod.is_empty() || lod.len() >= (last.end - last.start) as usize {
+            break;
+        }
+        let start = indices_out.len() as u32;
+        lod_counts.push(lod.len() / 3);
+        indices_out.extend(lod);
+        lods.push(start..indices_out.len() as u32);
+    }
+
+    log::info!(
+        "{}: {} -> {} vertices, {} triangles, acmr {:.2} -> {:.2}, lod triangles {:?}",
+        label,
+        vertices.len(),
+        vertices_out.len(),
+        indices.len() / 3,
+        acmr_before,
+        acmr(&indices_out[..lods[0].end as usize]),
+        lod_counts,
+    );
+    Optimized {
+        vertices: vertices_out,
+        indices: indices_out,
+        lods,
+    }
+}
+
+// vertices that are the same bit for bit become one
+fn dedup<V: bytemuck::Pod>(vertices: &[V], indices: &[u32]) -> (Vec<V>, Vec<u32>) {
+    let mut unique = Vec::new();
+    let mut seen: HashMap<&[u8], u32> = HashMap::new();
+    let remap = vertices
+        .iter()
+        .map(|v| {
+            *seen.entry(bytemuck::bytes_of(v)).or_insert_with(|| {
+                unique.push(*v);
+                unique.len() as u32 - 1
+            })
+        })
+        .collect::<Vec<_>>();
+    (unique, indices.iter().map(|i| remap[*i as usize]).collect())
+}
+
+// Tom Forsyth's linear-speed vertex cache optimisation
+fn optimize_vertex_cache(indices: &[u32], vertex_count: usize) -> Vec<u32> {
+    let triangle_count = indices.len() / 3;
+    let mut vertex_triangles = vec![Vec::new(); vertex_count];
+    for (t, triangle) in indices.chunks_exact(3).enumerate() {
+        for v in triangle {
+            vertex_triangles[*v as usize].push(t);
+        }
+    }
+
+    let vertex_score = |cache_position: Option<usize>, remaining: usize| {
+        if remaining == 0 {
+            return -1.0;
+        }
+        let cache = match cache_position {
+            // the triangle just drawn, using them again gains nothing much
+            Some(p) if p < 3 => 0.75,
+            Some(p) => (1.0 - (p - 3) as f32 / (CACHE_SIZE - 3) as f32).powf(1.5),
+            None => 0.0,
+        };
+        // vertices with few triangles left get finished off first
+        cache + 2.0 * (remaining as f32).powf(-0.5)
+    };
+
+    let mut scores = vertex_triangles
+        .iter()
+        .map(|triangles| vertex_score(None, triangles.len()))
+        .collect::<Vec<_>>();
+    let triangle_score = |scores: &[f32], t: usize| {
+        indices[t * 3..t * 3 + 3]
+            .iter()
+            .map(|v| scores[*v as usize])
+            .sum::<f32>()
+    };
+
+    let mut emitted = vec![false; triangle_count];
+    let mut cache: Vec<u32> = Vec::with_capacity(CACHE_SIZE + 3);
+    let mut out = Vec::with_capacity(indices.len());
+    let mut scan_from = 0;
+    let mut best = (0..triangle_count)
+        .max_by(|a, b| triangle_score(&scores, *a).total_cmp(&triangle_score(&scores, *b)));
+
+    while let Some(t) = best {
+        emitted[t] = true;
+        let triangle = &indices[t * 3..t * 3 + 3];
+        out.extend_from_slice(triangle);
+        for v in triangle {
+            vertex_triangles[*v as usize].retain(|other| *other != t);
+            cache.retain(|cached| cached != v);
+        }
+        for v in triangle.iter().rev() {
+            cache.insert(0, *v);
+        }
+        let evicted = cache.split_off(cache.len().min(CACHE_SIZE));
+
+        for (position, v) in cache.iter().enumerate() {
+            scores[*v as usize] = vertex_score(Some(position), vertex_triangles[*v as usize].len());
+        }
+        for v in &evicted {
+            scores[*v as usize] = vertex_score(None, vertex_triangles[*v as usize].len());
+        }
+
+        // the next triangle is almost always next to something in the cache
+        best = cache
+            .iter()
+            .flat_map(|v| vertex_triangles[*v as usize].iter().copied())
+            .max_by(|a, b| triangle_score(&scores, *a).total_cmp(&triangle_score(&scores, *b)));
+        if best.is_none() {
+            while scan_from < triangle_count && emitted[scan_from] {
+                scan_from += 1;
+            }
+            best = (scan_from < triangle_count).then_some(scan_from);
+        }
+    }
+    out
+}
+
+// renumbers vertices in the order the indices first use them, dropping unused ones
+fn optimize_vertex_fetch<V: Copy>(vertices: &[V], indices: &[u32]) -> (Vec<V>, Vec<u32>) {
+    let mut remap = vec![u32::MAX; vertices.len()];
+    let mut reordered = Vec::with_capacity(vertices.len());
+    let indices = indices
+        .iter()
+        .map(|i| {
+            let slot = &mut remap[*i as usize];
+            if *slot == u32::MAX {
+                *slot = reordered.len() as u32;
+                reordered.push(vertices[*i as usize]);
+            }
+            *slot
+        })
+        .collect();
+    (reordered, indices)
+}
+
+// Vertex clustering: snaps vertices to a grid that gets coarser with each
+// level, keeps one vertex per cell and drops triangles that collapse.
+fn simplify<V>(
+    vertices: &[V],
+    indices: &[u32],
+    level: usize,
+    position: impl Fn(&V) -> [f32; 3],
+) -> Vec<u32> {
+    let positions = vertices.iter().map(position).collect::<Vec<_>>();
+    let mut min = [f32::INFINITY; 3];
+    let mut max = [f32::NEG_INFINITY; 3];
+    for p in &positions {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(p[axis]);
+            max[axis] = max[axis].max(p[axis]);
+        }
+    }
+    let extent = (0..3).map(|axis| max[axis] - min[axis]).fold(0.0, f32::max);
+    // 32 cells across for level 1, halving after that
+    let cell = extent.max(f32::EPSILON) / (64 >> level.min(6)) as f32;
+
+    let mut cells: HashMap<[i32; 3], u32> = HashMap::new();
+    let representative = positions
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let key = [0, 1, 2].map(|axis| ((p[axis] - min[axis]) / cell) as i32);
+            *cells.entry(key).or_insert(i as u32)
+        })
+        .collect::<Vec<_>>();
+
+    indices
+        .chunks_exact(3)
+        .map(|t| {
+            t.iter()
+                .map(|i| representative[*i as usize])
+                .collect::<Vec<_>>()
+        })
+        .filter(|t| t[0] != t[1] && t[1] != t[2] && t[0] != t[2])
+        .flatten()
+        .collect()
+}
+
+// average cache miss ratio, transformed vertices per triangle through a FIFO cache
+fn acmr(indices: &[u32]) -> f32 {
+    if indices.is_empty() {
+        return 0.0;
+    }
+    let mut cache = std::collections::VecDeque::with_capacity(FIFO_SIZE);
+    let mut misses = 0;
+    for i in indices {
+        if !cache.contains(i) {
+            misses += 1;
+            if cache.len() == FIFO_SIZE {
+                cache.pop_front();
+            }
+            cache.push_back(*i);
+        }
+    }
+    misses as f32 / (indices.len() / 3) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // an n by n grid of quads, every triangle with its own three vertices
+    // the way a loader without single_index would give them
+    fn grid(n: usize) -> (Vec<[f32; 3]>, Vec<u32>) {
+        let mut vertices = Vec::new();
+        for z in 0..n {
+            for x in 0..n {
+                let corner = |dx: usize, dz: usize| [(x + dx) as f32, 0.0, (z + dz) as f32];
+                vertices.extend([corner(0, 0), corner(1, 0), corner(1, 1)]);
+                vertices.extend([corner(0, 0), corner(1, 1), corner(0, 1)]);
+            }
+        }
+        let indices = (0..vertices.len() as u32).collect();
+        (vertices, indices)
+    }
+
+    // each triangle as its positions, starting from the smallest so the
+    // winding is kept but not where it starts
+    fn triangles(vertices: &[[f32; 3]], indices: &[u32]) -> Vec<[[i32; 3]; 3]> {
+        let mut triangles = indices
+            .chunks_exact(3)
+            .map(|t| {
+                let mut corners = [0, 1, 2].map(|k| vertices[t[k] as usize].map(|c| c as i32));
+                let first = (0..3).min_by_key(|k| corners[*k]).unwrap();
+                corners.rotate_left(first);
+                corners
+            })
+            .collect::<Vec<_>>();
+        triangles.sort();
+        triangles
+    }
+
+    #[test]
+    fn keeps_every_triangle_and_merges_vertices() {
+        let (vertices, indices) = grid(8);
+        let optimized = optimize("grid", &vertices, &indices, 0, |v| *v);
+        assert_eq!(optimized.vertices.len(), 9 * 9);
+        assert_eq!(optimized.lods, vec![0..indices.len() as u32]);
+        assert_eq!(
+            triangles(&optimized.vertices, &optimized.indices),
+            triangles(&vertices, &indices)
+        );
+    }
+
+    #[test]
+    fn vertices_come_in_the_order_theyre_used() {
+        let (vertices, indices) = grid(8);
+        let optimized = optimize("grid", &vertices, &indices, 0, |v| *v);
+        let mut next = 0;
+        for i in &optimized.indices {
+            assert!(*i <= next);
+            if *i == next {
+                next += 1;
+            }
+        }
+        assert_eq!(next as usize, optimized.vertices.len());
+    }
+
+    #[test]
+    fn reuses_the_cache_better() {
+        let (vertices, indices) = grid(16);
+        let (deduped, deduped_indices) = dedup(&vertices, &indices);
+        // row by row, the way the grid was built, is already fair
+        let before = acmr(&deduped_indices);
+        let after = acmr(&optimize_vertex_cache(&deduped_indices, deduped.len()));
+        assert!(after <= before, "{} -> {}", before, after);
+    }
+
+    #[test]
+    fn lods_are_smaller_and_in_the_same_buffer() {
+        // finer than level 1's 32 cells across, or there's nothing to gain
+        let (vertices, indices) = grid(64);
+        let optimized = optimize("grid", &vertices, &indices, 2, |v| *v);
+        assert_eq!(optimized.lods.len(), 3);
+        for pair in optimized.lods.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+            assert!(pair[1].len() < pair[0].len());
+            assert_eq!(pair[1].len() % 3, 0);
+        }
+        assert_eq!(
+            optimized.lods.last().unwrap().end as usize,
+            optimized.indices.len()
+        );
+        assert!(optimized
+            .indices
+            .iter()
+            .all(|i| (*i as usize) < optimized.vertices.len()));
+    }
+
+    #[test]
+    fn acmr_of_a_strip() {
+        assert_eq!(acmr(&[]), 0.0);
+        // three new vertices, then one new each
+        assert_eq!(acmr(&[0, 1, 2, 1, 2, 3, 2, 3, 4]), 5.0 / 3.0);
+    }
+}
@@ -0,0 +1,362 @@
+use crate::arena::{MeshArena, MeshArenaBuilder};
+use crate::meshopt;
+use crate::texture;
+use crate::vertex::ModelVertex;
+use anyhow::Context;
+use core::ops::Range;
+use std::path::Path;
+use wgpu::util::DeviceExt;
+
+// An OBJ file's meshes, in the MeshArena, and the materials its MTL gives
+// them, with their textures. A mesh's `material` is its index in `materials`.
+pub struct Model {
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+}
+pub struct Material {
+    pub name: String,
+    pub diffuse_texture: texture::Texture,
+    // the layers as they were loaded, to put a skin back to
+    pub layers: Vec<image::RgbaImage>,
+    pub reflectivity: f32,
+    pub material_buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MaterialUniform {
+    pub reflectivity: f32,
+    // uniform buffers need 16 byte alignment
+    pub _padding: [f32; 3],
+    pub skins: [SkinRaw; MAX_SKINS],
+}
+
+// room for this many skins in the material uniform
+pub const MAX_SKINS: usize = 8;
+
+// what materials.rs edits per skin
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkinRaw {
+    // rgb, and roughness in w
+    pub tint: [f32; 4],
+    pub metallic: f32,
+    pub _padding: [f32; 3],
+}
+
+impl Default for SkinRaw {
+    fn default() -> Self {
+        Self {
+            tint: [1.0, 1.0, 1.0, 0.0],
+            metallic: 0.0,
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+pub struct Mesh {
+    #[allow(dead_code)]
+    pub name: String,
+    // where it is in the MeshArena
+    pub base_vertex: i32,
+    pub first_index: u32,
+    pub num_elements: u32,
+    // index ranges into the arena's index buffer, full detail first
+    pub lods: Vec<Range<u32>>,
+    pub material: usize,
+}
+
+impl Mesh {
+    // the full detail range
+    pub fn indices(&self) -> Range<u32> {
+        self.first_index..self.first_index + self.num_elements
+    }
+
+    // `level` past the coarsest there is gets the coarsest
+    pub fn lod(&self, level: usize) -> Range<u32> {
+        self.lods[level.min(self.lods.len() - 1)].clone()
+    }
+}
+
+// coarser versions to generate per mesh, stops early when simplifying gains nothing
+const LOD_LEVELS: usize = 2;
+
+impl Model {
+    // Textures and the MTL are looked for next to the OBJ. A material without
+    // a diffuse map gets a single pixel of its diffuse colour instead, and an
+    // OBJ without any materials one plain white one, so every mesh has one.
+    pub fn load(
+        path: &Path,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        arena: &mut MeshArenaBuilder,
+    ) -> anyhow::Result<Model> {
+        let (models, obj_materials) = tobj::load_obj(
+            path,
+            &tobj::LoadOptions {
+                triangulate: true,
+                single_index: true,
+                ..Default::default()
+            },
+        )
+        .with_context(|| format!("model {}", path.display()))?;
+        let directory = path.parent().unwrap_or(Path::new("."));
+
+        let mut materials = obj_materials
+            .with_context(|| format!("materials of {}", path.display()))?
+            .into_iter()
+            .map(|m| {
+                let diffuse = match &m.diffuse_texture {
+                    Some(file) => image::open(directory.join(file))
+                        .with_context(|| format!("{} texture {}", m.name, file))?
+                        .to_rgba8(),
+                    None => plain(m.diffuse.unwrap_or([1.0; 3])),
+                };
+                // mtl has no reflectivity, so borrow it from the specular colour
+                let reflectivity = m
+                    .specular
+                    .map(|ks| (ks[0] + ks[1] + ks[2]) / 3.0 * 0.5)
+                    .unwrap_or(0.0);
+                Material::new(device, queue, layout, &m.name, &[diffuse], reflectivity)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if materials.is_empty() {
+            materials.push(Material::new(
+                device,
+                queue,
+                layout,
+                "default",
+                &[plain([1.0; 3])],
+                0.0,
+            )?);
+        }
+
+        let meshes = models
+            .into_iter()
+            .map(|m| {
+                let vertices = (0..m.mesh.positions.len() / 3)
+                    .map(|i| ModelVertex {
+                        position: [
+                            m.mesh.positions[i * 3],
+                            m.mesh.positions[i * 3 + 1],
+                            m.mesh.positions[i * 3 + 2],
+                        ],
+                        tex_coords: if m.mesh.texcoords.is_empty() {
+                            [0.0, 0.0]
+                        } else {
+                            [m.mesh.texcoords[i * 2], 1.0 - m.mesh.texcoords[i * 2 + 1]]
+                        },
+                        normal: if m.mesh.normals.is_empty() {
+                            [0.0, 0.0, 0.0]
+                        } else {
+                            [
+                                m.mesh.normals[i * 3],
+                                m.mesh.normals[i * 3 + 1],
+                                m.mesh.normals[i * 3 + 2],
+                            ]
+                        },
+                    })
+                    .collect::<Vec<_>>();
+                let optimized = meshopt::optimize(
+                    &m.name,
+                    &vertices,
+                    &m.mesh.indices,
+                    LOD_LEVELS,
+                    |v: &ModelVertex| v.position,
+                );
+
+                let (base_vertex, first_index) = arena.add(&optimized.vertices, &optimized.indices);
+
+                Mesh {
+                    name: m.name,
+                    base_vertex,
+                    first_index,
+                    num_elements: optimized.lods[0].end,
+                    lods: optimized
+                        .lods
+                        .into_iter()
+                        .map(|lod| lod.start + first_index..lod.end + first_index)
+                        .collect(),
+                    material: m.mesh.material_id.unwrap_or(0).min(materials.len() - 1),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Model { meshes, materials })
+    }
+}
+
+// one pixel of `colour`, for a material with no texture
+fn plain(colour: [f32; 3]) -> image::RgbaImage {
+    let [r, g, b] = colour.map(|c| (c.clamp(0.0, 1.0).powf(1.0 / 2.2) * 255.0).round() as u8);
+    image::RgbaImage::from_pixel(1, 1, image::Rgba([r, g, b, 255]))
+}
+
+impl Material {
+    // A layer of the diffuse array per image, which all have to be the same
+    // size. Most materials have the one, the cube's has a layer per skin.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        name: &str,
+        layers: &[image::RgbaImage],
+        reflectivity: f32,
+    ) -> anyhow::Result<Self> {
+        let material_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} material buffer", name)),
+            contents: bytemuck::cast_slice(&[MaterialUniform {
+                reflectivity,
+                _padding: [0.0; 3],
+                skins: [SkinRaw::default(); MAX_SKINS],
+            }]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let diffuse_texture = texture::Texture::array_from_images(device, queue, layers, name)?;
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: material_buffer.as_entire_binding(),
+                },
+            ],
+            label: None,
+        });
+
+        Ok(Self {
+            name: name.to_string(),
+            diffuse_texture,
+            layers: layers.to_vec(),
+            reflectivity,
+            material_buffer,
+            bind_group,
+        })
+    }
+
+    // puts `image` in as skin `layer`, stretched to the size the others are
+    pub fn set_skin(&self, queue: &wgpu::Queue, layer: usize, image: &image::DynamicImage) {
+        let size = self.diffuse_texture.texture.size();
+        let image = image
+            .resize_exact(
+                size.width,
+                size.height,
+                image::imageops::FilterType::Triangle,
+            )
+            .to_rgba8();
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &self.diffuse_texture.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer as u32,
+                },
+            },
+            image.as_raw(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * size.width),
+                rows_per_image: Some(size.height),
+            },
+            wgpu::Extent3d {
+                depth_or_array_layers: 1,
+                ..size
+            },
+        );
+    }
+
+    // the per skin half of the uniform, the rest stays as loaded
+    pub fn set_skins(&self, queue: &wgpu::Queue, skins: &[SkinRaw; MAX_SKINS]) {
+        queue.write_buffer(
+            &self.material_buffer,
+            std::mem::offset_of!(MaterialUniform, skins) as u64,
+            bytemuck::cast_slice(skins),
+        );
+    }
+}
+
+pub trait DrawModel<'a> {
+    // binds the arena's buffers, once before any of the draws below
+    fn set_mesh_arena(&mut self, arena: &'a MeshArena);
+    #[allow(dead_code)]
+    fn draw_mesh(
+        &mut self,
+        mesh: &'a Mesh,
+        material: &'a Material,
+        camera_bind_group: &'a wgpu::BindGroup,
+    );
+    fn draw_mesh_instanced(
+        &mut self,
+        mesh: &'a Mesh,
+        material: &'a Material,
+        instances: Range<u32>,
+        camera_bind_group: &'a wgpu::BindGroup,
+    );
+    // every mesh with its own material, at `lod` (0 is full detail)
+    fn draw_model_instanced(
+        &mut self,
+        model: &'a Model,
+        lod: usize,
+        instances: Range<u32>,
+        camera_bind_group: &'a wgpu::BindGroup,
+    );
+}
+
+// render passes no longer borrow what gets bound to them, so the resources
+// don't have to outlive the pass
+impl<'b> DrawModel<'b> for wgpu::RenderPass<'_> {
+    fn set_mesh_arena(&mut self, arena: &'b MeshArena) {
+        self.set_vertex_buffer(0, arena.vertex_buffer.slice(..));
+        self.set_index_buffer(arena.index_buffer.slice(..), arena.index_format);
+    }
+
+    fn draw_mesh(
+        &mut self,
+        mesh: &'b Mesh,
+        material: &'b Material,
+        camera_bind_group: &'b wgpu::BindGroup,
+    ) {
+        self.draw_mesh_instanced(mesh, material, 0..1, camera_bind_group);
+    }
+
+    fn draw_mesh_instanced(
+        &mut self,
+        mesh: &'b Mesh,
+        material: &'b Material,
+        instances: Range<u32>,
+        camera_bind_group: &'b wgpu::BindGroup,
+    ) {
+        self.set_bind_group(0, &material.bind_group, &[]);
+        self.set_bind_group(1, camera_bind_group, &[]);
+        self.draw_indexed(mesh.indices(), mesh.base_vertex, instances);
+    }
+
+    fn draw_model_instanced(
+        &mut self,
+        model: &'b Model,
+        lod: usize,
+        instances: Range<u32>,
+        camera_bind_group: &'b wgpu::BindGroup,
+    ) {
+        for mesh in &model.meshes {
+            self.set_bind_group(0, &model.materials[mesh.material].bind_group, &[]);
+            self.set_bind_group(1, camera_bind_group, &[]);
+            self.draw_indexed(mesh.lod(lod), mesh.base_vertex, instances.clone());
+        }
+    }
+}
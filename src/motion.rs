@@ -0,0 +1,328 @@
+use crate::arena::MeshArena;
+use crate::math::Mat4;
+use crate::model::{DrawModel, Mesh};
+use crate::texture::Texture;
+use crate::vertex::{ModelFormat, Vertex};
+use crate::InstanceRaw;
+use std::time::Instant;
+
+const VELOCITY_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg16Float;
+const SAMPLES: f32 = 8.0;
+// below this the streaks get long enough to look like a bug
+const MIN_FPS: f32 = 30.0;
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Vertex)]
+#[vertex(instance)]
+struct PreviousRaw {
+    // a column per location, 11 to 14
+    #[vertex(location = 11)]
+    model: [[f32; 4]; 4],
+}
+
+// Per pixel motion blur. A velocity pass draws the cubes again with this
+// frame's and last frame's model and view matrices and keeps how far each
+// pixel moved, then the post effect smears the frame along that. Only the
+// cubes move, so everything else has no velocity and stays sharp.
+pub struct MotionBlur {
+    // degrees the shutter is open for out of 360, 0 is off. Set from the options.
+    pub shutter_angle: f32,
+    // EMA of the frame rate, blur switches itself off under MIN_FPS
+    fps: f32,
+    last_frame: Option<Instant>,
+    previous_view_proj: Option<Mat4>,
+    previous_models: Vec<[[f32; 4]; 4]>,
+    pub velocity: Texture,
+    previous_buffer: wgpu::Buffer,
+    motion_buffer: wgpu::Buffer,
+    blur_buffer: wgpu::Buffer,
+    motion_bind_group: wgpu::BindGroup,
+    blur_layout: wgpu::BindGroupLayout,
+    blur_bind_group: wgpu::BindGroup,
+    velocity_pipeline: wgpu::RenderPipeline,
+    blur_pipeline: wgpu::RenderPipeline,
+}
+
+impl MotionBlur {
+    pub fn new(
+        device: &wgpu::Device,
+        post: &crate::post::Post,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        model_format: ModelFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let velocity =
+            Texture::create_render_target(device, width, height, VELOCITY_FORMAT, "velocity");
+        let previous_buffer = Self::create_previous_buffer(device, 1);
+        let motion_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("motion uniform buffer"),
+            size: std::mem::size_of::<[[f32; 4]; 4]>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let blur_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("blur uniform buffer"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let motion_layout = crate::reflect::bind_group_layout(
+            device,
+            "motion bind group layout",
+            &[("velocity.wgsl", 1)],
+        )
+        .unwrap();
+        let motion_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &motion_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: motion_buffer.as_entire_binding(),
+            }],
+            label: Some("motion bind group"),
+        });
+        let blur_layout = crate::reflect::bind_group_layout(
+            device,
+            "blur bind group layout",
+            &[("motion_blur.wgsl", 1)],
+        )
+        .unwrap();
+        let blur_bind_group =
+            Self::create_blur_bind_group(device, &blur_layout, &blur_buffer, &velocity);
+
+        let velocity_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("velocity shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                crate::shaders::load("velocity.wgsl", &[]).unwrap().into(),
+            ),
+        });
+        let velocity_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("velocity pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &motion_layout],
+            push_constant_ranges: &[],
+        });
+        let velocity_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("velocity pipeline"),
+            layout: Some(&velocity_layout),
+            vertex: wgpu::VertexState {
+                module: &velocity_shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[
+                    model_format.desc(),
+                    InstanceRaw::desc(),
+                    PreviousRaw::desc(),
+                ],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &velocity_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: VELOCITY_FORMAT,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: crate::pipeline_cache::current().as_deref(),
+        });
+
+        let blur_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("motion blur shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                crate::shaders::load("motion_blur.wgsl", post.defines())
+                    .unwrap()
+                    .into(),
+            ),
+        });
+        let blur_pipeline = post.pipeline(
+            device,
+            "motion blur pipeline",
+            &blur_shader,
+            &[&blur_layout],
+        );
+
+        Self {
+            shutter_angle: 0.0,
+            fps: 60.0,
+            last_frame: None,
+            previous_view_proj: None,
+            previous_models: Vec::new(),
+            velocity,
+            previous_buffer,
+            motion_buffer,
+            blur_buffer,
+            motion_bind_group,
+            blur_layout,
+            blur_bind_group,
+            velocity_pipeline,
+            blur_pipeline,
+        }
+    }
+
+    fn create_blur_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        blur_buffer: &wgpu::Buffer,
+        velocity: &Texture,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: blur_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&velocity.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&velocity.sampler),
+                },
+            ],
+            label: Some("blur bind group"),
+        })
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.velocity =
+            Texture::create_render_target(device, width, height, VELOCITY_FORMAT, "velocity");
+        self.blur_bind_group = Self::create_blur_bind_group(
+            device,
+            &self.blur_layout,
+            &self.blur_buffer,
+            &self.velocity,
+        );
+    }
+
+    fn create_previous_buffer(device: &wgpu::Device, instances: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("previous instance buffer"),
+            size: (instances.max(1) * std::mem::size_of::<PreviousRaw>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    // off because of the frame rate rather than the setting
+    pub fn too_slow(&self) -> bool {
+        self.fps < MIN_FPS
+    }
+
+    // once a frame, before the velocity pass. A cube that's new since last
+    // frame counts as standing still.
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view_proj: Mat4,
+        instances: &[crate::Instance],
+    ) {
+        let now = Instant::now();
+        if let Some(last) = self.last_frame {
+            let fps = 1.0 / (now - last).as_secs_f32().max(1e-4);
+            self.fps = self.fps * 0.9 + fps * 0.1;
+        }
+        self.last_frame = Some(now);
+        if !crate::post::Effect::enabled(self) {
+            // starts over from standing still when it's back on
+            self.previous_view_proj = None;
+            self.previous_models.clear();
+            return;
+        }
+
+        let models = instances
+            .iter()
+            .map(|instance| instance.to_raw().model)
+            .collect::<Vec<_>>();
+        let previous_view_proj = self.previous_view_proj.unwrap_or(view_proj);
+        self.previous_view_proj = Some(view_proj);
+        let previous = models
+            .iter()
+            .enumerate()
+            .map(|(i, model)| PreviousRaw {
+                model: self.previous_models.get(i).copied().unwrap_or(*model),
+            })
+            .collect::<Vec<_>>();
+        self.previous_models = models;
+
+        if self.previous_buffer.size() < std::mem::size_of_val(&previous[..]) as u64 {
+            self.previous_buffer = Self::create_previous_buffer(device, previous.len() * 2);
+        }
+        queue.write_buffer(&self.previous_buffer, 0, bytemuck::cast_slice(&previous));
+        queue.write_buffer(
+            &self.motion_buffer,
+            0,
+            bytemuck::cast_slice(&crate::math::mat4_array(previous_view_proj)),
+        );
+        queue.write_buffer(
+            &self.blur_buffer,
+            0,
+            bytemuck::cast_slice(&[self.shutter_angle / 360.0, SAMPLES, 0.0, 0.0]),
+        );
+    }
+
+    pub fn render_velocity(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        camera_bind_group: &wgpu::BindGroup,
+        arena: &MeshArena,
+        meshes: &[Mesh],
+        instance_buffer: &wgpu::Buffer,
+        instances: u32,
+    ) {
+        if !crate::post::Effect::enabled(self) {
+            return;
+        }
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("velocity pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.velocity.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.velocity_pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.motion_bind_group, &[]);
+        render_pass.set_mesh_arena(arena);
+        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+        render_pass.set_vertex_buffer(2, self.previous_buffer.slice(..));
+        for mesh in meshes {
+            render_pass.draw_indexed(mesh.indices(), mesh.base_vertex, 0..instances);
+        }
+    }
+}
+
+impl crate::post::Effect for MotionBlur {
+    fn enabled(&self) -> bool {
+        self.shutter_angle > 0.0 && !self.too_slow()
+    }
+
+    fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_pipeline(&self.blur_pipeline);
+        render_pass.set_bind_group(1, &self.blur_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,26 @@
+#include "post.wgsl"
+
+struct Blur {
+    // the share of a frame's motion the shutter was open for
+    shutter: f32,
+    samples: f32,
+}
+@group(1) @binding(0)
+var<uniform> blur: Blur;
+@group(1) @binding(1)
+var t_velocity: texture_2d<f32>;
+@group(1) @binding(2)
+var s_velocity: sampler;
+
+@fragment
+fn fs_main(in: PostOutput) -> @location(0) vec4<f32> {
+    let velocity = textureSample(t_velocity, s_velocity, in.uv).xy * blur.shutter;
+    var color = vec4<f32>(0.0);
+    let samples = i32(blur.samples);
+    // centred on the pixel, half the streak either side
+    for (var i = 0; i < samples; i++) {
+        let t = (f32(i) + 0.5) / f32(samples) - 0.5;
+        color += textureSample(t_input, s_input, in.uv - velocity * t);
+    }
+    return color / f32(samples);
+}
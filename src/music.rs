@@ -0,0 +1,169 @@
+use crate::audio::{self, SAMPLE_RATE};
+
+const TEMPO: f32 = 96.0;
+const BEATS: usize = 8;
+// seconds a stem takes to fade all the way in or out
+const CROSSFADE: f32 = 2.0;
+// how far either side of its threshold a stem is on its way in
+const SPREAD: f32 = 0.1;
+const VOLUME: f32 = 0.25;
+
+// one loop of the music, playing once intensity passes `threshold`
+struct Stem {
+    samples: Vec<f32>,
+    threshold: f32,
+    gain: f32,
+}
+
+// Music in layers that come in as things get busier. Every stem is a loop
+// of the same length, all playing in step, and each one fades in over a
+// couple of seconds once the intensity passes its threshold and out again
+// once it drops back under: a drone on its own when it's quiet, then a pad,
+// an arpeggio and drums on top. Made up like the sound effects, and mixed
+// on the audio thread.
+pub struct Music {
+    stems: Vec<Stem>,
+    cursor: usize,
+    pub intensity: f32,
+    pub enabled: bool,
+}
+
+impl Music {
+    pub fn new() -> Self {
+        let length = (BEATS as f32 * 60.0 / TEMPO * SAMPLE_RATE as f32) as usize;
+        let stem = |threshold, samples| Stem {
+            samples,
+            threshold,
+            gain: 0.0,
+        };
+        Self {
+            stems: vec![
+                stem(0.0, drone(length)),
+                stem(0.25, pad(length)),
+                stem(0.5, arpeggio(length)),
+                stem(0.75, drums(length)),
+            ],
+            cursor: 0,
+            intensity: 0.0,
+            enabled: true,
+        }
+    }
+
+    // adds the next `block.len()` frames, moving each stem's gain towards
+    // where the intensity wants it
+    pub fn mix(&mut self, block: &mut [[f32; 2]]) {
+        let length = self.stems[0].samples.len();
+        let frames = block.len();
+        let fade = frames as f32 / SAMPLE_RATE as f32 / CROSSFADE;
+        for stem in &mut self.stems {
+            let wanted = if self.enabled {
+                ((self.intensity - stem.threshold) / SPREAD * 0.5 + 0.5).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let from = stem.gain;
+            stem.gain += (wanted - stem.gain).clamp(-fade, fade);
+            if from == 0.0 && stem.gain == 0.0 {
+                continue;
+            }
+            for (i, frame) in block.iter_mut().enumerate() {
+                let t = i as f32 / frames as f32;
+                let gain = (from + (stem.gain - from) * t) * VOLUME;
+                let sample = stem.samples[(self.cursor + i) % length] * gain;
+                frame[0] += sample;
+                frame[1] += sample;
+            }
+        }
+        self.cursor = (self.cursor + frames) % length;
+    }
+}
+
+// the nearest frequency to `hz` that goes round a whole number of times in
+// the loop, so it doesn't click where the loop starts again
+fn looping(hz: f32, length: usize) -> f32 {
+    let seconds = length as f32 / SAMPLE_RATE as f32;
+    (hz * seconds).round() / seconds
+}
+
+fn sine(hz: f32, i: usize) -> f32 {
+    (std::f32::consts::TAU * hz * i as f32 / SAMPLE_RATE as f32).sin()
+}
+
+fn beat_length() -> usize {
+    (60.0 / TEMPO * SAMPLE_RATE as f32) as usize
+}
+
+// a low A and its fifth, swelling slowly once a loop
+fn drone(length: usize) -> Vec<f32> {
+    let (root, fifth) = (looping(55.0, length), looping(82.5, length));
+    let swell = looping(1.0 / (length as f32 / SAMPLE_RATE as f32), length);
+    (0..length)
+        .map(|i| {
+            let level = 0.75 - 0.25 * sine(swell, i);
+            (sine(root, i) * 0.6 + sine(fifth, i) * 0.4) * level
+        })
+        .collect()
+}
+
+// A minor, with a slow tremolo
+fn pad(length: usize) -> Vec<f32> {
+    let notes = [220.0, 261.63, 329.63].map(|hz| looping(hz, length));
+    let tremolo = looping(0.5, length);
+    (0..length)
+        .map(|i| {
+            let chord = notes.iter().map(|hz| sine(*hz, i)).sum::<f32>() / 3.0;
+            chord * (0.6 + 0.2 * sine(tremolo, i))
+        })
+        .collect()
+}
+
+// plucked eighth notes up the chord
+fn arpeggio(length: usize) -> Vec<f32> {
+    let notes = [440.0, 523.25, 659.25, 880.0];
+    let eighth = beat_length() / 2;
+    let mut out = vec![0.0; length];
+    for (n, start) in (0..length).step_by(eighth).enumerate() {
+        let pluck = audio::chirp(notes[n % notes.len()], notes[n % notes.len()], 0.25, 12.0);
+        for (i, sample) in pluck.iter().enumerate() {
+            out[(start + i) % length] += sample * 0.5;
+        }
+    }
+    out
+}
+
+// a kick on every beat and a hat between
+fn drums(length: usize) -> Vec<f32> {
+    let beat = beat_length();
+    let kick = audio::chirp(120.0, 40.0, 0.25, 14.0);
+    let hat = audio::burst(0.08, 60.0, 0.9, 7);
+    let mut out = vec![0.0; length];
+    for start in (0..length).step_by(beat) {
+        for (i, sample) in kick.iter().enumerate() {
+            out[(start + i) % length] += sample;
+        }
+        for (i, sample) in hat.iter().enumerate() {
+            out[(start + beat / 2 + i) % length] += sample * 0.3;
+        }
+    }
+    out
+}
+
+// enemies within this of the player make things busier
+const NEAR: f32 = 20.0;
+// as much as each one adds, up to 1
+const PER_ENEMY: f32 = 0.2;
+// as much as the player moving flat out adds
+const SPEED: f32 = 0.3;
+
+// how busy things are around the player, 0..1: how many enemy cubes are
+// near it and how fast it's going, `speed` as a share of its top speed
+pub fn intensity(cubes: &[crate::Instance], speed: f32) -> f32 {
+    let player = cubes[0].position;
+    let enemies = cubes[1..]
+        .iter()
+        .filter(|c| {
+            c.layer.intersects(crate::physics::Layers::ENEMY) && c.position.distance(player) < NEAR
+        })
+        .count();
+    (enemies as f32 * PER_ENEMY + speed.clamp(0.0, 1.0) * SPEED).min(1.0)
+}
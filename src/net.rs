@@ -0,0 +1,762 @@
+use crate::math::{Quat, Vec3, Vec4};
+use crate::physics::Layers;
+use crate::Instance;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+// what `--server` listens on and `--connect` goes to without a port
+pub const DEFAULT_PORT: u16 = 7878;
+// under a typical MTU, so nothing gets fragmented on the way
+pub const MAX_PACKET: usize = 1200;
+// how long one server step is, 60 a second
+pub const TICK: Duration = Duration::from_micros(16_667);
+// either end gives the other up after this long without a packet
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+// every packet starts with this, anything else on the port is ignored
+const MAGIC: [u8; 4] = *b"prx1";
+// how often a client asks to join until it's let in
+const HELLO_EVERY: Duration = Duration::from_secs(1);
+// more cubes than a snapshot can say the world has, the client makes room
+// for all of them so this is what keeps a bad server from asking for gigabytes
+pub const MAX_CUBES: u32 = crate::instance_buffer::MAX_INSTANCES as u32;
+
+// magic, kind, tick, yours, count and how many follow
+const SNAPSHOT_HEADER: usize = 4 + 1 + 4 + 4 + 4 + 2;
+// index, position, rotation, skin and layer
+const ENTRY: usize = 4 + 12 + 16 + 4 + 4;
+const PER_SNAPSHOT: usize = (MAX_PACKET - SNAPSHOT_HEADER) / ENTRY;
+
+// what sending `entries` cubes in snapshots costs, headers and all
+pub fn snapshot_bytes(entries: usize) -> usize {
+    entries * ENTRY + entries.div_ceil(PER_SNAPSHOT) * SNAPSHOT_HEADER
+}
+
+pub enum ToServer {
+    Hello { name: String },
+    // which way the player's cube is trying to go, flat and at most 1 long
+    Input { tick: u32, direction: Vec3 },
+    Bye,
+    Chat { text: String },
+    // in a rollback session, our last few inputs up to and including
+    // `tick`'s, so one going missing is made up for by the next
+    Inputs { tick: u32, directions: Vec<Vec3> },
+}
+
+pub enum ToClient {
+    // the cube that's yours, by the server's numbering
+    Welcome {
+        cube: u32,
+    },
+    // some of the cubes as of server tick `tick`, out of `count` altogether,
+    // and which is ours now (it moves down when someone before it leaves)
+    Snapshot {
+        tick: u32,
+        yours: u32,
+        count: u32,
+        cubes: Vec<(u32, Instance)>,
+    },
+    // everyone playing, by cube, for their name tags
+    Players {
+        names: Vec<(u32, String)>,
+    },
+    // a chat line going out to everyone, `from` empty for the server's notices
+    Chat {
+        from: String,
+        text: String,
+    },
+    // a rollback session's begun, we're player `slot` of `players`
+    Start {
+        slot: u32,
+        players: u32,
+        seed: u64,
+    },
+    // player `slot`'s inputs, the last of them for `tick`
+    Inputs {
+        slot: u32,
+        tick: u32,
+        directions: Vec<Vec3>,
+    },
+}
+
+impl ToServer {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        match self {
+            ToServer::Hello { name } => {
+                out.push(0);
+                put_string(&mut out, name);
+            }
+            ToServer::Input { tick, direction } => {
+                out.push(1);
+                put_u32(&mut out, *tick);
+                put_vec3(&mut out, *direction);
+            }
+            ToServer::Bye => out.push(2),
+            ToServer::Chat { text } => {
+                out.push(3);
+                put_string(&mut out, text);
+            }
+            ToServer::Inputs { tick, directions } => {
+                out.push(4);
+                put_u32(&mut out, *tick);
+                put_directions(&mut out, directions);
+            }
+        }
+        out
+    }
+
+    pub fn decode(packet: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = Reader::new(packet)?;
+        Ok(match reader.u8()? {
+            0 => ToServer::Hello {
+                name: reader.string()?,
+            },
+            1 => ToServer::Input {
+                tick: reader.u32()?,
+                direction: reader.vec3()?,
+            },
+            2 => ToServer::Bye,
+            3 => ToServer::Chat {
+                text: reader.string()?,
+            },
+            4 => ToServer::Inputs {
+                tick: reader.u32()?,
+                directions: reader.directions()?,
+            },
+            kind => anyhow::bail!("no client message {}", kind),
+        })
+    }
+}
+
+impl ToClient {
+    pub fn welcome(cube: usize) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(0);
+        put_u32(&mut out, cube as u32);
+        out
+    }
+
+    // as many as fit in a packet, which is plenty with names kept short
+    pub fn players(names: &[(usize, &str)]) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(2);
+        let fit = names
+            .iter()
+            .scan(out.len() + 1, |length, (_, name)| {
+                *length += 4 + 1 + name.len().min(255);
+                Some(*length)
+            })
+            .take_while(|length| *length <= MAX_PACKET)
+            .count()
+            .min(255);
+        out.push(fit as u8);
+        for (cube, name) in names.iter().take(fit) {
+            put_u32(&mut out, *cube as u32);
+            put_string(&mut out, name);
+        }
+        out
+    }
+
+    pub fn chat(from: &str, text: &str) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(3);
+        put_string(&mut out, from);
+        put_string(&mut out, text);
+        out
+    }
+
+    pub fn start(slot: usize, players: usize, seed: u64) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(4);
+        put_u32(&mut out, slot as u32);
+        put_u32(&mut out, players as u32);
+        out.extend(seed.to_le_bytes());
+        out
+    }
+
+    pub fn inputs(slot: usize, tick: u32, directions: &[Vec3]) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(5);
+        put_u32(&mut out, slot as u32);
+        put_u32(&mut out, tick);
+        put_directions(&mut out, directions);
+        out
+    }
+
+    // `chosen` out of `cubes`, split into as many packets as it takes
+    pub fn snapshots(
+        tick: u32,
+        yours: usize,
+        cubes: &[Instance],
+        chosen: &[usize],
+    ) -> Vec<Vec<u8>> {
+        chosen
+            .chunks(PER_SNAPSHOT)
+            .map(|chunk| {
+                let mut out = MAGIC.to_vec();
+                out.push(1);
+                put_u32(&mut out, tick);
+                put_u32(&mut out, yours as u32);
+                put_u32(&mut out, cubes.len() as u32);
+                out.extend((chunk.len() as u16).to_le_bytes());
+                for &i in chunk {
+                    let cube = &cubes[i];
+                    put_u32(&mut out, i as u32);
+                    put_vec3(&mut out, cube.position);
+                    for f in cube.rotation.to_array() {
+                        put_f32(&mut out, f);
+                    }
+                    put_u32(&mut out, cube.skin);
+                    put_u32(&mut out, cube.layer.bits());
+                }
+                out
+            })
+            .collect()
+    }
+
+    pub fn decode(packet: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = Reader::new(packet)?;
+        Ok(match reader.u8()? {
+            0 => ToClient::Welcome {
+                cube: reader.u32()?,
+            },
+            1 => {
+                let tick = reader.u32()?;
+                let yours = reader.u32()?;
+                let count = reader.u32()?;
+                if count > MAX_CUBES {
+                    anyhow::bail!("a snapshot of {} cubes, more than there can be", count);
+                }
+                let entries = u16::from_le_bytes(reader.bytes()?);
+                let mut cubes = Vec::with_capacity(entries as usize);
+                for _ in 0..entries {
+                    let index = reader.u32()?;
+                    let position = reader.vec3()?;
+                    // a zero one can't be normalized, it'd be NaN all through
+                    let rotation = Vec4::from_array([
+                        reader.f32()?,
+                        reader.f32()?,
+                        reader.f32()?,
+                        reader.f32()?,
+                    ])
+                    .try_normalize()
+                    .map_or(Quat::IDENTITY, Quat::from_vec4);
+                    cubes.push((
+                        index,
+                        Instance {
+                            position,
+                            rotation,
+                            flash: crate::NO_FLASH,
+                            skin: reader.u32()?,
+                            layer: Layers::from_bits(reader.u32()?),
+                        },
+                    ));
+                }
+                ToClient::Snapshot {
+                    tick,
+                    yours,
+                    count,
+                    cubes,
+                }
+            }
+            2 => {
+                let count = reader.u8()?;
+                let mut names = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    names.push((reader.u32()?, reader.string()?));
+                }
+                ToClient::Players { names }
+            }
+            3 => ToClient::Chat {
+                from: reader.string()?,
+                text: reader.string()?,
+            },
+            4 => ToClient::Start {
+                slot: reader.u32()?,
+                players: reader.u32()?,
+                seed: u64::from_le_bytes(reader.bytes()?),
+            },
+            5 => ToClient::Inputs {
+                slot: reader.u32()?,
+                tick: reader.u32()?,
+                directions: reader.directions()?,
+            },
+            kind => anyhow::bail!("no server message {}", kind),
+        })
+    }
+}
+
+// `host`, `host:port` or just a port on `default_host`
+pub fn address(given: &str, default_host: &str) -> String {
+    if given.contains(':') {
+        given.to_string()
+    } else if given.parse::<u16>().is_ok() {
+        format!("{}:{}", default_host, given)
+    } else {
+        format!("{}:{}", given, DEFAULT_PORT)
+    }
+}
+
+// The server numbers cubes in the order they came, so a client's own is
+// anywhere, but a client wants it at 0 where the player always is. The two
+// just swap places.
+pub fn local(index: usize, yours: usize) -> usize {
+    match index {
+        i if i == yours => 0,
+        0 => yours,
+        i => i,
+    }
+}
+
+// The joining end, for `--connect <address>`. It keeps saying hello until
+// the server welcomes it, then sends its input every frame and takes in
+// whatever snapshots have arrived.
+pub struct Client {
+    socket: UdpSocket,
+    name: String,
+    // the server's index for our cube, once we're in
+    pub cube: Option<usize>,
+    // everyone's cube and name by the server's numbering, us included
+    pub players: Vec<(usize, String)>,
+    // the newest snapshot tick seen, older ones arriving late are dropped
+    tick: u32,
+    input_tick: u32,
+    last_heard: Instant,
+    last_hello: Option<Instant>,
+    buffer: Vec<u8>,
+}
+
+impl Client {
+    // `--connect <address>`, going by `--name <name>` or the login name
+    pub fn from_args() -> Option<Self> {
+        let args = std::env::args().collect::<Vec<_>>();
+        let value = |flag: &str| {
+            args.iter()
+                .position(|a| a == flag)
+                .and_then(|i| args.get(i + 1))
+        };
+        let address = address(value("--connect")?, "127.0.0.1");
+        let name = value("--name")
+            .cloned()
+            .or_else(|| std::env::var("USER").ok())
+            .unwrap_or_else(|| "player".into());
+        match Self::connect(&address, &name) {
+            Ok(client) => {
+                log::info!("joining {} as {}", address, name);
+                Some(client)
+            }
+            Err(e) => {
+                log::error!("couldn't connect to {}: {:#}", address, e);
+                None
+            }
+        }
+    }
+
+    pub fn connect(address: &str, name: &str) -> anyhow::Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(address)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            name: name.to_string(),
+            cube: None,
+            players: Vec::new(),
+            tick: 0,
+            input_tick: 0,
+            last_heard: Instant::now(),
+            last_hello: None,
+            buffer: vec![0; MAX_PACKET],
+        })
+    }
+
+    pub fn send_input(&mut self, direction: Vec3) {
+        if self.cube.is_none() {
+            return;
+        }
+        self.input_tick += 1;
+        self.send(ToServer::Input {
+            tick: self.input_tick,
+            direction,
+        });
+    }
+
+    // everything that's come in since the last poll
+    pub fn poll(&mut self) -> Vec<ToClient> {
+        if self.cube.is_none() && self.last_hello.is_none_or(|at| at.elapsed() > HELLO_EVERY) {
+            self.send(ToServer::Hello {
+                name: self.name.clone(),
+            });
+            self.last_hello = Some(Instant::now());
+        }
+        let mut received = Vec::new();
+        while let Ok(length) = self.socket.recv(&mut self.buffer) {
+            match ToClient::decode(&self.buffer[..length]) {
+                Ok(ToClient::Snapshot { tick, .. }) if tick < self.tick => {}
+                Ok(message) => {
+                    match &message {
+                        ToClient::Welcome { cube } => {
+                            if self.cube.is_none() {
+                                log::info!("joined as cube {}", cube);
+                            }
+                            self.cube = Some(*cube as usize);
+                        }
+                        ToClient::Snapshot { tick, yours, .. } => {
+                            self.tick = *tick;
+                            self.cube = Some(*yours as usize);
+                        }
+                        ToClient::Players { names } => {
+                            self.players = names
+                                .iter()
+                                .map(|(cube, name)| (*cube as usize, name.clone()))
+                                .collect();
+                        }
+                        ToClient::Start { slot, .. } => {
+                            // players' cubes come after the marker at 0
+                            self.cube = Some(*slot as usize + 1);
+                        }
+                        ToClient::Chat { .. } | ToClient::Inputs { .. } => {}
+                    }
+                    self.last_heard = Instant::now();
+                    received.push(message);
+                }
+                Err(e) => log::warn!("ignoring a packet from the server: {:#}", e),
+            }
+        }
+        if self.cube.is_some() && self.last_heard.elapsed() > TIMEOUT {
+            log::warn!("nothing from the server for {:?}, joining again", TIMEOUT);
+            self.cube = None;
+            self.tick = 0;
+            self.last_heard = Instant::now();
+        }
+        received
+    }
+
+    pub fn send_inputs(&self, tick: u32, directions: &[Vec3]) {
+        self.send(ToServer::Inputs {
+            tick,
+            directions: directions.to_vec(),
+        });
+    }
+
+    pub fn say(&self, text: &str) {
+        self.send(ToServer::Chat {
+            text: text.to_string(),
+        });
+    }
+
+    fn send(&self, message: ToServer) {
+        // the server not being up yet is fine, hello goes again in a second
+        let _ = self.socket.send(&message.encode());
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.send(ToServer::Bye);
+    }
+}
+
+struct Reader<'a> {
+    packet: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(packet: &'a [u8]) -> anyhow::Result<Self> {
+        if !packet.starts_with(&MAGIC) {
+            anyhow::bail!("not a praxis packet");
+        }
+        Ok(Self {
+            packet,
+            at: MAGIC.len(),
+        })
+    }
+
+    fn bytes<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        let bytes = self
+            .packet
+            .get(self.at..self.at + N)
+            .ok_or_else(|| anyhow::anyhow!("the packet runs out early"))?;
+        self.at += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.bytes::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes()?))
+    }
+
+    fn f32(&mut self) -> anyhow::Result<f32> {
+        let f = f32::from_le_bytes(self.bytes()?);
+        if !f.is_finite() {
+            anyhow::bail!("a number that isn't one");
+        }
+        Ok(f)
+    }
+
+    fn vec3(&mut self) -> anyhow::Result<Vec3> {
+        Ok(Vec3::new(self.f32()?, self.f32()?, self.f32()?))
+    }
+
+    fn directions(&mut self) -> anyhow::Result<Vec<Vec3>> {
+        let count = self.u8()?;
+        (0..count).map(|_| self.vec3()).collect()
+    }
+
+    fn string(&mut self) -> anyhow::Result<String> {
+        let length = self.u8()? as usize;
+        let bytes = self
+            .packet
+            .get(self.at..self.at + length)
+            .ok_or_else(|| anyhow::anyhow!("a string runs off the end"))?;
+        self.at += length;
+        Ok(std::str::from_utf8(bytes)?.to_string())
+    }
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend(value.to_le_bytes());
+}
+
+fn put_f32(out: &mut Vec<u8>, value: f32) {
+    out.extend(value.to_le_bytes());
+}
+
+fn put_vec3(out: &mut Vec<u8>, value: Vec3) {
+    for f in value.to_array() {
+        put_f32(out, f);
+    }
+}
+
+// a count byte then the vectors, at most 255 of them
+fn put_directions(out: &mut Vec<u8>, directions: &[Vec3]) {
+    let directions = &directions[..directions.len().min(255)];
+    out.push(directions.len() as u8);
+    for direction in directions {
+        put_vec3(out, *direction);
+    }
+}
+
+// a length byte then the text, cut short at 255 bytes
+fn put_string(out: &mut Vec<u8>, text: &str) {
+    let mut end = text.len().min(255);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    out.push(end as u8);
+    out.extend(&text.as_bytes()[..end]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube(x: f32) -> Instance {
+        Instance {
+            position: Vec3::new(x, 1.0, -2.0),
+            rotation: Quat::from_rotation_y(x),
+            flash: crate::NO_FLASH,
+            skin: 3,
+            layer: Layers::STATIC,
+        }
+    }
+
+    #[test]
+    fn client_messages_come_back_as_sent() {
+        let direction = Vec3::new(0.6, 0.0, -0.8);
+        let sent = [
+            ToServer::Hello { name: "ada".into() },
+            ToServer::Input {
+                tick: 42,
+                direction,
+            },
+            ToServer::Bye,
+            ToServer::Chat {
+                text: "hi there".into(),
+            },
+            ToServer::Inputs {
+                tick: 9,
+                directions: vec![direction, Vec3::ZERO],
+            },
+        ];
+        for message in &sent {
+            let back = ToServer::decode(&message.encode()).unwrap();
+            match (message, back) {
+                (ToServer::Hello { name }, ToServer::Hello { name: back }) => {
+                    assert_eq!(*name, back)
+                }
+                (
+                    ToServer::Input { tick, direction },
+                    ToServer::Input {
+                        tick: back_tick,
+                        direction: back_direction,
+                    },
+                ) => {
+                    assert_eq!(*tick, back_tick);
+                    assert_eq!(*direction, back_direction);
+                }
+                (ToServer::Bye, ToServer::Bye) => {}
+                (ToServer::Chat { text }, ToServer::Chat { text: back }) => assert_eq!(*text, back),
+                (
+                    ToServer::Inputs { tick, directions },
+                    ToServer::Inputs {
+                        tick: back_tick,
+                        directions: back_directions,
+                    },
+                ) => {
+                    assert_eq!(*tick, back_tick);
+                    assert_eq!(*directions, back_directions);
+                }
+                _ => panic!("came back as a different message"),
+            }
+        }
+    }
+
+    #[test]
+    fn server_messages_come_back_as_sent() {
+        let Ok(ToClient::Welcome { cube: 7 }) = ToClient::decode(&ToClient::welcome(7)) else {
+            panic!("welcome");
+        };
+        let Ok(ToClient::Players { names }) =
+            ToClient::decode(&ToClient::players(&[(0, "ada"), (4, "bo")]))
+        else {
+            panic!("players");
+        };
+        assert_eq!(names, vec![(0, "ada".to_string()), (4, "bo".to_string())]);
+        let Ok(ToClient::Chat { from, text }) = ToClient::decode(&ToClient::chat("", "bo left"))
+        else {
+            panic!("chat");
+        };
+        assert_eq!((from.as_str(), text.as_str()), ("", "bo left"));
+        let Ok(ToClient::Start {
+            slot: 1,
+            players: 2,
+            seed: 99,
+        }) = ToClient::decode(&ToClient::start(1, 2, 99))
+        else {
+            panic!("start");
+        };
+        let Ok(ToClient::Inputs {
+            slot: 1,
+            tick: 5,
+            directions,
+        }) = ToClient::decode(&ToClient::inputs(1, 5, &[Vec3::X]))
+        else {
+            panic!("inputs");
+        };
+        assert_eq!(directions, vec![Vec3::X]);
+    }
+
+    #[test]
+    fn snapshots_split_and_come_back() {
+        let cubes = (0..PER_SNAPSHOT + 5)
+            .map(|i| cube(i as f32))
+            .collect::<Vec<_>>();
+        let chosen = (0..cubes.len()).collect::<Vec<_>>();
+        let packets = ToClient::snapshots(12, 3, &cubes, &chosen);
+        assert_eq!(packets.len(), 2);
+        assert_eq!(
+            packets.iter().map(Vec::len).sum::<usize>(),
+            snapshot_bytes(cubes.len())
+        );
+        let mut seen = 0;
+        for packet in &packets {
+            assert!(packet.len() <= MAX_PACKET);
+            let Ok(ToClient::Snapshot {
+                tick: 12,
+                yours: 3,
+                count,
+                cubes: got,
+            }) = ToClient::decode(packet)
+            else {
+                panic!("snapshot");
+            };
+            assert_eq!(count as usize, cubes.len());
+            for (index, instance) in got {
+                let sent = &cubes[index as usize];
+                assert_eq!(instance.position, sent.position);
+                assert!(instance.rotation.abs_diff_eq(sent.rotation, 1e-6));
+                assert_eq!(instance.skin, sent.skin);
+                assert_eq!(instance.layer, sent.layer);
+                seen += 1;
+            }
+        }
+        assert_eq!(seen, cubes.len());
+    }
+
+    #[test]
+    fn malformed_packets_are_errors() {
+        let hello = ToServer::Hello { name: "ada".into() }.encode();
+        // every packet cut short, other than where it happens to end
+        for end in 0..hello.len() {
+            assert!(ToServer::decode(&hello[..end]).is_err());
+        }
+        let snapshot = ToClient::snapshots(1, 0, &[cube(1.0)], &[0]).remove(0);
+        for end in 0..snapshot.len() {
+            assert!(ToClient::decode(&snapshot[..end]).is_err());
+        }
+
+        // wrong magic, unknown kinds
+        assert!(ToServer::decode(b"nope\x00\x00").is_err());
+        assert!(ToServer::decode(&[&MAGIC[..], &[200]].concat()).is_err());
+        assert!(ToClient::decode(&[&MAGIC[..], &[200]].concat()).is_err());
+
+        // a NaN direction
+        let mut input = ToServer::Input {
+            tick: 1,
+            direction: Vec3::ZERO,
+        }
+        .encode();
+        let at = input.len() - 4;
+        input[at..].copy_from_slice(&f32::NAN.to_le_bytes());
+        assert!(ToServer::decode(&input).is_err());
+
+        // a string that isn't UTF-8
+        let mut chat = ToServer::Chat { text: "ab".into() }.encode();
+        let at = chat.len() - 1;
+        chat[at] = 0xff;
+        assert!(ToServer::decode(&chat).is_err());
+
+        // a snapshot claiming more cubes than there can be
+        let mut huge = ToClient::snapshots(1, 0, &[cube(1.0)], &[0]).remove(0);
+        let count = MAGIC.len() + 1 + 4 + 4;
+        huge[count..count + 4].copy_from_slice(&(MAX_CUBES + 1).to_le_bytes());
+        assert!(ToClient::decode(&huge).is_err());
+    }
+
+    #[test]
+    fn a_zero_rotation_comes_back_as_identity() {
+        let mut packet = ToClient::snapshots(1, 0, &[cube(1.0)], &[0]).remove(0);
+        let rotation = SNAPSHOT_HEADER + 4 + 12;
+        packet[rotation..rotation + 16].fill(0);
+        let Ok(ToClient::Snapshot { cubes, .. }) = ToClient::decode(&packet) else {
+            panic!("snapshot");
+        };
+        assert_eq!(cubes[0].1.rotation, Quat::IDENTITY);
+    }
+
+    #[test]
+    fn long_strings_are_cut_on_a_char_boundary() {
+        let name = "é".repeat(200);
+        let Ok(ToServer::Hello { name: back }) =
+            ToServer::decode(&ToServer::Hello { name: name.clone() }.encode())
+        else {
+            panic!("hello");
+        };
+        assert_eq!(back.len(), 254);
+        assert!(name.starts_with(&back));
+    }
+
+    #[test]
+    fn addresses() {
+        assert_eq!(address("1.2.3.4:5", "x"), "1.2.3.4:5");
+        assert_eq!(address("9000", "0.0.0.0"), "0.0.0.0:9000");
+        assert_eq!(
+            address("example.org", "x"),
+            format!("example.org:{}", DEFAULT_PORT)
+        );
+    }
+}
@@ -0,0 +1,93 @@
+// Gradient noise for the CPU, with a WGSL twin in noise.wgsl. Both use the
+// same hash and math so a seed gives the same field on either side.
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Noise {
+    pub seed: u32,
+}
+
+impl Noise {
+    // loads a shader that includes noise.wgsl with this seed
+    pub fn shader(&self, name: &str) -> anyhow::Result<String> {
+        crate::shaders::load(name, &[&format!("NOISE_SEED {}u", self.seed)])
+    }
+
+    fn gradient(&self, x: i32, y: i32) -> (f32, f32) {
+        let h = hash(x as u32 ^ hash(y as u32 ^ hash(self.seed)));
+        (
+            if h & 1 != 0 { 1.0 } else { -1.0 },
+            if h & 2 != 0 { 1.0 } else { -1.0 },
+        )
+    }
+
+    fn dot_gradient(&self, x: i32, y: i32, dx: f32, dy: f32) -> f32 {
+        let (gx, gy) = self.gradient(x, y);
+        gx * dx + gy * dy
+    }
+
+    // roughly -1..1
+    pub fn perlin(&self, x: f32, y: f32) -> f32 {
+        let (cx, cy) = (x.floor(), y.floor());
+        let (fx, fy) = (x - cx, y - cy);
+        let (ix, iy) = (cx as i32, cy as i32);
+        let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let (ux, uy) = (fade(fx), fade(fy));
+        let a = self.dot_gradient(ix, iy, fx, fy);
+        let b = self.dot_gradient(ix + 1, iy, fx - 1.0, fy);
+        let c = self.dot_gradient(ix, iy + 1, fx, fy - 1.0);
+        let d = self.dot_gradient(ix + 1, iy + 1, fx - 1.0, fy - 1.0);
+        lerp(lerp(a, b, ux), lerp(c, d, ux), uy)
+    }
+
+    // roughly -1..1
+    pub fn simplex(&self, x: f32, y: f32) -> f32 {
+        const F2: f32 = 0.366_025_4;
+        const G2: f32 = 0.211_324_87;
+        let s = (x + y) * F2;
+        let (cx, cy) = ((x + s).floor(), (y + s).floor());
+        let (ix, iy) = (cx as i32, cy as i32);
+        let t = (cx + cy) * G2;
+        let (x0, y0) = (x - (cx - t), y - (cy - t));
+        let (sx, sy) = if x0 > y0 { (1, 0) } else { (0, 1) };
+        let (x1, y1) = (x0 - sx as f32 + G2, y0 - sy as f32 + G2);
+        let (x2, y2) = (x0 - 1.0 + 2.0 * G2, y0 - 1.0 + 2.0 * G2);
+
+        let corner = |ix, iy, dx: f32, dy: f32| {
+            let t = 0.5 - dx * dx - dy * dy;
+            if t <= 0.0 {
+                0.0
+            } else {
+                t * t * t * t * self.dot_gradient(ix, iy, dx, dy)
+            }
+        };
+        let n = corner(ix, iy, x0, y0)
+            + corner(ix + sx, iy + sy, x1, y1)
+            + corner(ix + 1, iy + 1, x2, y2);
+        n * 45.0
+    }
+
+    // octaves of perlin noise, each twice the frequency and half the amplitude
+    pub fn fbm(&self, x: f32, y: f32, octaves: u32) -> f32 {
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut range = 0.0;
+        for _ in 0..octaves {
+            total += self.perlin(x * frequency, y * frequency) * amplitude;
+            range += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        total / range
+    }
+}
+
+fn hash(x: u32) -> u32 {
+    let state = x.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
@@ -0,0 +1,70 @@
+// Gradient noise, kept in step with noise.rs so the CPU and GPU agree on the
+// same field. NOISE_SEED is defined by noise::shader.
+
+#ifndef NOISE_SEED
+#define NOISE_SEED 0u
+#endif
+
+fn noise_hash(x: u32) -> u32 {
+    let state = x * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// one of the four diagonals, picked by hashing the lattice point
+fn noise_gradient(cell: vec2<i32>) -> vec2<f32> {
+    let h = noise_hash(bitcast<u32>(cell.x) ^ noise_hash(bitcast<u32>(cell.y) ^ noise_hash(NOISE_SEED)));
+    return vec2<f32>(select(-1.0, 1.0, (h & 1u) != 0u), select(-1.0, 1.0, (h & 2u) != 0u));
+}
+
+// roughly -1..1
+fn noise_perlin(p: vec2<f32>) -> f32 {
+    let cell = floor(p);
+    let f = p - cell;
+    let i = vec2<i32>(cell);
+    let u = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+    let a = dot(noise_gradient(i), f);
+    let b = dot(noise_gradient(i + vec2<i32>(1, 0)), f - vec2<f32>(1.0, 0.0));
+    let c = dot(noise_gradient(i + vec2<i32>(0, 1)), f - vec2<f32>(0.0, 1.0));
+    let d = dot(noise_gradient(i + vec2<i32>(1, 1)), f - vec2<f32>(1.0, 1.0));
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
+
+fn noise_simplex_corner(cell: vec2<i32>, offset: vec2<f32>) -> f32 {
+    let t = 0.5 - dot(offset, offset);
+    if t <= 0.0 {
+        return 0.0;
+    }
+    return t * t * t * t * dot(noise_gradient(cell), offset);
+}
+
+// roughly -1..1
+fn noise_simplex(p: vec2<f32>) -> f32 {
+    let F2 = 0.36602540;
+    let G2 = 0.21132487;
+    let cell = floor(p + (p.x + p.y) * F2);
+    let i = vec2<i32>(cell);
+    let x0 = p - (cell - (cell.x + cell.y) * G2);
+    let step = select(vec2<i32>(0, 1), vec2<i32>(1, 0), x0.x > x0.y);
+    let x1 = x0 - vec2<f32>(step) + G2;
+    let x2 = x0 - 1.0 + 2.0 * G2;
+    let n = noise_simplex_corner(i, x0)
+        + noise_simplex_corner(i + step, x1)
+        + noise_simplex_corner(i + vec2<i32>(1, 1), x2);
+    return n * 45.0;
+}
+
+// octaves of perlin noise, each twice the frequency and half the amplitude
+fn noise_fbm(p: vec2<f32>, octaves: u32) -> f32 {
+    var total = 0.0;
+    var amplitude = 1.0;
+    var frequency = 1.0;
+    var range = 0.0;
+    for (var i = 0u; i < octaves; i++) {
+        total += noise_perlin(p * frequency) * amplitude;
+        range += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    return total / range;
+}
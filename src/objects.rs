@@ -0,0 +1,198 @@
+// every shader that reads current_object() from common.wgsl, and its group
+pub const BIND_GROUP_USES: &[(&str, u32)] = &[("portal.wgsl", 2)];
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ObjectUniform {
+    pub model: [[f32; 4]; 4],
+    pub tint: [f32; 4],
+}
+
+// what the push constants hold, Draw in common.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct DrawConstants {
+    object: u32,
+    // free for per draw switches, nothing sets any yet
+    flags: u32,
+}
+
+const PUSH_CONSTANT_STAGES: wgpu::ShaderStages = wgpu::ShaderStages::VERTEX_FRAGMENT;
+pub const PUSH_CONSTANT_SIZE: u32 = std::mem::size_of::<DrawConstants>() as u32;
+
+// Per object uniforms for things drawn one at a time rather than
+// instanced. Every object gets a slice of one buffer each frame and one
+// bind group covers all of them, so an object doesn't need a buffer and
+// bind group of its own. Push everything for the frame, `upload` before the
+// first pass, then per pass `begin` and per draw `bind` with what push gave
+// back.
+//
+// Where the device has push constants, the buffer is a storage array bound
+// once per pass and each draw only pushes its object's index. Elsewhere it
+// falls back to binding the bind group again at the slice's dynamic offset.
+pub struct ObjectArena {
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    push_constants: bool,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    // bytes from one object to the next, the device's offset alignment
+    // unless the objects are indexed
+    stride: u64,
+    // objects the buffer has room for
+    capacity: u64,
+    data: Vec<u8>,
+}
+
+impl ObjectArena {
+    pub fn new(device: &wgpu::Device, push_constants: bool) -> Self {
+        let mut entries =
+            crate::reflect::entries(BIND_GROUP_USES, Self::defines_for(push_constants))
+                .map_err(|e| anyhow::anyhow!("object bind group layout: {}", e))
+                .unwrap();
+        for entry in &mut entries {
+            if let wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset,
+                ..
+            } = &mut entry.ty
+            {
+                *has_dynamic_offset = true;
+            }
+        }
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("object bind group layout"),
+            entries: &entries,
+        });
+
+        let size = std::mem::size_of::<ObjectUniform>() as u64;
+        let stride = if push_constants {
+            size
+        } else {
+            size.next_multiple_of(device.limits().min_uniform_buffer_offset_alignment as u64)
+        };
+        let capacity = 64;
+        let (buffer, bind_group) = Self::create_buffer(
+            device,
+            &bind_group_layout,
+            push_constants,
+            capacity * stride,
+        );
+        Self {
+            bind_group_layout,
+            push_constants,
+            buffer,
+            bind_group,
+            stride,
+            capacity,
+            data: Vec::new(),
+        }
+    }
+
+    fn defines_for(push_constants: bool) -> &'static [&'static str] {
+        if push_constants {
+            &["PUSH_CONSTANTS"]
+        } else {
+            &[]
+        }
+    }
+
+    // to load a shader that reads objects with
+    pub fn defines(&self) -> &'static [&'static str] {
+        Self::defines_for(self.push_constants)
+    }
+
+    // for the pipeline layout of anything that reads objects
+    pub fn push_constant_ranges(&self) -> &'static [wgpu::PushConstantRange] {
+        if self.push_constants {
+            &[wgpu::PushConstantRange {
+                stages: PUSH_CONSTANT_STAGES,
+                range: 0..PUSH_CONSTANT_SIZE,
+            }]
+        } else {
+            &[]
+        }
+    }
+
+    fn create_buffer(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        push_constants: bool,
+        size: u64,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("object arena buffer"),
+            size,
+            usage: if push_constants {
+                wgpu::BufferUsages::STORAGE
+            } else {
+                wgpu::BufferUsages::UNIFORM
+            } | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    // a dynamic offset picks one object, an index reads the lot
+                    size: if push_constants {
+                        None
+                    } else {
+                        wgpu::BufferSize::new(std::mem::size_of::<ObjectUniform>() as u64)
+                    },
+                }),
+            }],
+            label: Some("object arena bind group"),
+        });
+        (buffer, bind_group)
+    }
+
+    // the object's handle for `bind`, its index or the offset of its slice
+    pub fn push(&mut self, object: ObjectUniform) -> u32 {
+        let offset = self.data.len();
+        self.data.extend_from_slice(bytemuck::bytes_of(&object));
+        self.data.resize(offset + self.stride as usize, 0);
+        if self.push_constants {
+            (offset as u64 / self.stride) as u32
+        } else {
+            offset as u32
+        }
+    }
+
+    // Writes this frame's objects and starts the next frame empty. The
+    // buffer only grows here, before anything is bound.
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let objects = self.data.len() as u64 / self.stride;
+        if objects > self.capacity {
+            self.capacity = objects.next_power_of_two();
+            (self.buffer, self.bind_group) = Self::create_buffer(
+                device,
+                &self.bind_group_layout,
+                self.push_constants,
+                self.capacity * self.stride,
+            );
+        }
+        if !self.data.is_empty() {
+            queue.write_buffer(&self.buffer, 0, &self.data);
+        }
+        self.data.clear();
+    }
+
+    // once in a pass that draws objects, after the pipeline is set
+    pub fn begin(&self, render_pass: &mut wgpu::RenderPass, group: u32) {
+        if self.push_constants {
+            render_pass.set_bind_group(group, &self.bind_group, &[]);
+        }
+    }
+
+    pub fn bind(&self, render_pass: &mut wgpu::RenderPass, group: u32, object: u32) {
+        if self.push_constants {
+            let constants = DrawConstants { object, flags: 0 };
+            render_pass.set_push_constants(PUSH_CONSTANT_STAGES, 0, bytemuck::bytes_of(&constants));
+        } else {
+            render_pass.set_bind_group(group, &self.bind_group, &[object]);
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use crate::quality::{Graphics, Overrides, Preset};
+use wgpu_text::glyph_brush::{OwnedSection, Section as TextSection, Text};
+
+// the modes worth picking between, in the order 1 cycles through them
+const PRESENT_MODES: [wgpu::PresentMode; 3] = [
+    wgpu::PresentMode::Fifo,
+    wgpu::PresentMode::Mailbox,
+    wgpu::PresentMode::Immediate,
+];
+const MAX_FRAME_LATENCY: u32 = 3;
+const LUT_STRENGTHS: [f32; 4] = [0.25, 0.5, 0.75, 1.0];
+// numbered 1 to ITEMS in the menu, 0 is the quality preset
+pub const ITEMS: u32 = 9;
+// curvature and strength
+const CRT_PRESETS: [(&str, f32, f32); 3] = [
+    ("off", 0.0, 0.0),
+    ("subtle", 0.04, 0.5),
+    ("strong", 0.12, 1.0),
+];
+const SHUTTER_ANGLES: [f32; 5] = [0.0, 90.0, 180.0, 270.0, 360.0];
+
+// what a profile keeps of the options, by name where the option is one
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct OptionSettings {
+    pub present_mode: String,
+    pub frame_latency: u32,
+    pub lut: Option<String>,
+    pub lut_strength: f32,
+    pub quality: Preset,
+    pub overrides: Overrides,
+    pub retro: crate::retro::RetroSettings,
+    pub crt_curvature: f32,
+    pub crt_strength: f32,
+}
+
+impl Default for OptionSettings {
+    fn default() -> Self {
+        Options::default().settings()
+    }
+}
+
+// The F7 menu. Everything in here only needs the surface configured again
+// or a uniform written, so changes apply on the next frame without
+// rebuilding anything.
+pub struct Options {
+    pub open: bool,
+    pub present_mode: wgpu::PresentMode,
+    pub frame_latency: u32,
+    // what the current surface supports out of PRESENT_MODES, Fifo always is
+    available: Vec<wgpu::PresentMode>,
+    // color grading, None is off
+    pub lut: Option<usize>,
+    pub lut_strength: f32,
+    lut_names: Vec<String>,
+    // from a profile, picked out once the LUTs are known
+    wanted_lut: Option<String>,
+    // the preset and whatever's been set by hand over it
+    pub quality: Preset,
+    pub overrides: Overrides,
+    pub retro: crate::retro::RetroSettings,
+    pub crt_curvature: f32,
+    pub crt_strength: f32,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            open: false,
+            present_mode: wgpu::PresentMode::Fifo,
+            frame_latency: 1,
+            available: vec![wgpu::PresentMode::Fifo],
+            lut: None,
+            lut_strength: 1.0,
+            lut_names: Vec::new(),
+            wanted_lut: None,
+            quality: Preset::default(),
+            overrides: Overrides::default(),
+            retro: Default::default(),
+            crt_curvature: 0.0,
+            crt_strength: 0.0,
+        }
+    }
+}
+
+impl Options {
+    // called with every new surface, a mode the new one can't do falls back to Fifo
+    pub fn set_available(&mut self, supported: &[wgpu::PresentMode]) {
+        self.available = PRESENT_MODES
+            .into_iter()
+            .filter(|m| *m == wgpu::PresentMode::Fifo || supported.contains(m))
+            .collect();
+        if !self.available.contains(&self.present_mode) {
+            log::warn!("{:?} isn't supported here, using Fifo", self.present_mode);
+            self.present_mode = wgpu::PresentMode::Fifo;
+        }
+    }
+
+    pub fn cycle_present_mode(&mut self) {
+        let i = self
+            .available
+            .iter()
+            .position(|m| *m == self.present_mode)
+            .unwrap_or(0);
+        self.present_mode = self.available[(i + 1) % self.available.len()];
+    }
+
+    pub fn cycle_frame_latency(&mut self) {
+        self.frame_latency = self.frame_latency % MAX_FRAME_LATENCY + 1;
+    }
+
+    pub fn set_luts(&mut self, names: Vec<String>) {
+        self.lut = self.lut.filter(|i| *i < names.len());
+        if let Some(wanted) = self.wanted_lut.take() {
+            self.lut = names.iter().position(|name| *name == wanted);
+        }
+        self.lut_names = names;
+    }
+
+    // off, then each LUT in turn
+    pub fn cycle_lut(&mut self) {
+        self.lut = match self.lut {
+            None if !self.lut_names.is_empty() => Some(0),
+            Some(i) if i + 1 < self.lut_names.len() => Some(i + 1),
+            _ => None,
+        };
+    }
+
+    pub fn cycle_lut_strength(&mut self) {
+        let i = LUT_STRENGTHS
+            .iter()
+            .position(|s| *s == self.lut_strength)
+            .unwrap_or(0);
+        self.lut_strength = LUT_STRENGTHS[(i + 1) % LUT_STRENGTHS.len()];
+    }
+
+    // what the graphics settings should be, the watchdog may have some turned down
+    pub fn graphics(&self) -> Graphics {
+        self.overrides.apply(self.quality.graphics())
+    }
+
+    // motion blur, an override on the preset like anything set by hand
+    pub fn cycle_shutter_angle(&mut self) {
+        let i = SHUTTER_ANGLES
+            .iter()
+            .position(|a| *a == self.graphics().shutter_angle)
+            .unwrap_or(0);
+        self.overrides.shutter_angle = Some(SHUTTER_ANGLES[(i + 1) % SHUTTER_ANGLES.len()]);
+    }
+
+    // `--quality <preset>` and any number of `--graphics name=value`, after
+    // the profile so they win over it
+    pub fn quality_from_args(&mut self) {
+        let args = std::env::args().collect::<Vec<_>>();
+        if let Some(name) = args
+            .iter()
+            .position(|a| a == "--quality")
+            .and_then(|i| args.get(i + 1))
+        {
+            match Preset::parse(name) {
+                Some(preset) => self.quality = preset,
+                None => log::error!(
+                    "ignoring --quality {}, try low, medium, high or ultra",
+                    name
+                ),
+            }
+        }
+        for setting in args
+            .windows(2)
+            .filter(|pair| pair[0] == "--graphics")
+            .map(|pair| &pair[1])
+        {
+            let result = setting
+                .split_once('=')
+                .ok_or_else(|| "it takes name=value".to_string())
+                .and_then(|(name, value)| self.overrides.set(name, value));
+            if let Err(e) = result {
+                log::error!("ignoring --graphics {}: {}", setting, e);
+            }
+        }
+    }
+
+    // through the presets, `crt` in the console sets anything in between
+    pub fn cycle_crt(&mut self) {
+        let i = CRT_PRESETS
+            .iter()
+            .position(|(_, c, s)| *c == self.crt_curvature && *s == self.crt_strength)
+            .map_or(0, |i| (i + 1) % CRT_PRESETS.len());
+        (_, self.crt_curvature, self.crt_strength) = CRT_PRESETS[i];
+    }
+
+    pub fn settings(&self) -> OptionSettings {
+        OptionSettings {
+            present_mode: format!("{:?}", self.present_mode),
+            frame_latency: self.frame_latency,
+            lut: self.lut.map(|i| self.lut_names[i].clone()),
+            lut_strength: self.lut_strength,
+            quality: self.quality,
+            overrides: self.overrides.clone(),
+            retro: self.retro,
+            crt_curvature: self.crt_curvature,
+            crt_strength: self.crt_strength,
+        }
+    }
+
+    // before the surface is made, which checks the present mode
+    pub fn apply_settings(&mut self, settings: &OptionSettings) {
+        self.present_mode = PRESENT_MODES
+            .into_iter()
+            .find(|m| format!("{:?}", m) == settings.present_mode)
+            .unwrap_or(wgpu::PresentMode::Fifo);
+        self.frame_latency = settings.frame_latency.clamp(1, MAX_FRAME_LATENCY);
+        self.wanted_lut = settings.lut.clone();
+        self.lut_strength = settings.lut_strength.clamp(0.0, 1.0);
+        self.quality = settings.quality;
+        self.overrides = settings.overrides.clone();
+        self.retro = settings.retro;
+        self.crt_curvature = settings.crt_curvature.clamp(0.0, 1.0);
+        self.crt_strength = settings.crt_strength.clamp(0.0, 1.0);
+    }
+
+    pub fn apply(&self, config: &mut wgpu::SurfaceConfiguration) {
+        config.present_mode = self.present_mode;
+        config.desired_maximum_frame_latency = self.frame_latency;
+    }
+
+    // Roughly how many refreshes a presented frame waits before it's scanned
+    // out. Fifo queues up to `frame_latency` frames and then waits for vblank,
+    // Mailbox shows the newest frame at the next vblank, and Immediate doesn't
+    // wait at all (and tears instead).
+    pub fn queued_refreshes(&self) -> f32 {
+        match self.present_mode {
+            wgpu::PresentMode::Mailbox => 0.5,
+            wgpu::PresentMode::Immediate => 0.0,
+            _ => self.frame_latency as f32 - 0.5,
+        }
+    }
+
+    pub fn section(&self, height: f32) -> Option<OwnedSection> {
+        if !self.open {
+            return None;
+        }
+        let modes = self
+            .available
+            .iter()
+            .map(|m| format!("{:?}", m))
+            .collect::<Vec<_>>()
+            .join("/");
+        let lut = self
+            .lut
+            .map(|i| self.lut_names[i].as_str())
+            .unwrap_or("off");
+        let text = format!(
+            "options (F7 closes)\n0  effects preset  {:?}{}\n1  present mode  {:?}  ({})\n2  frame latency  {}\n\
+             3  color grading  {}\n4  grading strength  {}\n\
+             5  motion blur shutter  {}  (off under 30 fps)\n\
+             6  retro palette  {:?}{}\n7  retro pixel size  {}\n8  retro dithering  {}\n\
+             9  crt  {}\n",
+            self.quality,
+            match self.overrides.names().as_slice() {
+                [] => String::new(),
+                names => format!("  ({} set by hand)", names.join(", ")),
+            },
+            self.present_mode,
+            modes,
+            self.frame_latency,
+            lut,
+            self.lut_strength,
+            match self.graphics().shutter_angle {
+                0.0 => "off".to_string(),
+                angle => format!("{}°", angle),
+            },
+            self.retro.palette,
+            if self.retro.enabled {
+                ""
+            } else {
+                "  (p turns retro on)"
+            },
+            self.retro.pixel_size,
+            if self.retro.dither { "on" } else { "off" },
+            CRT_PRESETS
+                .iter()
+                .find(|(_, c, s)| *c == self.crt_curvature && *s == self.crt_strength)
+                .map(|(name, ..)| name.to_string())
+                .unwrap_or_else(|| format!(
+                    "curvature {}, strength {}",
+                    self.crt_curvature, self.crt_strength
+                )),
+        );
+        Some(
+            TextSection::default()
+                .add_text(
+                    Text::new(&text)
+                        .with_color([0.9, 1.0, 1.0, 1.0])
+                        .with_scale(20.0),
+                )
+                .with_screen_position((10.0, height / 2.0))
+                .to_owned(),
+        )
+    }
+}
@@ -0,0 +1,274 @@
+use crate::arena::MeshArena;
+use crate::model::{DrawModel, Mesh};
+use crate::vertex::{ModelFormat, Vertex};
+use crate::InstanceRaw;
+use wgpu::util::DeviceExt;
+
+const STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Stencil8;
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct OutlineUniform {
+    color: [f32; 4],
+    thickness: f32,
+    _padding: f32,
+    viewport: [f32; 2],
+}
+
+// Draws a colored edge around selected cubes. Runs as its own pass after the
+// opaque pass: the selected cubes are written into a stencil buffer, then a
+// slightly bigger copy is drawn everywhere the stencil wasn't set. There's no
+// depth buffer, so the outline also shows through anything in front of it.
+pub struct Outline {
+    stencil_view: wgpu::TextureView,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    mask_pipeline: wgpu::RenderPipeline,
+    hull_pipeline: wgpu::RenderPipeline,
+    viewport: [f32; 2],
+
+    color: [f32; 4],
+    // in pixels
+    thickness: f32,
+}
+
+impl Outline {
+    pub fn new(
+        device: &wgpu::Device,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        model_format: ModelFormat,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let stencil_view = Self::create_stencil_view(device, width, height);
+
+        let color = [1.0, 0.6, 0.1, 1.0];
+        let thickness = 3.0;
+        let viewport = [width as f32, height as f32];
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("outline uniform buffer"),
+            contents: bytemuck::cast_slice(&[OutlineUniform {
+                color,
+                thickness,
+                _padding: 0.0,
+                viewport,
+            }]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = crate::reflect::bind_group_layout(
+            device,
+            "outline bind group layout",
+            &[("outline.wgsl", 1)],
+        )
+        .unwrap();
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("outline bind group"),
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("outline shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                crate::shaders::load("outline.wgsl", &[]).unwrap().into(),
+            ),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("outline pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |label, entry_point, write_mask, stencil| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point,
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    buffers: &[model_format.desc(), InstanceRaw::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: STENCIL_FORMAT,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Always,
+                    stencil: wgpu::StencilState {
+                        front: stencil,
+                        back: stencil,
+                        read_mask: 0xff,
+                        write_mask: 0xff,
+                    },
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+                cache: crate::pipeline_cache::current().as_deref(),
+            })
+        };
+        let mask_pipeline = pipeline(
+            "outline mask pipeline",
+            "vs_mask",
+            wgpu::ColorWrites::empty(),
+            wgpu::StencilFaceState {
+                compare: wgpu::CompareFunction::Always,
+                fail_op: wgpu::StencilOperation::Keep,
+                depth_fail_op: wgpu::StencilOperation::Keep,
+                pass_op: wgpu::StencilOperation::Replace,
+            },
+        );
+        let hull_pipeline = pipeline(
+            "outline hull pipeline",
+            "vs_hull",
+            wgpu::ColorWrites::ALL,
+            wgpu::StencilFaceState {
+                compare: wgpu::CompareFunction::NotEqual,
+                fail_op: wgpu::StencilOperation::Keep,
+                depth_fail_op: wgpu::StencilOperation::Keep,
+                pass_op: wgpu::StencilOperation::Keep,
+            },
+        );
+
+        Self {
+            stencil_view,
+            uniform_buffer,
+            bind_group,
+            mask_pipeline,
+            hull_pipeline,
+            viewport,
+            color,
+            thickness,
+        }
+    }
+
+    fn create_stencil_view(device: &wgpu::Device, width: u32, height: u32) -> wgpu::TextureView {
+        device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("outline stencil"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: STENCIL_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    // the stencil has to be the size of the frame it's drawn over
+    pub fn resize(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, width: u32, height: u32) {
+        self.stencil_view = Self::create_stencil_view(device, width, height);
+        self.viewport = [width as f32, height as f32];
+        self.set_style(queue, self.color, self.thickness);
+    }
+
+    pub fn color(&self) -> [f32; 4] {
+        self.color
+    }
+
+    pub fn thickness(&self) -> f32 {
+        self.thickness
+    }
+
+    pub fn set_style(&mut self, queue: &wgpu::Queue, color: [f32; 4], thickness: f32) {
+        self.color = color;
+        self.thickness = thickness.max(0.0);
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[OutlineUniform {
+                color: self.color,
+                thickness: self.thickness,
+                _padding: 0.0,
+                viewport: self.viewport,
+            }]),
+        );
+    }
+
+    // `selected` are indices into the instance buffer
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        camera_bind_group: &wgpu::BindGroup,
+        arena: &MeshArena,
+        meshes: &[Mesh],
+        instance_buffer: &wgpu::Buffer,
+        selected: &[u32],
+    ) {
+        if selected.is_empty() {
+            return;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("outline pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.stencil_view,
+                depth_ops: None,
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_stencil_reference(1);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.set_mesh_arena(arena);
+        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+
+        // every mask has to be in before any hull, or cubes next to each
+        // other would draw their outlines over one another
+        for pipeline in [&self.mask_pipeline, &self.hull_pipeline] {
+            render_pass.set_pipeline(pipeline);
+            for &i in selected {
+                for mesh in meshes {
+                    render_pass.draw_indexed(mesh.indices(), mesh.base_vertex, i..i + 1);
+                }
+            }
+        }
+    }
+}
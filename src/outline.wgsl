@@ -0,0 +1,48 @@
+#define CAMERA_GROUP 0
+#define INSTANCED
+#include "common.wgsl"
+
+struct Outline {
+    color: vec4<f32>,
+    // width of the edge in pixels
+    thickness: f32,
+    viewport: vec2<f32>,
+}
+@group(1) @binding(0)
+var<uniform> outline: Outline;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+}
+
+// writes the selected cube into the stencil buffer
+@vertex
+fn vs_mask(model: VertexInput, instance: InstanceInput) -> @builtin(position) vec4<f32> {
+    return camera.view_proj * model_matrix(instance) * vec4<f32>(model.position, 1.0);
+}
+
+// the same cube pushed outwards in screen space. The cube's normals are per
+// face, which would tear the hull apart at the edges, so the direction from
+// the model's center is used instead.
+@vertex
+fn vs_hull(model: VertexInput, instance: InstanceInput) -> @builtin(position) vec4<f32> {
+    let matrix = model_matrix(instance);
+    let world = matrix * vec4<f32>(model.position, 1.0);
+    let outward = matrix * vec4<f32>(normalize(model.position), 0.0);
+
+    var clip = camera.view_proj * world;
+    let tip = camera.view_proj * (world + outward);
+    let screen = tip.xy / tip.w - clip.xy / clip.w;
+    if length(screen) > 0.0 {
+        clip = vec4<f32>(
+            clip.xy + normalize(screen) * outline.thickness * 2.0 / outline.viewport * clip.w,
+            clip.zw,
+        );
+    }
+    return clip;
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return outline.color;
+}
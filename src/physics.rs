@@ -0,0 +1,331 @@
+use crate::math::{Point3, Quat, Vec3};
+use crate::Instance;
+use std::ops::BitOr;
+
+// What kind of thing a cube is as far as collisions go, one bit per layer so
+// a query can take any set of them as a mask.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Layers(u32);
+
+impl Layers {
+    pub const NONE: Layers = Layers(0);
+    pub const PLAYER: Layers = Layers(1 << 0);
+    pub const ENEMY: Layers = Layers(1 << 1);
+    pub const PROJECTILE: Layers = Layers(1 << 2);
+    pub const TRIGGER: Layers = Layers(1 << 3);
+    pub const STATIC: Layers = Layers(1 << 4);
+    pub const ALL: Layers = Layers((1 << 5) - 1);
+
+    // the single layers, in bit order, with their scene file names
+    pub const NAMED: [(&'static str, Layers); 5] = [
+        ("player", Layers::PLAYER),
+        ("enemy", Layers::ENEMY),
+        ("projectile", Layers::PROJECTILE),
+        ("trigger", Layers::TRIGGER),
+        ("static", Layers::STATIC),
+    ];
+
+    pub const fn union(self, other: Layers) -> Layers {
+        Layers(self.0 | other.0)
+    }
+
+    pub const fn without(self, other: Layers) -> Layers {
+        Layers(self.0 & !other.0)
+    }
+
+    pub fn intersects(self, other: Layers) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    // as sent over the network, bits past the known layers are dropped
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub fn from_bits(bits: u32) -> Layers {
+        Layers(bits & Layers::ALL.0)
+    }
+
+    pub fn parse(name: &str) -> Option<Layers> {
+        Self::NAMED
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, l)| *l)
+    }
+
+    // the name of a single layer, None for a mask of several
+    pub fn name(self) -> Option<&'static str> {
+        Self::NAMED
+            .iter()
+            .find(|(_, l)| *l == self)
+            .map(|(n, _)| *n)
+    }
+
+    // The layer a cube gets when nothing says otherwise. The first cube is
+    // the player's, everything else spawned is scenery.
+    pub fn spawned(index: usize) -> Layers {
+        if index == 0 {
+            Layers::PLAYER
+        } else {
+            Layers::STATIC
+        }
+    }
+
+    fn bit(self) -> usize {
+        self.0.trailing_zeros() as usize
+    }
+}
+
+impl BitOr for Layers {
+    type Output = Layers;
+
+    fn bitor(self, other: Layers) -> Layers {
+        self.union(other)
+    }
+}
+
+// Which layers touch which, a row per layer. It's kept symmetric, so a pair
+// collides or doesn't whichever of the two is asked about first. Contacts
+// only look at pairs this lets through, queries take their own mask instead.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Filter {
+    rows: [Layers; 5],
+}
+
+impl Default for Filter {
+    // Projectiles don't hit each other or set off triggers, triggers only
+    // notice the player and enemies, and static cubes don't touch each other.
+    fn default() -> Self {
+        let mut filter = Filter {
+            rows: [Layers::NONE; 5],
+        };
+        let everything = Layers::ALL;
+        filter.set_mask(Layers::PLAYER, everything);
+        filter.set_mask(Layers::ENEMY, everything);
+        filter.set(Layers::PROJECTILE, Layers::STATIC, true);
+        filter
+    }
+}
+
+impl Filter {
+    pub fn collides(&self, a: Layers, b: Layers) -> bool {
+        self.rows[a.bit()].intersects(b)
+    }
+
+    // every layer `layer` collides with
+    pub fn mask(&self, layer: Layers) -> Layers {
+        self.rows[layer.bit()]
+    }
+
+    pub fn set(&mut self, a: Layers, b: Layers, collide: bool) {
+        for (row, other) in [(a, b), (b, a)] {
+            let mask = &mut self.rows[row.bit()].0;
+            if collide {
+                *mask |= other.0;
+            } else {
+                *mask &= !other.0;
+            }
+        }
+    }
+
+    fn set_mask(&mut self, layer: Layers, mask: Layers) {
+        for (_, other) in Layers::NAMED {
+            if mask.intersects(other) {
+                self.set(layer, other, true);
+            }
+        }
+    }
+
+    // The `collide a b` and `ignore a b` lines a scene file needs to get
+    // this filter back from the default one, each pair once.
+    pub fn scene_lines(&self) -> Vec<String> {
+        let default = Filter::default();
+        let mut lines = Vec::new();
+        for (i, (a_name, a)) in Layers::NAMED.iter().enumerate() {
+            for (b_name, b) in &Layers::NAMED[i..] {
+                let collides = self.collides(*a, *b);
+                if collides != default.collides(*a, *b) {
+                    let key = if collides { "collide" } else { "ignore" };
+                    lines.push(format!("{} {} {}", key, a_name, b_name));
+                }
+            }
+        }
+        lines
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    // the box around a 2 across cube, however it's turned
+    pub fn of_cube(position: Point3, rotation: Quat) -> Self {
+        let half =
+            (rotation * Vec3::X).abs() + (rotation * Vec3::Y).abs() + (rotation * Vec3::Z).abs();
+        Self {
+            min: position - half,
+            max: position + half,
+        }
+    }
+
+    // How far along `direction` (normalized) a ray from `origin` first
+    // enters the box within `max`, and the face it goes in through. A ray
+    // starting inside never hits, there's no face to have come in by.
+    pub fn cast(&self, origin: Point3, direction: Vec3, max: f32) -> Option<(f32, Vec3)> {
+        let mut near = f32::NEG_INFINITY;
+        let mut far = max;
+        let mut normal = Vec3::ZERO;
+        for axis in 0..3 {
+            let t0 = (self.min[axis] - origin[axis]) / direction[axis];
+            let t1 = (self.max[axis] - origin[axis]) / direction[axis];
+            if t0.min(t1) > near {
+                near = t0.min(t1);
+                normal = Vec3::ZERO;
+                normal[axis] = -direction[axis].signum();
+            }
+            far = far.min(t0.max(t1));
+        }
+        (near <= far && near >= 0.0).then_some((near, normal))
+    }
+
+    pub fn grown(&self, by: Vec3) -> Aabb {
+        Aabb {
+            min: self.min - by,
+            max: self.max + by,
+        }
+    }
+
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && self.max.cmpge(other.min).all()
+    }
+}
+
+// The ones out of `others` that a box on `layer` is touching, skipping
+// pairs the filter keeps apart before their boxes are even looked at.
+pub fn contacts<'a>(
+    filter: &'a Filter,
+    layer: Layers,
+    body: Aabb,
+    others: impl Iterator<Item = (usize, Layers, Aabb)> + 'a,
+) -> impl Iterator<Item = usize> + 'a {
+    others
+        .filter(move |(_, other, _)| filter.collides(layer, *other))
+        .filter(move |(_, _, aabb)| body.overlaps(aabb))
+        .map(|(i, _, _)| i)
+}
+
+// The cube half size, see math.rs
+const CUBE: Aabb = Aabb {
+    min: Vec3::NEG_ONE,
+    max: Vec3::ONE,
+};
+
+#[derive(Copy, Clone, Debug)]
+pub struct Hit {
+    // into the cubes
+    pub index: usize,
+    // along the cast, to where the shape first touches
+    pub distance: f32,
+    // on the cube's surface
+    pub point: Point3,
+    pub normal: Vec3,
+}
+
+// The cubes, for casting rays and shapes into. Every cast takes a mask and
+// only sees the cubes on layers in it, and one cube can be left out
+// altogether, usually the one doing the casting. Casts that start inside a
+// cube go straight through it.
+#[derive(Copy, Clone)]
+pub struct World<'a> {
+    cubes: &'a [Instance],
+    ignore: Option<usize>,
+}
+
+impl<'a> World<'a> {
+    pub fn new(cubes: &'a [Instance]) -> Self {
+        Self {
+            cubes,
+            ignore: None,
+        }
+    }
+
+    pub fn ignoring(self, index: usize) -> Self {
+        Self {
+            ignore: Some(index),
+            ..self
+        }
+    }
+
+    // the nearest cube along the ray, `direction` normalized
+    pub fn raycast(&self, origin: Point3, direction: Vec3, mask: Layers) -> Option<Hit> {
+        self.sphere_cast(origin, 0.0, direction, f32::INFINITY, mask)
+    }
+
+    // Sweeps a ball along `direction` for up to `max`. Each cube is cast
+    // against in its own space as a box grown by the radius, which is a
+    // little generous around the edges and corners.
+    pub fn sphere_cast(
+        &self,
+        center: Point3,
+        radius: f32,
+        direction: Vec3,
+        max: f32,
+        mask: Layers,
+    ) -> Option<Hit> {
+        self.nearest(mask, |cube| {
+            let to_local = cube.rotation.inverse();
+            let (distance, normal) = CUBE.grown(Vec3::splat(radius)).cast(
+                to_local * (center - cube.position),
+                to_local * direction,
+                max,
+            )?;
+            let normal = cube.rotation * normal;
+            Some((
+                distance,
+                center + direction * distance - normal * radius,
+                normal,
+            ))
+        })
+    }
+
+    // Sweeps an axis aligned box along `direction` for up to `max`, against
+    // the boxes around the cubes, so turned cubes stop it a bit early.
+    pub fn box_cast(&self, aabb: Aabb, direction: Vec3, max: f32, mask: Layers) -> Option<Hit> {
+        let center = (aabb.min + aabb.max) / 2.0;
+        let half = (aabb.max - aabb.min) / 2.0;
+        self.nearest(mask, |cube| {
+            let (distance, normal) = Aabb::of_cube(cube.position, cube.rotation)
+                .grown(half)
+                .cast(center, direction, max)?;
+            Some((
+                distance,
+                center + direction * distance - normal * half,
+                normal,
+            ))
+        })
+    }
+
+    fn nearest(
+        &self,
+        mask: Layers,
+        cast: impl Fn(&Instance) -> Option<(f32, Point3, Vec3)>,
+    ) -> Option<Hit> {
+        self.cubes
+            .iter()
+            .enumerate()
+            .filter(|(i, cube)| Some(*i) != self.ignore && cube.layer.intersects(mask))
+            .filter_map(|(index, cube)| {
+                let (distance, point, normal) = cast(cube)?;
+                Some(Hit {
+                    index,
+                    distance,
+                    point,
+                    normal,
+                })
+            })
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+}
@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+// the cache every pipeline is made with, swapped when the device is
+static CURRENT: RwLock<Option<Arc<wgpu::PipelineCache>>> = RwLock::new(None);
+
+// for a pipeline descriptor's `cache: cache.as_deref()`, None where the
+// backend keeps no cache
+pub fn current() -> Option<Arc<wgpu::PipelineCache>> {
+    CURRENT.read().unwrap().clone()
+}
+
+// Compiled pipelines kept on disk between runs, so startup skips compiling
+// shaders it's compiled before. Backends that can't (only Vulkan can so far)
+// go without. The file is named by the adapter and driver and a hash of
+// every shader source, so a driver update or a shader edit starts over
+// instead of loading a stale cache, and the stale one is deleted.
+pub struct PipelineCache {
+    cache: Arc<wgpu::PipelineCache>,
+    path: PathBuf,
+    // prefix shared with older caches for the same adapter
+    key: String,
+}
+
+impl PipelineCache {
+    pub fn features(adapter: &wgpu::Adapter) -> wgpu::Features {
+        adapter.features() & wgpu::Features::PIPELINE_CACHE
+    }
+
+    // makes the cache current, loading what the last run left
+    pub fn load(device: &wgpu::Device, info: &wgpu::AdapterInfo) -> Option<Self> {
+        *CURRENT.write().unwrap() = None;
+        if !device.features().contains(wgpu::Features::PIPELINE_CACHE) {
+            return None;
+        }
+        let key = wgpu::util::pipeline_cache_key(info)?;
+        let path = crate::dirs::cache_dir().join("pipelines").join(format!(
+            "{}-{:016x}",
+            key,
+            crate::shaders::hash()
+        ));
+        let data = std::fs::read(&path).ok();
+        log::info!(
+            "pipeline cache {}",
+            if data.is_some() { "found" } else { "is empty" }
+        );
+        // Safety: the data is only ever what get_data gave back for this
+        // adapter and driver, and fallback drops it if it doesn't fit anyway
+        let cache = Arc::new(unsafe {
+            device.create_pipeline_cache(&wgpu::PipelineCacheDescriptor {
+                label: Some("pipeline cache"),
+                data: data.as_deref(),
+                fallback: true,
+            })
+        });
+        *CURRENT.write().unwrap() = Some(cache.clone());
+        Some(Self { cache, path, key })
+    }
+
+    // writes everything compiled so far back, replacing older caches
+    pub fn save(&self) {
+        let Some(data) = self.cache.get_data() else {
+            return;
+        };
+        let dir = self.path.parent().unwrap();
+        let temporary = self.path.with_extension("tmp");
+        let written = std::fs::create_dir_all(dir)
+            .and_then(|_| std::fs::write(&temporary, &data))
+            .and_then(|_| std::fs::rename(&temporary, &self.path));
+        if let Err(e) = written {
+            log::warn!("couldn't write {}: {}", self.path.display(), e);
+            return;
+        }
+        for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            let stale = path != self.path
+                && path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with(&self.key));
+            if stale {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
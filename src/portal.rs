@@ -0,0 +1,334 @@
+use crate::camera::{Camera, CameraUniform, Frustum};
+use crate::math::{self, Mat4, Point3, Quat, Vec3, Vec4};
+use crate::objects::{ObjectArena, ObjectUniform};
+use crate::texture::Texture;
+use wgpu::util::DeviceExt;
+
+// half the size of the opening, wide enough for a cube to get through
+const HALF_WIDTH: f32 = 2.0;
+const HALF_HEIGHT: f32 = 2.0;
+
+const RIMS: [[f32; 4]; 2] = [[0.2, 0.5, 1.0, 1.0], [1.0, 0.5, 0.1, 1.0]];
+
+// One end: an opening in its own xy plane, looking out along its +z.
+#[derive(Copy, Clone, Debug)]
+struct End {
+    position: Point3,
+    rotation: Quat,
+}
+
+impl End {
+    fn to_world(self) -> Mat4 {
+        math::model_matrix(self.position, self.rotation)
+    }
+
+    fn normal(self) -> Vec3 {
+        self.rotation * Vec3::Z
+    }
+
+    // whether `p` is in front of the opening, and where it is across it
+    fn local(self, p: Point3) -> Vec3 {
+        self.rotation.inverse() * (p - self.position)
+    }
+}
+
+// takes what's near `from` to the same place near `to`, turned around so
+// going in the front of one comes out of the front of the other
+fn through(from: End, to: End) -> Mat4 {
+    to.to_world() * Mat4::from_rotation_y(std::f32::consts::PI) * from.to_world().inverse()
+}
+
+// `projection` with its near plane swapped for `plane` (in view space, the
+// kept side positive), so nothing between the camera and the far portal gets
+// drawn. Lengyel's oblique frustum, for wgpu's 0..1 depth.
+fn oblique(projection: Mat4, plane: Vec4) -> Mat4 {
+    let corner = projection.inverse() * Vec4::new(plane.x.signum(), plane.y.signum(), 1.0, 1.0);
+    let near = plane / plane.dot(corner);
+    let mut projection = projection;
+    for i in 0..4 {
+        projection.col_mut(i)[2] = near[i];
+    }
+    projection
+}
+
+// Two linked openings. Each one shows the scene from behind the other, drawn
+// into a texture the size of the screen with the camera carried through the
+// pair, and the player cube walking into one steps out of the other.
+// `portals` in the console turns them on.
+pub struct Portals {
+    pub enabled: bool,
+    ends: [End; 2],
+    pub views: [Texture; 2],
+    pub depths: [Texture; 2],
+    camera_buffers: [wgpu::Buffer; 2],
+    camera_bind_groups: [wgpu::BindGroup; 2],
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_groups: [wgpu::BindGroup; 2],
+    pipeline: wgpu::RenderPipeline,
+    // where the player cube was last frame, to see it cross
+    last: Option<Point3>,
+}
+
+impl Portals {
+    pub fn new(
+        device: &wgpu::Device,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        objects: &ObjectArena,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        // standing on the floor on either side of the start, both turned
+        // towards the camera
+        let ends = [
+            End {
+                position: Point3::new(-8.0, 0.0, 0.0),
+                rotation: Quat::from_rotation_y(std::f32::consts::FRAC_PI_2),
+            },
+            End {
+                position: Point3::new(0.0, 0.0, 8.0),
+                rotation: Quat::from_rotation_y(std::f32::consts::PI),
+            },
+        ];
+        let views = ["portal view a", "portal view b"]
+            .map(|label| Texture::create_render_target(device, width, height, format, label));
+        let depths = ["portal depth a", "portal depth b"]
+            .map(|label| Texture::create_depth_target(device, width, height, label));
+        let camera_buffers = [0, 1].map(|_| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("portal camera buffer"),
+                contents: bytemuck::cast_slice(&[CameraUniform::new()]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            })
+        });
+        let camera_bind_groups = [0, 1].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: camera_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffers[i].as_entire_binding(),
+                }],
+                label: Some("portal camera bind group"),
+            })
+        });
+
+        let bind_group_layout = crate::reflect::bind_group_layout(
+            device,
+            "portal bind group layout",
+            &[("portal.wgsl", 1)],
+        )
+        .unwrap();
+        let bind_groups = Self::create_bind_groups(device, &bind_group_layout, &views);
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("portal shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                crate::shaders::load("portal.wgsl", objects.defines())
+                    .unwrap()
+                    .into(),
+            ),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("portal pipeline layout"),
+            bind_group_layouts: &[
+                camera_bind_group_layout,
+                &bind_group_layout,
+                &objects.bind_group_layout,
+            ],
+            push_constant_ranges: objects.push_constant_ranges(),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("portal render pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                // the back of an opening is drawn too, just without the view
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(Texture::depth_state(true, wgpu::CompareFunction::Less)),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: crate::pipeline_cache::current().as_deref(),
+        });
+
+        Self {
+            enabled: false,
+            ends,
+            views,
+            depths,
+            camera_buffers,
+            camera_bind_groups,
+            bind_group_layout,
+            bind_groups,
+            pipeline,
+            last: None,
+        }
+    }
+
+    fn create_bind_groups(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        views: &[Texture; 2],
+    ) -> [wgpu::BindGroup; 2] {
+        [0, 1].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&views[i].view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&views[i].sampler),
+                    },
+                ],
+                label: Some("portal bind group"),
+            })
+        })
+    }
+
+    // the views are the size of the screen, the openings sample them by
+    // where they are on it
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        let format = self.views[0].texture.format();
+        self.views = ["portal view a", "portal view b"]
+            .map(|label| Texture::create_render_target(device, width, height, format, label));
+        self.depths = ["portal depth a", "portal depth b"]
+            .map(|label| Texture::create_depth_target(device, width, height, label));
+        self.bind_groups = Self::create_bind_groups(device, &self.bind_group_layout, &self.views);
+    }
+
+    // Draws what each opening looks onto. `draw` gets the pass and the camera
+    // carried through the pair, with its frustum; the portals themselves aren't in it, so they
+    // don't show up inside each other.
+    pub fn render_views(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        queue: &wgpu::Queue,
+        camera: &Camera,
+        mut draw: impl FnMut(&mut wgpu::RenderPass, &wgpu::BindGroup, &Frustum),
+    ) {
+        let view = math::look_at(camera.eye, camera.target, camera.up);
+        let projection = math::perspective(camera.fovy, camera.aspect, camera.znear, camera.zfar);
+        for i in 0..2 {
+            let (near, far) = (self.ends[i], self.ends[1 - i]);
+            if near.local(camera.eye).z <= 0.0 {
+                // only the back of it is in sight, which shows no view
+                continue;
+            }
+            let carried = view * through(far, near);
+            // the far opening's plane, facing away from the carried camera
+            let normal = far.normal();
+            let plane = carried.inverse().transpose() * normal.extend(-normal.dot(far.position));
+            let view_proj = oblique(projection, plane) * carried;
+            let eye = through(near, far).transform_point3(camera.eye);
+            let mut uniform = CameraUniform::new();
+            uniform.set(eye, view_proj);
+            queue.write_buffer(&self.camera_buffers[i], 0, bytemuck::cast_slice(&[uniform]));
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("portal view pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.views[i].view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.1,
+                            g: 0.2,
+                            b: 0.3,
+                            a: 1.0,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depths[i].view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            draw(
+                &mut render_pass,
+                &self.camera_bind_groups[i],
+                &Frustum::from_view_proj(view_proj),
+            );
+        }
+    }
+
+    // each opening, for the frame's ObjectArena
+    pub fn objects(&self) -> [ObjectUniform; 2] {
+        [0, 1].map(|i| ObjectUniform {
+            model: math::mat4_array(
+                self.ends[i].to_world() * Mat4::from_scale(Vec3::new(HALF_WIDTH, HALF_HEIGHT, 1.0)),
+            ),
+            tint: RIMS[i],
+        })
+    }
+
+    // `offsets` are what the arena gave back for `objects`
+    pub fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        camera_bind_group: &wgpu::BindGroup,
+        objects: &ObjectArena,
+        offsets: [u32; 2],
+    ) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        objects.begin(render_pass, 2);
+        for (bind_group, offset) in self.bind_groups.iter().zip(offsets) {
+            render_pass.set_bind_group(1, bind_group, &[]);
+            objects.bind(render_pass, 2, offset);
+            render_pass.draw(0..6, 0..1);
+        }
+    }
+
+    // Once a frame with the player cube. Moving from the front of an opening
+    // to behind it, inside the frame, gives where it comes out of the other.
+    pub fn cross(&mut self, position: Point3, rotation: Quat) -> Option<(Point3, Quat)> {
+        let last = self.last.replace(position);
+        if !self.enabled {
+            return None;
+        }
+        let last = last?;
+        let i = (0..2).find(|&i| {
+            let (before, after) = (self.ends[i].local(last), self.ends[i].local(position));
+            before.z >= 0.0
+                && after.z < 0.0
+                && after.x.abs() < HALF_WIDTH
+                && after.y.abs() < HALF_HEIGHT
+        })?;
+        let jump = through(self.ends[i], self.ends[1 - i]);
+        let (_, turn, _) = jump.to_scale_rotation_translation();
+        let position = jump.transform_point3(position);
+        self.last = Some(position);
+        Some((position, (turn * rotation).normalize()))
+    }
+}
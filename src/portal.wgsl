@@ -0,0 +1,42 @@
+#define CAMERA_GROUP 0
+#define OBJECT_GROUP 2
+#include "common.wgsl"
+
+// the scene through the other end, drawn with the same screen as this one
+@group(1) @binding(0)
+var t_view: texture_2d<f32>;
+@group(1) @binding(1)
+var s_view: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // -1..1 across the opening
+    @location(0) local: vec2<f32>,
+    @location(1) rim: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, -1.0), vec2<f32>(-1.0, 1.0),
+        vec2<f32>(-1.0, 1.0), vec2<f32>(1.0, -1.0), vec2<f32>(1.0, 1.0),
+    );
+    let object = current_object();
+    var out: VertexOutput;
+    out.local = corners[index];
+    out.clip_position = camera.view_proj * object.model * vec4<f32>(corners[index], 0.0, 1.0);
+    // the object's tint is the rim
+    out.rim = object.tint;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput, @builtin(front_facing) front: bool) -> @location(0) vec4<f32> {
+    let screen_uv = in.clip_position.xy / vec2<f32>(textureDimensions(t_view));
+    let through = textureSample(t_view, s_view, screen_uv).rgb;
+    // the back is a flat slab of the rim color
+    var color = select(in.rim.rgb * 0.3, through, front);
+    let edge = max(abs(in.local.x), abs(in.local.y));
+    color = mix(color, in.rim.rgb, smoothstep(0.92, 0.98, edge));
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,196 @@
+use crate::texture::Texture;
+
+// every effect shader, they all read their input (and the tweaks) at group 0
+pub const INPUT_USES: &[(&str, u32)] = &[
+    ("grade.wgsl", 0),
+    ("motion_blur.wgsl", 0),
+    ("retro.wgsl", 0),
+    ("crt.wgsl", 0),
+];
+
+// One full screen effect. Post sets group 0 to the effect's input before
+// calling draw, the effect sets its pipeline and anything after that.
+pub trait Effect {
+    fn enabled(&self) -> bool;
+    fn draw(&self, render_pass: &mut wgpu::RenderPass);
+}
+
+// Runs the post effects between the scene and the text. While any effect is
+// on, the scene draws into one of two offscreen targets instead of the
+// swapchain; each effect reads the target the one before it wrote and writes
+// the other, and the last one writes the frame itself.
+pub struct Post {
+    pub targets: [Texture; 2],
+    inputs: [wgpu::BindGroup; 2],
+    pub input_layout: wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+}
+
+impl Post {
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        tweaks: &wgpu::Buffer,
+    ) -> Self {
+        let input_layout =
+            crate::reflect::bind_group_layout(device, "post input layout", INPUT_USES).unwrap();
+        let (targets, inputs) =
+            Self::create_targets(device, &input_layout, width, height, format, tweaks);
+        Self {
+            targets,
+            inputs,
+            input_layout,
+            format,
+        }
+    }
+
+    fn create_targets(
+        device: &wgpu::Device,
+        input_layout: &wgpu::BindGroupLayout,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        tweaks: &wgpu::Buffer,
+    ) -> ([Texture; 2], [wgpu::BindGroup; 2]) {
+        let targets = ["post target a", "post target b"]
+            .map(|label| Texture::create_render_target(device, width, height, format, label));
+        let inputs = [0, 1].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: input_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&targets[i].view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&targets[i].sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: tweaks.as_entire_binding(),
+                    },
+                ],
+                label: Some("post input bind group"),
+            })
+        });
+        (targets, inputs)
+    }
+
+    // the scene draws into these in place of the frame, so they follow its size
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        tweaks: &wgpu::Buffer,
+    ) {
+        (self.targets, self.inputs) = Self::create_targets(
+            device,
+            &self.input_layout,
+            width,
+            height,
+            self.format,
+            tweaks,
+        );
+    }
+
+    // where the scene should draw this frame
+    pub fn scene_view<'a>(
+        &'a self,
+        effects: &[&dyn Effect],
+        frame: &'a wgpu::TextureView,
+    ) -> &'a wgpu::TextureView {
+        if effects.iter().any(|e| e.enabled()) {
+            &self.targets[0].view
+        } else {
+            frame
+        }
+    }
+
+    pub fn run(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        effects: &[&dyn Effect],
+        frame: &wgpu::TextureView,
+    ) {
+        let enabled = effects.iter().filter(|e| e.enabled()).collect::<Vec<_>>();
+        for (i, effect) in enabled.iter().enumerate() {
+            let output = if i + 1 == enabled.len() {
+                frame
+            } else {
+                &self.targets[(i + 1) % 2].view
+            };
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("post pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: output,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_bind_group(0, &self.inputs[i % 2], &[]);
+            effect.draw(&mut render_pass);
+        }
+    }
+
+    // a pipeline for an effect's fragment shader, `layouts` come after the input
+    pub fn pipeline(
+        &self,
+        device: &wgpu::Device,
+        label: &str,
+        shader: &wgpu::ShaderModule,
+        layouts: &[&wgpu::BindGroupLayout],
+    ) -> wgpu::RenderPipeline {
+        let bind_group_layouts = std::iter::once(&self.input_layout)
+            .chain(layouts.iter().copied())
+            .collect::<Vec<_>>();
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(label),
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: self.format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: crate::pipeline_cache::current().as_deref(),
+        })
+    }
+
+    // the defines an effect's shader is loaded with
+    pub fn defines(&self) -> &'static [&'static str] {
+        if self.format.is_srgb() {
+            &["SRGB_TARGET"]
+        } else {
+            &[]
+        }
+    }
+}
@@ -0,0 +1,55 @@
+// What every post effect starts from: the frame so far (the scene, or what
+// the effect before this one made of it) and a triangle covering the screen.
+@group(0) @binding(0)
+var t_input: texture_2d<f32>;
+@group(0) @binding(1)
+var s_input: sampler;
+
+// whatever the shaders' `// @tweak` comments declared, see tweaks.rs; each
+// declared name stands for its value in here
+struct Tweaks {
+    values: array<vec4<f32>, 32>,
+}
+@group(0) @binding(2)
+var<uniform> tweaks: Tweaks;
+
+struct PostOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> PostOutput {
+    // (0,0), (2,0), (0,2) in uv, one corner past each edge
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: PostOutput;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+// for effects that work on the colors as they're shown rather than linear ones
+fn to_srgb(c: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+}
+
+fn to_linear(c: vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
+// the frame's colors as they end up on screen, and back
+fn shown(c: vec3<f32>) -> vec3<f32> {
+#ifdef SRGB_TARGET
+    return to_srgb(saturate(c));
+#else
+    return saturate(c);
+#endif
+}
+
+fn stored(c: vec3<f32>) -> vec3<f32> {
+#ifdef SRGB_TARGET
+    return to_linear(c);
+#else
+    return c;
+#endif
+}
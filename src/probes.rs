@@ -0,0 +1,136 @@
+use crate::math::{Point3, Vec3};
+use crate::noise::Noise;
+
+// probes across the middle of the ground, at the height of a cube's centre
+pub const GRID: u32 = 5;
+const SPACING: f32 = 8.0;
+const HEIGHT: f32 = -1.0;
+// directions each probe is baked from
+const SAMPLES: u32 = 1024;
+
+// 9 rgb coefficients, each padded to a vec4 for the uniform
+pub type Sh9 = [[f32; 4]; 9];
+
+// the real spherical harmonics up to band 2
+fn basis(d: Vec3) -> [f32; 9] {
+    [
+        0.282_095,
+        0.488_603 * d.y,
+        0.488_603 * d.z,
+        0.488_603 * d.x,
+        1.092_548 * d.x * d.y,
+        1.092_548 * d.y * d.z,
+        0.315_392 * (3.0 * d.z * d.z - 1.0),
+        1.092_548 * d.x * d.z,
+        0.546_274 * (d.x * d.x - d.y * d.y),
+    ]
+}
+
+// band by band, the cosine lobe convolution divided by pi, so evaluating the
+// result at a normal gives what a white surface facing that way reflects
+const LAMBERT: [f32; 9] = [
+    1.0,
+    2.0 / 3.0,
+    2.0 / 3.0,
+    2.0 / 3.0,
+    0.25,
+    0.25,
+    0.25,
+    0.25,
+    0.25,
+];
+
+// evenly spread over the sphere, the Fibonacci spiral
+fn directions() -> impl Iterator<Item = Vec3> {
+    let golden = std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+    (0..SAMPLES).map(move |i| {
+        let y = 1.0 - 2.0 * (i as f32 + 0.5) / SAMPLES as f32;
+        let r = (1.0 - y * y).sqrt();
+        let phi = golden * i as f32;
+        Vec3::new(r * phi.cos(), y, r * phi.sin())
+    })
+}
+
+// what the skybox shows along `d`, linear. The faces are picked the way the
+// GPU picks cubemap faces.
+fn sky(faces: &[image::RgbaImage; 6], d: Vec3) -> Vec3 {
+    let a = d.abs();
+    let (face, s, t, major) = if a.x >= a.y && a.x >= a.z {
+        if d.x > 0.0 {
+            (0, -d.z, -d.y, a.x)
+        } else {
+            (1, d.z, -d.y, a.x)
+        }
+    } else if a.y >= a.z {
+        if d.y > 0.0 {
+            (2, d.x, d.z, a.y)
+        } else {
+            (3, d.x, -d.z, a.y)
+        }
+    } else if d.z > 0.0 {
+        (4, d.x, -d.y, a.z)
+    } else {
+        (5, -d.x, -d.y, a.z)
+    };
+    let image = &faces[face];
+    let pixel = |v: f32, size: u32| (((v / major + 1.0) * 0.5 * size as f32) as u32).min(size - 1);
+    let [r, g, b, _] = image
+        .get_pixel(pixel(s, image.width()), pixel(t, image.height()))
+        .0;
+    let linear = |c: u8| (c as f32 / 255.0).powf(2.2);
+    Vec3::new(linear(r), linear(g), linear(b))
+}
+
+pub fn position(i: u32) -> Point3 {
+    let half = (GRID - 1) as f32 * SPACING / 2.0;
+    Point3::new(
+        (i % GRID) as f32 * SPACING - half,
+        HEIGHT,
+        (i / GRID) as f32 * SPACING - half,
+    )
+}
+
+// Bakes every probe from the skybox above and the ground below, the ground
+// lit by the sky. Only the static scene goes in, the cubes move too much to
+// be worth baking. Probe `i` in the result is at `position(i)`.
+pub fn bake(faces: &[image::RgbaImage; 6], noise: &Noise) -> Vec<Sh9> {
+    // how bright the ground is for a white albedo, the average sky above it
+    let up = directions().filter(|d| d.y > 0.0).collect::<Vec<_>>();
+    let sky_light = up.iter().map(|d| sky(faces, *d) * d.y).sum::<Vec3>() * 2.0 / up.len() as f32;
+
+    let baked = (0..GRID * GRID)
+        .map(|i| {
+            let probe = position(i);
+            let mut sh = [Vec3::ZERO; 9];
+            for d in directions() {
+                let radiance = if d.y < 0.0 {
+                    let t = (probe.y - crate::floor::FLOOR_Y) / -d.y;
+                    let hit = probe + d * t;
+                    crate::floor::ground_color(noise, hit.x, hit.z) * sky_light
+                } else {
+                    sky(faces, d)
+                };
+                for (c, y) in sh.iter_mut().zip(basis(d)) {
+                    *c += radiance * y;
+                }
+            }
+            let weight = 4.0 * std::f32::consts::PI / SAMPLES as f32;
+            std::array::from_fn(|k| sh[k] * weight * LAMBERT[k])
+        })
+        .collect::<Vec<[Vec3; 9]>>();
+
+    // There's no direct light, so this is all the light there is, and the
+    // backdrop is dark. Scaled so the average over every probe and direction
+    // is 1, what the cubes had before, keeping how it changes from one
+    // direction and place to another.
+    let average = baked
+        .iter()
+        .map(|sh| sh[0].dot(Vec3::ONE) / 3.0)
+        .sum::<f32>()
+        * basis(Vec3::Y)[0]
+        / baked.len() as f32;
+    baked
+        .iter()
+        .map(|sh| sh.map(|c| (c / average.max(1e-4)).extend(0.0).to_array()))
+        .collect()
+}
@@ -0,0 +1,86 @@
+use crate::bindings::Bindings;
+use crate::options::{OptionSettings, Options};
+use std::path::PathBuf;
+
+// everything a profile keeps
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct Settings {
+    options: OptionSettings,
+    // action and key, as `bind` in the console takes them
+    bindings: Vec<(String, String)>,
+}
+
+// A named set of settings (the F7 options and the key bindings), so one
+// checkout can be run on a laptop and a desktop without the two fighting
+// over present modes. `--profile <name>` picks one, "default" otherwise.
+// They're kept as <config dir>/praxis/profiles/<name>.ron, loaded at
+// startup and written back on quit.
+pub struct Profile {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl Profile {
+    pub fn from_args() -> anyhow::Result<Self> {
+        let args = std::env::args().collect::<Vec<_>>();
+        let name = args
+            .iter()
+            .position(|a| a == "--profile")
+            .and_then(|i| args.get(i + 1))
+            .map_or("default", String::as_str);
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            anyhow::bail!("a profile name is letters, digits, - and _, not {:?}", name);
+        }
+        let dir = crate::dirs::config_dir()
+            .ok_or_else(|| anyhow::anyhow!("there's no config directory to keep profiles in"))?;
+        Ok(Self {
+            name: name.to_string(),
+            path: dir.join("profiles").join(format!("{}.ron", name)),
+        })
+    }
+
+    // a missing file is a fresh profile, a broken one is left alone
+    pub fn load(&self, options: &mut Options, bindings: &mut Bindings) {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                log::info!("new profile {}", self.name);
+                return;
+            }
+            Err(e) => {
+                log::error!("couldn't read {}: {}", self.path.display(), e);
+                return;
+            }
+        };
+        let settings: Settings = match ron::from_str(&text) {
+            Ok(settings) => settings,
+            Err(e) => {
+                log::error!("ignoring {}: {}", self.path.display(), e);
+                return;
+            }
+        };
+        options.apply_settings(&settings.options);
+        for (action, key) in &settings.bindings {
+            if let Err(e) = bindings.bind(action, key) {
+                log::warn!("{}: {}", self.path.display(), e);
+            }
+        }
+        log::info!("loaded profile {} from {}", self.name, self.path.display());
+    }
+
+    pub fn save(&self, options: &Options, bindings: &Bindings) -> anyhow::Result<()> {
+        let settings = Settings {
+            options: options.settings(),
+            bindings: bindings.pairs(),
+        };
+        let text = ron::ser::to_string_pretty(&settings, ron::ser::PrettyConfig::default())?;
+        std::fs::create_dir_all(self.path.parent().unwrap())?;
+        std::fs::write(&self.path, text)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+// The graphics settings that cost frame time, as they end up applied. This
+// renderer has no MSAA, shadows, SSAO, bloom or render scale yet, so these
+// aren't quality tiers in the usual sense: a preset only picks which of the
+// optional passes it does have are on.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Graphics {
+    // the cubemap re-rendered around the scene every frame
+    pub reflections: bool,
+    pub mirror: bool,
+    pub shutter_angle: f32,
+    // how much of the configured weather falls
+    pub particles: f32,
+    // far cubes as impostor billboards
+    pub impostors: bool,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Preset {
+    Low,
+    // how praxis looked before there were presets
+    #[default]
+    Medium,
+    High,
+    Ultra,
+}
+
+const PRESETS: [Preset; 4] = [Preset::Low, Preset::Medium, Preset::High, Preset::Ultra];
+// what `--graphics` and `quality set` call each setting
+pub const NAMES: [&str; 5] = [
+    "reflections",
+    "mirror",
+    "motion_blur",
+    "particles",
+    "impostors",
+];
+
+impl Preset {
+    pub fn graphics(self) -> Graphics {
+        match self {
+            Preset::Low => Graphics {
+                reflections: false,
+                mirror: false,
+                shutter_angle: 0.0,
+                particles: 0.25,
+                impostors: true,
+            },
+            Preset::Medium => Graphics {
+                reflections: false,
+                mirror: false,
+                shutter_angle: 0.0,
+                particles: 1.0,
+                impostors: true,
+            },
+            Preset::High => Graphics {
+                reflections: true,
+                mirror: false,
+                shutter_angle: 180.0,
+                particles: 1.0,
+                impostors: true,
+            },
+            Preset::Ultra => Graphics {
+                reflections: true,
+                mirror: true,
+                shutter_angle: 270.0,
+                particles: 1.0,
+                impostors: false,
+            },
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        PRESETS
+            .into_iter()
+            .find(|p| format!("{:?}", p).eq_ignore_ascii_case(name))
+    }
+
+    pub fn next(self) -> Self {
+        let i = PRESETS.iter().position(|p| *p == self).unwrap();
+        PRESETS[(i + 1) % PRESETS.len()]
+    }
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "on" | "true" | "1" => Ok(true),
+        "off" | "false" | "0" => Ok(false),
+        _ => Err(format!("{} isn't on or off", value)),
+    }
+}
+
+// What's been set by hand on top of the preset. These stay when the preset
+// changes, `quality reset` drops them.
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Overrides {
+    pub reflections: Option<bool>,
+    pub mirror: Option<bool>,
+    pub shutter_angle: Option<f32>,
+    pub particles: Option<f32>,
+    pub impostors: Option<bool>,
+}
+
+impl Overrides {
+    pub fn apply(&self, preset: Graphics) -> Graphics {
+        Graphics {
+            reflections: self.reflections.unwrap_or(preset.reflections),
+            mirror: self.mirror.unwrap_or(preset.mirror),
+            shutter_angle: self.shutter_angle.unwrap_or(preset.shutter_angle),
+            particles: self.particles.unwrap_or(preset.particles),
+            impostors: self.impostors.unwrap_or(preset.impostors),
+        }
+    }
+
+    // one of NAMES to a value, from `--graphics name=value` or the console
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "reflections" => self.reflections = Some(parse_bool(value)?),
+            "mirror" => self.mirror = Some(parse_bool(value)?),
+            "impostors" => self.impostors = Some(parse_bool(value)?),
+            "motion_blur" => {
+                let angle = value
+                    .parse::<f32>()
+                    .map_err(|_| format!("{} isn't a shutter angle", value))?;
+                self.shutter_angle = Some(angle.clamp(0.0, 360.0));
+            }
+            "particles" => {
+                let density = value
+                    .parse::<f32>()
+                    .map_err(|_| format!("{} isn't a fraction", value))?;
+                self.particles = Some(density.clamp(0.0, 1.0));
+            }
+            _ => {
+                return Err(format!(
+                    "no setting called {}, try {}",
+                    name,
+                    NAMES.join(", ")
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    // the ones that are set, for the menu
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            self.reflections.is_some(),
+            self.mirror.is_some(),
+            self.shutter_angle.is_some(),
+            self.particles.is_some(),
+            self.impostors.is_some(),
+        ]
+        .into_iter()
+        .zip(NAMES)
+        .filter(|(set, _)| *set)
+        .map(|(_, name)| name)
+        .collect()
+    }
+}
@@ -0,0 +1,256 @@
+use anyhow::{anyhow, bail, Result};
+use std::collections::BTreeMap;
+
+// Bind group layouts read off the shaders instead of written out by hand.
+// `uses` lists every (shader, group) the layout is bound at, visibility is
+// whatever stages touch each binding across all of them, and two shaders that
+// disagree about what a binding is stop startup with both of them named.
+//
+// Sampled float textures are assumed filterable, which is all praxis uses.
+pub fn bind_group_layout(
+    device: &wgpu::Device,
+    label: &str,
+    uses: &[(&str, u32)],
+) -> Result<wgpu::BindGroupLayout> {
+    let entries = entries(uses, &[]).map_err(|e| anyhow!("{}: {}", label, e))?;
+    Ok(
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(label),
+            entries: &entries,
+        }),
+    )
+}
+
+// The entries on their own, with `defines` set on every shader, for layouts
+// that need something the WGSL can't say (objects.rs).
+pub fn entries(uses: &[(&str, u32)], defines: &[&str]) -> Result<Vec<wgpu::BindGroupLayoutEntry>> {
+    // binding -> (entry, the shader and variable it was first seen as)
+    let mut found: BTreeMap<u32, (wgpu::BindGroupLayoutEntry, String)> = BTreeMap::new();
+    for (shader, group) in uses {
+        let source = crate::shaders::load(shader, defines)?;
+        let module = naga::front::wgsl::parse_str(&source)
+            .map_err(|e| anyhow!("{}", e.emit_to_string_with_path(&source, shader)))?;
+        let info = naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .map_err(|e| anyhow!("{}", e.emit_to_string_with_path(&source, shader)))?;
+
+        for (handle, variable) in module.global_variables.iter() {
+            let Some(binding) = &variable.binding else {
+                continue;
+            };
+            if binding.group != *group {
+                continue;
+            }
+            let seen_as = format!(
+                "`{}` in {}",
+                variable.name.as_deref().unwrap_or("?"),
+                shader
+            );
+            let ty = binding_type(&module, variable)
+                .ok_or_else(|| anyhow!("{} isn't a kind of binding praxis handles", seen_as))?;
+            let visibility = module
+                .entry_points
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !info.get_entry_point(*i)[handle].is_empty())
+                .fold(wgpu::ShaderStages::NONE, |stages, (_, entry_point)| {
+                    stages
+                        | match entry_point.stage {
+                            naga::ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+                            naga::ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+                            naga::ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+                        }
+                });
+
+            match found.get_mut(&binding.binding) {
+                Some((entry, first)) if entry.ty != ty => bail!(
+                    "binding {} is {:?} as {} but {:?} as {}",
+                    binding.binding,
+                    entry.ty,
+                    first,
+                    ty,
+                    seen_as
+                ),
+                Some((entry, _)) => entry.visibility |= visibility,
+                None => {
+                    let entry = wgpu::BindGroupLayoutEntry {
+                        binding: binding.binding,
+                        visibility,
+                        ty,
+                        count: None,
+                    };
+                    found.insert(binding.binding, (entry, seen_as));
+                }
+            }
+        }
+    }
+    if found.is_empty() {
+        bail!("nothing is bound at {:?}", uses);
+    }
+    Ok(found.into_values().map(|(entry, _)| entry).collect())
+}
+
+fn binding_type(
+    module: &naga::Module,
+    variable: &naga::GlobalVariable,
+) -> Option<wgpu::BindingType> {
+    let buffer = |ty| {
+        Some(wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        })
+    };
+    match variable.space {
+        naga::AddressSpace::Uniform => return buffer(wgpu::BufferBindingType::Uniform),
+        naga::AddressSpace::Storage { access } => {
+            return buffer(wgpu::BufferBindingType::Storage {
+                read_only: !access.contains(naga::StorageAccess::STORE),
+            })
+        }
+        naga::AddressSpace::Handle => {}
+        _ => return None,
+    }
+
+    match module.types[variable.ty].inner {
+        naga::TypeInner::Sampler { comparison } => {
+            Some(wgpu::BindingType::Sampler(if comparison {
+                wgpu::SamplerBindingType::Comparison
+            } else {
+                wgpu::SamplerBindingType::Filtering
+            }))
+        }
+        naga::TypeInner::Image {
+            dim,
+            arrayed,
+            class,
+        } => {
+            let view_dimension = match (dim, arrayed) {
+                (naga::ImageDimension::D1, false) => wgpu::TextureViewDimension::D1,
+                (naga::ImageDimension::D2, false) => wgpu::TextureViewDimension::D2,
+                (naga::ImageDimension::D2, true) => wgpu::TextureViewDimension::D2Array,
+                (naga::ImageDimension::D3, false) => wgpu::TextureViewDimension::D3,
+                (naga::ImageDimension::Cube, false) => wgpu::TextureViewDimension::Cube,
+                (naga::ImageDimension::Cube, true) => wgpu::TextureViewDimension::CubeArray,
+                _ => return None,
+            };
+            let (sample_type, multisampled) = match class {
+                naga::ImageClass::Sampled { kind, multi } => (
+                    match kind {
+                        naga::ScalarKind::Float => {
+                            wgpu::TextureSampleType::Float { filterable: true }
+                        }
+                        naga::ScalarKind::Sint => wgpu::TextureSampleType::Sint,
+                        naga::ScalarKind::Uint => wgpu::TextureSampleType::Uint,
+                        _ => return None,
+                    },
+                    multi,
+                ),
+                naga::ImageClass::Depth { multi } => (wgpu::TextureSampleType::Depth, multi),
+                naga::ImageClass::Storage { .. } => return None,
+            };
+            Some(wgpu::BindingType::Texture {
+                sample_type,
+                view_dimension,
+                multisampled,
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every layout praxis builds, as the modules that build them pass it
+    const LAYOUTS: &[&[(&str, u32)]] = &[
+        &[("basic.wgsl", 0)],
+        &[("cloth.wgsl", 0)],
+        &[("cloth_draw.wgsl", 1)],
+        &[("crt.wgsl", 1)],
+        &[("cube.wgsl", 0)],
+        &[("cube.wgsl", 3), ("water.wgsl", 3), ("crt.wgsl", 2)],
+        &[("floor.wgsl", 1)],
+        &[("grade.wgsl", 1)],
+        &[("impostor.wgsl", 1)],
+        &[("lod_cull.wgsl", 0)],
+        &[("motion_blur.wgsl", 1)],
+        &[("outline.wgsl", 1)],
+        &[("portal.wgsl", 1)],
+        &[("retro.wgsl", 1)],
+        &[("skybox.wgsl", 1), ("cube.wgsl", 2), ("water.wgsl", 2)],
+        &[("splash.wgsl", 0)],
+        &[("sprite.wgsl", 1)],
+        &[("tilemap.wgsl", 0)],
+        &[("ui.wgsl", 0)],
+        &[("velocity.wgsl", 1)],
+        &[("water.wgsl", 1)],
+        &[("weather.wgsl", 0)],
+        &[("weather_draw.wgsl", 1)],
+    ];
+
+    #[test]
+    fn every_layout_reflects() {
+        for uses in LAYOUTS {
+            entries(uses, &[]).unwrap_or_else(|e| panic!("{:?}: {}", uses, e));
+        }
+        for defines in [&[][..], &["PUSH_CONSTANTS"]] {
+            entries(crate::objects::BIND_GROUP_USES, defines).unwrap();
+        }
+    }
+
+    #[test]
+    fn environment_has_the_lights() {
+        let entries = entries(
+            &[("skybox.wgsl", 1), ("cube.wgsl", 2), ("water.wgsl", 2)],
+            &[],
+        )
+        .unwrap();
+        let bindings: Vec<u32> = entries.iter().map(|e| e.binding).collect();
+        assert_eq!(bindings, [0, 1, 2, 3, 4]);
+
+        let uniform = wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        };
+        assert_eq!(
+            entries[0].ty,
+            wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::Cube,
+                multisampled: false,
+            }
+        );
+        assert_eq!(
+            entries[1].ty,
+            wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering)
+        );
+        assert_eq!(entries[3].ty, uniform);
+        assert_eq!(entries[4].ty, uniform);
+        assert!(entries[3].visibility.contains(wgpu::ShaderStages::FRAGMENT));
+    }
+
+    #[test]
+    fn disagreeing_shaders_are_an_error() {
+        // the environment cube map at 2 in cube.wgsl, the timer in crt.wgsl
+        let error = entries(&[("cube.wgsl", 2), ("crt.wgsl", 2)], &[])
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("cube.wgsl") && error.contains("crt.wgsl"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn nothing_bound_is_an_error() {
+        assert!(entries(&[("cube.wgsl", 7)], &[]).is_err());
+        assert!(entries(&[("nowhere.wgsl", 0)], &[]).is_err());
+    }
+}
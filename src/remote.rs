@@ -0,0 +1,215 @@
+use crate::math::Point3;
+use std::net::UdpSocket;
+
+// what's listened on without `--osc <address>`'s host
+const DEFAULT_HOST: &str = "127.0.0.1";
+// a datagram bigger than this isn't an OSC message praxis knows
+const MAX_PACKET: usize = 1536;
+
+// everything that can be controlled, and the arguments it takes
+const ADDRESSES: [(&str, &str); 6] = [
+    ("/camera/eye", "x y z"),
+    ("/camera/target", "x y z"),
+    ("/spawn", "[formation]"),
+    ("/shatter", "cube"),
+    ("/flash", "cube"),
+    ("/weather", "rain|snow|off"),
+];
+
+pub enum Remote {
+    CameraEye(Point3),
+    CameraTarget(Point3),
+    // a formation the way the console's `spawn` takes it, one cube if None
+    Spawn(Option<String>),
+    Shatter(usize),
+    Flash(usize),
+    Weather(String),
+}
+
+enum Arg {
+    Int(i32),
+    Float(f32),
+    Str(String),
+}
+
+impl Arg {
+    fn number(&self) -> Option<f32> {
+        match self {
+            Arg::Int(i) => Some(*i as f32),
+            Arg::Float(f) => Some(*f),
+            Arg::Str(_) => None,
+        }
+    }
+}
+
+// OSC over UDP, so a running praxis can be driven from a controller app,
+// a sequencer or a script: `--osc 9000` listens on localhost, `--osc
+// 0.0.0.0:9000` on every interface. Only the ADDRESSES are understood, and
+// `--osc-allow /camera/eye,/spawn` narrows that down to the ones named;
+// anything else is logged and dropped.
+pub struct RemoteControl {
+    socket: UdpSocket,
+    allowed: Vec<&'static str>,
+    buffer: Vec<u8>,
+}
+
+impl RemoteControl {
+    pub fn from_args() -> Option<Self> {
+        let args = std::env::args().collect::<Vec<_>>();
+        let value = |flag: &str| {
+            args.iter()
+                .position(|a| a == flag)
+                .and_then(|i| args.get(i + 1))
+        };
+        let address = value("--osc")?;
+        let address = if address.contains(':') {
+            address.clone()
+        } else {
+            format!("{}:{}", DEFAULT_HOST, address)
+        };
+        let allowed: Vec<&str> = match value("--osc-allow") {
+            Some(list) => {
+                let named = list.split(',').map(str::trim).collect::<Vec<_>>();
+                for name in named
+                    .iter()
+                    .filter(|n| !ADDRESSES.iter().any(|(a, _)| a == *n))
+                {
+                    let known = ADDRESSES.map(|(a, args)| format!("{} {}", a, args));
+                    log::warn!(
+                        "--osc-allow: there's no {}, only {}",
+                        name,
+                        known.join(", ")
+                    );
+                }
+                ADDRESSES
+                    .iter()
+                    .map(|(a, _)| *a)
+                    .filter(|a| named.contains(a))
+                    .collect()
+            }
+            None => ADDRESSES.iter().map(|(a, _)| *a).collect(),
+        };
+        let socket = match UdpSocket::bind(&address).and_then(|s| {
+            s.set_nonblocking(true)?;
+            Ok(s)
+        }) {
+            Ok(socket) => socket,
+            Err(e) => {
+                log::error!("no remote control, couldn't listen on {}: {}", address, e);
+                return None;
+            }
+        };
+        log::info!(
+            "listening for OSC on {}, allowing {}",
+            address,
+            allowed.join(" ")
+        );
+        Some(Self {
+            socket,
+            allowed,
+            buffer: vec![0; MAX_PACKET],
+        })
+    }
+
+    // everything that's come in since the last poll
+    pub fn poll(&mut self) -> Vec<Remote> {
+        let mut received = Vec::new();
+        while let Ok((length, from)) = self.socket.recv_from(&mut self.buffer) {
+            match parse(&self.buffer[..length]).and_then(|(address, args)| {
+                if !self.allowed.contains(&address.as_str()) {
+                    anyhow::bail!("{} isn't allowed", address);
+                }
+                remote(&address, &args)
+            }) {
+                Ok(message) => received.push(message),
+                Err(e) => log::warn!("ignoring OSC from {}: {:#}", from, e),
+            }
+        }
+        received
+    }
+}
+
+fn remote(address: &str, args: &[Arg]) -> anyhow::Result<Remote> {
+    let point = || match args {
+        [x, y, z] => match (x.number(), y.number(), z.number()) {
+            (Some(x), Some(y), Some(z)) => Ok(Point3::new(x, y, z)),
+            _ => anyhow::bail!("{} takes three numbers", address),
+        },
+        _ => anyhow::bail!("{} takes three numbers", address),
+    };
+    let cube = || match args {
+        [Arg::Int(i)] if *i >= 0 => Ok(*i as usize),
+        _ => anyhow::bail!("{} takes a cube index", address),
+    };
+    Ok(match address {
+        "/camera/eye" => Remote::CameraEye(point()?),
+        "/camera/target" => Remote::CameraTarget(point()?),
+        "/spawn" => match args {
+            [] => Remote::Spawn(None),
+            [Arg::Str(formation)] => Remote::Spawn(Some(formation.clone())),
+            _ => anyhow::bail!("/spawn takes a formation or nothing"),
+        },
+        "/shatter" => Remote::Shatter(cube()?),
+        "/flash" => Remote::Flash(cube()?),
+        "/weather" => match args {
+            [Arg::Str(kind)] => Remote::Weather(kind.clone()),
+            _ => anyhow::bail!("/weather takes rain, snow or off"),
+        },
+        _ => anyhow::bail!("there's no {}", address),
+    })
+}
+
+// One OSC message: the address, the type tags and the arguments, strings
+// nul terminated and everything padded out to four bytes, numbers big
+// endian. Bundles and the rarer types aren't handled.
+fn parse(packet: &[u8]) -> anyhow::Result<(String, Vec<Arg>)> {
+    let mut at = 0;
+    let address = string(packet, &mut at)?;
+    if address == "#bundle" {
+        anyhow::bail!("bundles aren't handled, send messages on their own");
+    }
+    if !address.starts_with('/') {
+        anyhow::bail!("not an OSC message");
+    }
+    // a message with no arguments can leave the tags out altogether
+    let tags = if at < packet.len() {
+        string(packet, &mut at)?
+    } else {
+        ",".to_string()
+    };
+    let Some(tags) = tags.strip_prefix(',') else {
+        anyhow::bail!("{} has no type tags", address);
+    };
+    let mut args = Vec::new();
+    for tag in tags.chars() {
+        args.push(match tag {
+            'i' => Arg::Int(i32::from_be_bytes(word(packet, &mut at)?)),
+            'f' => Arg::Float(f32::from_be_bytes(word(packet, &mut at)?)),
+            's' => Arg::Str(string(packet, &mut at)?),
+            _ => anyhow::bail!(
+                "{} has a {} argument, only i, f and s are handled",
+                address,
+                tag
+            ),
+        });
+    }
+    Ok((address, args))
+}
+
+fn string(packet: &[u8], at: &mut usize) -> anyhow::Result<String> {
+    let rest = packet.get(*at..).unwrap_or_default();
+    let Some(end) = rest.iter().position(|b| *b == 0) else {
+        anyhow::bail!("a string runs off the end");
+    };
+    let text = std::str::from_utf8(&rest[..end])?.to_string();
+    *at += (end + 4) & !3;
+    Ok(text)
+}
+
+fn word(packet: &[u8], at: &mut usize) -> anyhow::Result<[u8; 4]> {
+    let bytes = packet
+        .get(*at..*at + 4)
+        .ok_or_else(|| anyhow::anyhow!("an argument runs off the end"))?;
+    *at += 4;
+    Ok(bytes.try_into().unwrap())
+}
@@ -0,0 +1,184 @@
+use crate::texture::Texture;
+use crate::timer::Timer;
+use crate::{
+    arena, buffer_inspector, cloth, crt, environment, floor, gpu_timer, grade, instance_buffer,
+    light, lod, model, motion, objects, outline, pipeline_cache, portal, post, retro, shatter,
+    sprite, staging, stream, tilemap, water, weather,
+};
+use wgpu_text::glyph_brush::ab_glyph::FontRef;
+use wgpu_text::TextBrush;
+
+// Everything on the GPU: the device and the surface it draws to, the
+// pipelines and buffers the frame is made from, and the passes that keep
+// their own. App::init_gpu starts it, on startup and again whenever the
+// device is lost or the backend switched, and App::finish_gpu puts it
+// together once the loading worker's done, so it's either all there or not
+// at all and nothing in here needs unwrapping. The few that are optional
+// are ones that can fail to load, or are only sometimes on.
+pub struct Renderer {
+    #[allow(dead_code)]
+    pub instance: wgpu::Instance,
+    pub surface: wgpu::Surface<'static>,
+    pub adapter_info: wgpu::AdapterInfo,
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    pub config: wgpu::SurfaceConfiguration,
+    // the main pass's, so cubes hide what's behind them whatever order they're drawn in
+    pub depth_texture: Texture,
+    pub brush: TextBrush<FontRef<'static>>,
+    pub timer: Timer,
+
+    // the effect quad, nothing draws it yet
+    #[allow(dead_code)]
+    pub vertex_buffer: wgpu::Buffer,
+    #[allow(dead_code)]
+    pub index_buffer: wgpu::Buffer,
+    pub camera_buffer: wgpu::Buffer,
+    pub camera_bind_group: wgpu::BindGroup,
+
+    pub background_render_pipeline: wgpu::RenderPipeline,
+    pub background_texture_bind_group: wgpu::BindGroup,
+    pub background_vertex_buffer: wgpu::Buffer,
+    pub background_index_buffer: wgpu::Buffer,
+
+    pub cube_pipeline: wgpu::RenderPipeline,
+    // draws the cube into the dynamic cubemap faces, which need clockwise culling
+    pub cube_env_pipeline: wgpu::RenderPipeline,
+    // only made again when the cubes outgrow it, see App::upload_instances
+    pub cube_instances: instance_buffer::InstanceBuffer,
+    pub cube_model: model::Model,
+    pub mesh_arena: arena::MeshArena,
+    pub staging: staging::StagingRing,
+    pub objects: objects::ObjectArena,
+
+    pub environment: environment::Environment,
+    pub outline: outline::Outline,
+    pub post: post::Post,
+    pub grade: grade::ColorGrade,
+    pub motion_blur: motion::MotionBlur,
+    pub retro: retro::Retro,
+    pub crt: crt::Crt,
+    pub floor: floor::Floor,
+    pub water: water::Water,
+    pub cloth: cloth::Cloth,
+    pub portals: portal::Portals,
+    pub lod: lod::Lod,
+    // pieces of shattered cubes
+    pub shatter: shatter::Shatter,
+    // the sun and point lights, bound with the environment
+    pub light: light::Light,
+
+    pub sprites: Option<sprite::SpriteLayer>,
+    // rain or snow around the camera, `weather` in the console
+    pub weather: Option<weather::Weather>,
+    // 2D layer, `--tilemap <file.tmx>` or the built in demo
+    pub tilemap: Option<tilemap::TileLayer>,
+    // generated cubes around the camera, apart from the sim's
+    pub stream: Option<stream::InstanceStream>,
+    // --benchmark, and the GPU times it reports
+    pub gpu_timer: Option<gpu_timer::GpuTimer>,
+    pub pipeline_cache: Option<pipeline_cache::PipelineCache>,
+}
+
+// What a rebuild carries over from the old renderer: the time, and whatever
+// the player set up that lives on the GPU side. Everything else goes with
+// the old device.
+pub struct Kept {
+    pub timer: Timer,
+    pub sprites: Option<sprite::SpriteLayer>,
+    pub weather: Option<weather::Weather>,
+    pub tilemap: Option<tilemap::TileLayer>,
+    pub stream: Option<stream::InstanceStream>,
+    pub portals_enabled: bool,
+}
+
+impl Renderer {
+    // the old surface has to go before a new one can be made for the same
+    // window, so this drops the rest straight away
+    pub fn keep(self) -> Kept {
+        Kept {
+            timer: self.timer,
+            sprites: self.sprites,
+            weather: self.weather,
+            tilemap: self.tilemap,
+            stream: self.stream,
+            portals_enabled: self.portals.enabled,
+        }
+    }
+
+    // The swapchain, the depth buffer and every target drawn at the size of
+    // the screen (post, outline, motion blur, the reflections and portal
+    // views) are made again at the new size. Gives back whether anything
+    // changed: minimizing gives 0x0, which can't be configured, so that's
+    // left until the window comes back.
+    pub fn resize(&mut self, width: u32, height: u32, tweaks: &wgpu::Buffer) -> bool {
+        if width == 0 || height == 0 || (self.config.width, self.config.height) == (width, height) {
+            return false;
+        }
+        self.config.width = width;
+        self.config.height = height;
+        self.surface.configure(&self.device, &self.config);
+        self.depth_texture =
+            Texture::create_depth_texture(&self.device, &self.config, "depth texture");
+        self.brush
+            .resize_view(width as f32, height as f32, &self.queue);
+        self.post.resize(&self.device, width, height, tweaks);
+        self.outline
+            .resize(&self.device, &self.queue, width, height);
+        self.motion_blur.resize(&self.device, width, height);
+        self.floor.resize(&self.device, width, height);
+        self.water.resize(&self.device, width, height);
+        self.portals.resize(&self.device, width, height);
+        if let Some(tilemap) = &mut self.tilemap {
+            tilemap.resize(width, height);
+        }
+        true
+    }
+
+    // the surface's, which is the window's
+    pub fn screen_size(&self) -> (f32, f32) {
+        (self.config.width as f32, self.config.height as f32)
+    }
+
+    // every texture kept from frame to frame that the texture viewer can
+    // show, the targets first. There's no shadow map yet.
+    pub fn textures(&self) -> Vec<(&'static str, &wgpu::Texture)> {
+        vec![
+            ("depth", &self.depth_texture.texture),
+            ("post a", &self.post.targets[0].texture),
+            ("post b", &self.post.targets[1].texture),
+            ("velocity", &self.motion_blur.velocity.texture),
+            ("floor reflection", &self.floor.reflection.texture),
+            ("water reflection", &self.water.reflection.texture),
+            ("portal a", &self.portals.views[0].texture),
+            ("portal b", &self.portals.views[1].texture),
+            ("portal a depth", &self.portals.depths[0].texture),
+            ("portal b depth", &self.portals.depths[1].texture),
+            ("environment", &self.environment.dynamic_map.texture),
+            ("impostor atlas", &self.lod.atlas.texture),
+            ("floor ao", &self.floor.ao.texture),
+            (
+                "cube skins",
+                &self.cube_model.materials[0].diffuse_texture.texture,
+            ),
+        ]
+    }
+
+    // the buffers the buffer inspector can read back and what's in them,
+    // only the first `cubes` of the instance buffer are
+    pub fn buffers(&self, cubes: usize) -> Vec<buffer_inspector::Inspectable<'_>> {
+        use buffer_inspector::{whole, CAMERA, INSTANCE, LIGHT, POINT_LIGHT, TIMER};
+        vec![
+            whole("camera", &self.camera_buffer, CAMERA),
+            (
+                "instances",
+                &self.cube_instances.buffer,
+                INSTANCE,
+                cubes as u64,
+            ),
+            whole("timer", &self.timer.timer_buffer, TIMER),
+            whole("light", &self.light.buffer, LIGHT),
+            whole("point lights", &self.light.points, POINT_LIGHT),
+        ]
+    }
+}
@@ -0,0 +1,164 @@
+const MAX_COLORS: usize = 16;
+const PIXEL_SIZES: [u32; 4] = [2, 3, 4, 6];
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Palette {
+    #[default]
+    Full,
+    // 4 steps per channel
+    SixtyFour,
+    Pico8,
+    GameBoy,
+}
+
+impl Palette {
+    fn colors(self) -> &'static [u32] {
+        match self {
+            Palette::Full | Palette::SixtyFour => &[],
+            Palette::Pico8 => &[
+                0x000000, 0x1d2b53, 0x7e2553, 0x008751, 0xab5236, 0x5f574f, 0xc2c3c7, 0xfff1e8,
+                0xff004d, 0xffa300, 0xffec27, 0x00e436, 0x29adff, 0x83769c, 0xff77a8, 0xffccaa,
+            ],
+            Palette::GameBoy => &[0x0f380f, 0x306230, 0x8bac0f, 0x9bbc0f],
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            Palette::Full => Palette::SixtyFour,
+            Palette::SixtyFour => Palette::Pico8,
+            Palette::Pico8 => Palette::GameBoy,
+            Palette::GameBoy => Palette::Full,
+        }
+    }
+}
+
+// what the options menu keeps for the retro look, so it survives the gpu
+// side being rebuilt
+#[derive(Copy, Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct RetroSettings {
+    pub enabled: bool,
+    pub pixel_size: u32,
+    pub palette: Palette,
+    pub dither: bool,
+}
+
+impl Default for RetroSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            pixel_size: 4,
+            palette: Palette::Full,
+            dither: true,
+        }
+    }
+}
+
+impl RetroSettings {
+    pub fn cycle_pixel_size(&mut self) {
+        let i = PIXEL_SIZES
+            .iter()
+            .position(|s| *s == self.pixel_size)
+            .unwrap_or(0);
+        self.pixel_size = PIXEL_SIZES[(i + 1) % PIXEL_SIZES.len()];
+    }
+
+    pub fn cycle_palette(&mut self) {
+        self.palette = self.palette.next();
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct RetroUniform {
+    pixel_size: f32,
+    levels: f32,
+    colors: f32,
+    dither: f32,
+    palette: [[f32; 4]; MAX_COLORS],
+}
+
+// A chunky low resolution look: the frame is shown in blocks of
+// `pixel_size` screen pixels, optionally snapped to a small palette with
+// ordered dithering. `p` toggles it, the rest is in the options menu.
+pub struct Retro {
+    settings: RetroSettings,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Retro {
+    pub fn new(device: &wgpu::Device, post: &crate::post::Post) -> Self {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("retro uniform buffer"),
+            size: std::mem::size_of::<RetroUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = crate::reflect::bind_group_layout(
+            device,
+            "retro bind group layout",
+            &[("retro.wgsl", 1)],
+        )
+        .unwrap();
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("retro bind group"),
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("retro shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                crate::shaders::load("retro.wgsl", post.defines())
+                    .unwrap()
+                    .into(),
+            ),
+        });
+        let pipeline = post.pipeline(device, "retro pipeline", &shader, &[&bind_group_layout]);
+
+        Self {
+            settings: RetroSettings::default(),
+            uniform_buffer,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    pub fn set(&mut self, queue: &wgpu::Queue, settings: RetroSettings) {
+        self.settings = settings;
+        let mut palette = [[0.0; 4]; MAX_COLORS];
+        let colors = settings.palette.colors();
+        for (slot, rgb) in palette.iter_mut().zip(colors) {
+            let channel = |shift: u32| ((rgb >> shift) & 0xff) as f32 / 255.0;
+            *slot = [channel(16), channel(8), channel(0), 1.0];
+        }
+        let uniform = RetroUniform {
+            pixel_size: settings.pixel_size as f32,
+            levels: if settings.palette == Palette::SixtyFour {
+                4.0
+            } else {
+                0.0
+            },
+            colors: colors.len() as f32,
+            dither: if settings.dither { 1.0 } else { 0.0 },
+            palette,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+}
+
+impl crate::post::Effect for Retro {
+    fn enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
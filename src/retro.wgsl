@@ -0,0 +1,67 @@
+#include "post.wgsl"
+
+#define MAX_COLORS 16
+
+struct Retro {
+    // screen pixels per retro pixel
+    pixel_size: f32,
+    // steps per channel, 0 for none
+    levels: f32,
+    // colors in `palette` to snap to, 0 for none
+    colors: f32,
+    // 1 for ordered dithering before snapping
+    dither: f32,
+    palette: array<vec4<f32>, MAX_COLORS>,
+}
+@group(1) @binding(0)
+var<uniform> retro: Retro;
+
+// 4x4 Bayer matrix, -0.5..0.5
+fn bayer(pixel: vec2<u32>) -> f32 {
+    var matrix = array<f32, 16>(
+        0.0, 8.0, 2.0, 10.0,
+        12.0, 4.0, 14.0, 6.0,
+        3.0, 11.0, 1.0, 9.0,
+        15.0, 7.0, 13.0, 5.0,
+    );
+    return (matrix[(pixel.y % 4u) * 4u + pixel.x % 4u] + 0.5) / 16.0 - 0.5;
+}
+
+fn nearest(color: vec3<f32>) -> vec3<f32> {
+    var best = retro.palette[0].rgb;
+    for (var i = 1; i < i32(retro.colors); i++) {
+        let candidate = retro.palette[i].rgb;
+        if distance(color, candidate) < distance(color, best) {
+            best = candidate;
+        }
+    }
+    return best;
+}
+
+@fragment
+fn fs_main(in: PostOutput) -> @location(0) vec4<f32> {
+    // Every screen pixel in a block reads the block's centre, which is what
+    // a point sampled low resolution frame scaled up with nearest would show.
+    let size = vec2<f32>(textureDimensions(t_input));
+    let block = floor(in.uv * size / retro.pixel_size);
+    let centre = min((block + 0.5) * retro.pixel_size, size - 1.0);
+    let color = textureLoad(t_input, vec2<i32>(centre), 0);
+
+    var display = shown(color.rgb);
+    var step = 0.0;
+    if retro.levels > 0.0 {
+        step = 1.0 / (retro.levels - 1.0);
+    } else if retro.colors > 0.0 {
+        // palettes are far apart, so dither about as much as a few levels
+        step = 0.25;
+    }
+    if retro.dither > 0.0 {
+        display += bayer(vec2<u32>(block)) * step;
+    }
+    if retro.levels > 0.0 {
+        display = round(saturate(display) / step) * step;
+    } else if retro.colors > 0.0 {
+        display = nearest(saturate(display));
+    }
+    return vec4<f32>(stored(display), color.a);
+}
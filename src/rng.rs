@@ -0,0 +1,60 @@
+use rand::{Rng as _, SeedableRng};
+
+// All gameplay randomness goes through this, so the same seed replays the
+// same run. Anything reaching for rand::random directly breaks that.
+#[derive(Clone)]
+pub struct Rng {
+    seed: u64,
+    rng: rand::rngs::StdRng,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: rand::rngs::StdRng::seed_from_u64(seed),
+        }
+    }
+
+    // `--seed <n>` on the command line, then PRAXIS_SEED, otherwise a fresh one.
+    // Always logged so an interesting run can be repeated.
+    pub fn from_args() -> Self {
+        let args = std::env::args().collect::<Vec<_>>();
+        let from_cli = args
+            .iter()
+            .position(|a| a == "--seed")
+            .and_then(|i| args.get(i + 1));
+        let seed = from_cli
+            .cloned()
+            .or_else(|| std::env::var("PRAXIS_SEED").ok())
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_else(rand::random);
+        log::info!("seed {}", seed);
+        Self::new(seed)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // uniform in 0..1
+    pub fn f32(&mut self) -> f32 {
+        self.rng.gen()
+    }
+
+    // uniform in 0..n
+    pub fn below(&mut self, n: u32) -> u32 {
+        self.rng.gen_range(0..n)
+    }
+
+    // for seeding an rng of its own
+    pub fn u64(&mut self) -> u64 {
+        self.rng.gen()
+    }
+}
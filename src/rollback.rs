@@ -0,0 +1,263 @@
+use crate::math::Vec3;
+use crate::net;
+use crate::rng::Rng;
+use crate::simulation::{self, Simulation};
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+// ticks of state kept to go back to, so how late an input can be and still
+// be fixed up; anyone further behind than this is waited for
+const HISTORY: usize = 12;
+// how many of our latest inputs go in each packet
+const REDUNDANCY: usize = 8;
+// at most this many ticks are run in one frame, after a hitch it's let go
+const MAX_CATCH_UP: u32 = 10;
+const WINDOW: Duration = Duration::from_secs(1);
+// every peer simulates everyone, so it stays small
+pub const MAX_PLAYERS: usize = 8;
+
+// what the stats overlay shows, over the last second
+#[derive(Copy, Clone, Default)]
+pub struct Depth {
+    pub deepest: u32,
+    pub rollbacks: u32,
+    // ticks run again
+    pub resimulated: u32,
+    // frames that waited on someone's inputs
+    pub stalls: u32,
+}
+
+#[derive(Default)]
+struct Inputs {
+    known: BTreeMap<u32, Vec3>,
+    // what each tick was actually run with, guessed or not
+    used: BTreeMap<u32, Vec3>,
+    // every tick before this one is known
+    confirmed: u32,
+}
+
+impl Inputs {
+    // known, or failing that the last one known before it, or standing still
+    fn at(&self, tick: u32) -> Vec3 {
+        self.known
+            .range(..=tick)
+            .next_back()
+            .map_or(Vec3::ZERO, |(_, input)| *input)
+    }
+}
+
+// An experiment with rollback netcode, for `--server --rollback <players>`
+// sessions. There's no state from the server at all: every peer runs the
+// same Simulation at the same fixed 60Hz from the same seed, the only thing
+// going between them is inputs. Ours go in straight away, and everyone
+// else's are guessed to be whatever they last were. When one turns up for
+// a tick that's already been run and it isn't what was guessed, the state
+// from before that tick is put back and everything since run again with
+// it, which is what the last HISTORY ticks of state are kept for. Each
+// rollback costs a copy of the whole simulation a tick, so it's only for a
+// handful of cubes.
+pub struct Rollback {
+    pub slot: usize,
+    sim: Simulation,
+    rng: Rng,
+    // the next tick to run
+    tick: u32,
+    inputs: Vec<Inputs>,
+    // the state just before each of the last few ticks
+    history: VecDeque<(u32, Simulation, Rng)>,
+    // time not yet made into ticks
+    acc: f64,
+    depth: Depth,
+    pub shown: Depth,
+    since_shown: Instant,
+    desynced: bool,
+}
+
+impl Rollback {
+    pub fn new(slot: usize, players: usize, seed: u64) -> Self {
+        let mut sim = Simulation::hosted();
+        for _ in 0..players {
+            sim.join();
+        }
+        Self {
+            slot,
+            sim,
+            rng: Rng::new(seed),
+            tick: 0,
+            inputs: (0..players).map(|_| Inputs::default()).collect(),
+            history: VecDeque::new(),
+            acc: 0.0,
+            depth: Depth::default(),
+            shown: Depth::default(),
+            since_shown: Instant::now(),
+            desynced: false,
+        }
+    }
+
+    pub fn cubes(&self) -> &[crate::Instance] {
+        &self.sim.cubes
+    }
+
+    // Runs however many ticks `frame_time` covers with us going `direction`,
+    // giving back our latest inputs to send if any were.
+    pub fn update(&mut self, frame_time: f64, direction: Vec3) -> Option<(u32, Vec<Vec3>)> {
+        let tick_seconds = net::TICK.as_secs_f64();
+        self.acc = (self.acc + frame_time).min(tick_seconds * MAX_CATCH_UP as f64);
+        let mut ran = false;
+        while self.acc >= tick_seconds {
+            // nobody can be rolled back past the history, so whoever's
+            // slowest is waited for rather than run ahead of
+            let slowest = self
+                .inputs
+                .iter()
+                .enumerate()
+                .filter(|(p, _)| *p != self.slot)
+                .map(|(_, inputs)| inputs.confirmed)
+                .min()
+                .unwrap_or(self.tick);
+            if self.tick >= slowest.saturating_add(HISTORY as u32 - 1) {
+                self.depth.stalls += 1;
+                break;
+            }
+            let ours = &mut self.inputs[self.slot];
+            ours.known.insert(self.tick, direction);
+            ours.confirmed = self.tick.saturating_add(1);
+            self.advance();
+            self.acc -= tick_seconds;
+            ran = true;
+        }
+        if self.since_shown.elapsed() > WINDOW {
+            self.shown = std::mem::take(&mut self.depth);
+            self.since_shown = Instant::now();
+        }
+        ran.then(|| {
+            let last = self.tick - 1;
+            let first = last.saturating_sub(REDUNDANCY as u32 - 1);
+            let ours = &self.inputs[self.slot];
+            (last, (first..=last).map(|t| ours.at(t)).collect())
+        })
+    }
+
+    // Player `slot`'s inputs, the last for `tick`. Any that change a tick
+    // already run send everything back to just before it.
+    pub fn receive(&mut self, slot: usize, tick: u32, directions: &[Vec3]) {
+        let Some(inputs) = self.inputs.get_mut(slot).filter(|_| slot != self.slot) else {
+            return;
+        };
+        let first = tick
+            .saturating_add(1)
+            .saturating_sub(directions.len() as u32);
+        let mut earliest = None;
+        for (t, direction) in (first..=tick).zip(directions) {
+            if inputs.known.insert(t, *direction).is_none() && earliest.is_none() {
+                earliest = Some(t);
+            }
+        }
+        while inputs.confirmed < u32::MAX && inputs.known.contains_key(&inputs.confirmed) {
+            inputs.confirmed += 1;
+        }
+        let Some(earliest) = earliest else {
+            return;
+        };
+        // a new input can change the guesses after it as well as its own tick
+        let wrong = inputs
+            .used
+            .range(earliest..)
+            .find(|(t, used)| inputs.at(**t) != **used)
+            .map(|(t, _)| *t);
+        if let Some(wrong) = wrong {
+            self.roll_back(wrong);
+        }
+    }
+
+    fn roll_back(&mut self, to: u32) {
+        let Some(i) = self.history.iter().position(|(t, ..)| *t == to) else {
+            if !self.desynced {
+                log::error!(
+                    "an input for tick {} came too late to roll back for, out of sync from here",
+                    to
+                );
+                self.desynced = true;
+            }
+            return;
+        };
+        let now = self.tick;
+        let (_, sim, rng) = self.history.drain(i..).next().unwrap();
+        self.sim = sim;
+        self.rng = rng;
+        self.tick = to;
+        while self.tick < now {
+            self.advance();
+        }
+        let depth = now - to;
+        self.depth.deepest = self.depth.deepest.max(depth);
+        self.depth.rollbacks += 1;
+        self.depth.resimulated += depth;
+    }
+
+    fn advance(&mut self) {
+        self.history
+            .push_back((self.tick, self.sim.clone(), self.rng.clone()));
+        if self.history.len() > HISTORY {
+            self.history.pop_front();
+        }
+        let oldest = self.history.front().map_or(0, |(t, ..)| *t);
+        for (player, inputs) in self.sim.players.iter_mut().zip(&mut self.inputs) {
+            let input = inputs.at(self.tick);
+            inputs.used.insert(self.tick, input);
+            player.wanted = Some(simulation::walk(input));
+            // nothing before the history can be gone back to, but the
+            // newest known before it is still the guess after it
+            while inputs
+                .used
+                .first_key_value()
+                .is_some_and(|(t, _)| *t < oldest)
+            {
+                inputs.used.pop_first();
+            }
+            while inputs.known.len() > 1 && inputs.known.keys().nth(1).is_some_and(|t| *t <= oldest)
+            {
+                inputs.known.pop_first();
+            }
+        }
+        // sounds and flashes would go off again on every rollback
+        self.sim.step(net::TICK.as_secs_f64(), &mut self.rng);
+        self.sim.dirty.clear();
+        self.tick = self.tick.saturating_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(rollback: &Rollback) -> Vec<Vec3> {
+        rollback.cubes().iter().map(|c| c.position).collect()
+    }
+
+    #[test]
+    fn a_late_input_ends_up_where_knowing_it_would_have() {
+        let right = Vec3::new(1.0, 0.0, 0.0);
+        let mut late = Rollback::new(0, 2, 7);
+        let mut known = Rollback::new(0, 2, 7);
+        known.receive(1, 5, &[right; 6]);
+        for _ in 0..6 {
+            late.update(net::TICK.as_secs_f64(), Vec3::ZERO);
+            known.update(net::TICK.as_secs_f64(), Vec3::ZERO);
+        }
+        assert_ne!(positions(&late), positions(&known));
+        late.receive(1, 5, &[right; 6]);
+        assert_eq!(positions(&late), positions(&known));
+        assert_eq!(late.depth.rollbacks, 1);
+        assert_eq!(late.depth.deepest, 6);
+    }
+
+    #[test]
+    fn nonsense_inputs_dont_panic() {
+        let mut rollback = Rollback::new(0, 2, 7);
+        rollback.receive(1, u32::MAX, &[Vec3::ONE; 4]);
+        rollback.receive(5, 3, &[Vec3::ONE]);
+        rollback.receive(0, 3, &[Vec3::ONE]);
+        rollback.update(net::TICK.as_secs_f64(), Vec3::ZERO);
+    }
+}
@@ -0,0 +1,287 @@
+use crate::math::{Quat, Rotation, Vec3};
+use crate::physics::{Filter, Layers};
+use anyhow::{bail, Result};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const THUMBNAIL_SIZE: (u32, u32) = (160, 120);
+// from `--bake`, only there while the scene is the one it was baked from
+const AO_FILE: &str = "ao.png";
+const AUTOSAVE_PREFIX: &str = "autosave";
+const AUTOSAVE_SLOTS: usize = 3;
+// exists while praxis is running, so finding one at startup means the last run crashed
+const LOCK_FILE: &str = "running.lock";
+
+// saves/ in the data dir
+fn save_dir() -> PathBuf {
+    crate::dirs::data_dir().join("saves")
+}
+
+// Slots used to go in the working directory, they're still found there
+// until moved.
+pub fn warn_about_old_saves() {
+    let old = PathBuf::from("saves");
+    if old.is_dir() && std::fs::canonicalize(&old).ok() != std::fs::canonicalize(save_dir()).ok() {
+        log::warn!(
+            "found saves in {}, praxis keeps them in {} now (move them, or run with --data-dir .)",
+            std::env::current_dir().unwrap_or_default().display(),
+            save_dir().display()
+        );
+    }
+}
+
+// A saved scene. Each slot is a folder under saves/ in the data dir holding scene.txt, which
+// is one `key value` per line, and a thumbnail of the frame it was saved on.
+//
+// A `cube x y z` line can be followed by a rotation line to turn that cube,
+// in any of the forms `math::Rotation` parses (`euler 0 45 0`, ...). An
+// `skin n` line after a cube picks its cube::SKINS layer, and a `layer name`
+// line its collision layer (physics::Layers, player for the first cube and
+// static for the rest otherwise). `collide a b` and `ignore a b` lines change
+// which layers touch from physics::Filter's default. An
+// `ao min_x min_z size` line says a baked ao.png covers that square of ground.
+pub struct Slot {
+    pub name: String,
+    pub saved_at: SystemTime,
+    pub cubes: Vec<Cube>,
+    pub ao: Option<crate::bake::Area>,
+    pub filter: Filter,
+}
+
+pub struct Cube {
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub skin: u32,
+    pub layer: Layers,
+}
+
+impl Slot {
+    fn dir(name: &str) -> PathBuf {
+        save_dir().join(name.replace(|c: char| !c.is_alphanumeric(), "-"))
+    }
+
+    // "slot 1", "slot 2", ... skipping names that are taken
+    pub fn next_name() -> String {
+        (1..)
+            .map(|i| format!("slot {}", i))
+            .find(|name| !Self::dir(name).exists())
+            .unwrap()
+    }
+
+    pub fn ao_path(&self) -> PathBuf {
+        Self::dir(&self.name).join(AO_FILE)
+    }
+
+    // the baked AO map, when there is one and it can be read
+    pub fn ao_image(&self) -> Option<image::DynamicImage> {
+        self.ao?;
+        image::open(self.ao_path())
+            .map_err(|e| log::warn!("ignoring the AO map of {}: {}", self.name, e))
+            .ok()
+    }
+
+    // an AO map from before is dropped, the cubes it was baked from may have moved
+    pub fn save(&self, thumbnail: Option<&image::RgbaImage>) -> Result<()> {
+        let dir = Self::dir(&self.name);
+        std::fs::create_dir_all(&dir)?;
+
+        let mut scene = format!(
+            "name {}\nsaved {}\n",
+            self.name,
+            self.saved_at.duration_since(UNIX_EPOCH)?.as_secs()
+        );
+        if let Some(ao) = self.ao {
+            scene += &format!("ao {} {} {}\n", ao.min.x, ao.min.y, ao.size);
+        }
+        for line in self.filter.scene_lines() {
+            scene += &format!("{}\n", line);
+        }
+        for (i, cube) in self.cubes.iter().enumerate() {
+            let p = cube.position;
+            scene += &format!("cube {} {} {}\n", p.x, p.y, p.z);
+            if !cube.rotation.abs_diff_eq(Quat::IDENTITY, 1e-6) {
+                scene += &format!("{}\n", Rotation::euler(cube.rotation));
+            }
+            if cube.skin != 0 {
+                scene += &format!("skin {}\n", cube.skin);
+            }
+            if cube.layer != Layers::spawned(i) {
+                // only ever a single layer, so it has a name
+                scene += &format!("layer {}\n", cube.layer.name().unwrap_or("static"));
+            }
+        }
+        std::fs::write(dir.join("scene.txt"), scene)?;
+        let _ = std::fs::remove_file(dir.join(AO_FILE));
+
+        if let Some(frame) = thumbnail {
+            let (width, height) = THUMBNAIL_SIZE;
+            image::imageops::thumbnail(frame, width, height).save(dir.join("thumbnail.png"))?;
+        }
+        Ok(())
+    }
+
+    fn load(dir: PathBuf) -> Result<Self> {
+        let scene = std::fs::read_to_string(dir.join("scene.txt"))?;
+        let mut slot = Slot {
+            name: String::new(),
+            saved_at: UNIX_EPOCH,
+            cubes: Vec::new(),
+            ao: None,
+            filter: Filter::default(),
+        };
+        for line in scene.lines() {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "name" => slot.name = value.to_string(),
+                "saved" => slot.saved_at = UNIX_EPOCH + Duration::from_secs(value.parse()?),
+                "cube" => {
+                    let xyz = value
+                        .split_whitespace()
+                        .map(|v| v.parse::<f32>())
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if xyz.len() != 3 {
+                        bail!("bad cube line in {:?}: {}", dir, line);
+                    }
+                    slot.cubes.push(Cube {
+                        position: Vec3::new(xyz[0], xyz[1], xyz[2]),
+                        rotation: Quat::IDENTITY,
+                        skin: 0,
+                        layer: Layers::spawned(slot.cubes.len()),
+                    });
+                }
+                "ao" => {
+                    let values = value
+                        .split_whitespace()
+                        .map(|v| v.parse::<f32>())
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let &[x, z, size] = &values[..] else {
+                        bail!("bad ao line in {:?}: {}", dir, line);
+                    };
+                    slot.ao = Some(crate::bake::Area {
+                        min: crate::math::Vec2::new(x, z),
+                        size,
+                    });
+                }
+                "skin" => {
+                    let Some(cube) = slot.cubes.last_mut() else {
+                        bail!("skin before any cube in {:?}: {}", dir, line);
+                    };
+                    cube.skin = value.parse()?;
+                    if cube.skin as usize >= crate::cube::SKINS.len() {
+                        bail!("no skin {} in {:?}", cube.skin, dir);
+                    }
+                }
+                "layer" => {
+                    let Some(cube) = slot.cubes.last_mut() else {
+                        bail!("layer before any cube in {:?}: {}", dir, line);
+                    };
+                    let Some(layer) = Layers::parse(value) else {
+                        bail!("no layer {} in {:?}", value, dir);
+                    };
+                    cube.layer = layer;
+                }
+                "collide" | "ignore" => {
+                    let layers = value
+                        .split_whitespace()
+                        .map(Layers::parse)
+                        .collect::<Option<Vec<_>>>();
+                    let Some(&[a, b]) = layers.as_deref() else {
+                        bail!("bad {} line in {:?}: {}", key, dir, line);
+                    };
+                    slot.filter.set(a, b, key == "collide");
+                }
+                "euler" | "axis" | "look_at" => {
+                    let Some(cube) = slot.cubes.last_mut() else {
+                        bail!("rotation before any cube in {:?}: {}", dir, line);
+                    };
+                    let rotation = line
+                        .parse::<Rotation>()
+                        .map_err(|e| anyhow::anyhow!("bad rotation in {:?}: {}", dir, e))?;
+                    cube.rotation = rotation.placed_at(cube.position).to_quat()?;
+                }
+                _ => log::warn!("unknown line in {:?}: {}", dir, line),
+            }
+        }
+        Ok(slot)
+    }
+
+    // every readable slot, newest first
+    pub fn list() -> Vec<Slot> {
+        let Ok(entries) = std::fs::read_dir(save_dir()) else {
+            return Vec::new();
+        };
+        let mut slots = entries
+            .flatten()
+            .filter_map(|entry| match Self::load(entry.path()) {
+                Ok(slot) => Some(slot),
+                Err(e) => {
+                    log::warn!("skipping save {:?}: {}", entry.path(), e);
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        slots.sort_by_key(|slot| std::cmp::Reverse(slot.saved_at));
+        slots
+    }
+
+    pub fn latest_autosave() -> Option<Slot> {
+        Self::list()
+            .into_iter()
+            .find(|slot| slot.name.starts_with(AUTOSAVE_PREFIX))
+    }
+
+    pub fn age(&self) -> String {
+        let secs = self.saved_at.elapsed().unwrap_or_default().as_secs();
+        match secs {
+            0..=59 => "just now".to_string(),
+            60..=3599 => format!("{} min ago", secs / 60),
+            3600..=86399 => format!("{} h ago", secs / 3600),
+            _ => format!("{} days ago", secs / 86400),
+        }
+    }
+}
+
+// Writes the scene to autosave 1..3 in turn, so a save that gets cut off
+// halfway still leaves the older ones intact.
+pub struct Autosave {
+    pub interval: Duration,
+    last: Instant,
+    next: usize,
+}
+
+impl Default for Autosave {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            last: Instant::now(),
+            // so the first autosave goes into slot 1
+            next: AUTOSAVE_SLOTS - 1,
+        }
+    }
+}
+
+impl Autosave {
+    // the slot name to autosave into, when it's time to
+    pub fn due(&mut self) -> Option<String> {
+        if self.last.elapsed() < self.interval {
+            return None;
+        }
+        self.last = Instant::now();
+        self.next = (self.next + 1) % AUTOSAVE_SLOTS;
+        Some(format!("{} {}", AUTOSAVE_PREFIX, self.next + 1))
+    }
+}
+
+// takes the lock for this run, returns whether the previous run didn't let go of it
+pub fn lock() -> bool {
+    let path = save_dir().join(LOCK_FILE);
+    let crashed = path.exists();
+    if let Err(e) = std::fs::create_dir_all(save_dir()).and_then(|_| std::fs::write(&path, "")) {
+        log::warn!("couldn't create {:?}: {}", path, e);
+    }
+    crashed
+}
+
+pub fn unlock() {
+    let _ = std::fs::remove_file(save_dir().join(LOCK_FILE));
+}
@@ -0,0 +1,151 @@
+use std::path::PathBuf;
+use std::time::Instant;
+
+// cubes the ramp starts from, it doubles from here
+const START: usize = 1024;
+// past this the instance buffer would be bigger than wgpu promises to allow
+const MAX: usize = 1 << 21;
+// frames after a change that aren't timed, while the buffer's made again
+const SETTLE: u32 = 20;
+// frames timed per count, their median is its frame time
+const FRAMES: usize = 60;
+// the bisection stops once it's down to this fraction of the count
+const PRECISION: f64 = 0.02;
+// in cube half sizes, so a cube's width apart
+pub const SPACING: f32 = 4.0;
+
+pub enum Step {
+    Keep,
+    // make it this many cubes, the player's included
+    Set(usize),
+    Done,
+}
+
+// `--scaling` times frames at more and more cubes, doubling from START
+// until the median frame takes longer than `--scaling-ms` (a 60th of a
+// second unless given), then bisects between the last count that made it
+// and the first that didn't. What it ends up with is the most cubes that
+// still fit the budget: one number for how the instancing path is doing,
+// printed as JSON on stdout and to `--scaling-json <path>`. The camera stays
+// where it starts, so it's the cost of there being that many, not of them
+// all filling the screen, and it wants the frames unsynced (it asks for
+// Immediate) or the refresh rate is all it measures.
+pub struct Scaling {
+    pub budget_ms: f64,
+    pub json_path: Option<PathBuf>,
+    count: usize,
+    // the most found to fit, and the fewest found not to
+    good: usize,
+    bad: Option<usize>,
+    settle: u32,
+    last: Option<Instant>,
+    // milliseconds
+    frames: Vec<f64>,
+    // every count tried and its median, in order
+    tried: Vec<(usize, f64)>,
+}
+
+impl Scaling {
+    pub fn from_args() -> Option<Self> {
+        let args = std::env::args().collect::<Vec<_>>();
+        if !args.iter().any(|a| a == "--scaling") {
+            return None;
+        }
+        let value = |name: &str| {
+            args.iter()
+                .position(|a| a == name)
+                .and_then(|i| args.get(i + 1))
+        };
+        Some(Self {
+            budget_ms: value("--scaling-ms")
+                .and_then(|ms| ms.parse().ok())
+                .unwrap_or(1000.0 / 60.0),
+            json_path: value("--scaling-json").map(PathBuf::from),
+            count: START,
+            good: 0,
+            bad: None,
+            settle: SETTLE,
+            last: None,
+            frames: Vec::new(),
+            tried: Vec::new(),
+        })
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    // once a frame, with what to do next
+    pub fn frame(&mut self) -> Step {
+        let now = Instant::now();
+        let last = self.last.replace(now);
+        if self.settle > 0 {
+            self.settle -= 1;
+            return Step::Keep;
+        }
+        if let Some(last) = last {
+            self.frames.push((now - last).as_secs_f64() * 1000.0);
+        }
+        if self.frames.len() < FRAMES {
+            return Step::Keep;
+        }
+
+        self.frames.sort_by(f64::total_cmp);
+        let median = self.frames[FRAMES / 2];
+        self.frames.clear();
+        self.tried.push((self.count, median));
+        log::info!("{} cubes: {:.2} ms", self.count, median);
+        if median <= self.budget_ms {
+            self.good = self.count;
+        } else {
+            self.bad = Some(self.count);
+        }
+
+        let next = match self.bad {
+            None if self.count >= MAX => return Step::Done,
+            None => (self.count * 2).min(MAX),
+            Some(bad) => {
+                let close_enough = ((self.good as f64 * PRECISION) as usize).max(1);
+                if bad - self.good <= close_enough {
+                    return Step::Done;
+                }
+                (self.good + bad) / 2
+            }
+        };
+        self.count = next.max(1);
+        self.settle = SETTLE;
+        Step::Set(self.count)
+    }
+
+    // only numbers and labels from the code go in, nothing needs escaping
+    pub fn json(&self, adapter: &str, backend: &str) -> String {
+        let tried = self
+            .tried
+            .iter()
+            .map(|(count, ms)| format!("[{}, {:.3}]", count, ms))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "{{\"adapter\": \"{}\", \"backend\": \"{}\", \"budget_ms\": {:.3}, \
+             \"sustainable_cubes\": {}, \"capped\": {}, \"tried\": [{}]}}\n",
+            adapter.replace(['"', '\\'], ""),
+            backend,
+            self.budget_ms,
+            self.good,
+            self.bad.is_none(),
+            tried
+        )
+    }
+
+    pub fn finish(&self, adapter: &str, backend: &str) {
+        log::info!("{} cubes fit in {:.2} ms", self.good, self.budget_ms);
+        let json = self.json(adapter, backend);
+        print!("{}", json);
+        if let Some(path) = &self.json_path {
+            match std::fs::write(path, &json) {
+                Ok(()) => log::info!("wrote the scaling test to {}", path.display()),
+                Err(e) => log::error!("couldn't write {}: {}", path.display(), e),
+            }
+        }
+    }
+}
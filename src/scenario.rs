@@ -0,0 +1,309 @@
+use crate::light::Lighting;
+use crate::math::{self, Quat, Vec3};
+use crate::Instance;
+
+// where a cube's center is when it sits on the floor (floor.wgsl draws it at -2)
+const GROUND: f32 = -1.0;
+const GRAVITY: f32 = 9.8;
+
+// A demo picked from the F1 menu. `setup` gets the cubes with only the first
+// one left and fills in the rest, `update` then moves them every frame. The
+// first cube is the player's and is left alone.
+pub trait Scenario {
+    fn name(&self) -> &'static str;
+    fn setup(&mut self, cubes: &mut Vec<Instance>, rng: &mut crate::rng::Rng);
+    fn update(&mut self, dt: f32, cubes: &mut [Instance]);
+    // the sun while it runs
+    fn lighting(&self) -> Lighting {
+        Lighting::default()
+    }
+    // nothing moves, so update isn't called and the cubes aren't uploaded
+    // again every frame
+    fn still(&self) -> bool {
+        false
+    }
+}
+
+// in the order the menu lists them
+pub fn all() -> Vec<Box<dyn Scenario>> {
+    vec![
+        Box::new(GravityWell::default()),
+        Box::new(Dominoes::default()),
+        Box::new(Fountain::default()),
+        Box::new(City),
+    ]
+}
+
+fn cube(position: Vec3, rotation: Quat) -> Instance {
+    Instance {
+        position,
+        rotation,
+        flash: crate::NO_FLASH,
+        skin: 0,
+        layer: crate::physics::Layers::STATIC,
+    }
+}
+
+///// gravity well
+const WELL_CUBES: usize = 200;
+// G times the well's mass
+const WELL_STRENGTH: f32 = 400.0;
+
+// cubes on roughly circular, slightly tilted orbits around the first cube
+#[derive(Default)]
+struct GravityWell {
+    velocities: Vec<Vec3>,
+    spins: Vec<Quat>,
+}
+
+impl Scenario for GravityWell {
+    fn name(&self) -> &'static str {
+        "gravity well"
+    }
+
+    fn setup(&mut self, cubes: &mut Vec<Instance>, rng: &mut crate::rng::Rng) {
+        let center = cubes[0].position;
+        self.velocities.clear();
+        self.spins.clear();
+        for _ in 0..WELL_CUBES {
+            let radius = 8.0 + rng.f32() * 25.0;
+            let angle = rng.f32() * std::f32::consts::TAU;
+            let offset = Vec3::new(angle.cos(), (rng.f32() - 0.5) * 0.3, angle.sin()) * radius;
+            // the speed of a circular orbit, give or take
+            let speed = (WELL_STRENGTH / radius).sqrt() * (0.9 + rng.f32() * 0.2);
+            let tangent = math::UP.cross(offset).normalize();
+            cubes.push(cube(center + offset, Quat::IDENTITY));
+            self.velocities.push(tangent * speed);
+            self.spins.push(Quat::from_axis_angle(
+                Vec3::new(rng.f32(), rng.f32(), rng.f32()).normalize_or(Vec3::Y),
+                rng.f32() * 0.05,
+            ));
+        }
+    }
+
+    // out in space: a hard sun from the side and not much sky
+    fn lighting(&self) -> Lighting {
+        Lighting {
+            direction: Vec3::new(-1.0, -0.2, 0.0),
+            color: Vec3::new(1.2, 1.1, 1.0),
+            ambient: 0.25,
+        }
+    }
+
+    fn update(&mut self, dt: f32, cubes: &mut [Instance]) {
+        let center = cubes[0].position;
+        for ((cube, velocity), spin) in cubes[1..]
+            .iter_mut()
+            .zip(&mut self.velocities)
+            .zip(&self.spins)
+        {
+            let offset = cube.position - center;
+            // softened so a close pass doesn't fling anything off forever
+            let distance_squared = offset.length_squared() + 1.0;
+            *velocity -= offset.normalize_or_zero() * WELL_STRENGTH / distance_squared * dt;
+            cube.position += *velocity * dt;
+            cube.rotation = (*spin * cube.rotation).normalize();
+        }
+    }
+}
+
+///// dominoes
+const DOMINOES: usize = 40;
+// center to center, a domino is 2 wide so that leaves a gap of 1
+const DOMINO_SPACING: f32 = 3.0;
+// asin(gap / height), where a domino's top edge reaches the next one. It
+// knocks that one over and stays leaning on it.
+const DOMINO_LEAN_ANGLE: f32 = std::f32::consts::FRAC_PI_6;
+// radians per second a domino gets from the one hitting it
+const DOMINO_KNOCK: f32 = 1.5;
+
+// A row of cubes along +x that topple one after the other, each tipping
+// over its bottom front edge. The first one starts already falling.
+#[derive(Default)]
+struct Dominoes {
+    start: Vec3,
+    // radians tipped over and how fast that's growing
+    angles: Vec<f32>,
+    speeds: Vec<f32>,
+}
+
+impl Scenario for Dominoes {
+    fn name(&self) -> &'static str {
+        "dominoes"
+    }
+
+    fn setup(&mut self, cubes: &mut Vec<Instance>, _rng: &mut crate::rng::Rng) {
+        let player = cubes[0].position;
+        self.start = Vec3::new(player.x + 4.0, GROUND, player.z);
+        self.angles = vec![0.0; DOMINOES];
+        self.speeds = vec![0.0; DOMINOES];
+        self.speeds[0] = DOMINO_KNOCK;
+        for i in 0..DOMINOES {
+            let position = self.start + Vec3::X * i as f32 * DOMINO_SPACING;
+            cubes.push(cube(position, Quat::IDENTITY));
+        }
+    }
+
+    fn update(&mut self, dt: f32, cubes: &mut [Instance]) {
+        for i in 0..DOMINOES {
+            // the last one has nothing to lean on and goes flat
+            let rest = if i + 1 == DOMINOES {
+                std::f32::consts::FRAC_PI_2
+            } else {
+                DOMINO_LEAN_ANGLE
+            };
+            if self.speeds[i] > 0.0 {
+                // A real cube is too wide to topple from a nudge, so this
+                // pretends they're thin: gravity's torque goes with sin(angle).
+                let torque = self.angles[i].sin();
+                self.speeds[i] += torque * GRAVITY * 0.5 * dt;
+                self.angles[i] += self.speeds[i] * dt;
+                if self.angles[i] >= rest {
+                    self.angles[i] = rest;
+                    self.speeds[i] = 0.0;
+                }
+            }
+            if self.angles[i] >= DOMINO_LEAN_ANGLE && i + 1 < DOMINOES && self.angles[i + 1] == 0.0
+            {
+                self.speeds[i + 1] = DOMINO_KNOCK;
+            }
+
+            // tipping towards +x is a rotation around -z, about the edge at (+1, -1)
+            let rotation = Quat::from_rotation_z(-self.angles[i]);
+            let edge = self.start + Vec3::new(i as f32 * DOMINO_SPACING + 1.0, -1.0, 0.0);
+            let cube = &mut cubes[1 + i];
+            cube.rotation = rotation;
+            cube.position = edge + rotation * Vec3::new(-1.0, 1.0, 0.0);
+        }
+    }
+}
+
+///// fountain
+const FOUNTAIN_CUBES: usize = 300;
+const FOUNTAIN_LIFE: f32 = 6.0;
+
+// cubes shot up from next to the player, bouncing on the floor until they're recycled
+#[derive(Default)]
+struct Fountain {
+    origin: Vec3,
+    velocities: Vec<Vec3>,
+    ages: Vec<f32>,
+    rng: Option<crate::rng::Rng>,
+}
+
+impl Fountain {
+    fn launch(&mut self, i: usize, cube: &mut Instance) {
+        let rng = self.rng.as_mut().unwrap();
+        let angle = rng.f32() * std::f32::consts::TAU;
+        let spread = rng.f32() * 3.0;
+        self.velocities[i] = Vec3::new(
+            angle.cos() * spread,
+            14.0 + rng.f32() * 4.0,
+            angle.sin() * spread,
+        );
+        cube.position = self.origin;
+        cube.rotation = Quat::IDENTITY;
+    }
+}
+
+impl Scenario for Fountain {
+    fn name(&self) -> &'static str {
+        "cube fountain"
+    }
+
+    fn setup(&mut self, cubes: &mut Vec<Instance>, rng: &mut crate::rng::Rng) {
+        let player = cubes[0].position;
+        self.origin = Vec3::new(player.x, GROUND, player.z + 6.0);
+        // its own stream so relaunching doesn't need the app's rng every frame
+        self.rng = Some(crate::rng::Rng::new(rng.u64()));
+        self.velocities = vec![Vec3::ZERO; FOUNTAIN_CUBES];
+        // staggered so they don't all go up at once
+        self.ages = (0..FOUNTAIN_CUBES)
+            .map(|i| -(i as f32) / FOUNTAIN_CUBES as f32 * FOUNTAIN_LIFE)
+            .collect();
+        for _ in 0..FOUNTAIN_CUBES {
+            cubes.push(cube(self.origin, Quat::IDENTITY));
+        }
+    }
+
+    fn update(&mut self, dt: f32, cubes: &mut [Instance]) {
+        for i in 0..FOUNTAIN_CUBES {
+            let cube = &mut cubes[1 + i];
+            let before = self.ages[i];
+            self.ages[i] += dt;
+            if before < 0.0 && self.ages[i] >= 0.0 || self.ages[i] >= FOUNTAIN_LIFE {
+                self.ages[i] = self.ages[i].max(0.0) % FOUNTAIN_LIFE;
+                self.launch(i, cube);
+            }
+            if self.ages[i] < 0.0 {
+                continue;
+            }
+
+            let velocity = &mut self.velocities[i];
+            velocity.y -= GRAVITY * dt;
+            cube.position += *velocity * dt;
+            if cube.position.y < GROUND {
+                cube.position.y = GROUND;
+                velocity.y = -velocity.y * 0.5;
+                velocity.x *= 0.8;
+                velocity.z *= 0.8;
+            }
+            let spin = Quat::from_rotation_x(velocity.z * dt * 0.5)
+                * Quat::from_rotation_z(-velocity.x * dt * 0.5);
+            cube.rotation = (spin * cube.rotation).normalize();
+        }
+    }
+}
+
+///// cube city
+// blocks out from the player's corner each way
+const CITY_RADIUS: i32 = 6;
+
+// The streaming city's blocks (stream::city_block), but a fixed patch of
+// them put down as the sim's own cubes: towers of stacked cubes, one or two
+// wide, with streets between the blocks and downtown where the noise puts
+// it. It's all from the run's seed (`--seed`), so the same seed is the same
+// city. Several thousand cubes that mostly don't fit in the view at once,
+// which is what the culling and the impostors are for.
+struct City;
+
+impl Scenario for City {
+    fn name(&self) -> &'static str {
+        "cube city"
+    }
+
+    fn setup(&mut self, cubes: &mut Vec<Instance>, rng: &mut crate::rng::Rng) {
+        let player = cubes[0].position;
+        // the player starts on a crossing, where four blocks meet
+        let origin = Vec3::new(player.x, 0.0, player.z);
+        for x in -CITY_RADIUS..CITY_RADIUS {
+            for z in -CITY_RADIUS..CITY_RADIUS {
+                crate::stream::city_block(rng.seed(), (x, z), cubes);
+            }
+        }
+        for cube in &mut cubes[1..] {
+            cube.position += origin;
+        }
+        log::info!(
+            "{} blocks, {} cubes, from seed {}",
+            (CITY_RADIUS * 2).pow(2),
+            cubes.len() - 1,
+            rng.seed()
+        );
+    }
+
+    fn update(&mut self, _dt: f32, _cubes: &mut [Instance]) {}
+
+    // late in the day, low and warm
+    fn lighting(&self) -> Lighting {
+        Lighting {
+            direction: Vec3::new(-1.0, -0.5, -0.4),
+            color: Vec3::new(0.9, 0.7, 0.5),
+            ambient: 0.7,
+        }
+    }
+
+    fn still(&self) -> bool {
+        true
+    }
+}
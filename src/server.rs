@@ -0,0 +1,337 @@
+use crate::interest::Interest;
+use crate::math::Vec3;
+use crate::net::{self, ToClient, ToServer};
+use crate::rollback;
+use crate::saves::Slot;
+use crate::simulation::{self, Simulation};
+use crate::Instance;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Instant;
+
+// what's listened on without `--server <address>`'s host, everywhere since
+// the point is other machines joining
+const DEFAULT_HOST: &str = "0.0.0.0";
+// snapshots go out every this many ticks, 20 a second
+const SNAPSHOT_EVERY: u32 = 3;
+// what each client can be sent without `--bandwidth <KB/s>`
+const BANDWIDTH: f32 = 48.0;
+// everyone's names go out this often, and straight away when someone comes or goes
+const PLAYERS_EVERY: u32 = 60;
+// names are cut down to this many characters, to keep the tags short
+const MAX_NAME: usize = 24;
+
+// someone playing, in step with `Simulation::players`
+struct Client {
+    address: SocketAddr,
+    name: String,
+    last_heard: Instant,
+    // the newest input taken, older ones arriving late are dropped
+    input_tick: u32,
+    // which cubes it's sent, and how often
+    interest: Interest,
+}
+
+// `--server [address]`: the simulation on its own, no window and no GPU,
+// for others to `--connect` to. It steps at a fixed 60Hz, gives everyone
+// who says hello a cube of their own to walk around and sends them all
+// where every cube is. Cube 0 is a fixed marker at the origin rather than
+// anyone's player, unless `--slot <name>` starts it from a save slot's
+// cubes instead. Each client is only sent what's near its cube, as much as
+// `--bandwidth <KB/s>` allows (see interest.rs). Chat goes round through
+// here too, with everyone's names for the tags over their cubes. Runs until
+// it's killed.
+pub fn run(address: &str) -> anyhow::Result<()> {
+    let args = std::env::args().collect::<Vec<_>>();
+    let value = |flag: &str| {
+        args.iter()
+            .position(|a| a == flag)
+            .and_then(|i| args.get(i + 1))
+    };
+    let bandwidth = match value("--bandwidth") {
+        Some(kb) => kb
+            .parse::<f32>()
+            .map_err(|_| anyhow::anyhow!("--bandwidth takes KB a second, not {:?}", kb))?,
+        None => BANDWIDTH,
+    };
+    let address = net::address(address, DEFAULT_HOST);
+    let socket = UdpSocket::bind(&address)?;
+    if let Some(players) = value("--rollback") {
+        let players = match players.parse::<usize>() {
+            Ok(players @ 1..=rollback::MAX_PLAYERS) => players,
+            _ => anyhow::bail!(
+                "--rollback takes how many are playing, 1 to {}",
+                rollback::MAX_PLAYERS
+            ),
+        };
+        log::info!("relaying for {} rollback players on {}", players, address);
+        return relay(socket, players);
+    }
+    socket.set_nonblocking(true)?;
+    log::info!(
+        "serving on {}, up to {}KB/s to each client",
+        address,
+        bandwidth
+    );
+
+    let mut sim = Simulation::hosted();
+    if let Some(name) = value("--slot") {
+        let Some(slot) = Slot::list().into_iter().find(|slot| &slot.name == name) else {
+            anyhow::bail!("no save slot called {:?}", name);
+        };
+        if slot.cubes.is_empty() {
+            anyhow::bail!("{} has no cubes", name);
+        }
+        log::info!("starting from {}, {} cubes", name, slot.cubes.len());
+        sim.cubes = slot
+            .cubes
+            .iter()
+            .map(|cube| Instance {
+                position: cube.position,
+                rotation: cube.rotation,
+                flash: crate::NO_FLASH,
+                skin: cube.skin,
+                layer: cube.layer,
+            })
+            .collect();
+        sim.filter = slot.filter;
+    }
+    // where every cube was a tick ago, for how fast they're going
+    let mut last = Vec::new();
+    let mut velocities = Vec::new();
+    let mut clients = Vec::<Client>::new();
+    let mut rng = crate::rng::Rng::from_args();
+    let mut buffer = vec![0; net::MAX_PACKET];
+    let mut tick = 0u32;
+    let mut next = Instant::now();
+    let mut players_changed = false;
+
+    loop {
+        while let Ok((length, from)) = socket.recv_from(&mut buffer) {
+            let message = match ToServer::decode(&buffer[..length]) {
+                Ok(message) => message,
+                Err(e) => {
+                    log::warn!("ignoring a packet from {}: {:#}", from, e);
+                    continue;
+                }
+            };
+            let known = clients.iter().position(|c| c.address == from);
+            match (message, known) {
+                (ToServer::Hello { name }, None) => {
+                    let name = name.chars().take(MAX_NAME).collect::<String>();
+                    let cube = sim.join();
+                    log::info!("{} joined from {} as cube {}", name, from, cube);
+                    clients.push(Client {
+                        address: from,
+                        name,
+                        last_heard: Instant::now(),
+                        input_tick: 0,
+                        interest: Interest::new(),
+                    });
+                    let _ = socket.send_to(&ToClient::welcome(cube), from);
+                    let joined = format!("{} joined", clients[clients.len() - 1].name);
+                    broadcast(&socket, &clients, &ToClient::chat("", &joined));
+                    players_changed = true;
+                }
+                // the welcome went missing
+                (ToServer::Hello { .. }, Some(k)) => {
+                    clients[k].last_heard = Instant::now();
+                    let _ = socket.send_to(&ToClient::welcome(sim.players[k].cube), from);
+                }
+                (ToServer::Input { tick, direction }, Some(k)) => {
+                    let client = &mut clients[k];
+                    client.last_heard = Instant::now();
+                    if tick > client.input_tick {
+                        client.input_tick = tick;
+                        sim.players[k].wanted = Some(simulation::walk(direction));
+                    }
+                }
+                (ToServer::Bye, Some(k)) => {
+                    let left = format!("{} left", clients[k].name);
+                    log::info!("{}", left);
+                    leave(&mut sim, &mut clients, k);
+                    broadcast(&socket, &clients, &ToClient::chat("", &left));
+                    last.clear();
+                    players_changed = true;
+                }
+                (ToServer::Chat { text }, Some(k)) => {
+                    clients[k].last_heard = Instant::now();
+                    log::info!("<{}> {}", clients[k].name, text);
+                    broadcast(&socket, &clients, &ToClient::chat(&clients[k].name, &text));
+                }
+                (ToServer::Inputs { .. }, Some(_)) => {
+                    log::warn!(
+                        "ignoring rollback inputs from {}, this isn't --rollback",
+                        from
+                    )
+                }
+                (_, None) => log::warn!("ignoring {}, it hasn't said hello", from),
+            }
+        }
+        while let Some(k) = clients
+            .iter()
+            .position(|c| c.last_heard.elapsed() > net::TIMEOUT)
+        {
+            let left = format!("{} timed out", clients[k].name);
+            log::info!("{}", left);
+            leave(&mut sim, &mut clients, k);
+            broadcast(&socket, &clients, &ToClient::chat("", &left));
+            last.clear();
+            players_changed = true;
+        }
+        if players_changed || tick.is_multiple_of(PLAYERS_EVERY) {
+            let names = clients
+                .iter()
+                .zip(&sim.players)
+                .map(|(client, player)| (player.cube, client.name.as_str()))
+                .collect::<Vec<_>>();
+            broadcast(&socket, &clients, &ToClient::players(&names));
+            players_changed = false;
+        }
+
+        // events are for showing and playing, there's no one here to see them
+        sim.step(net::TICK.as_secs_f64(), &mut rng);
+        sim.dirty.clear();
+        tick += 1;
+        velocities.clear();
+        velocities.extend(sim.cubes.iter().enumerate().map(|(i, cube)| {
+            last.get(i)
+                .map_or(Vec3::ZERO, |at: &Vec3| cube.position - *at)
+        }));
+        last.clear();
+        last.extend(sim.cubes.iter().map(|cube| cube.position));
+
+        if tick.is_multiple_of(SNAPSHOT_EVERY) && !clients.is_empty() {
+            let dt = (net::TICK * SNAPSHOT_EVERY).as_secs_f32();
+            for (client, player) in clients.iter_mut().zip(&sim.players) {
+                let chosen = client.interest.choose(
+                    dt,
+                    player.cube,
+                    &sim.cubes,
+                    &velocities,
+                    bandwidth * 1000.0,
+                );
+                for packet in ToClient::snapshots(tick, player.cube, &sim.cubes, &chosen) {
+                    let _ = socket.send_to(&packet, client.address);
+                }
+            }
+        }
+
+        // a fixed step, catching up rather than sleeping if it fell behind
+        next += net::TICK;
+        let now = Instant::now();
+        if next > now {
+            std::thread::sleep(next - now);
+        } else if now - next > net::TICK * 10 {
+            log::warn!("running {:?} behind, skipping ahead", now - next);
+            next = now;
+        }
+    }
+}
+
+fn broadcast(socket: &UdpSocket, clients: &[Client], packet: &[u8]) {
+    for client in clients {
+        let _ = socket.send_to(packet, client.address);
+    }
+}
+
+// client `k`'s cube goes with them
+fn leave(sim: &mut Simulation, clients: &mut Vec<Client>, k: usize) {
+    clients.remove(k);
+    let cube = sim.players.remove(k).cube;
+    sim.cubes.remove(cube);
+    sim.removed(cube);
+    for client in clients {
+        client.interest.forget();
+    }
+}
+
+// `--server --rollback <players>`: nothing's simulated here at all. Once
+// that many have said hello, everyone's told to start, with the same seed,
+// and from then on each one's inputs are passed to the others, who all run
+// the same simulation themselves (see rollback.rs). Nobody new can come
+// into a session that's started, and it's over once anyone leaves.
+fn relay(socket: UdpSocket, players: usize) -> anyhow::Result<()> {
+    socket.set_read_timeout(Some(net::TIMEOUT))?;
+    let seed = crate::rng::Rng::from_args().seed();
+    let mut peers = Vec::<(SocketAddr, String)>::new();
+    let mut buffer = vec![0; net::MAX_PACKET];
+    let start = |socket: &UdpSocket, peers: &[(SocketAddr, String)], k: usize| {
+        let names = peers
+            .iter()
+            .enumerate()
+            .map(|(slot, (_, name))| (slot + 1, name.as_str()))
+            .collect::<Vec<_>>();
+        let address = peers[k].0;
+        let _ = socket.send_to(&ToClient::start(k, players, seed), address);
+        let _ = socket.send_to(&ToClient::players(&names), address);
+    };
+    loop {
+        let (length, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                if peers.len() == players {
+                    log::warn!("nothing from anyone for {:?}", net::TIMEOUT);
+                }
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let message = match ToServer::decode(&buffer[..length]) {
+            Ok(message) => message,
+            Err(e) => {
+                log::warn!("ignoring a packet from {}: {:#}", from, e);
+                continue;
+            }
+        };
+        let known = peers.iter().position(|(address, _)| *address == from);
+        match (message, known) {
+            (ToServer::Hello { name }, None) if peers.len() < players => {
+                let name = name.chars().take(MAX_NAME).collect::<String>();
+                log::info!("{} is player {} of {}", name, peers.len() + 1, players);
+                peers.push((from, name));
+                if peers.len() == players {
+                    log::info!("everyone's here, starting");
+                    for k in 0..players {
+                        start(&socket, &peers, k);
+                    }
+                }
+            }
+            (ToServer::Hello { .. }, None) => {
+                log::warn!("turning {} away, the session's started", from)
+            }
+            // the start went missing, or it's still waiting for the rest
+            (ToServer::Hello { .. }, Some(k)) => {
+                if peers.len() == players {
+                    start(&socket, &peers, k);
+                }
+            }
+            (ToServer::Inputs { tick, directions }, Some(k)) => {
+                let packet = ToClient::inputs(k, tick, &directions);
+                for (address, _) in peers.iter().filter(|(address, _)| *address != from) {
+                    let _ = socket.send_to(&packet, address);
+                }
+            }
+            (ToServer::Chat { text }, Some(k)) => {
+                log::info!("<{}> {}", peers[k].1, text);
+                let packet = ToClient::chat(&peers[k].1, &text);
+                for (address, _) in &peers {
+                    let _ = socket.send_to(&packet, address);
+                }
+            }
+            (ToServer::Bye, Some(k)) => {
+                log::info!("{} left, that's the end of the session", peers[k].1);
+                let packet =
+                    ToClient::chat("", &format!("{} left, the session's over", peers[k].1));
+                for (address, _) in &peers {
+                    let _ = socket.send_to(&packet, address);
+                }
+                return Ok(());
+            }
+            (ToServer::Input { .. }, Some(_)) => {
+                log::warn!("ignoring snapshot input from {}, this is --rollback", from)
+            }
+            (_, None) => log::warn!("ignoring {}, it hasn't said hello", from),
+        }
+    }
+}
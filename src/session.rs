@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+use std::time::Instant;
+
+// Numbers about the whole run, printed when praxis closes. `--session-json
+// <path>` also writes them out as JSON for scripts to pick up.
+pub struct Session {
+    started: Instant,
+    frames: u64,
+    pub cubes_spawned: u32,
+    // how far the player moved the cubes, in cube half sizes
+    pub distance: f32,
+    peak_instances: usize,
+    pub json_path: Option<PathBuf>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            frames: 0,
+            cubes_spawned: 0,
+            distance: 0.0,
+            peak_instances: 0,
+            json_path: None,
+        }
+    }
+}
+
+impl Session {
+    pub fn frame(&mut self, instances: usize) {
+        self.frames += 1;
+        self.peak_instances = self.peak_instances.max(instances);
+    }
+
+    fn average_fps(&self) -> f64 {
+        self.frames as f64 / self.started.elapsed().as_secs_f64().max(f64::EPSILON)
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "session: {:.1}s, {} frames at {:.1} fps average\n\
+             cubes spawned: {}, peak instances: {}, distance moved: {:.1}",
+            self.started.elapsed().as_secs_f64(),
+            self.frames,
+            self.average_fps(),
+            self.cubes_spawned,
+            self.peak_instances,
+            self.distance
+        )
+    }
+
+    // only numbers go in, so there's nothing to escape
+    pub fn json(&self) -> String {
+        format!(
+            "{{\"seconds\": {:.3}, \"frames\": {}, \"average_fps\": {:.3}, \
+             \"cubes_spawned\": {}, \"peak_instances\": {}, \"distance\": {:.3}}}\n",
+            self.started.elapsed().as_secs_f64(),
+            self.frames,
+            self.average_fps(),
+            self.cubes_spawned,
+            self.peak_instances,
+            self.distance
+        )
+    }
+
+    pub fn finish(&self) {
+        println!("{}", self.summary());
+        if let Some(path) = &self.json_path {
+            match std::fs::write(path, self.json()) {
+                Ok(()) => log::info!("wrote session stats to {}", path.display()),
+                Err(e) => log::error!("couldn't write {}: {}", path.display(), e),
+            }
+        }
+    }
+}
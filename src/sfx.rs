@@ -0,0 +1,211 @@
+use crate::audio::{self, Samples};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+// how often a --sounds file is looked at for changes
+const POLL: Duration = Duration::from_secs(1);
+
+// things that happen that make a sound
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, serde::Deserialize)]
+pub enum Event {
+    // a cube appearing
+    Spawn,
+    // the player cube landing
+    Impact,
+    Shatter,
+}
+
+// a sound made up from numbers, see audio::chirp and audio::burst
+#[derive(Clone, Debug, serde::Deserialize)]
+enum Synth {
+    Chirp {
+        from: f32,
+        to: f32,
+        seconds: f32,
+        decay: f32,
+    },
+    Burst {
+        seconds: f32,
+        decay: f32,
+        cutoff: f32,
+    },
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+struct BankConfig {
+    variations: Vec<Synth>,
+    #[serde(default = "one")]
+    volume: f32,
+    // each play is up to this share louder or quieter
+    #[serde(default)]
+    volume_jitter: f32,
+    // and up to this share higher or lower, faster or slower
+    #[serde(default)]
+    pitch_jitter: f32,
+    // seconds after a play that the event is ignored
+    #[serde(default)]
+    cooldown: f32,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+struct Bank {
+    variations: Vec<Samples>,
+    volume: f32,
+    volume_jitter: f32,
+    pitch_jitter: f32,
+    cooldown: Duration,
+    played: Option<Instant>,
+    // the variation played last, not picked twice running
+    last: usize,
+}
+
+// Which sound each event makes, from res/sounds.ron or `--sounds <file>`.
+// An event has a bank of variations, one picked at random each time with
+// some jitter on its volume and pitch so repeats don't sound canned, and a
+// cooldown so a pile of them at once is one sound. A --sounds file is
+// watched and loaded again when it changes; one that doesn't parse is
+// logged and the banks from before stay.
+pub struct Sfx {
+    banks: HashMap<Event, Bank>,
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    checked: Instant,
+    // its own, so sounds don't change what a --seed spawns
+    rng: crate::rng::Rng,
+}
+
+impl Default for Sfx {
+    fn default() -> Self {
+        Self {
+            banks: HashMap::new(),
+            path: None,
+            modified: None,
+            checked: Instant::now(),
+            rng: crate::rng::Rng::new(0),
+        }
+    }
+}
+
+impl Sfx {
+    pub fn from_args() -> Self {
+        let mut sfx = Self::default();
+        match parse(include_str!("../res/sounds.ron")) {
+            Ok(banks) => sfx.banks = banks,
+            Err(e) => log::error!("no sounds: {:#}", e),
+        }
+        let args = std::env::args().collect::<Vec<_>>();
+        sfx.path = args
+            .iter()
+            .position(|a| a == "--sounds")
+            .and_then(|i| args.get(i + 1))
+            .map(PathBuf::from);
+        sfx.reload();
+        if let (Some(path), None) = (&sfx.path, sfx.modified) {
+            log::error!("ignoring --sounds {}, it can't be read", path.display());
+        }
+        sfx
+    }
+
+    // loads the --sounds file again if it's changed since last time
+    pub fn poll(&mut self) {
+        if self.path.is_none() || self.checked.elapsed() < POLL {
+            return;
+        }
+        self.checked = Instant::now();
+        self.reload();
+    }
+
+    fn reload(&mut self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        if modified.is_none() || modified == self.modified {
+            return;
+        }
+        let first = self.modified.is_none();
+        self.modified = modified;
+        match std::fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|text| parse(&text))
+        {
+            Ok(banks) => {
+                self.banks = banks;
+                if !first {
+                    log::info!("sounds reloaded from {}", path.display());
+                }
+            }
+            Err(e) => log::error!("keeping the sounds from before {}: {:#}", path.display(), e),
+        }
+    }
+
+    // the samples, gain and rate to play `event` with, unless it's cooling down
+    pub fn pick(&mut self, event: Event) -> Option<(Samples, f32, f32)> {
+        let bank = self.banks.get_mut(&event)?;
+        if bank.variations.is_empty() || bank.played.is_some_and(|t| t.elapsed() < bank.cooldown) {
+            return None;
+        }
+        bank.played = Some(Instant::now());
+        let count = bank.variations.len() as u32;
+        let mut i = self.rng.below(count) as usize;
+        if count > 1 && i == bank.last {
+            i = (i + 1 + self.rng.below(count - 1) as usize) % count as usize;
+        }
+        bank.last = i;
+        let gain = bank.volume * (1.0 + (self.rng.f32() * 2.0 - 1.0) * bank.volume_jitter);
+        let rate = 1.0 + (self.rng.f32() * 2.0 - 1.0) * bank.pitch_jitter;
+        Some((bank.variations[i].clone(), gain.max(0.0), rate.max(0.1)))
+    }
+}
+
+fn parse(ron: &str) -> anyhow::Result<HashMap<Event, Bank>> {
+    let config: HashMap<Event, BankConfig> = ron::from_str(ron)?;
+    let mut banks = HashMap::new();
+    for (event, bank) in config {
+        if bank.volume < 0.0 || bank.cooldown < 0.0 {
+            anyhow::bail!("{:?} needs a volume and cooldown of at least 0", event);
+        }
+        if !(0.0..1.0).contains(&bank.pitch_jitter) || !(0.0..=1.0).contains(&bank.volume_jitter) {
+            anyhow::bail!("{:?} has jitter outside 0..1", event);
+        }
+        let variations = bank
+            .variations
+            .iter()
+            .enumerate()
+            .map(|(i, synth)| {
+                // the same noise for the same variation every time
+                let seed = event as u64 * 100 + i as u64;
+                match *synth {
+                    Synth::Chirp {
+                        from,
+                        to,
+                        seconds,
+                        decay,
+                    } => audio::chirp(from, to, seconds, decay),
+                    Synth::Burst {
+                        seconds,
+                        decay,
+                        cutoff,
+                    } => audio::burst(seconds, decay, cutoff, seed),
+                }
+            })
+            .collect();
+        banks.insert(
+            event,
+            Bank {
+                variations,
+                volume: bank.volume,
+                volume_jitter: bank.volume_jitter,
+                pitch_jitter: bank.pitch_jitter,
+                cooldown: Duration::from_secs_f32(bank.cooldown),
+                played: None,
+                last: usize::MAX,
+            },
+        );
+    }
+    Ok(banks)
+}
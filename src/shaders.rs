@@ -0,0 +1,226 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+
+// every shader that can be loaded or included, by file name
+pub const SOURCES: &[(&str, &str)] = &[
+    ("common.wgsl", include_str!("common.wgsl")),
+    ("basic.wgsl", include_str!("basic.wgsl")),
+    ("cloth.wgsl", include_str!("cloth.wgsl")),
+    ("cloth_draw.wgsl", include_str!("cloth_draw.wgsl")),
+    ("crt.wgsl", include_str!("crt.wgsl")),
+    ("cube.wgsl", include_str!("cube.wgsl")),
+    ("floor.wgsl", include_str!("floor.wgsl")),
+    ("grade.wgsl", include_str!("grade.wgsl")),
+    ("impostor.wgsl", include_str!("impostor.wgsl")),
+    ("lod_cull.wgsl", include_str!("lod_cull.wgsl")),
+    ("motion_blur.wgsl", include_str!("motion_blur.wgsl")),
+    ("noise.wgsl", include_str!("noise.wgsl")),
+    ("outline.wgsl", include_str!("outline.wgsl")),
+    ("portal.wgsl", include_str!("portal.wgsl")),
+    ("post.wgsl", include_str!("post.wgsl")),
+    ("retro.wgsl", include_str!("retro.wgsl")),
+    ("skybox.wgsl", include_str!("skybox.wgsl")),
+    ("splash.wgsl", include_str!("splash.wgsl")),
+    ("sprite.wgsl", include_str!("sprite.wgsl")),
+    ("texture_viewer.wgsl", include_str!("texture_viewer.wgsl")),
+    ("ui.wgsl", include_str!("ui.wgsl")),
+    ("tilemap.wgsl", include_str!("tilemap.wgsl")),
+    ("velocity.wgsl", include_str!("velocity.wgsl")),
+    ("water.wgsl", include_str!("water.wgsl")),
+    ("weather.wgsl", include_str!("weather.wgsl")),
+    ("weather_draw.wgsl", include_str!("weather_draw.wgsl")),
+];
+
+// of every source, for telling whether anything compiled from them is stale
+pub fn hash() -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    SOURCES.hash(&mut hasher);
+    hasher.finish()
+}
+
+// A very small preprocessor run over shaders before wgpu sees them:
+//   #include "file.wgsl"   pastes another shader in, once per file
+//   #define NAME [value]   a flag, or a value that replaces NAME from then on
+//   #ifdef / #ifndef NAME, #else, #endif
+// `defines` are set before the first line, for flags chosen at runtime.
+// A `// @tweak` comment defines its name as well, see tweaks.rs.
+pub fn load(name: &str, defines: &[&str]) -> Result<String> {
+    load_from(SOURCES, name, defines)
+}
+
+fn load_from(sources: &[(&str, &str)], name: &str, defines: &[&str]) -> Result<String> {
+    let mut preprocessor = Preprocessor {
+        sources,
+        defines: defines
+            .iter()
+            .map(|d| {
+                let (name, value) = d.split_once(' ').unwrap_or((d, ""));
+                (name.to_string(), value.trim().to_string())
+            })
+            .collect(),
+        included: Vec::new(),
+        out: String::new(),
+    };
+    preprocessor.file(name)?;
+    Ok(preprocessor.out)
+}
+
+struct Preprocessor<'a> {
+    sources: &'a [(&'a str, &'a str)],
+    defines: HashMap<String, String>,
+    included: Vec<String>,
+    out: String,
+}
+
+impl Preprocessor<'_> {
+    fn file(&mut self, name: &str) -> Result<()> {
+        if self.included.iter().any(|n| n == name) {
+            return Ok(());
+        }
+        self.included.push(name.to_string());
+        let Some((_, source)) = self.sources.iter().find(|(n, _)| *n == name) else {
+            bail!("no shader called {}", name);
+        };
+
+        // whether each open #ifdef is taking its lines, innermost last
+        let mut taking: Vec<bool> = Vec::new();
+        for (number, line) in source.lines().enumerate() {
+            let at = || format!("{}:{}", name, number + 1);
+            let active = taking.iter().all(|t| *t);
+            let Some(directive) = line.trim_start().strip_prefix('#') else {
+                if let Some(tweak) = crate::tweaks::parse(line).filter(|_| active) {
+                    let (tweak, ..) = tweak.with_context(at)?;
+                    let expression = crate::tweaks::expression(name, &tweak).with_context(at)?;
+                    self.defines.insert(tweak, expression);
+                }
+                if active {
+                    self.out += &self.substitute(line);
+                    self.out.push('\n');
+                }
+                continue;
+            };
+            let (keyword, rest) = directive.split_once(' ').unwrap_or((directive, ""));
+            let rest = rest.trim();
+            match keyword {
+                "ifdef" => taking.push(self.defines.contains_key(rest)),
+                "ifndef" => taking.push(!self.defines.contains_key(rest)),
+                "else" => match taking.last_mut() {
+                    Some(t) => *t = !*t,
+                    None => bail!("{}: #else without #ifdef", at()),
+                },
+                "endif" => {
+                    if taking.pop().is_none() {
+                        bail!("{}: #endif without #ifdef", at());
+                    }
+                }
+                _ if !active => {}
+                "define" => {
+                    let (define, value) = rest.split_once(' ').unwrap_or((rest, ""));
+                    self.defines
+                        .insert(define.to_string(), value.trim().to_string());
+                }
+                "include" => {
+                    let included = rest.trim_matches('"');
+                    self.file(included).with_context(at)?;
+                }
+                _ => bail!("{}: unknown directive #{}", at(), keyword),
+            }
+        }
+        if !taking.is_empty() {
+            bail!("{}: #ifdef without #endif", name);
+        }
+        Ok(())
+    }
+
+    // swaps defined names that have a value for it, whole identifiers only
+    // and not in comments
+    fn substitute(&self, line: &str) -> String {
+        let (code, comment) = line.split_at(line.find("//").unwrap_or(line.len()));
+        let mut out = String::with_capacity(line.len());
+        let mut rest = code;
+        while let Some(start) = rest.find(|c: char| c.is_alphabetic() || c == '_') {
+            out += &rest[..start];
+            rest = &rest[start..];
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            match self.defines.get(word) {
+                Some(value) if !value.is_empty() => out += value,
+                _ => out += word,
+            }
+            rest = &rest[end..];
+        }
+        out + rest + comment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCES: &[(&str, &str)] = &[
+        ("a.wgsl", "#include \"b.wgsl\"\n#include \"b.wgsl\"\nfn a() {}\n"),
+        ("b.wgsl", "// from b\nconst B: f32 = SIZE;\n"),
+        (
+            "flags.wgsl",
+            "#ifdef FAST\nfast\n#else\nslow\n#endif\n#ifndef FAST\n#ifdef LOUD\nloud\n#endif\n#endif\n",
+        ),
+        ("else.wgsl", "#else\n"),
+        ("endif.wgsl", "#endif\n"),
+        ("open.wgsl", "#ifdef FAST\n"),
+        ("what.wgsl", "#pragma once\n"),
+        ("missing.wgsl", "#include \"nowhere.wgsl\"\n"),
+        (
+            "define.wgsl",
+            "#define SIZE 4.0\nlet size = SIZE; // SIZE\nlet sizes = SIZES + MY_SIZE;\n",
+        ),
+    ];
+
+    fn load(name: &str, defines: &[&str]) -> Result<String> {
+        load_from(SOURCES, name, defines)
+    }
+
+    #[test]
+    fn includes_each_file_once() {
+        let out = load("a.wgsl", &["SIZE 2.0"]).unwrap();
+        assert_eq!(out, "// from b\nconst B: f32 = 2.0;\nfn a() {}\n");
+    }
+
+    #[test]
+    fn ifdef_else_and_nesting() {
+        assert_eq!(load("flags.wgsl", &["FAST"]).unwrap(), "fast\n");
+        assert_eq!(load("flags.wgsl", &[]).unwrap(), "slow\n");
+        assert_eq!(load("flags.wgsl", &["LOUD"]).unwrap(), "slow\nloud\n");
+    }
+
+    #[test]
+    fn defines_replace_whole_words_outside_comments() {
+        assert_eq!(
+            load("define.wgsl", &[]).unwrap(),
+            "let size = 4.0; // SIZE\nlet sizes = SIZES + MY_SIZE;\n"
+        );
+    }
+
+    #[test]
+    fn broken_directives_are_errors() {
+        for name in [
+            "else.wgsl",
+            "endif.wgsl",
+            "open.wgsl",
+            "what.wgsl",
+            "missing.wgsl",
+            "nowhere.wgsl",
+        ] {
+            assert!(load(name, &[]).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn every_shader_preprocesses() {
+        for (name, _) in super::SOURCES {
+            super::load(name, &[]).unwrap();
+        }
+    }
+}
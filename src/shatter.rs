@@ -0,0 +1,139 @@
+use crate::math::{Mat4, Point3, Quat, Vec3};
+use crate::{Instance, InstanceRaw};
+
+// fragments around at once, a new shatter takes over the oldest slots when
+// they're all in use so nothing is allocated after startup
+const POOL: usize = 256;
+const LIFETIME: f32 = 2.0;
+const GRAVITY: f32 = 9.8;
+// how fast the pieces fly apart, give or take a bit each
+const BURST: f32 = 5.0;
+// the share of its speed a piece keeps bouncing off the floor
+const BOUNCE: f32 = 0.3;
+// where a piece's center is when it lies on the floor, they're half size
+const GROUND: f32 = crate::floor::FLOOR_Y + 0.5;
+
+#[derive(Copy, Clone, Default)]
+struct Fragment {
+    position: Point3,
+    velocity: Vec3,
+    rotation: Quat,
+    // axis times radians per second
+    spin: Vec3,
+    skin: u32,
+    // timer time it broke off, it's gone LIFETIME later
+    born: f32,
+    alive: bool,
+}
+
+// Pieces of destroyed cubes. A cube breaks along its three middle planes
+// into eight half size cubes, which fly apart from its center, tumble and
+// bounce on the floor for a couple of seconds and dither out at the end.
+// They live in a fixed pool with a buffer of their own, drawn with the cube
+// pipeline.
+pub struct Shatter {
+    fragments: Vec<Fragment>,
+    // the slot the next piece goes in
+    next: usize,
+    raw: Vec<InstanceRaw>,
+    buffer: wgpu::Buffer,
+}
+
+impl Shatter {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            fragments: vec![Fragment::default(); POOL],
+            next: 0,
+            raw: Vec::with_capacity(POOL),
+            buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("fragment instance buffer"),
+                size: (POOL * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        }
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn len(&self) -> usize {
+        self.raw.len()
+    }
+
+    // breaks `cube` up at timer time `now`, `impulse` is added to every piece
+    pub fn shatter(&mut self, cube: &Instance, impulse: Vec3, now: f32, rng: &mut crate::rng::Rng) {
+        for octant in 0..8 {
+            let corner = Vec3::new(
+                if octant & 1 == 0 { -0.5 } else { 0.5 },
+                if octant & 2 == 0 { -0.5 } else { 0.5 },
+                if octant & 4 == 0 { -0.5 } else { 0.5 },
+            );
+            let outward = cube.rotation * corner.normalize();
+            let spin = Vec3::new(rng.f32() - 0.5, rng.f32() - 0.5, rng.f32() - 0.5) * 8.0;
+            self.fragments[self.next] = Fragment {
+                position: cube.position + cube.rotation * corner,
+                velocity: outward * BURST * (0.7 + rng.f32() * 0.6) + impulse,
+                rotation: cube.rotation,
+                spin,
+                skin: cube.skin,
+                born: now,
+                alive: true,
+            };
+            self.next = (self.next + 1) % POOL;
+        }
+    }
+
+    pub fn update(&mut self, dt: f32, now: f32) {
+        for fragment in self.fragments.iter_mut().filter(|f| f.alive) {
+            if now - fragment.born >= LIFETIME {
+                fragment.alive = false;
+                continue;
+            }
+            fragment.velocity.y -= GRAVITY * dt;
+            fragment.position += fragment.velocity * dt;
+            fragment.rotation =
+                (Quat::from_scaled_axis(fragment.spin * dt) * fragment.rotation).normalize();
+            if fragment.position.y < GROUND {
+                fragment.position.y = GROUND;
+                fragment.velocity.y = -fragment.velocity.y * BOUNCE;
+                fragment.velocity.x *= 1.0 - BOUNCE;
+                fragment.velocity.z *= 1.0 - BOUNCE;
+                fragment.spin *= 1.0 - BOUNCE;
+            }
+        }
+    }
+
+    // writes every live piece, giving back how many bytes went up
+    pub fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        staging: &mut crate::staging::StagingRing,
+    ) -> usize {
+        self.raw.clear();
+        self.raw.extend(
+            self.fragments
+                .iter()
+                .filter(|f| f.alive)
+                .map(|f| InstanceRaw {
+                    model: crate::math::mat4_array(Mat4::from_scale_rotation_translation(
+                        Vec3::splat(0.5),
+                        f.rotation,
+                        f.position,
+                    )),
+                    flash: crate::NO_FLASH,
+                    skin: f.skin,
+                    // dithered out over the end of its life
+                    fade: -(f.born + LIFETIME - crate::stream::FADE_SECONDS),
+                }),
+        );
+        if self.raw.is_empty() {
+            return 0;
+        }
+        let bytes: &[u8] = bytemuck::cast_slice(&self.raw);
+        staging.write(device, queue, &self.buffer, 0, bytes);
+        bytes.len()
+    }
+}
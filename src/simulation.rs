@@ -0,0 +1,227 @@
+use crate::math::{Point3, Quat, Vec3};
+use crate::physics::{self, Layers};
+use crate::Instance;
+
+// how far a networked player's cube goes in a tick flat out, the same as on
+// screen at 60fps
+const SPEED: f32 = 0.5;
+// networked players are dropped in a row along x, this far apart and from this high
+const SPACING: f32 = 4.0;
+const DROP: f32 = 10.0;
+
+// one cube someone's walking around, through a character controller
+#[derive(Clone)]
+pub struct Player {
+    pub cube: usize,
+    pub controller: crate::character::CharacterController,
+    // where it's trying to go this step, None leaves it where it is (grid
+    // mode moves it itself)
+    pub wanted: Option<Vec3>,
+    // how far it went last step
+    pub moved: Vec3,
+    // trigger cubes it's inside, to notice it going into new ones
+    touching: Vec<usize>,
+}
+
+impl Player {
+    pub fn new(cube: usize) -> Self {
+        Self {
+            cube,
+            controller: Default::default(),
+            wanted: None,
+            moved: Vec3::ZERO,
+            touching: Vec::new(),
+        }
+    }
+}
+
+// what happened in a step that something outside might want to show or play
+pub enum Event {
+    // a player came down onto something, at the cube's position
+    Landed(Point3),
+    // a player went into trigger cube `usize`
+    Triggered(usize),
+}
+
+// The part of a frame that doesn't need a window or a GPU: players walking,
+// boids, joints and triggers, over the cubes. App runs one for the game on
+// screen and server.rs runs one with no window at all, so nothing in here
+// can touch wgpu; what changed is left in `dirty` for whoever draws it.
+// It's cloned for rollback.rs to go back to, so it's plain data all through.
+#[derive(Clone)]
+pub struct Simulation {
+    pub cubes: Vec<Instance>,
+    // instances changed since the last upload
+    pub dirty: crate::dirty::DirtyRanges,
+    // which collision layers touch, from the scene file
+    pub filter: physics::Filter,
+    // the first is the one on this machine, on cube 0
+    pub players: Vec<Player>,
+    // flocking for every cube after the first
+    pub boids: crate::boids::Boids,
+    // cubes connected into contraptions from the console
+    pub joints: crate::joints::Joints,
+    // steps run so far
+    pub tick: u64,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self {
+            cubes: Vec::new(),
+            dirty: Default::default(),
+            filter: Default::default(),
+            players: vec![Player::new(0)],
+            boids: Default::default(),
+            joints: Default::default(),
+            tick: 0,
+        }
+    }
+}
+
+impl Simulation {
+    // what a server starts from: a fixed marker at the origin and nobody playing
+    pub fn hosted() -> Self {
+        Self {
+            cubes: vec![Instance {
+                position: Vec3::ZERO,
+                rotation: Quat::IDENTITY,
+                flash: crate::NO_FLASH,
+                skin: 0,
+                layer: Layers::STATIC,
+            }],
+            players: Vec::new(),
+            ..Default::default()
+        }
+    }
+
+    // someone new over the network, with a cube of their own; gives back the cube
+    pub fn join(&mut self) -> usize {
+        let cube = self.cubes.len();
+        self.cubes.push(Instance {
+            position: Vec3::new(self.players.len() as f32 * SPACING + SPACING, DROP, 0.0),
+            rotation: Quat::IDENTITY,
+            flash: crate::NO_FLASH,
+            skin: 0,
+            layer: Layers::PLAYER,
+        });
+        self.players.push(Player::new(cube));
+        cube
+    }
+
+    pub fn step(&mut self, frame_time: f64, rng: &mut crate::rng::Rng) -> Vec<Event> {
+        self.tick += 1;
+        let mut events = Vec::new();
+        for player in &mut self.players {
+            player.moved = Vec3::ZERO;
+            let Some(wanted) = player.wanted else {
+                continue;
+            };
+            let cube = player.cube;
+            // triggers are noticed, not stood on
+            let solid = self
+                .filter
+                .mask(self.cubes[cube].layer)
+                .without(Layers::TRIGGER);
+            let from = self.cubes[cube].position;
+            let was_grounded = player.controller.grounded;
+            let to = player.controller.step(
+                frame_time as f32,
+                from,
+                wanted,
+                physics::World::new(&self.cubes).ignoring(cube),
+                solid,
+            );
+            if to != from {
+                self.cubes[cube].position = to;
+                self.dirty.mark(cube..cube + 1);
+                player.moved = to - from;
+            }
+            if player.controller.grounded && !was_grounded {
+                events.push(Event::Landed(to));
+            }
+        }
+
+        if self.boids.enabled && self.cubes.len() > 1 {
+            let mut positions = self.cubes[1..]
+                .iter()
+                .map(|c| c.position)
+                .collect::<Vec<_>>();
+            let steps = self
+                .boids
+                .update(frame_time, self.cubes[0].position, &mut positions, rng);
+            if steps > 0 {
+                for (i, (cube, position)) in self.cubes[1..].iter_mut().zip(positions).enumerate() {
+                    cube.position = position;
+                    if let Ok(rotation) =
+                        crate::math::look_rotation(self.boids.velocity(i), crate::math::UP)
+                    {
+                        cube.rotation = rotation;
+                    }
+                }
+                self.dirty.mark(1..self.cubes.len());
+            }
+        }
+        if self.joints.update(frame_time, &mut self.cubes) > 0 {
+            for i in self.joints.moving() {
+                self.dirty.mark(i..i + 1);
+            }
+        }
+        self.touch_triggers(&mut events);
+        events
+    }
+
+    // Notices players going into trigger cubes, once per visit.
+    fn touch_triggers(&mut self, events: &mut Vec<Event>) {
+        for player in &mut self.players {
+            let cube = &self.cubes[player.cube];
+            let touching = physics::contacts(
+                &self.filter,
+                cube.layer,
+                physics::Aabb::of_cube(cube.position, cube.rotation),
+                self.cubes
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, cube)| *i != player.cube && cube.layer == Layers::TRIGGER)
+                    .map(|(i, cube)| {
+                        (
+                            i,
+                            cube.layer,
+                            physics::Aabb::of_cube(cube.position, cube.rotation),
+                        )
+                    }),
+            )
+            .collect::<Vec<_>>();
+            for &i in &touching {
+                if !player.touching.contains(&i) {
+                    events.push(Event::Triggered(i));
+                }
+            }
+            player.touching = touching;
+        }
+    }
+
+    // everything that remembers cubes by index, after cube `i` was taken out
+    pub fn removed(&mut self, i: usize) {
+        self.joints.removed(i);
+        for player in &mut self.players {
+            player.touching.clear();
+            if player.cube > i {
+                player.cube -= 1;
+            }
+        }
+    }
+
+    // a different set of cubes altogether, nothing carries over
+    pub fn replaced(&mut self) {
+        self.joints.clear();
+        for player in &mut self.players {
+            player.touching.clear();
+        }
+    }
+}
+
+// what a networked player trying to go `direction` wants this tick
+pub fn walk(direction: Vec3) -> Vec3 {
+    direction.with_y(0.0).clamp_length_max(1.0) * SPEED
+}
@@ -0,0 +1,30 @@
+#define CAMERA_GROUP 0
+#include "common.wgsl"
+
+@group(1) @binding(0)
+var t_environment: texture_cube<f32>;
+@group(1) @binding(1)
+var s_environment: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+}
+
+// one triangle covering the whole screen, no vertex buffer needed
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.ndc = uv * 2.0 - 1.0;
+    out.clip_position = vec4<f32>(out.ndc, 1.0, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let near = camera.inv_view_proj * vec4<f32>(in.ndc, 0.0, 1.0);
+    let far = camera.inv_view_proj * vec4<f32>(in.ndc, 1.0, 1.0);
+    let dir = far.xyz / far.w - near.xyz / near.w;
+    return textureSample(t_environment, s_environment, normalize(dir));
+}
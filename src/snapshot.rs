@@ -0,0 +1,169 @@
+use crate::simulation::Simulation;
+
+// older ones are dropped past this, each is a copy of every cube
+const MAX_SNAPSHOTS: usize = 16;
+// changed values listed before the rest are just counted
+const MAX_CHANGES: usize = 20;
+
+// the whole simulation as it was at one tick
+pub struct Snapshot {
+    pub name: String,
+    pub tick: u64,
+    pub state: Simulation,
+}
+
+// `snapshot [name]` in the console keeps a copy of the simulation and `diff
+// <a> [b]` prints what's different between two of them, or between one and
+// now: how many cubes there are, and every value of every cube and player
+// that isn't exactly the same. Exactly, so a run that should have come out
+// the same and didn't shows where it started to go its own way.
+#[derive(Default)]
+pub struct Snapshots {
+    taken: Vec<Snapshot>,
+    // for naming the next one
+    count: usize,
+}
+
+impl Snapshots {
+    // gives back the name it went under, one of its own if none was given
+    pub fn take(&mut self, name: Option<&str>, sim: &Simulation) -> String {
+        self.count += 1;
+        let name = name.map_or_else(|| format!("s{}", self.count), str::to_string);
+        self.taken.retain(|s| s.name != name);
+        if self.taken.len() == MAX_SNAPSHOTS {
+            self.taken.remove(0);
+        }
+        self.taken.push(Snapshot {
+            name: name.clone(),
+            tick: sim.tick,
+            state: sim.clone(),
+        });
+        name
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Snapshot> {
+        self.taken.iter().find(|s| s.name == name)
+    }
+
+    pub fn list(&self) -> String {
+        if self.taken.is_empty() {
+            return "no snapshots, snapshot [name] takes one".to_string();
+        }
+        self.taken
+            .iter()
+            .map(|s| {
+                format!(
+                    "{} at tick {}, {} cubes",
+                    s.name,
+                    s.tick,
+                    s.state.cubes.len()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn clear(&mut self) {
+        self.taken.clear();
+    }
+}
+
+// what changed from `a` to `b`, a line each
+pub fn diff(a: &Snapshot, b_name: &str, b: &Simulation) -> Vec<String> {
+    let before = &a.state;
+    let mut lines = vec![format!(
+        "{} (tick {}) -> {} (tick {})",
+        a.name, a.tick, b_name, b.tick
+    )];
+    if before.cubes.len() != b.cubes.len() {
+        lines.push(format!(
+            "cubes: {} -> {} ({:+})",
+            before.cubes.len(),
+            b.cubes.len(),
+            b.cubes.len() as i64 - before.cubes.len() as i64
+        ));
+    }
+
+    let mut changes = Vec::new();
+    let mut changed_cubes = 0;
+    for (i, (was, is)) in before.cubes.iter().zip(&b.cubes).enumerate() {
+        let count = changes.len();
+        if was.position != is.position {
+            changes.push(format!(
+                "cube {} position {} -> {}",
+                i, was.position, is.position
+            ));
+        }
+        if was.rotation != is.rotation {
+            changes.push(format!(
+                "cube {} rotation {} -> {}",
+                i, was.rotation, is.rotation
+            ));
+        }
+        if was.skin != is.skin {
+            changes.push(format!("cube {} skin {} -> {}", i, was.skin, is.skin));
+        }
+        if was.layer != is.layer {
+            changes.push(format!(
+                "cube {} layer {} -> {}",
+                i,
+                was.layer.name().unwrap_or("several"),
+                is.layer.name().unwrap_or("several")
+            ));
+        }
+        if was.flash != is.flash {
+            changes.push(format!("cube {} flash {} -> {}", i, was.flash, is.flash));
+        }
+        if changes.len() > count {
+            changed_cubes += 1;
+        }
+    }
+
+    if before.players.len() != b.players.len() {
+        changes.push(format!(
+            "players: {} -> {}",
+            before.players.len(),
+            b.players.len()
+        ));
+    }
+    for (i, (was, is)) in before.players.iter().zip(&b.players).enumerate() {
+        if was.cube != is.cube {
+            changes.push(format!("player {} cube {} -> {}", i, was.cube, is.cube));
+        }
+        if was.controller.grounded != is.controller.grounded {
+            changes.push(format!(
+                "player {} grounded {} -> {}",
+                i, was.controller.grounded, is.controller.grounded
+            ));
+        }
+        if was.moved != is.moved {
+            changes.push(format!("player {} moved {} -> {}", i, was.moved, is.moved));
+        }
+    }
+    if before.joints.joints.len() != b.joints.joints.len() {
+        changes.push(format!(
+            "joints: {} -> {}",
+            before.joints.joints.len(),
+            b.joints.joints.len()
+        ));
+    }
+    if before.boids.enabled != b.boids.enabled {
+        changes.push(format!(
+            "boids {} -> {}",
+            before.boids.enabled, b.boids.enabled
+        ));
+    }
+
+    let shared = before.cubes.len().min(b.cubes.len());
+    if changes.is_empty() && before.cubes.len() == b.cubes.len() {
+        lines.push("identical".to_string());
+        return lines;
+    }
+    lines.push(format!("{} of {} cubes changed", changed_cubes, shared));
+    let more = changes.len().saturating_sub(MAX_CHANGES);
+    lines.extend(changes.into_iter().take(MAX_CHANGES));
+    if more > 0 {
+        lines.push(format!("... and {} more", more));
+    }
+    lines
+}
@@ -0,0 +1,153 @@
+use wgpu::util::DeviceExt;
+use wgpu_text::glyph_brush::ab_glyph::FontRef;
+use wgpu_text::glyph_brush::{HorizontalAlign, Layout, Section as TextSection, Text};
+use wgpu_text::TextBrush;
+
+// how fast the spinner goes round, in turns a second
+const TURNS: f32 = 0.8;
+
+// What's on screen while everything else is being made. It's one small
+// pipeline made as soon as the surface is, and `show` draws and presents a
+// whole frame on its own, so Loading can put one up every frame while its
+// worker makes the rest.
+pub struct Splash {
+    pipeline: wgpu::RenderPipeline,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    started: std::time::Instant,
+}
+
+impl Splash {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("splash buffer"),
+            contents: bytemuck::cast_slice(&[0.0f32; 4]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group_layout = crate::reflect::bind_group_layout(
+            device,
+            "splash bind group layout",
+            &[("splash.wgsl", 0)],
+        )
+        .unwrap();
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("splash bind group"),
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("splash shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                crate::shaders::load("splash.wgsl", &[]).unwrap().into(),
+            ),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("splash pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("splash render pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: crate::pipeline_cache::current().as_deref(),
+        });
+        Self {
+            pipeline,
+            buffer,
+            bind_group,
+            started: std::time::Instant::now(),
+        }
+    }
+
+    // draws the bar at `progress` (0 to 1) with `label` under it and presents
+    #[allow(clippy::too_many_arguments)]
+    pub fn show(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        surface: &wgpu::Surface,
+        config: &wgpu::SurfaceConfiguration,
+        brush: &mut TextBrush<FontRef<'static>>,
+        progress: f32,
+        label: &str,
+    ) {
+        let output = match surface.get_current_texture() {
+            Ok(output) => output,
+            // nothing to show it on yet, init carries on regardless
+            Err(e) => {
+                log::debug!("no splash frame: {}", e);
+                return;
+            }
+        };
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let angle = self.started.elapsed().as_secs_f32() * TURNS * std::f32::consts::TAU;
+        let aspect = config.width as f32 / config.height.max(1) as f32;
+        queue.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::cast_slice(&[progress, angle, aspect, 0.0]),
+        );
+        // the brush is made at the surface's size, so the text goes by it too
+        let (width, height) = (config.width as f32, config.height as f32);
+        let section = TextSection::default()
+            .add_text(
+                Text::new(label)
+                    .with_scale(18.0)
+                    .with_color([0.6, 0.7, 0.8, 1.0]),
+            )
+            .with_screen_position((width / 2.0, height * 0.6))
+            .with_layout(Layout::default().h_align(HorizontalAlign::Center));
+        brush.queue(device, queue, [&section]).unwrap();
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("splash encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("splash pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+            brush.draw(&mut render_pass);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+    }
+}
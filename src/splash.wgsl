@@ -0,0 +1,62 @@
+// The loading screen: a progress bar across the middle and a spinning arc
+// above it, worked out per pixel from one triangle over the screen so it
+// needs nothing else set up first.
+struct Loading {
+    // how far along, 0 to 1
+    progress: f32,
+    // the spinner's turn in radians
+    angle: f32,
+    // width over height
+    aspect: f32,
+    _pad: f32,
+}
+@group(0) @binding(0)
+var<uniform> loading: Loading;
+
+const BACKGROUND = vec3<f32>(0.01, 0.012, 0.02);
+const TRACK = vec3<f32>(0.04, 0.05, 0.07);
+const ACCENT = vec3<f32>(0.3, 0.8, 1.0);
+const PI = 3.14159265;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+// 1 inside, 0 outside, with a pixel or so of smoothing at the edge
+fn inside(distance: f32, width: f32) -> f32 {
+    return 1.0 - smoothstep(-width, width, distance);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // y from the middle in screen heights, x the same scale
+    let p = vec2<f32>((in.uv.x - 0.5) * loading.aspect, in.uv.y - 0.5);
+    let edge = fwidth(p.y);
+    var color = BACKGROUND;
+
+    // the bar, a rounded strip
+    let half_size = vec2<f32>(0.25, 0.006);
+    let q = abs(p - vec2<f32>(0.0, 0.05)) - half_size + 0.006;
+    let bar = length(max(q, vec2<f32>(0.0))) - 0.006;
+    color = mix(color, TRACK, inside(bar, edge));
+    let filled = p.x < mix(-half_size.x, half_size.x, saturate(loading.progress));
+    color = mix(color, ACCENT, inside(bar, edge) * select(0.0, 1.0, filled));
+
+    // a quarter of a ring going round
+    let centre = p - vec2<f32>(0.0, -0.05);
+    let ring = abs(length(centre) - 0.03) - 0.004;
+    let around = fract((atan2(centre.y, centre.x) - loading.angle) / (2.0 * PI));
+    color = mix(color, ACCENT, inside(ring, edge) * smoothstep(0.0, 0.25, around) * step(around, 0.25));
+
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,268 @@
+use crate::math::Point3;
+use crate::vertex::Vertex;
+use std::collections::BTreeMap;
+use wgpu::util::DeviceExt;
+
+const MAX_SPRITES: usize = 1024;
+
+#[derive(Copy, Clone, Debug, PartialEq, serde::Deserialize)]
+pub enum LoopMode {
+    Loop,
+    // forwards then backwards, without showing the end frames twice
+    PingPong,
+    // holds the last frame once it's played through
+    Once,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct Animation {
+    pub frames: Vec<u32>,
+    pub fps: f32,
+    pub mode: LoopMode,
+}
+
+impl Animation {
+    // the atlas frame `seconds` into the animation
+    pub fn frame_at(&self, seconds: f32) -> u32 {
+        let n = self.frames.len();
+        let k = (seconds.max(0.0) * self.fps) as usize;
+        let i = match self.mode {
+            LoopMode::Loop => k % n,
+            LoopMode::PingPong if n > 1 => {
+                let period = 2 * n - 2;
+                let i = k % period;
+                if i < n {
+                    i
+                } else {
+                    period - i
+                }
+            }
+            LoopMode::PingPong => 0,
+            LoopMode::Once => k.min(n - 1),
+        };
+        self.frames[i]
+    }
+}
+
+// what res/sprites/sprites.ron holds
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct AnimationSet {
+    pub atlas: String,
+    pub frame_size: (u32, u32),
+    pub animations: BTreeMap<String, Animation>,
+}
+
+impl AnimationSet {
+    pub fn parse(ron: &str) -> anyhow::Result<Self> {
+        let set: AnimationSet = ron::from_str(ron)?;
+        for (name, animation) in &set.animations {
+            if animation.frames.is_empty() || animation.fps <= 0.0 {
+                anyhow::bail!("animation {} needs frames and a positive fps", name);
+            }
+        }
+        Ok(set)
+    }
+}
+
+pub struct Sprite {
+    pub position: Point3,
+    // half the width and height
+    pub size: f32,
+    pub animation: String,
+    // clock seconds the animation started at
+    pub started: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Vertex)]
+#[vertex(instance)]
+struct SpriteRaw {
+    #[vertex(location = 0)]
+    position: [f32; 3],
+    #[vertex(location = 1)]
+    size: f32,
+    #[vertex(location = 2)]
+    frame: u32,
+}
+
+// Camera facing billboards animated from an atlas. Which frame each sprite
+// shows comes from the session clock, `clock reset` restarts them all.
+pub struct SpriteLayer {
+    pub sprites: Vec<Sprite>,
+    pub set: AnimationSet,
+    instance_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl SpriteLayer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> anyhow::Result<Self> {
+        let set = AnimationSet::parse(include_str!("../res/sprites/sprites.ron"))?;
+        // the atlas is the one file next to the .ron
+        if set.atlas != "sprites.png" {
+            anyhow::bail!(
+                "sprites.ron names {}, only sprites.png is built in",
+                set.atlas
+            );
+        }
+        let image = image::load_from_memory(include_bytes!("../res/sprites/sprites.png"))?;
+        let columns = image.width() / set.frame_size.0;
+        let rows = image.height() / set.frame_size.1;
+        let texture = crate::texture::Texture::from_image(
+            device,
+            queue,
+            &image,
+            Some("sprite atlas"),
+            false,
+        )?;
+        // linear filtering would bleed the neighbouring frames in at the edges
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("sprite atlas sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let atlas_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("sprite atlas buffer"),
+            contents: bytemuck::cast_slice(&[columns as f32, rows as f32, 0.0, 0.0]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sprite instance buffer"),
+            size: (MAX_SPRITES * std::mem::size_of::<SpriteRaw>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = crate::reflect::bind_group_layout(
+            device,
+            "sprite bind group layout",
+            &[("sprite.wgsl", 1)],
+        )?;
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: atlas_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some("sprite bind group"),
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("sprite shader"),
+            source: wgpu::ShaderSource::Wgsl(crate::shaders::load("sprite.wgsl", &[])?.into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("sprite pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("sprite render pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[SpriteRaw::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(crate::texture::Texture::depth_state(
+                true,
+                wgpu::CompareFunction::Less,
+            )),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: crate::pipeline_cache::current().as_deref(),
+        });
+
+        Ok(Self {
+            sprites: Vec::new(),
+            set,
+            instance_buffer,
+            bind_group,
+            pipeline,
+        })
+    }
+
+    pub fn spawn(&mut self, animation: &str, position: Point3, now: f32) -> anyhow::Result<()> {
+        if !self.set.animations.contains_key(animation) {
+            anyhow::bail!("no animation called {}", animation);
+        }
+        if self.sprites.len() == MAX_SPRITES {
+            anyhow::bail!("already at {} sprites", MAX_SPRITES);
+        }
+        self.sprites.push(Sprite {
+            position,
+            size: 1.0,
+            animation: animation.to_string(),
+            started: now,
+        });
+        Ok(())
+    }
+
+    // picks every sprite's frame for clock time `now`
+    pub fn update(&self, queue: &wgpu::Queue, now: f32) {
+        if self.sprites.is_empty() {
+            return;
+        }
+        let raw = self
+            .sprites
+            .iter()
+            .map(|sprite| SpriteRaw {
+                position: crate::math::vec3_array(sprite.position),
+                size: sprite.size,
+                frame: self.set.animations[&sprite.animation].frame_at(now - sprite.started),
+            })
+            .collect::<Vec<_>>();
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&raw));
+    }
+
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass, camera_bind_group: &wgpu::BindGroup) {
+        if self.sprites.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.draw(0..6, 0..self.sprites.len() as u32);
+    }
+}
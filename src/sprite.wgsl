@@ -0,0 +1,59 @@
+#define CAMERA_GROUP 0
+#include "common.wgsl"
+
+// atlas columns and rows
+struct Atlas {
+    size: vec4<f32>,
+}
+@group(1) @binding(0)
+var<uniform> atlas: Atlas;
+@group(1) @binding(1)
+var t_atlas: texture_2d<f32>;
+@group(1) @binding(2)
+var s_atlas: sampler;
+
+struct SpriteInput {
+    @location(0) position: vec3<f32>,
+    @location(1) size: f32,
+    @location(2) frame: u32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, sprite: SpriteInput) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, 1.0), vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, 1.0),
+        vec2<f32>(1.0, 1.0), vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, -1.0),
+    );
+    let corner = corners[index];
+
+    // turned towards the eye, staying upright unless it's looked at from straight above
+    let forward = normalize(camera.view_pos.xyz - sprite.position);
+    var right = cross(vec3<f32>(0.0, 1.0, 0.0), forward);
+    if length(right) < 0.001 {
+        right = vec3<f32>(1.0, 0.0, 0.0);
+    }
+    right = normalize(right);
+    let up = cross(forward, right);
+    let world = sprite.position + (right * corner.x + up * corner.y) * sprite.size;
+
+    let columns = u32(atlas.size.x);
+    let cell = vec2<f32>(f32(sprite.frame % columns), f32(sprite.frame / columns));
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(world, 1.0);
+    out.uv = (cell + vec2<f32>(corner.x, -corner.y) * 0.5 + 0.5) / atlas.size.xy;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_atlas, s_atlas, in.uv);
+    if color.a < 0.5 {
+        discard;
+    }
+    return color;
+}
@@ -0,0 +1,125 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// enough for a few thousand cubes moving every frame
+const SLOT_SIZE: wgpu::BufferAddress = 1 << 20;
+// how many frames the GPU can be behind before writes fall back
+const SLOTS: usize = 3;
+
+struct Slot {
+    buffer: wgpu::Buffer,
+    // set by the map callback once the GPU is done copying out of it
+    mapped: Arc<AtomicBool>,
+    cursor: wgpu::BufferAddress,
+}
+
+// A ring of staging buffers for data that changes every frame. Writes are
+// copied into the current slot while it's mapped and a copy into the real
+// buffer is recorded, so nothing gets allocated per frame. After submitting,
+// the used slot is mapped again in the background and the ring moves on, and
+// a slot only gets written once its map has come back, which is what keeps
+// the GPU from reading a slot that's being overwritten. When the next slot
+// isn't back yet or is full, writes go through queue.write_buffer instead.
+pub struct StagingRing {
+    slots: Vec<Slot>,
+    current: usize,
+    encoder: Option<wgpu::CommandEncoder>,
+    // the current slot got unmapped for a submit and has to be mapped again
+    submitted: bool,
+    // bytes that had to go through queue.write_buffer since the last `take_fallback_bytes`
+    fallback_bytes: usize,
+}
+
+impl StagingRing {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let slots = (0..SLOTS)
+            .map(|i| Slot {
+                buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(&format!("staging ring slot {}", i)),
+                    size: SLOT_SIZE,
+                    usage: wgpu::BufferUsages::MAP_WRITE | wgpu::BufferUsages::COPY_SRC,
+                    mapped_at_creation: true,
+                }),
+                mapped: Arc::new(AtomicBool::new(true)),
+                cursor: 0,
+            })
+            .collect();
+        Self {
+            slots,
+            current: 0,
+            encoder: None,
+            submitted: false,
+            fallback_bytes: 0,
+        }
+    }
+
+    pub fn write(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        target: &wgpu::Buffer,
+        offset: wgpu::BufferAddress,
+        data: &[u8],
+    ) {
+        let size = data.len() as wgpu::BufferAddress;
+        let slot = &mut self.slots[self.current];
+        let start = slot.cursor.next_multiple_of(wgpu::MAP_ALIGNMENT);
+        let fits = start + size <= SLOT_SIZE
+            && size.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT)
+            && offset.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT);
+        if size == 0 || !fits || !slot.mapped.load(Ordering::Acquire) {
+            queue.write_buffer(target, offset, data);
+            self.fallback_bytes += data.len();
+            return;
+        }
+
+        slot.buffer
+            .slice(start..start + size)
+            .get_mapped_range_mut()
+            .copy_from_slice(data);
+        slot.cursor = start + size;
+        self.encoder
+            .get_or_insert_with(|| {
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("staging ring encoder"),
+                })
+            })
+            .copy_buffer_to_buffer(&slot.buffer, start, target, offset, size);
+    }
+
+    // the copies recorded since last time, to be submitted before anything
+    // that reads the buffers they write
+    pub fn finish(&mut self) -> Option<wgpu::CommandBuffer> {
+        let encoder = self.encoder.take()?;
+        let slot = &self.slots[self.current];
+        slot.mapped.store(false, Ordering::Release);
+        slot.buffer.unmap();
+        self.submitted = true;
+        Some(encoder.finish())
+    }
+
+    // call after submitting what `finish` returned
+    pub fn recall(&mut self, device: &wgpu::Device) {
+        // fires the callbacks of slots the GPU has finished with
+        device.poll(wgpu::Maintain::Poll);
+        if !self.submitted {
+            // nothing written this frame, keep filling the same slot
+            return;
+        }
+        self.submitted = false;
+        let slot = &mut self.slots[self.current];
+        slot.cursor = 0;
+        let mapped = slot.mapped.clone();
+        slot.buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Write, move |result| match result {
+                Ok(()) => mapped.store(true, Ordering::Release),
+                Err(e) => log::error!("couldn't map a staging slot again: {}", e),
+            });
+        self.current = (self.current + 1) % SLOTS;
+    }
+
+    pub fn take_fallback_bytes(&mut self) -> usize {
+        std::mem::take(&mut self.fallback_bytes)
+    }
+}
@@ -0,0 +1,145 @@
+use wgpu_text::glyph_brush::{HorizontalAlign, Layout, OwnedSection, Section as TextSection, Text};
+
+// Per frame numbers shown in the corner while F3 is on. Counters are added to
+// during the frame and start over once they've been shown.
+#[derive(Default)]
+pub struct Stats {
+    pub visible: bool,
+    pub instance_bytes: usize,
+    // dynamic data that couldn't go through the staging ring
+    pub staging_fallback_bytes: usize,
+    // the main pass's mesh draws, and the state they had to change
+    pub draws: usize,
+    pub pipeline_switches: usize,
+    pub bind_group_switches: usize,
+    pub buffer_switches: usize,
+    // how long the last frame took, set once a frame
+    pub frame_ms: f64,
+    // in a rollback session, how far it's been going back
+    pub rollback: Option<crate::rollback::Depth>,
+    // the counters above as they were shown last, for json()
+    last: [usize; 6],
+    // the oldest input that hasn't made it to the screen yet
+    input_at: Option<std::time::Instant>,
+    // smoothed input to photon estimate
+    latency_ms: Option<f32>,
+}
+
+impl Stats {
+    pub fn input(&mut self) {
+        self.input_at.get_or_insert_with(std::time::Instant::now);
+    }
+
+    // After a present: the time since the input, plus however long the frame
+    // will sit in the swapchain before scanout. The second half can't be
+    // measured from here, so it's an estimate from the present settings.
+    pub fn presented(&mut self, queued_refreshes: f32, refresh: std::time::Duration) {
+        let Some(input_at) = self.input_at.take() else {
+            return;
+        };
+        let sample =
+            (input_at.elapsed().as_secs_f32() + queued_refreshes * refresh.as_secs_f32()) * 1000.0;
+        self.latency_ms = Some(match self.latency_ms {
+            Some(latency) => latency * 0.9 + sample * 0.1,
+            None => sample,
+        });
+    }
+
+    // the last frame's numbers as JSON, only numbers go in so nothing to escape
+    #[cfg(feature = "http")]
+    pub fn json(&self, cubes: usize, streamed: usize) -> String {
+        let [instance_bytes, staging_fallback_bytes, draws, pipelines, materials, buffers] =
+            self.last;
+        format!(
+            "{{\"frame_ms\": {:.3}, \"cubes\": {}, \"streamed_instances\": {}, \
+             \"instance_upload_bytes\": {}, \"staging_fallback_bytes\": {}, \
+             \"draws\": {}, \"pipeline_switches\": {}, \"bind_group_switches\": {}, \
+             \"buffer_switches\": {}, \"latency_ms\": {}}}\n",
+            self.frame_ms,
+            cubes,
+            streamed,
+            instance_bytes,
+            staging_fallback_bytes,
+            draws,
+            pipelines,
+            materials,
+            buffers,
+            self.latency_ms
+                .map_or("null".to_string(), |l| format!("{:.2}", l))
+        )
+    }
+
+    pub fn section(&mut self, width: f32) -> Option<OwnedSection> {
+        // read every frame, so what's shown is always just the one frame's
+        let (allocs, heap) = crate::allocs::frame();
+        let mut text = format!(
+            "instance upload {} B/frame\nstaging fallback {} B/frame\n\
+             {} mesh draws: {} pipeline, {} material, {} instance buffer changes",
+            self.instance_bytes,
+            self.staging_fallback_bytes,
+            self.draws,
+            self.pipeline_switches,
+            self.bind_group_switches,
+            self.buffer_switches
+        );
+        if let Some(latency) = self.latency_ms {
+            text += &format!("\ninput to photon ~{:.1} ms", latency);
+        }
+        text += &format!(
+            "\nheap {}, {} allocations this frame",
+            size(heap.max(0) as u64),
+            allocs.iter().map(|a| a.count).sum::<u64>()
+        );
+        for usage in &allocs {
+            text += &format!(
+                "\n{} {} allocs, {}/frame, {} in all",
+                usage.name,
+                usage.count,
+                size(usage.bytes),
+                size(usage.total)
+            );
+        }
+        if let Some(depth) = self.rollback {
+            text += &format!(
+                "\nrollback depth {} ticks at most, {} rollbacks and {} ticks run again a second, {} stalls",
+                depth.deepest, depth.rollbacks, depth.resimulated, depth.stalls
+            );
+        }
+        self.last = [
+            self.instance_bytes,
+            self.staging_fallback_bytes,
+            self.draws,
+            self.pipeline_switches,
+            self.bind_group_switches,
+            self.buffer_switches,
+        ];
+        self.instance_bytes = 0;
+        self.staging_fallback_bytes = 0;
+        self.draws = 0;
+        self.pipeline_switches = 0;
+        self.bind_group_switches = 0;
+        self.buffer_switches = 0;
+        if !self.visible {
+            return None;
+        }
+        Some(
+            TextSection::default()
+                .add_text(
+                    Text::new(&text)
+                        .with_color([0.8, 1.0, 0.8, 1.0])
+                        .with_scale(18.0),
+                )
+                .with_screen_position((width - 10.0, 10.0))
+                .with_layout(Layout::default().h_align(HorizontalAlign::Right))
+                .to_owned(),
+        )
+    }
+}
+
+fn size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1048576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1048576.0),
+    }
+}
@@ -0,0 +1,64 @@
+use wgpu_text::glyph_brush::{HorizontalAlign, Layout, OwnedSection, Section as TextSection, Text};
+
+// one step of a paused simulation, the same as a frame at 60fps
+pub const TICK: f64 = 1.0 / 60.0;
+
+// Frame by frame debugging. Paused, the simulation and the clock stand still
+// (frames are still drawn, of the same moment) and each press of the step
+// key runs exactly one TICK of both, so a physics or animation problem can
+// be watched a tick at a time. The tick count goes across the top meanwhile.
+#[derive(Default)]
+pub struct Stepping {
+    pub paused: bool,
+    // presses of the step key not run yet
+    pending: u32,
+}
+
+impl Stepping {
+    pub fn toggle(&mut self) {
+        self.paused = !self.paused;
+        self.pending = 0;
+    }
+
+    pub fn step(&mut self) {
+        if self.paused {
+            self.pending += 1;
+        } else {
+            // the first press stops it where it is
+            self.paused = true;
+        }
+    }
+
+    // How far this frame moves the clock: None runs as normal, 0 stands
+    // still and TICK is one step asked for.
+    pub fn frame(&mut self) -> Option<f64> {
+        if !self.paused {
+            return None;
+        }
+        if self.pending > 0 {
+            self.pending -= 1;
+            return Some(TICK);
+        }
+        Some(0.0)
+    }
+
+    pub fn section(&self, tick: u64, width: f32, pause: &str, step: &str) -> Option<OwnedSection> {
+        if !self.paused {
+            return None;
+        }
+        Some(
+            TextSection::default()
+                .add_text(
+                    Text::new(&format!(
+                        "paused at tick {} ({} steps, {} resumes)",
+                        tick, step, pause
+                    ))
+                    .with_color([1.0, 0.85, 0.4, 1.0])
+                    .with_scale(20.0),
+                )
+                .with_screen_position((width / 2.0, 10.0))
+                .with_layout(Layout::default().h_align(HorizontalAlign::Center))
+                .to_owned(),
+        )
+    }
+}
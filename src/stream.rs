@@ -0,0 +1,312 @@
+use crate::math::{Point3, Quat, Vec3};
+use crate::{Instance, InstanceRaw};
+use std::collections::HashMap;
+
+// cells loaded each side of the one the eye is in
+const RADIUS: i32 = 3;
+// at most this many cells are generated a tick, nearest first, so moving
+// fast fills in over a few frames instead of all in one
+const CELLS_PER_TICK: usize = 4;
+const INITIAL_CAPACITY: usize = 1024;
+// how long a cell takes to fade in or out, FADE in cube.wgsl has to match
+pub const FADE_SECONDS: f32 = 0.5;
+
+type Cell = (i32, i32);
+
+// Makes the instances for one square of the world. It's asked again for a
+// cell that went out of range and came back, so the same cell has to give
+// the same instances every time.
+pub trait Generator {
+    fn name(&self) -> &'static str;
+    // how wide a cell is, in world units
+    fn cell_size(&self) -> f32;
+    fn generate(&mut self, cell: Cell, out: &mut Vec<Instance>);
+}
+
+pub fn by_name(name: &str, seed: u64) -> Option<Box<dyn Generator>> {
+    match name {
+        "city" => Some(Box::new(CubeCity { seed })),
+        _ => None,
+    }
+}
+
+// Instances from a generator for the cells around the camera, in a buffer
+// of their own next to the player's cubes. Cells coming into range are
+// appended and cells leaving have their slots filled from the end, so each
+// tick only writes the instances that moved or are new, and the buffer is
+// only made again when it has to grow.
+//
+// Nothing pops: a new cell's instances carry the time they arrived and the
+// cube shader dithers them in from there, and a cell out of range is marked
+// with the time it left and only removed once it's dithered away.
+pub struct InstanceStream {
+    generator: Box<dyn Generator>,
+    loaded: HashMap<Cell, Vec<usize>>,
+    // cells out of range that are still fading out, and since when
+    leaving: HashMap<Cell, f32>,
+    raw: Vec<InstanceRaw>,
+    // which cell each slot belongs to
+    owners: Vec<Cell>,
+    buffer: wgpu::Buffer,
+    capacity: usize,
+    dirty: crate::dirty::DirtyRanges,
+    // bigger than the buffer, so everything goes up again
+    grown: bool,
+    scratch: Vec<Instance>,
+}
+
+impl InstanceStream {
+    pub fn new(device: &wgpu::Device, generator: Box<dyn Generator>) -> Self {
+        Self {
+            generator,
+            loaded: HashMap::new(),
+            leaving: HashMap::new(),
+            raw: Vec::new(),
+            owners: Vec::new(),
+            buffer: create_buffer(device, INITIAL_CAPACITY),
+            capacity: INITIAL_CAPACITY,
+            dirty: Default::default(),
+            grown: false,
+            scratch: Vec::new(),
+        }
+    }
+
+    // the same generator on a new device, everything in range comes back
+    pub fn restart(self, device: &wgpu::Device) -> Self {
+        Self::new(device, self.generator)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.generator.name()
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn len(&self) -> usize {
+        self.raw.len()
+    }
+
+    pub fn cells(&self) -> usize {
+        self.loaded.len()
+    }
+
+    // fades out what's out of range of `eye` and generates some of what's
+    // come into it, `now` is the timer time the cube shader sees
+    pub fn update(&mut self, eye: Point3, now: f32) {
+        let size = self.generator.cell_size();
+        let center = ((eye.x / size).floor() as i32, (eye.z / size).floor() as i32);
+        let in_range = |cell: &Cell| {
+            (cell.0 - center.0).abs() <= RADIUS && (cell.1 - center.1).abs() <= RADIUS
+        };
+
+        let left = self
+            .loaded
+            .keys()
+            .copied()
+            .filter(|cell| !in_range(cell) && !self.leaving.contains_key(cell))
+            .collect::<Vec<_>>();
+        for cell in left {
+            self.set_fade(cell, -now);
+            self.leaving.insert(cell, now);
+        }
+        // back in range before it was gone, in again from as faded as it got
+        let back = self
+            .leaving
+            .iter()
+            .filter(|(cell, _)| in_range(cell))
+            .map(|(cell, since)| (*cell, *since))
+            .collect::<Vec<_>>();
+        for (cell, since) in back {
+            let shown = 1.0 - ((now - since) / FADE_SECONDS).clamp(0.0, 1.0);
+            self.set_fade(cell, now - shown * FADE_SECONDS);
+            self.leaving.remove(&cell);
+        }
+        let gone = self
+            .leaving
+            .iter()
+            .filter(|(_, since)| now - **since >= FADE_SECONDS)
+            .map(|(cell, _)| *cell)
+            .collect::<Vec<_>>();
+        for cell in gone {
+            self.leaving.remove(&cell);
+            self.remove(cell);
+        }
+
+        let mut wanted = (-RADIUS..=RADIUS)
+            .flat_map(|x| (-RADIUS..=RADIUS).map(move |z| (center.0 + x, center.1 + z)))
+            .filter(|cell| !self.loaded.contains_key(cell))
+            .collect::<Vec<_>>();
+        wanted.sort_by_key(|cell| (cell.0 - center.0).pow(2) + (cell.1 - center.1).pow(2));
+        for cell in wanted.into_iter().take(CELLS_PER_TICK) {
+            self.add(cell, now);
+        }
+    }
+
+    fn set_fade(&mut self, cell: Cell, fade: f32) {
+        for &slot in &self.loaded[&cell] {
+            self.raw[slot].fade = fade;
+            self.dirty.mark(slot..slot + 1);
+        }
+    }
+
+    fn add(&mut self, cell: Cell, now: f32) {
+        let mut instances = std::mem::take(&mut self.scratch);
+        instances.clear();
+        self.generator.generate(cell, &mut instances);
+        let start = self.raw.len();
+        self.raw
+            .extend(instances.iter().map(|instance| InstanceRaw {
+                fade: now,
+                ..instance.to_raw()
+            }));
+        self.owners
+            .extend(std::iter::repeat_n(cell, instances.len()));
+        self.loaded.insert(cell, (start..self.raw.len()).collect());
+        self.dirty.mark(start..self.raw.len());
+        self.scratch = instances;
+    }
+
+    fn remove(&mut self, cell: Cell) {
+        let Some(mut slots) = self.loaded.remove(&cell) else {
+            return;
+        };
+        // highest first, so a slot being filled is never one still to go
+        slots.sort_unstable_by(|a, b| b.cmp(a));
+        for slot in slots {
+            let last = self.raw.len() - 1;
+            self.raw.swap_remove(slot);
+            self.owners.swap_remove(slot);
+            if slot == last {
+                continue;
+            }
+            // the instance from the end moved into `slot`
+            let owner = self.owners[slot];
+            let moved = self.loaded.get_mut(&owner).unwrap();
+            *moved.iter_mut().find(|s| **s == last).unwrap() = slot;
+            self.dirty.mark(slot..slot + 1);
+        }
+    }
+
+    // writes this tick's changes, giving back how many bytes went up
+    pub fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        staging: &mut crate::staging::StagingRing,
+    ) -> usize {
+        if self.raw.len() > self.capacity {
+            self.capacity = self.raw.len().next_power_of_two();
+            self.buffer = create_buffer(device, self.capacity);
+            self.grown = true;
+        }
+        if std::mem::take(&mut self.grown) {
+            self.dirty.clear();
+            self.dirty.mark(0..self.raw.len());
+        }
+        let mut uploaded = 0;
+        for range in self.dirty.take() {
+            // slots past the end were removed since they were marked
+            let range = range.start..range.end.min(self.raw.len());
+            if range.is_empty() {
+                continue;
+            }
+            let bytes: &[u8] = bytemuck::cast_slice(&self.raw[range.clone()]);
+            staging.write(
+                device,
+                queue,
+                &self.buffer,
+                (range.start * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress,
+                bytes,
+            );
+            uploaded += bytes.len();
+        }
+        uploaded
+    }
+}
+
+fn create_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("streamed instance buffer"),
+        size: (capacity * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+///// cube city
+pub const BLOCK: f32 = 32.0;
+// lots a block is split into each way, with half a street along each edge
+const LOTS: i32 = 3;
+const STREET: f32 = 8.0;
+const MAX_FLOORS: f32 = 12.0;
+
+// Blocks of towers made of stacked cubes, tallest where the noise says
+// downtown is. Each cell is a block and everything about it comes from the
+// seed and the cell, so a block looks the same each time it streams back in.
+struct CubeCity {
+    seed: u64,
+}
+
+impl Generator for CubeCity {
+    fn name(&self) -> &'static str {
+        "city"
+    }
+
+    fn cell_size(&self) -> f32 {
+        BLOCK
+    }
+
+    fn generate(&mut self, cell: Cell, out: &mut Vec<Instance>) {
+        city_block(self.seed, cell, out);
+    }
+}
+
+// One block of the city, its corner at the cell times BLOCK, so the streets
+// run along the cell edges. The city scenario lays out a fixed patch of
+// them with the same seed and gets the same city.
+pub fn city_block(seed: u64, cell: Cell, out: &mut Vec<Instance>) {
+    let mut rng = crate::rng::Rng::new(
+        seed ^ (cell.0 as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            ^ (cell.1 as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f),
+    );
+    let noise = crate::noise::Noise { seed: seed as u32 };
+    let downtown = (noise.fbm(cell.0 as f32 * 0.15, cell.1 as f32 * 0.15, 3) + 1.0) / 2.0;
+    let corner = Vec3::new(cell.0 as f32 * BLOCK, 0.0, cell.1 as f32 * BLOCK);
+    let lot = (BLOCK - STREET) / LOTS as f32;
+    for x in 0..LOTS {
+        for z in 0..LOTS {
+            // an empty lot now and then
+            if rng.f32() < 0.15 {
+                continue;
+            }
+            let floors = 1 + (rng.f32() * downtown * downtown * MAX_FLOORS) as u32;
+            // one or two cubes across
+            let width = 1 + rng.below(2);
+            let skin = rng.below(crate::cube::SKINS.len() as u32);
+            let base = corner
+                + Vec3::new(
+                    STREET / 2.0 + (x as f32 + 0.5) * lot,
+                    0.0,
+                    STREET / 2.0 + (z as f32 + 0.5) * lot,
+                );
+            for floor in 0..floors {
+                for i in 0..width * width {
+                    let offset = Vec3::new(
+                        (i % width) as f32 * 2.0 - (width - 1) as f32,
+                        crate::floor::FLOOR_Y + 1.0 + floor as f32 * 2.0,
+                        (i / width) as f32 * 2.0 - (width - 1) as f32,
+                    );
+                    out.push(Instance {
+                        position: base + offset,
+                        rotation: Quat::IDENTITY,
+                        flash: crate::NO_FLASH,
+                        skin,
+                        layer: crate::physics::Layers::STATIC,
+                    });
+                }
+            }
+        }
+    }
+}
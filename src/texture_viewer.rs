@@ -0,0 +1,299 @@
+use wgpu_text::glyph_brush::{OwnedSection, Section as TextSection, Text};
+
+// what of the texture is shown, one channel on its own is grey
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Channel {
+    #[default]
+    All,
+    Red,
+    Green,
+    Blue,
+    Alpha,
+}
+
+impl Channel {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "rgba" | "all" => Some(Channel::All),
+            "r" => Some(Channel::Red),
+            "g" => Some(Channel::Green),
+            "b" => Some(Channel::Blue),
+            "a" => Some(Channel::Alpha),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Channel::All => "rgba",
+            Channel::Red => "r",
+            Channel::Green => "g",
+            Channel::Blue => "b",
+            Channel::Alpha => "a",
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ViewerUniform {
+    rect: [f32; 4],
+    screen: [f32; 4],
+    channel: u32,
+    _padding: [u32; 3],
+}
+
+struct ViewerGpu {
+    uniform_buffer: wgpu::Buffer,
+    color_layout: wgpu::BindGroupLayout,
+    color_pipeline: wgpu::RenderPipeline,
+    depth_layout: wgpu::BindGroupLayout,
+    depth_pipeline: wgpu::RenderPipeline,
+}
+
+// `textures` in the console. Lists every texture the renderer keeps (see
+// Renderer::textures) down the left, and draws the picked one into the
+// right half of the screen, the whole of it scaled to fit: one layer and
+// one mip of it, all of its channels or just one. Depth is shown as grey.
+// It's drawn over the finished frame, so it shows what this frame left in
+// them.
+#[derive(Default)]
+pub struct TextureViewer {
+    pub open: bool,
+    selected: usize,
+    pub channel: Channel,
+    pub mip: u32,
+    pub layer: u32,
+    gpu: Option<ViewerGpu>,
+}
+
+impl TextureViewer {
+    pub fn init_gpu(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("texture viewer uniform buffer"),
+            size: std::mem::size_of::<ViewerUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let pipeline = |label: &str, defines: &[&str]| {
+            let mut entries =
+                crate::reflect::entries(&[("texture_viewer.wgsl", 0)], defines).unwrap();
+            // loaded, not sampled, so float formats that can't be filtered do too
+            for entry in &mut entries {
+                if let wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable },
+                    ..
+                } = &mut entry.ty
+                {
+                    *filterable = false;
+                }
+            }
+            let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some(label),
+                entries: &entries,
+            });
+            let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Wgsl(
+                    crate::shaders::load("texture_viewer.wgsl", defines)
+                        .unwrap()
+                        .into(),
+                ),
+            });
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            });
+            let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: crate::pipeline_cache::current().as_deref(),
+            });
+            (layout, pipeline)
+        };
+        let (color_layout, color_pipeline) = pipeline("texture viewer", &[]);
+        let (depth_layout, depth_pipeline) = pipeline("texture viewer depth", &["DEPTH"]);
+        self.gpu = Some(ViewerGpu {
+            uniform_buffer,
+            color_layout,
+            color_pipeline,
+            depth_layout,
+            depth_pipeline,
+        });
+    }
+
+    // by its number in the list or its name
+    pub fn select(&mut self, which: &str, textures: &[(&str, &wgpu::Texture)]) -> String {
+        let found = which
+            .parse::<usize>()
+            .ok()
+            .filter(|i| *i < textures.len())
+            .or_else(|| textures.iter().position(|(name, _)| *name == which));
+        match found {
+            Some(i) => {
+                self.selected = i;
+                self.mip = 0;
+                self.layer = 0;
+                format!("viewing {}", textures[i].0)
+            }
+            None => format!("no texture {}, textures lists them", which),
+        }
+    }
+
+    pub fn list(&self, textures: &[(&str, &wgpu::Texture)]) -> String {
+        textures
+            .iter()
+            .enumerate()
+            .map(|(i, (name, texture))| format!("{} {} {}", i, name, describe(texture)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn section(
+        &self,
+        textures: &[(&str, &wgpu::Texture)],
+        height: f32,
+    ) -> Option<OwnedSection> {
+        if !self.open {
+            return None;
+        }
+        let mut text = "textures (texture <n|name>, texture channel <rgba|r|g|b|a>, texture mip <n>, texture layer <n>)\n".to_string();
+        for (i, (name, texture)) in textures.iter().enumerate() {
+            let marker = if i == self.selected { ">" } else { " " };
+            text += &format!("{} {:>2} {:<18} {}\n", marker, i, name, describe(texture));
+        }
+        if let Some((_, texture)) = textures.get(self.selected) {
+            let (mip, layer) = self.clamped(texture);
+            text += &format!(
+                "\nmip {} of {}, layer {} of {}, {}",
+                mip,
+                texture.mip_level_count(),
+                layer,
+                texture.depth_or_array_layers(),
+                self.channel.name()
+            );
+        }
+        Some(
+            TextSection::default()
+                .add_text(
+                    Text::new(&text)
+                        .with_color([0.9, 1.0, 1.0, 1.0])
+                        .with_scale(18.0),
+                )
+                .with_screen_position((10.0, height / 4.0))
+                .to_owned(),
+        )
+    }
+
+    // the mip and layer asked for, or the last there is
+    fn clamped(&self, texture: &wgpu::Texture) -> (u32, u32) {
+        (
+            self.mip.min(texture.mip_level_count() - 1),
+            self.layer.min(texture.depth_or_array_layers() - 1),
+        )
+    }
+
+    pub fn draw(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        render_pass: &mut wgpu::RenderPass,
+        textures: &[(&str, &wgpu::Texture)],
+        (width, height): (f32, f32),
+    ) {
+        let (Some(gpu), true) = (&self.gpu, self.open) else {
+            return;
+        };
+        let Some((name, texture)) = textures.get(self.selected) else {
+            return;
+        };
+        let (mip, layer) = self.clamped(texture);
+        let depth = texture.format().has_depth_aspect();
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some(name),
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            aspect: if depth {
+                wgpu::TextureAspect::DepthOnly
+            } else {
+                wgpu::TextureAspect::All
+            },
+            base_mip_level: mip,
+            mip_level_count: Some(1),
+            base_array_layer: layer,
+            array_layer_count: Some(1),
+            ..Default::default()
+        });
+        let (layout, pipeline) = if depth {
+            (&gpu.depth_layout, &gpu.depth_pipeline)
+        } else {
+            (&gpu.color_layout, &gpu.color_pipeline)
+        };
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: gpu.uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+            ],
+            label: Some("texture viewer bind group"),
+        });
+
+        // as big as fits in the right half, going by the full size's shape
+        let size = texture.size();
+        let scale =
+            ((width / 2.0 - 10.0) / size.width as f32).min(height / 2.0 / size.height as f32);
+        let (w, h) = (size.width as f32 * scale, size.height as f32 * scale);
+        queue.write_buffer(
+            &gpu.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[ViewerUniform {
+                rect: [width - 10.0 - w, (height - h) / 2.0, w, h],
+                screen: [width, height, 0.0, 0.0],
+                channel: self.channel as u32,
+                _padding: [0; 3],
+            }]),
+        );
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..6, 0..1);
+    }
+}
+
+// its size, format and how many mips and layers it has
+fn describe(texture: &wgpu::Texture) -> String {
+    let size = texture.size();
+    format!(
+        "{}x{} {:?}, {} mips, {} layers",
+        size.width,
+        size.height,
+        texture.format(),
+        texture.mip_level_count(),
+        size.depth_or_array_layers
+    )
+}
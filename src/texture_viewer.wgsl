@@ -0,0 +1,60 @@
+// One texture's layer and mip drawn into a rectangle of the screen, see
+// texture_viewer.rs. The view is made of just those, so it's level 0 here.
+struct Viewer {
+    // x, y, width, height in pixels from the top left
+    rect: vec4<f32>,
+    // screen size in pixels, then unused
+    screen: vec4<f32>,
+    // 0 for all of them, 1 to 4 for red, green, blue or alpha on its own
+    channel: u32,
+}
+@group(0) @binding(0)
+var<uniform> viewer: Viewer;
+#ifdef DEPTH
+@group(0) @binding(1)
+var t_view: texture_depth_2d;
+#else
+@group(0) @binding(1)
+var t_view: texture_2d<f32>;
+#endif
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0), vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 0.0), vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 1.0),
+    );
+    let pixel = viewer.rect.xy + corners[index] * viewer.rect.zw;
+    let ndc = pixel / viewer.screen.xy * 2.0 - 1.0;
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc.x, -ndc.y, 0.0, 1.0);
+    out.uv = corners[index];
+    return out;
+}
+
+// loaded rather than sampled, so unfilterable formats show too and every
+// texel is exactly what's stored
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = textureDimensions(t_view);
+    let texel = min(vec2<u32>(in.uv * vec2<f32>(size)), size - 1u);
+#ifdef DEPTH
+    // everything past a few units is crowded up near 1, this spreads it out
+    let depth = pow(textureLoad(t_view, texel, 0), 32.0);
+    return vec4<f32>(vec3<f32>(depth), 1.0);
+#else
+    let c = textureLoad(t_view, texel, 0);
+    switch viewer.channel {
+        case 1u: { return vec4<f32>(vec3<f32>(c.r), 1.0); }
+        case 2u: { return vec4<f32>(vec3<f32>(c.g), 1.0); }
+        case 3u: { return vec4<f32>(vec3<f32>(c.b), 1.0); }
+        case 4u: { return vec4<f32>(vec3<f32>(c.a), 1.0); }
+        default: { return vec4<f32>(c.rgb, 1.0); }
+    }
+#endif
+}
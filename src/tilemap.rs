@@ -0,0 +1,370 @@
+use crate::vertex::Vertex;
+use anyhow::{bail, Context};
+use quick_xml::events::{BytesStart, Event};
+use std::path::Path;
+use wgpu::util::DeviceExt;
+
+// Tiled keeps the flip flags in the top bits of every gid
+const GID_MASK: u32 = 0x1fff_ffff;
+
+// The parts of a Tiled map (.tmx) the 2D layer draws: orthogonal, one
+// embedded tileset, CSV encoded tile layers drawn in file order.
+pub struct TileMapData {
+    pub width: u32,
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub first_gid: u32,
+    pub columns: u32,
+    pub tile_count: u32,
+    // relative to the .tmx
+    pub image: String,
+    // a gid per cell, row by row, 0 where the layer is empty
+    pub layers: Vec<Vec<u32>>,
+}
+
+impl std::str::FromStr for TileMapData {
+    type Err = anyhow::Error;
+
+    fn from_str(tmx: &str) -> anyhow::Result<Self> {
+        let mut map = TileMapData {
+            width: 0,
+            height: 0,
+            tile_width: 0,
+            tile_height: 0,
+            first_gid: 0,
+            columns: 0,
+            tile_count: 0,
+            image: String::new(),
+            layers: Vec::new(),
+        };
+        let mut reader = quick_xml::Reader::from_str(tmx);
+        reader.config_mut().trim_text(true);
+        let mut in_data = false;
+        let mut tilesets = 0;
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) | Event::Empty(e) => match e.name().as_ref() {
+                    b"map" => {
+                        if attribute(&e, "orientation")? != "orthogonal" {
+                            bail!("only orthogonal maps are supported");
+                        }
+                        if attribute(&e, "infinite").is_ok_and(|v| v == "1") {
+                            bail!("infinite maps aren't supported");
+                        }
+                        map.width = number(&e, "width")?;
+                        map.height = number(&e, "height")?;
+                        map.tile_width = number(&e, "tilewidth")?;
+                        map.tile_height = number(&e, "tileheight")?;
+                    }
+                    b"tileset" => {
+                        tilesets += 1;
+                        if attribute(&e, "source").is_ok() {
+                            bail!("external tilesets (.tsx) aren't supported, embed it in the map");
+                        }
+                        map.first_gid = number(&e, "firstgid")?;
+                        map.columns = number(&e, "columns")?;
+                        map.tile_count = number(&e, "tilecount")?;
+                    }
+                    b"image" => map.image = attribute(&e, "source")?,
+                    b"data" => {
+                        if attribute(&e, "encoding").ok().as_deref() != Some("csv") {
+                            bail!("only csv encoded layers are supported");
+                        }
+                        in_data = true;
+                    }
+                    _ => (),
+                },
+                Event::Text(text) if in_data => {
+                    let gids = text
+                        .unescape()?
+                        .split(',')
+                        .map(|gid| gid.trim().parse::<u32>().map(|gid| gid & GID_MASK))
+                        .collect::<Result<Vec<_>, _>>()
+                        .context("layer data")?;
+                    if gids.len() != (map.width * map.height) as usize {
+                        bail!(
+                            "a layer has {} tiles, the map is {}x{}",
+                            gids.len(),
+                            map.width,
+                            map.height
+                        );
+                    }
+                    map.layers.push(gids);
+                }
+                Event::End(e) if e.name().as_ref() == b"data" => in_data = false,
+                Event::Eof => break,
+                _ => (),
+            }
+        }
+        if tilesets != 1 {
+            bail!("expected exactly one tileset, found {}", tilesets);
+        }
+        if map.columns == 0 || map.image.is_empty() {
+            bail!("the tileset has no image");
+        }
+        Ok(map)
+    }
+}
+
+fn attribute(e: &BytesStart, name: &str) -> anyhow::Result<String> {
+    let Some(attr) = e.try_get_attribute(name)? else {
+        bail!(
+            "<{}> has no {}",
+            String::from_utf8_lossy(e.name().as_ref()),
+            name
+        );
+    };
+    Ok(attr.unescape_value()?.into_owned())
+}
+
+fn number(e: &BytesStart, name: &str) -> anyhow::Result<u32> {
+    Ok(attribute(e, name)?.parse()?)
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Vertex)]
+#[vertex(instance)]
+struct TileRaw {
+    #[vertex(location = 0)]
+    cell: [f32; 2],
+    // index into the tileset, left to right and top to bottom
+    #[vertex(location = 1)]
+    tile: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct TileViewUniform {
+    view_proj: [[f32; 4]; 4],
+    atlas: [f32; 4],
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Order {
+    Behind,
+    Above,
+}
+
+// A tilemap drawn as a flat 2D layer, either before the 3D scene or on top
+// of it. It has its own orthographic camera in map pixels: `position` is the
+// map pixel in the top left corner and `zoom` screen pixels per map pixel.
+pub struct TileLayer {
+    pub order: Option<Order>,
+    pub position: crate::math::Vec2,
+    pub zoom: f32,
+    atlas: [f32; 4],
+    screen: (f32, f32),
+    view_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+    instance_buffer: wgpu::Buffer,
+    instance_count: u32,
+}
+
+impl TileLayer {
+    // res/tilemaps/demo.tmx, built in
+    pub fn demo(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> anyhow::Result<Self> {
+        let map = include_str!("../res/tilemaps/demo.tmx").parse()?;
+        let tileset = image::load_from_memory(include_bytes!("../res/tilemaps/demo_tiles.png"))?;
+        Ok(Self::new(
+            device, queue, format, &map, &tileset, width, height,
+        ))
+    }
+
+    pub fn load(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        path: &Path,
+        width: u32,
+        height: u32,
+    ) -> anyhow::Result<Self> {
+        let map: TileMapData = std::fs::read_to_string(path)?.parse()?;
+        let image_path = path.parent().unwrap_or(Path::new(".")).join(&map.image);
+        let tileset = image::open(&image_path)
+            .with_context(|| format!("tileset {}", image_path.display()))?;
+        Ok(Self::new(
+            device, queue, format, &map, &tileset, width, height,
+        ))
+    }
+
+    fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        map: &TileMapData,
+        tileset: &image::DynamicImage,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let rows = map.tile_count.div_ceil(map.columns);
+        let tiles = map
+            .layers
+            .iter()
+            .flat_map(|layer| layer.iter().enumerate())
+            .filter(|(_, gid)| **gid >= map.first_gid && **gid < map.first_gid + map.tile_count)
+            .map(|(i, gid)| TileRaw {
+                cell: [(i as u32 % map.width) as f32, (i as u32 / map.width) as f32],
+                tile: gid - map.first_gid,
+            })
+            .collect::<Vec<_>>();
+        log::info!(
+            "tilemap {}x{}, {} layers, {} tiles",
+            map.width,
+            map.height,
+            map.layers.len(),
+            tiles.len()
+        );
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("tilemap instance buffer"),
+            contents: bytemuck::cast_slice(&tiles),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let texture =
+            crate::texture::Texture::from_image(device, queue, tileset, Some("tileset"), false)
+                .unwrap();
+        // linear filtering would bleed the neighbouring tiles in at the edges
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("tileset sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let view_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("tilemap view buffer"),
+            size: std::mem::size_of::<TileViewUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = crate::reflect::bind_group_layout(
+            device,
+            "tilemap bind group layout",
+            &[("tilemap.wgsl", 0)],
+        )
+        .unwrap();
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: view_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some("tilemap bind group"),
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("tilemap shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                crate::shaders::load("tilemap.wgsl", &[]).unwrap().into(),
+            ),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("tilemap pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("tilemap render pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[TileRaw::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            // drawn behind or above everything by order, not depth
+            depth_stencil: Some(crate::texture::Texture::depth_state(
+                false,
+                wgpu::CompareFunction::Always,
+            )),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: crate::pipeline_cache::current().as_deref(),
+        });
+
+        Self {
+            order: None,
+            position: crate::math::Vec2::ZERO,
+            zoom: 2.0,
+            atlas: [
+                map.columns as f32,
+                rows as f32,
+                map.tile_width as f32,
+                map.tile_height as f32,
+            ],
+            screen: (width as f32, height as f32),
+            view_buffer,
+            bind_group,
+            pipeline,
+            instance_buffer,
+            instance_count: tiles.len() as u32,
+        }
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.screen = (width as f32, height as f32);
+    }
+
+    // call before the pass so the 2D camera is current
+    pub fn update(&self, queue: &wgpu::Queue) {
+        let (width, height) = (self.screen.0 / self.zoom, self.screen.1 / self.zoom);
+        let projection = crate::math::Mat4::orthographic_rh(0.0, width, height, 0.0, -1.0, 1.0);
+        let view = crate::math::Mat4::from_translation(-self.position.extend(0.0));
+        queue.write_buffer(
+            &self.view_buffer,
+            0,
+            bytemuck::cast_slice(&[TileViewUniform {
+                view_proj: crate::math::mat4_array(projection * view),
+                atlas: self.atlas,
+            }]),
+        );
+    }
+
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.draw(0..6, 0..self.instance_count);
+    }
+}
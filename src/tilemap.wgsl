@@ -0,0 +1,49 @@
+// the 2D layer's own orthographic camera, in map pixels with y down
+struct TileView {
+    view_proj: mat4x4<f32>,
+    // tileset columns and rows, then one tile's size in pixels
+    atlas: vec4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> view: TileView;
+@group(0) @binding(1)
+var t_tiles: texture_2d<f32>;
+@group(0) @binding(2)
+var s_tiles: sampler;
+
+struct TileInput {
+    @location(0) cell: vec2<f32>,
+    @location(1) tile: u32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, in: TileInput) -> VertexOutput {
+    // two triangles over the unit square
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0), vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 0.0), vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 1.0),
+    );
+    let corner = corners[index];
+    let columns = u32(view.atlas.x);
+    let atlas_cell = vec2<f32>(f32(in.tile % columns), f32(in.tile / columns));
+
+    var out: VertexOutput;
+    let pixel = (in.cell + corner) * view.atlas.zw;
+    out.clip_position = view.view_proj * vec4<f32>(pixel, 0.0, 1.0);
+    out.uv = (atlas_cell + corner) / view.atlas.xy;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_tiles, s_tiles, in.uv);
+    if color.a < 0.5 {
+        discard;
+    }
+    return color;
+}
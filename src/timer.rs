@@ -12,6 +12,9 @@ pub struct Timer {
     pub elapsed: f64,
     pub last: f64,
     pub acc: f64,
+    // how far behind the wall clock the timer is, from frames that were
+    // stepped rather than timed
+    pub offset: f64,
     pub timer_uniform: TimerUniform,
     pub timer_buffer: wgpu::Buffer,
     pub timer_bind_group: wgpu::BindGroup,
@@ -23,24 +26,17 @@ impl Timer {
         let timer_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Timer Buffer"),
             contents: &timer_uniform.t.to_le_bytes(),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            usage: wgpu::BufferUsages::UNIFORM
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
         });
 
-        let timer_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("bind_group_for_timer_uniform"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-
-                    count: None,
-                }],
-            });
+        let timer_bind_group_layout = crate::reflect::bind_group_layout(
+            device,
+            "bind_group_for_timer_uniform",
+            &[("cube.wgsl", 3), ("water.wgsl", 3), ("crt.wgsl", 2)],
+        )
+        .unwrap();
 
         let timer_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: None,
@@ -58,10 +54,26 @@ impl Timer {
             elapsed: 0.0,
             last: 0.0,
             acc: 0.0f64,
+            offset: 0.0,
             timer_uniform,
             timer_buffer,
             timer_bind_group,
             timer_bind_group_layout,
         }
     }
+
+    // Seconds since the start, or exactly `step` on from the last frame
+    // when it's given. Either way it carries on smoothly from where the
+    // other left off.
+    pub fn now(&mut self, step: Option<f64>) -> f64 {
+        let wall = self.start.elapsed().as_secs_f64();
+        match step {
+            Some(step) => {
+                let now = self.last + step;
+                self.offset = wall - now;
+                now
+            }
+            None => wall - self.offset,
+        }
+    }
 }
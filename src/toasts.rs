@@ -0,0 +1,52 @@
+use std::time::{Duration, Instant};
+use wgpu_text::glyph_brush::{HorizontalAlign, Layout, OwnedSection, Section as TextSection, Text};
+
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+const FADE_SECONDS: f32 = 0.25;
+const MAX_TOASTS: usize = 6;
+const LINE_HEIGHT: f32 = 24.0;
+
+// Short notices for things that happened (saved, loaded, captured), stacked
+// in the top right corner newest first. Each one fades in, stays for a bit
+// and fades out again. GPU errors keep their own list in errors.rs.
+#[derive(Default)]
+pub struct Toasts {
+    recent: Vec<(Instant, String)>,
+}
+
+impl Toasts {
+    pub fn push(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        log::info!("{}", message);
+        self.recent.push((Instant::now(), message));
+        if self.recent.len() > MAX_TOASTS {
+            self.recent.remove(0);
+        }
+    }
+
+    // one section per toast so each can fade on its own
+    pub fn sections(&mut self, width: f32) -> Vec<OwnedSection> {
+        self.recent.retain(|(at, _)| at.elapsed() < TOAST_DURATION);
+        self.recent
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, (at, message))| {
+                let age = at.elapsed().as_secs_f32();
+                let left = TOAST_DURATION.as_secs_f32() - age;
+                let alpha = (age / FADE_SECONDS)
+                    .min(left / FADE_SECONDS)
+                    .clamp(0.0, 1.0);
+                TextSection::default()
+                    .add_text(
+                        Text::new(message)
+                            .with_color([1.0, 0.95, 0.7, alpha])
+                            .with_scale(18.0),
+                    )
+                    .with_screen_position((width - 10.0, 10.0 + i as f32 * LINE_HEIGHT))
+                    .with_layout(Layout::default().h_align(HorizontalAlign::Right))
+                    .to_owned()
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,349 @@
+use anyhow::{anyhow, bail, Result};
+use std::sync::OnceLock;
+use wgpu_text::glyph_brush::{OwnedSection, Section as TextSection, Text};
+use winit::event::{ElementState, KeyEvent, WindowEvent};
+use winit::keyboard::{Key, NamedKey};
+
+// one vec4 each in the tweak buffer, the array in post.wgsl is this long
+pub const MAX_TWEAKS: usize = 32;
+// how many presses of an arrow key go from one end of a range to the other
+const STEPS: f32 = 50.0;
+const SLIDER_WIDTH: usize = 20;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Kind {
+    Slider { min: f32, max: f32 },
+    // 0 to 1 per channel
+    Color,
+}
+
+pub struct Tweak {
+    pub shader: &'static str,
+    pub name: String,
+    pub kind: Kind,
+    pub default: [f32; 3],
+}
+
+// A shader asks for a value to play with by annotating a comment:
+//   // @tweak crt_glow 0..2 = 0.5
+//   // @tweak grade_tint color = 1 0.9 0.8
+// and from that line on the name stands for that value out of the tweak
+// buffer, an f32 or a vec3. None if the line isn't an annotation.
+pub fn parse(line: &str) -> Option<Result<(String, Kind, [f32; 3])>> {
+    let rest = line.trim_start().strip_prefix("// @tweak ")?;
+    Some(parse_annotation(rest))
+}
+
+fn parse_annotation(rest: &str) -> Result<(String, Kind, [f32; 3])> {
+    let (declared, default) = rest
+        .split_once('=')
+        .ok_or_else(|| anyhow!("a tweak needs `= <default>`"))?;
+    let numbers = |s: &str| {
+        s.split_whitespace()
+            .map(|n| {
+                n.parse::<f32>()
+                    .map_err(|_| anyhow!("{} isn't a number", n))
+            })
+            .collect::<Result<Vec<_>>>()
+    };
+    let default = numbers(default)?;
+    match declared.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [name, "color"] => match default.as_slice() {
+            [r, g, b] => Ok((name.to_string(), Kind::Color, [*r, *g, *b])),
+            _ => bail!("color tweak {} needs an r g b default", name),
+        },
+        [name, range] => {
+            let (min, max) = range
+                .split_once("..")
+                .ok_or_else(|| anyhow!("tweak {} needs a <min>..<max> range or color", name))?;
+            let (min, max) = (numbers(min)?, numbers(max)?);
+            match (min.as_slice(), max.as_slice(), default.as_slice()) {
+                ([min], [max], [default]) if min < max => Ok((
+                    name.to_string(),
+                    Kind::Slider {
+                        min: *min,
+                        max: *max,
+                    },
+                    [default.clamp(*min, *max), 0.0, 0.0],
+                )),
+                _ => bail!(
+                    "tweak {} needs one number each side of .. and one default",
+                    name
+                ),
+            }
+        }
+        _ => bail!("a tweak is `<name> <min>..<max> = <default>` or `<name> color = <r> <g> <b>`"),
+    }
+}
+
+// Every tweak in every shader, in the order of shaders::SOURCES, which is
+// also the order they sit in the buffer. Broken annotations are left out
+// here, loading the shader says what's wrong with them.
+pub fn all() -> &'static [Tweak] {
+    static ALL: OnceLock<Vec<Tweak>> = OnceLock::new();
+    ALL.get_or_init(|| {
+        crate::shaders::SOURCES
+            .iter()
+            .flat_map(|(shader, source)| {
+                source.lines().filter_map(|line| match parse(line)? {
+                    Ok((name, kind, default)) => Some(Tweak {
+                        shader,
+                        name,
+                        kind,
+                        default,
+                    }),
+                    Err(_) => None,
+                })
+            })
+            .collect()
+    })
+}
+
+// what `name` in `shader` stands for once it's been declared
+pub fn expression(shader: &str, name: &str) -> Result<String> {
+    let slot = all()
+        .iter()
+        .position(|t| t.shader == shader && t.name == name)
+        .ok_or_else(|| anyhow!("tweak {} isn't declared", name))?;
+    if slot >= MAX_TWEAKS {
+        bail!(
+            "tweak {} is past the {} the buffer has room for",
+            name,
+            MAX_TWEAKS
+        );
+    }
+    Ok(match all()[slot].kind {
+        Kind::Slider { .. } => format!("tweaks.values[{}].x", slot),
+        Kind::Color => format!("tweaks.values[{}].rgb", slot),
+    })
+}
+
+// The F11 panel over every tweak the shaders declare. Up and down pick one,
+// left and right slide it (tab picks which channel of a color), backspace
+// puts it back to what the shader says. Changes are written into the tweak
+// buffer once a frame, so they show straight away without reloading
+// anything, and aren't kept between runs: once a value looks right it goes
+// back into the annotation by hand.
+pub struct Tweaks {
+    pub open: bool,
+    values: Vec<[f32; 3]>,
+    selected: usize,
+    channel: usize,
+    buffer: Option<wgpu::Buffer>,
+    dirty: bool,
+}
+
+impl Default for Tweaks {
+    fn default() -> Self {
+        Self {
+            open: false,
+            values: all().iter().map(|t| t.default).collect(),
+            selected: 0,
+            channel: 0,
+            buffer: None,
+            dirty: true,
+        }
+    }
+}
+
+impl Tweaks {
+    // made with the post passes that bind it, then handed to init_gpu
+    pub fn create_buffer(device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("tweak buffer"),
+            size: (MAX_TWEAKS * 16) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn init_gpu(&mut self, buffer: wgpu::Buffer) {
+        self.buffer = Some(buffer);
+        self.dirty = true;
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        self.buffer.as_ref().unwrap()
+    }
+
+    pub fn upload(&mut self, queue: &wgpu::Queue) {
+        let Some(buffer) = &self.buffer else {
+            return;
+        };
+        if !self.dirty {
+            return;
+        }
+        let raw = self
+            .values
+            .iter()
+            .take(MAX_TWEAKS)
+            .map(|[x, y, z]| [*x, *y, *z, 0.0])
+            .collect::<Vec<_>>();
+        if !raw.is_empty() {
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(&raw));
+        }
+        self.dirty = false;
+    }
+
+    // whether the event was used, named keys are while the panel's open
+    pub fn process_events(&mut self, event: &WindowEvent) -> bool {
+        if !self.open {
+            return false;
+        }
+        let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    logical_key: Key::Named(key),
+                    ..
+                },
+            ..
+        } = event
+        else {
+            return false;
+        };
+        let count = self.values.len();
+        match key {
+            NamedKey::ArrowUp if count > 0 => {
+                self.selected = (self.selected + count - 1) % count;
+                self.channel = 0;
+            }
+            NamedKey::ArrowDown if count > 0 => {
+                self.selected = (self.selected + 1) % count;
+                self.channel = 0;
+            }
+            NamedKey::ArrowLeft => self.nudge(-1.0),
+            NamedKey::ArrowRight => self.nudge(1.0),
+            NamedKey::Tab => self.channel = (self.channel + 1) % 3,
+            NamedKey::Backspace if count > 0 => {
+                self.values[self.selected] = all()[self.selected].default;
+                self.dirty = true;
+            }
+            NamedKey::Escape => self.open = false,
+            // F11 still gets to close it
+            NamedKey::F11 => return false,
+            _ => {}
+        }
+        true
+    }
+
+    fn nudge(&mut self, direction: f32) {
+        let Some(tweak) = all().get(self.selected) else {
+            return;
+        };
+        let value = &mut self.values[self.selected];
+        match tweak.kind {
+            Kind::Slider { min, max } => {
+                value[0] = (value[0] + direction * (max - min) / STEPS).clamp(min, max)
+            }
+            Kind::Color => {
+                let channel = &mut value[self.channel];
+                *channel = (*channel + direction / STEPS).clamp(0.0, 1.0);
+            }
+        }
+        self.dirty = true;
+    }
+
+    // `tweak <name> <value>` or `<r> <g> <b>` in the console
+    pub fn set(&mut self, name: &str, values: &[f32]) -> Result<String, String> {
+        let i = all()
+            .iter()
+            .position(|t| t.name == name)
+            .ok_or_else(|| format!("no tweak called {}", name))?;
+        let value = &mut self.values[i];
+        match (all()[i].kind, values) {
+            (Kind::Slider { min, max }, [v]) => value[0] = v.clamp(min, max),
+            (Kind::Color, [r, g, b]) => *value = [r, g, b].map(|c| c.clamp(0.0, 1.0)),
+            (Kind::Slider { min, max }, _) => {
+                return Err(format!("{} takes one value, {} to {}", name, min, max))
+            }
+            (Kind::Color, _) => return Err(format!("{} takes r g b, each 0 to 1", name)),
+        }
+        self.dirty = true;
+        Ok(format!("{} = {}", name, self.shown(i)))
+    }
+
+    pub fn reset(&mut self) {
+        self.values = all().iter().map(|t| t.default).collect();
+        self.dirty = true;
+    }
+
+    // every tweak and its value, one a line
+    pub fn list(&self) -> String {
+        if self.values.is_empty() {
+            return "no shader declares any tweaks".to_string();
+        }
+        all()
+            .iter()
+            .enumerate()
+            .map(|(i, t)| format!("{} ({}) = {}", t.name, t.shader, self.shown(i)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn shown(&self, i: usize) -> String {
+        let [x, y, z] = self.values[i];
+        match all()[i].kind {
+            Kind::Slider { .. } => format!("{:.3}", x),
+            Kind::Color => format!("{:.2} {:.2} {:.2}", x, y, z),
+        }
+    }
+
+    pub fn section(&self, height: f32) -> Option<OwnedSection> {
+        if !self.open {
+            return None;
+        }
+        let white = [0.9, 1.0, 1.0, 1.0];
+        let mut texts = vec![(
+            "shader tweaks (F11 closes, up/down picks, left/right changes, tab picks a channel, backspace resets)\n"
+                .to_string(),
+            white,
+        )];
+        if self.values.is_empty() {
+            texts.push(("no shader declares any tweaks\n".to_string(), white));
+        }
+        for (i, tweak) in all().iter().enumerate() {
+            let selected = i == self.selected;
+            let marker = if selected { ">" } else { " " };
+            let label = format!("{} {:<20} {:<12}", marker, tweak.name, tweak.shader);
+            let [x, y, z] = self.values[i];
+            match tweak.kind {
+                Kind::Slider { min, max } => {
+                    let filled = (((x - min) / (max - min)) * SLIDER_WIDTH as f32).round() as usize;
+                    texts.push((
+                        format!(
+                            "{} [{}{}] {:.3}\n",
+                            label,
+                            "=".repeat(filled),
+                            "-".repeat(SLIDER_WIDTH - filled),
+                            x
+                        ),
+                        white,
+                    ));
+                }
+                Kind::Color => {
+                    texts.push((format!("{} ", label), white));
+                    // a swatch in the color itself
+                    texts.push(("########".to_string(), [x, y, z, 1.0]));
+                    let channels = [("r", x), ("g", y), ("b", z)]
+                        .iter()
+                        .enumerate()
+                        .map(|(c, (name, v))| {
+                            if selected && c == self.channel {
+                                format!("[{} {:.2}]", name, v)
+                            } else {
+                                format!(" {} {:.2} ", name, v)
+                            }
+                        })
+                        .collect::<String>();
+                    texts.push((format!(" {}\n", channels), white));
+                }
+            }
+        }
+        let mut section = TextSection::default().with_screen_position((10.0, height / 2.0));
+        for (text, color) in &texts {
+            section = section.add_text(Text::new(text).with_color(*color).with_scale(20.0));
+        }
+        Some(section.to_owned())
+    }
+}
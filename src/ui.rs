@@ -0,0 +1,373 @@
+use crate::vertex::Vertex;
+use wgpu_text::glyph_brush::ab_glyph::FontRef;
+use wgpu_text::glyph_brush::{OwnedSection, Section as TextSection, Text};
+use wgpu_text::TextBrush;
+
+// sizes are authored for this screen and scaled to whatever the real one is
+const REFERENCE_SIZE: (f32, f32) = (1024.0, 768.0);
+const MAX_QUADS: usize = 256;
+
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    // 0, 0.5 or 1 across and down the screen
+    fn factors(self) -> (f32, f32) {
+        match self {
+            Anchor::TopLeft => (0.0, 0.0),
+            Anchor::Top => (0.5, 0.0),
+            Anchor::TopRight => (1.0, 0.0),
+            Anchor::Left => (0.0, 0.5),
+            Anchor::Center => (0.5, 0.5),
+            Anchor::Right => (1.0, 0.5),
+            Anchor::BottomLeft => (0.0, 1.0),
+            Anchor::Bottom => (0.5, 1.0),
+            Anchor::BottomRight => (1.0, 1.0),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+pub enum Content {
+    Text {
+        text: String,
+        scale: f32,
+        color: [f32; 4],
+    },
+    // a plain rectangle in reference pixels, stretched across a stack it's in
+    Panel {
+        size: (f32, f32),
+    },
+    Stack {
+        axis: Axis,
+        spacing: f32,
+        children: Vec<NodeId>,
+    },
+}
+
+pub struct Node {
+    pub content: Content,
+    pub padding: f32,
+    // drawn behind the node's content, padding included
+    pub background: Option<[f32; 4]>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NodeId(usize);
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Vertex)]
+#[vertex(instance)]
+struct QuadRaw {
+    #[vertex(location = 0)]
+    rect: [f32; 4],
+    #[vertex(location = 1)]
+    color: [f32; 4],
+}
+
+struct UiGpu {
+    screen_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+    quad_buffer: wgpu::Buffer,
+}
+
+// A small retained layer for HUD elements. Nodes are added once and kept,
+// roots are pinned to a screen anchor, and the layout is only worked out
+// again when some text changes or the screen is resized. Everything is
+// authored in REFERENCE_SIZE pixels and scaled to the real screen.
+pub struct Ui {
+    nodes: Vec<Node>,
+    roots: Vec<(NodeId, Anchor, (f32, f32))>,
+    screen: (f32, f32),
+    dirty: bool,
+    sections: Vec<OwnedSection>,
+    quads: Vec<QuadRaw>,
+    gpu: Option<UiGpu>,
+}
+
+impl Default for Ui {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            roots: Vec::new(),
+            screen: REFERENCE_SIZE,
+            dirty: true,
+            sections: Vec::new(),
+            quads: Vec::new(),
+            gpu: None,
+        }
+    }
+}
+
+impl Ui {
+    pub fn add(&mut self, node: Node) -> NodeId {
+        self.nodes.push(node);
+        self.dirty = true;
+        NodeId(self.nodes.len() - 1)
+    }
+
+    // `offset` pushes the node in from its anchor, in reference pixels
+    pub fn add_root(&mut self, node: NodeId, anchor: Anchor, offset: (f32, f32)) {
+        self.roots.push((node, anchor, offset));
+        self.dirty = true;
+    }
+
+    pub fn set_text(&mut self, node: NodeId, new: &str) {
+        if let Content::Text { text, .. } = &mut self.nodes[node.0].content {
+            if text != new {
+                *text = new.to_string();
+                self.dirty = true;
+            }
+        }
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.screen = (width as f32, height as f32);
+        self.dirty = true;
+    }
+
+    fn scale(&self) -> f32 {
+        (self.screen.0 / REFERENCE_SIZE.0).min(self.screen.1 / REFERENCE_SIZE.1)
+    }
+
+    fn text_section(&self, text: &str, scale: f32, color: [f32; 4]) -> OwnedSection {
+        TextSection::default()
+            .add_text(
+                Text::new(text)
+                    .with_color(color)
+                    .with_scale(scale * self.scale()),
+            )
+            .to_owned()
+    }
+
+    // width and height in screen pixels, padding included
+    fn measure(&self, node: NodeId, brush: &mut TextBrush<FontRef<'static>>) -> (f32, f32) {
+        let node = &self.nodes[node.0];
+        let (width, height) = match &node.content {
+            Content::Text { text, scale, color } => brush
+                .glyph_bounds(&self.text_section(text, *scale, *color))
+                .map(|bounds| (bounds.width(), bounds.height()))
+                .unwrap_or((0.0, 0.0)),
+            Content::Panel { size } => (size.0 * self.scale(), size.1 * self.scale()),
+            Content::Stack {
+                axis,
+                spacing,
+                children,
+            } => {
+                let sizes = children
+                    .iter()
+                    .map(|child| self.measure(*child, brush))
+                    .collect::<Vec<_>>();
+                let gaps = children.len().saturating_sub(1) as f32 * spacing * self.scale();
+                let along = |(w, h): (f32, f32)| if *axis == Axis::Vertical { h } else { w };
+                let across = |(w, h): (f32, f32)| if *axis == Axis::Vertical { w } else { h };
+                let length = sizes.iter().copied().map(along).sum::<f32>() + gaps;
+                let thickness = sizes.iter().copied().map(across).fold(0.0, f32::max);
+                match axis {
+                    Axis::Vertical => (thickness, length),
+                    Axis::Horizontal => (length, thickness),
+                }
+            }
+        };
+        let padding = 2.0 * node.padding * self.scale();
+        (width + padding, height + padding)
+    }
+
+    // `size` is the node's measured size unless a stack stretched it
+    fn place(
+        &mut self,
+        node: NodeId,
+        at: (f32, f32),
+        size: (f32, f32),
+        brush: &mut TextBrush<FontRef<'static>>,
+    ) {
+        let node = &self.nodes[node.0];
+        if let Some(color) = node.background {
+            self.quads.push(QuadRaw {
+                rect: [at.0, at.1, size.0, size.1],
+                color,
+            });
+        }
+        let padding = node.padding * self.scale();
+        let inner = (at.0 + padding, at.1 + padding);
+        match &node.content {
+            Content::Text { text, scale, color } => {
+                let mut section = self.text_section(text, *scale, *color);
+                section.screen_position = inner;
+                self.sections.push(section);
+            }
+            Content::Panel { .. } => (),
+            Content::Stack {
+                axis,
+                spacing,
+                children,
+            } => {
+                let (axis, spacing, children) = (*axis, *spacing * self.scale(), children.clone());
+                let thickness = match axis {
+                    Axis::Vertical => size.0,
+                    Axis::Horizontal => size.1,
+                } - 2.0 * padding;
+                let mut cursor = inner;
+                for child in children {
+                    let (mut w, mut h) = self.measure(child, brush);
+                    if let Content::Panel { .. } = self.nodes[child.0].content {
+                        match axis {
+                            Axis::Vertical => w = thickness,
+                            Axis::Horizontal => h = thickness,
+                        }
+                    }
+                    self.place(child, cursor, (w, h), brush);
+                    match axis {
+                        Axis::Vertical => cursor.1 += h + spacing,
+                        Axis::Horizontal => cursor.0 += w + spacing,
+                    }
+                }
+            }
+        }
+    }
+
+    // works the layout out again if anything changed, brush measures the text
+    pub fn layout(&mut self, queue: &wgpu::Queue, brush: &mut TextBrush<FontRef<'static>>) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        self.sections.clear();
+        self.quads.clear();
+        for (node, anchor, offset) in self.roots.clone() {
+            let (w, h) = self.measure(node, brush);
+            let (ax, ay) = anchor.factors();
+            // offsets point inwards from whichever edge the anchor is on
+            let inward = |a: f32| if a == 1.0 { -1.0 } else { 1.0 };
+            let x = ax * (self.screen.0 - w) + offset.0 * self.scale() * inward(ax);
+            let y = ay * (self.screen.1 - h) + offset.1 * self.scale() * inward(ay);
+            self.place(node, (x, y), (w, h), brush);
+        }
+        self.quads.truncate(MAX_QUADS);
+
+        if let Some(gpu) = &self.gpu {
+            queue.write_buffer(
+                &gpu.screen_buffer,
+                0,
+                bytemuck::cast_slice(&[self.screen.0, self.screen.1, 0.0, 0.0]),
+            );
+            queue.write_buffer(&gpu.quad_buffer, 0, bytemuck::cast_slice(&self.quads));
+        }
+    }
+
+    // the text to queue with the brush this frame
+    pub fn sections(&self) -> &[OwnedSection] {
+        &self.sections
+    }
+
+    pub fn init_gpu(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        let screen_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ui screen buffer"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let quad_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ui quad buffer"),
+            size: (MAX_QUADS * std::mem::size_of::<QuadRaw>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout =
+            crate::reflect::bind_group_layout(device, "ui bind group layout", &[("ui.wgsl", 0)])
+                .unwrap();
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: screen_buffer.as_entire_binding(),
+            }],
+            label: Some("ui bind group"),
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("ui shader"),
+            source: wgpu::ShaderSource::Wgsl(crate::shaders::load("ui.wgsl", &[]).unwrap().into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("ui pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("ui render pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[QuadRaw::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: crate::pipeline_cache::current().as_deref(),
+        });
+        self.gpu = Some(UiGpu {
+            screen_buffer,
+            bind_group,
+            pipeline,
+            quad_buffer,
+        });
+        // the new buffers start out empty
+        self.dirty = true;
+    }
+
+    // the panels, the text on them goes through the brush afterwards
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        let Some(gpu) = &self.gpu else {
+            return;
+        };
+        if self.quads.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&gpu.pipeline);
+        render_pass.set_bind_group(0, &gpu.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, gpu.quad_buffer.slice(..));
+        render_pass.draw(0..6, 0..self.quads.len() as u32);
+    }
+}
@@ -0,0 +1,36 @@
+// screen size in pixels, then unused
+struct Screen {
+    size: vec4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> screen: Screen;
+
+struct QuadInput {
+    // x, y, width, height in pixels from the top left
+    @location(0) rect: vec4<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, quad: QuadInput) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0), vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 0.0), vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 1.0),
+    );
+    let pixel = quad.rect.xy + corners[index] * quad.rect.zw;
+    let ndc = pixel / screen.size.xy * 2.0 - 1.0;
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc.x, -ndc.y, 0.0, 1.0);
+    out.color = quad.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
@@ -0,0 +1,45 @@
+#define CAMERA_GROUP 0
+#define INSTANCED
+#include "common.wgsl"
+
+// how far each pixel of a cube moved since the last frame, in uv
+struct Motion {
+    previous_view_proj: mat4x4<f32>,
+}
+@group(1) @binding(0)
+var<uniform> motion: Motion;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+}
+
+// the instance's model matrix last frame, a column per location
+struct PreviousInput {
+    @location(11) model_0: vec4<f32>,
+    @location(12) model_1: vec4<f32>,
+    @location(13) model_2: vec4<f32>,
+    @location(14) model_3: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) current: vec4<f32>,
+    @location(1) previous: vec4<f32>,
+}
+
+@vertex
+fn vs_main(model: VertexInput, instance: InstanceInput, last: PreviousInput) -> VertexOutput {
+    let previous_model = mat4x4<f32>(last.model_0, last.model_1, last.model_2, last.model_3);
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * model_matrix(instance) * vec4<f32>(model.position, 1.0);
+    out.current = out.clip_position;
+    out.previous = motion.previous_view_proj * previous_model * vec4<f32>(model.position, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // clip space is y up and twice the size of uv
+    let moved = in.current.xy / in.current.w - in.previous.xy / in.previous.w;
+    return vec4<f32>(moved.x * 0.5, -moved.y * 0.5, 0.0, 0.0);
+}
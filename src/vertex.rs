@@ -1,92 +1,122 @@
+pub use vertex_derive::Vertex;
+
+// `#[derive(Vertex)]` writes this from the fields, see vertex_derive
 pub trait Vertex {
     fn desc() -> wgpu::VertexBufferLayout<'static>;
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Vertex)]
 pub struct ModelVertex {
+    #[vertex(location = 0)]
     pub position: [f32; 3],
+    #[vertex(location = 1)]
     pub tex_coords: [f32; 2],
+    #[vertex(location = 2)]
     pub normal: [f32; 3],
 }
 
+// ModelVertex at 20 bytes instead of 32: uvs as Unorm16x2 (so they have to
+// stay in 0..1) and normals as Snorm8x4, the shader sees the same vectors
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct BasicVertex {
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Vertex)]
+pub struct PackedModelVertex {
+    #[vertex(location = 0)]
     pub position: [f32; 3],
-    pub tex_coords: [f32; 2],
+    #[vertex(location = 1, format = Unorm16x2)]
+    pub tex_coords: [u16; 2],
+    #[vertex(location = 2, format = Snorm8x4)]
+    pub normal: [i8; 4],
 }
-impl Vertex for BasicVertex {
-    fn desc() -> wgpu::VertexBufferLayout<'static> {
-        wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<BasicVertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-                wgpu::VertexAttribute {
-                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                    shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x2,
-                },
+
+impl From<ModelVertex> for PackedModelVertex {
+    fn from(v: ModelVertex) -> Self {
+        let unorm16 = |x: f32| (x.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16;
+        let snorm8 = |x: f32| (x.clamp(-1.0, 1.0) * i8::MAX as f32).round() as i8;
+        Self {
+            position: v.position,
+            tex_coords: v.tex_coords.map(unorm16),
+            normal: [
+                snorm8(v.normal[0]),
+                snorm8(v.normal[1]),
+                snorm8(v.normal[2]),
+                0,
             ],
         }
     }
 }
 
-#[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct EffectVertex {
-    pub position: [f32; 3],
-    pub color: [f32; 3],
+// which of the two layouts model vertex buffers use, `--packed-vertices` picks Packed
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum ModelFormat {
+    #[default]
+    Full,
+    Packed,
 }
-impl Vertex for EffectVertex {
-    fn desc() -> wgpu::VertexBufferLayout<'static> {
-        wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<EffectVertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-                wgpu::VertexAttribute {
-                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                    shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-            ],
+
+impl ModelFormat {
+    pub fn desc(self) -> wgpu::VertexBufferLayout<'static> {
+        match self {
+            ModelFormat::Full => ModelVertex::desc(),
+            ModelFormat::Packed => PackedModelVertex::desc(),
         }
     }
-}
 
-impl Vertex for ModelVertex {
-    fn desc() -> wgpu::VertexBufferLayout<'static> {
-        use std::mem;
-        wgpu::VertexBufferLayout {
-            array_stride: mem::size_of::<ModelVertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-                wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                    shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x2,
-                },
-                wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
-                    shader_location: 2,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-            ],
+    pub fn vertex_bytes(self, vertices: &[ModelVertex]) -> Vec<u8> {
+        match self {
+            ModelFormat::Full => bytemuck::cast_slice(vertices).to_vec(),
+            ModelFormat::Packed => {
+                let packed = vertices
+                    .iter()
+                    .map(|v| PackedModelVertex::from(*v))
+                    .collect::<Vec<_>>();
+                bytemuck::cast_slice(&packed).to_vec()
+            }
         }
     }
 }
+
+// 16 bit indices whenever every index fits, 32 bit otherwise
+pub fn create_index_buffer(
+    device: &wgpu::Device,
+    label: &str,
+    indices: &[u32],
+) -> (wgpu::Buffer, wgpu::IndexFormat) {
+    use wgpu::util::DeviceExt;
+    let (contents, format) = if indices.iter().all(|i| *i <= u16::MAX as u32) {
+        let narrow = indices.iter().map(|i| *i as u16).collect::<Vec<_>>();
+        (
+            bytemuck::cast_slice(&narrow).to_vec(),
+            wgpu::IndexFormat::Uint16,
+        )
+    } else {
+        (
+            bytemuck::cast_slice(indices).to_vec(),
+            wgpu::IndexFormat::Uint32,
+        )
+    };
+    let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(label),
+        contents: &contents,
+        usage: wgpu::BufferUsages::INDEX,
+    });
+    (buffer, format)
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Vertex)]
+pub struct BasicVertex {
+    #[vertex(location = 0)]
+    pub position: [f32; 3],
+    #[vertex(location = 1)]
+    pub tex_coords: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Vertex)]
+pub struct EffectVertex {
+    #[vertex(location = 0)]
+    pub position: [f32; 3],
+    #[vertex(location = 1)]
+    pub color: [f32; 3],
+}
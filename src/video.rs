@@ -0,0 +1,120 @@
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+
+// Records every presented frame into a video. Frames go out as a y4m stream
+// (raw YUV 4:4:4 with a small header), written straight to the file when it
+// ends in .y4m and otherwise piped through `ffmpeg` on the PATH, which picks
+// the encoder from the extension (.mp4, .webm, ...).
+//
+// While recording, the timer steps exactly one frame at `fps` per frame
+// instead of following the wall clock, so everything moves as it would at a
+// steady frame rate however long each frame takes to draw and encode.
+pub struct VideoRecorder {
+    pub path: std::path::PathBuf,
+    pub fps: u32,
+    out: Box<dyn Write>,
+    ffmpeg: Option<Child>,
+    // set by the first frame, later frames of another size are dropped
+    size: Option<(u32, u32)>,
+    pub frames: u32,
+}
+
+impl VideoRecorder {
+    pub fn start(path: impl Into<std::path::PathBuf>, fps: u32) -> anyhow::Result<Self> {
+        let path = path.into();
+        let (out, ffmpeg): (Box<dyn Write>, _) = if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("y4m"))
+        {
+            let file = std::fs::File::create(&path)?;
+            (Box::new(std::io::BufWriter::new(file)), None)
+        } else {
+            let mut child = Command::new("ffmpeg")
+                .args(["-y", "-loglevel", "error", "-f", "yuv4mpegpipe", "-i", "-"])
+                // most players want 4:2:0, which needs even sizes
+                .args([
+                    "-vf",
+                    "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+                    "-pix_fmt",
+                    "yuv420p",
+                ])
+                .arg(&path)
+                .stdin(Stdio::piped())
+                .spawn()
+                .map_err(|e| anyhow::anyhow!("couldn't run ffmpeg ({}), try a .y4m", e))?;
+            let stdin = child.stdin.take().unwrap();
+            (Box::new(std::io::BufWriter::new(stdin)), Some(child))
+        };
+        log::info!("recording {} at {} fps", path.display(), fps);
+        Ok(Self {
+            path,
+            fps,
+            out,
+            ffmpeg,
+            size: None,
+            frames: 0,
+        })
+    }
+
+    // how far the timer moves per frame while recording
+    pub fn step(&self) -> f64 {
+        1.0 / self.fps as f64
+    }
+
+    pub fn push(&mut self, frame: &image::RgbaImage) -> anyhow::Result<()> {
+        let (width, height) = frame.dimensions();
+        match self.size {
+            None => {
+                writeln!(
+                    self.out,
+                    "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+                    width, height, self.fps
+                )?;
+                self.size = Some((width, height));
+            }
+            Some(size) if size != (width, height) => {
+                log::warn!(
+                    "skipping a {}x{} frame, the video is {:?}",
+                    width,
+                    height,
+                    size
+                );
+                return Ok(());
+            }
+            Some(_) => {}
+        }
+
+        // BT.601 in studio range, what y4m players assume
+        let pixels = (width * height) as usize;
+        let mut planes = vec![0u8; pixels * 3];
+        for (i, p) in frame.pixels().enumerate() {
+            let [r, g, b] = [p[0], p[1], p[2]].map(|c| c as f32 / 255.0);
+            planes[i] = (16.0 + 65.481 * r + 128.553 * g + 24.966 * b) as u8;
+            planes[pixels + i] = (128.0 - 37.797 * r - 74.203 * g + 112.0 * b) as u8;
+            planes[2 * pixels + i] = (128.0 + 112.0 * r - 93.786 * g - 18.214 * b) as u8;
+        }
+        self.out.write_all(b"FRAME\n")?;
+        self.out.write_all(&planes)?;
+        self.frames += 1;
+        Ok(())
+    }
+}
+
+impl Drop for VideoRecorder {
+    // flushes the stream and waits for ffmpeg to finish the file
+    fn drop(&mut self) {
+        if let Err(e) = self.out.flush() {
+            log::error!("couldn't finish {}: {}", self.path.display(), e);
+        }
+        // closing its stdin is what tells ffmpeg the video is over
+        self.out = Box::new(std::io::sink());
+        if let Some(mut ffmpeg) = self.ffmpeg.take() {
+            match ffmpeg.wait() {
+                Ok(status) if !status.success() => log::error!("ffmpeg failed: {}", status),
+                Err(e) => log::error!("ffmpeg failed: {}", e),
+                Ok(_) => {}
+            }
+        }
+        log::info!("recorded {} frames to {}", self.frames, self.path.display());
+    }
+}
@@ -0,0 +1,157 @@
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, Sample, SizedSample};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+// samples per FFT, about 23 ms at 44.1 kHz
+const WINDOW: usize = 1024;
+// edges of the bands in Hz: bass, low mids, high mids, treble
+const BANDS: [(f32, f32); 4] = [
+    (20.0, 250.0),
+    (250.0, 1000.0),
+    (1000.0, 4000.0),
+    (4000.0, 16000.0),
+];
+// a band this loud is 0 and this loud is 1, in dB
+const FLOOR_DB: f32 = -70.0;
+const CEILING_DB: f32 = -10.0;
+// how fast a level falls back once the sound's gone, per second
+const RELEASE: f32 = 4.0;
+// bass this much over its recent average is a beat
+const BEAT: f32 = 1.4;
+const BEAT_GAP: f32 = 0.15;
+
+// Cubes and the background moving to whatever the default input hears,
+// microphone or line in (or the speakers, with a monitor source picked in
+// the mixer). The default input device's callback keeps the latest WINDOW
+// samples, mixed down to mono, each frame those go through an FFT, and the
+// result is four band levels, 0..1, and whether there was a beat.
+pub struct Visualizer {
+    // listening until it's dropped
+    _stream: cpal::Stream,
+    sample_rate: f32,
+    latest: Arc<Mutex<VecDeque<f32>>>,
+    pub levels: [f32; 4],
+    // the bass, slowly, to pick out beats against
+    average: f32,
+    since_beat: f32,
+}
+
+impl Visualizer {
+    pub fn start() -> anyhow::Result<Self> {
+        let device = cpal::default_host()
+            .default_input_device()
+            .ok_or_else(|| anyhow::anyhow!("there's no input device"))?;
+        let config = device.default_input_config()?;
+        let latest = Arc::new(Mutex::new(VecDeque::from(vec![0.0; WINDOW])));
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::F32 => listen::<f32>(&device, &config.config(), latest.clone())?,
+            cpal::SampleFormat::I16 => listen::<i16>(&device, &config.config(), latest.clone())?,
+            cpal::SampleFormat::U16 => listen::<u16>(&device, &config.config(), latest.clone())?,
+            format => anyhow::bail!("the input gives {} samples", format),
+        };
+        stream.play()?;
+        Ok(Self {
+            _stream: stream,
+            sample_rate: config.sample_rate().0 as f32,
+            latest,
+            levels: [0.0; 4],
+            average: 0.0,
+            since_beat: 0.0,
+        })
+    }
+
+    // the levels for what was heard just now, and whether it was a beat
+    pub fn update(&mut self, dt: f32) -> bool {
+        let mut re = [0.0f32; WINDOW];
+        let mut im = [0.0f32; WINDOW];
+        for (i, (x, sample)) in re
+            .iter_mut()
+            .zip(self.latest.lock().unwrap().iter())
+            .enumerate()
+        {
+            // a Hann window, so the edges of the window don't smear into everything
+            let hann = 0.5 - 0.5 * (std::f32::consts::TAU * i as f32 / WINDOW as f32).cos();
+            *x = sample * hann;
+        }
+        fft(&mut re, &mut im);
+
+        let bin = self.sample_rate / WINDOW as f32;
+        let fall = (-RELEASE * dt).exp();
+        for (level, (low, high)) in self.levels.iter_mut().zip(BANDS) {
+            let bins = (low / bin).ceil() as usize..=((high / bin) as usize).min(WINDOW / 2);
+            let count = bins.clone().count().max(1) as f32;
+            let magnitude = bins
+                .map(|k| (re[k] * re[k] + im[k] * im[k]).sqrt() * 4.0 / WINDOW as f32)
+                .sum::<f32>()
+                / count;
+            let db = 20.0 * magnitude.max(1e-9).log10();
+            let heard = ((db - FLOOR_DB) / (CEILING_DB - FLOOR_DB)).clamp(0.0, 1.0);
+            *level = heard.max(*level * fall);
+        }
+
+        let bass = self.levels[0];
+        self.since_beat += dt;
+        let beat = bass > self.average * BEAT && bass > 0.3 && self.since_beat > BEAT_GAP;
+        if beat {
+            self.since_beat = 0.0;
+        }
+        self.average += (bass - self.average) * (dt * 2.0).min(1.0);
+        beat
+    }
+}
+
+// each of the callback's frames, averaged over its channels, onto `latest`
+fn listen<T: SizedSample>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    latest: Arc<Mutex<VecDeque<f32>>>,
+) -> anyhow::Result<cpal::Stream>
+where
+    f32: FromSample<T>,
+{
+    let channels = config.channels.max(1) as usize;
+    Ok(device.build_input_stream(
+        config,
+        move |data: &[T], _| {
+            let mut latest = latest.lock().unwrap();
+            for frame in data.chunks_exact(channels) {
+                let sum = frame.iter().map(|s| f32::from_sample(*s)).sum::<f32>();
+                latest.pop_front();
+                latest.push_back(sum / channels as f32);
+            }
+        },
+        |e| log::warn!("visualizer input: {}", e),
+        None,
+    )?)
+}
+
+// in place, radix 2, `re` and `im` a power of two long
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if j > i {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut size = 2;
+    while size <= n {
+        let angle = -std::f32::consts::TAU / size as f32;
+        for start in (0..n).step_by(size) {
+            for k in 0..size / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + size / 2);
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        size *= 2;
+    }
+}
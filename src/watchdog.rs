@@ -0,0 +1,99 @@
+// frames have to run over budget for this long, in seconds, before anything's turned down
+const SUSTAINED: f64 = 3.0;
+// after a change, time for it to show in the frame times before judging again
+const SETTLE: f64 = 5.0;
+// one stall (a window drag, a breakpoint) only counts for this much
+const MAX_SAMPLE: f64 = 0.25;
+// 30 fps, `--frame-budget <ms>` sets another
+const DEFAULT_BUDGET: f64 = 1.0 / 30.0;
+// weather doesn't get thinner than this
+pub const MIN_DENSITY: f32 = 0.25;
+
+// Something the watchdog turned down. Putting them back is setting the
+// graphics options again, they still say what was wanted. There's no render
+// scale, shadows or MSAA to turn down yet, so it's only these.
+#[derive(Copy, Clone, Debug)]
+pub enum Downgrade {
+    Reflections,
+    Mirror,
+    MotionBlur,
+    Particles,
+    Impostors,
+}
+
+impl Downgrade {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Downgrade::Reflections => "dynamic reflections off",
+            Downgrade::Mirror => "mirror floor off",
+            Downgrade::MotionBlur => "motion blur off",
+            Downgrade::Particles => "half the weather particles",
+            Downgrade::Impostors => "impostors on",
+        }
+    }
+}
+
+// Watches frame times and says when they've been over budget for long
+// enough that something should give. Which setting goes is up to the
+// caller, the watchdog keeps the list of what it took.
+pub struct Watchdog {
+    pub enabled: bool,
+    // seconds a frame may take
+    pub budget: f64,
+    // seconds over budget lately, frames under budget pay it back
+    over: f64,
+    settle: f64,
+    pub taken: Vec<Downgrade>,
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            budget: DEFAULT_BUDGET,
+            over: 0.0,
+            settle: SETTLE,
+            taken: Vec::new(),
+        }
+    }
+}
+
+impl Watchdog {
+    // after each frame, true when it's time to turn something down
+    pub fn frame(&mut self, frame_time: f64) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let frame_time = frame_time.min(MAX_SAMPLE);
+        if self.settle > 0.0 {
+            self.settle -= frame_time;
+            return false;
+        }
+        if frame_time > self.budget {
+            self.over += frame_time;
+        } else {
+            self.over = (self.over - frame_time).max(0.0);
+        }
+        if self.over < SUSTAINED {
+            return false;
+        }
+        self.reset();
+        true
+    }
+
+    // starts judging over, after a change or while things are being rebuilt
+    pub fn reset(&mut self) {
+        self.over = 0.0;
+        self.settle = SETTLE;
+    }
+
+    pub fn took(&mut self, downgrade: Downgrade) {
+        log::warn!(
+            "frames over {:.1} ms for {}s, {}",
+            self.budget * 1000.0,
+            SUSTAINED,
+            downgrade.describe()
+        );
+        self.taken.push(downgrade);
+    }
+}
@@ -0,0 +1,276 @@
+use crate::camera::{Camera, CameraUniform};
+use crate::math::{Mat4, Vec3};
+use crate::noise::Noise;
+use crate::texture::Texture;
+use wgpu::util::DeviceExt;
+
+const SIZE: f32 = 60.0;
+const SUBDIVISIONS: u32 = 128;
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct WaterUniform {
+    height: f32,
+    _padding: [f32; 3],
+}
+
+// A wavy plane under the cubes. What's above the water is drawn once more
+// into `reflection` with the camera mirrored through the plane, and the
+// water shader mixes that with the skybox by fresnel.
+pub struct Water {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    index_format: wgpu::IndexFormat,
+    num_indices: u32,
+    pub reflection: Texture,
+    reflection_camera_buffer: wgpu::Buffer,
+    reflection_camera_bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+    height: f32,
+}
+
+impl Water {
+    pub fn new(
+        device: &wgpu::Device,
+        layouts: [&wgpu::BindGroupLayout; 3],
+        noise: &Noise,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let [camera_bind_group_layout, environment_bind_group_layout, timer_bind_group_layout] =
+            layouts;
+        let water_height = -3.0;
+
+        let step = SIZE / SUBDIVISIONS as f32;
+        let vertices = (0..=SUBDIVISIONS)
+            .flat_map(|z| {
+                (0..=SUBDIVISIONS)
+                    .map(move |x| [x as f32 * step - SIZE / 2.0, z as f32 * step - SIZE / 2.0])
+            })
+            .collect::<Vec<[f32; 2]>>();
+        let row = SUBDIVISIONS + 1;
+        let indices = (0..SUBDIVISIONS)
+            .flat_map(|z| {
+                (0..SUBDIVISIONS).flat_map(move |x| {
+                    let i = z * row + x;
+                    [i, i + row, i + 1, i + 1, i + row, i + row + 1]
+                })
+            })
+            .collect::<Vec<u32>>();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("water vertex buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let (index_buffer, index_format) =
+            crate::vertex::create_index_buffer(device, "water index buffer", &indices);
+
+        let reflection =
+            Texture::create_render_target(device, width, height, format, "water reflection");
+        let reflection_camera_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("water reflection camera buffer"),
+                contents: bytemuck::cast_slice(&[CameraUniform::new()]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+        let reflection_camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: reflection_camera_buffer.as_entire_binding(),
+            }],
+            label: Some("water reflection camera bind group"),
+        });
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("water uniform buffer"),
+            contents: bytemuck::cast_slice(&[WaterUniform {
+                height: water_height,
+                _padding: [0.0; 3],
+            }]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let bind_group_layout = crate::reflect::bind_group_layout(
+            device,
+            "water bind group layout",
+            &[("water.wgsl", 1)],
+        )
+        .unwrap();
+        let bind_group =
+            Self::create_bind_group(device, &bind_group_layout, &reflection, &uniform_buffer);
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("water shader"),
+            source: wgpu::ShaderSource::Wgsl(noise.shader("water.wgsl").unwrap().into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("water pipeline layout"),
+            bind_group_layouts: &[
+                camera_bind_group_layout,
+                &bind_group_layout,
+                environment_bind_group_layout,
+                timer_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("water render pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &[wgpu::VertexAttribute {
+                        offset: 0,
+                        shader_location: 0,
+                        format: wgpu::VertexFormat::Float32x2,
+                    }],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(Texture::depth_state(true, wgpu::CompareFunction::Less)),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: crate::pipeline_cache::current().as_deref(),
+        });
+
+        Self {
+            vertex_buffer,
+            index_buffer,
+            index_format,
+            num_indices: indices.len() as u32,
+            reflection,
+            reflection_camera_buffer,
+            reflection_camera_bind_group,
+            uniform_buffer,
+            bind_group_layout,
+            bind_group,
+            pipeline,
+            height: water_height,
+        }
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        reflection: &Texture,
+        uniform_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&reflection.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&reflection.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("water bind group"),
+        })
+    }
+
+    // the reflection is looked up by screen position, so it's the frame's size
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        let format = self.reflection.texture.format();
+        self.reflection =
+            Texture::create_render_target(device, width, height, format, "water reflection");
+        self.bind_group = Self::create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.reflection,
+            &self.uniform_buffer,
+        );
+    }
+
+    // Draws the reflected scene. The mirror flips winding, so `draw` should use
+    // a clockwise pipeline. Cleared to transparent so the water falls back to
+    // the skybox wherever nothing was drawn.
+    pub fn render_reflection(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        queue: &wgpu::Queue,
+        camera: &Camera,
+        mut draw: impl FnMut(&mut wgpu::RenderPass, &wgpu::BindGroup),
+    ) {
+        let mirror = Mat4::from_translation(Vec3::new(0.0, 2.0 * self.height, 0.0))
+            * Mat4::from_scale(Vec3::new(1.0, -1.0, 1.0));
+        let mut eye = camera.eye;
+        eye.y = 2.0 * self.height - eye.y;
+        let mut uniform = CameraUniform::new();
+        uniform.set(eye, camera.build_view_projection_matrix() * mirror);
+        queue.write_buffer(
+            &self.reflection_camera_buffer,
+            0,
+            bytemuck::cast_slice(&[uniform]),
+        );
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("water reflection pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.reflection.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        draw(&mut render_pass, &self.reflection_camera_bind_group);
+    }
+
+    pub fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        camera_bind_group: &wgpu::BindGroup,
+        environment_bind_group: &wgpu::BindGroup,
+        timer_bind_group: &wgpu::BindGroup,
+    ) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.set_bind_group(2, environment_bind_group, &[]);
+        render_pass.set_bind_group(3, timer_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), self.index_format);
+        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+    }
+}
@@ -0,0 +1,83 @@
+#define CAMERA_GROUP 0
+#define TIMER_GROUP 3
+#include "common.wgsl"
+#include "noise.wgsl"
+
+struct Water {
+    height: f32,
+}
+@group(1) @binding(0)
+var t_reflection: texture_2d<f32>;
+@group(1) @binding(1)
+var s_reflection: sampler;
+@group(1) @binding(2)
+var<uniform> water: Water;
+
+@group(2) @binding(0)
+var t_environment: texture_cube<f32>;
+@group(2) @binding(1)
+var s_environment: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+}
+
+// direction.xy, steepness, wavelength
+const WAVES: array<vec4<f32>, 3> = array<vec4<f32>, 3>(
+    vec4<f32>(1.0, 0.0, 0.25, 12.0),
+    vec4<f32>(0.0, 1.0, 0.2, 7.0),
+    vec4<f32>(1.0, 1.0, 0.15, 3.5),
+);
+
+@vertex
+fn vs_main(@location(0) position: vec2<f32>) -> VertexOutput {
+    var world = vec3<f32>(position.x, water.height, position.y);
+    var tangent = vec3<f32>(1.0, 0.0, 0.0);
+    var binormal = vec3<f32>(0.0, 0.0, 1.0);
+    // Gerstner waves, the points move in circles so the crests get sharp
+    var waves = WAVES;
+    for (var i = 0; i < 3; i++) {
+        let wave = waves[i];
+        let d = normalize(wave.xy);
+        let k = 6.2831853 / wave.w;
+        let speed = sqrt(9.8 / k);
+        let f = k * (dot(d, position) - speed * timer.t);
+        let a = wave.z / k;
+        let s = wave.z * sin(f);
+        let c = wave.z * cos(f);
+        world += vec3<f32>(d.x * a * cos(f), a * sin(f), d.y * a * cos(f));
+        tangent += vec3<f32>(-d.x * d.x * s, d.x * c, -d.x * d.y * s);
+        binormal += vec3<f32>(-d.x * d.y * s, d.y * c, -d.y * d.y * s);
+    }
+
+    // small slow chop on top so the swell doesn't look so regular
+    world.y += noise_fbm(position * 0.2 + vec2<f32>(timer.t * 0.15), 3u) * 0.3;
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(world, 1.0);
+    out.world_position = world;
+    out.normal = normalize(cross(binormal, tangent));
+    return out;
+}
+
+const DEEP_COLOR: vec3<f32> = vec3<f32>(0.02, 0.12, 0.2);
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let normal = normalize(in.normal);
+    let view_dir = normalize(in.world_position - camera.view_pos.xyz);
+
+    // the reflection target was drawn with a mirrored camera, so it lines up
+    // with the screen; nudging by the normal makes it ripple
+    let screen_uv = in.clip_position.xy / vec2<f32>(textureDimensions(t_reflection));
+    let planar = textureSample(t_reflection, s_reflection, screen_uv + normal.xz * 0.03);
+    let sky = textureSample(t_environment, s_environment, reflect(view_dir, normal));
+    let reflection = mix(sky.rgb, planar.rgb, planar.a);
+
+    // Schlick with water's F0
+    let cos_theta = saturate(dot(-view_dir, normal));
+    let fresnel = 0.02 + 0.98 * pow(1.0 - cos_theta, 5.0);
+    return vec4<f32>(mix(DEEP_COLOR, reflection, fresnel), 1.0);
+}
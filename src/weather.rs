@@ -0,0 +1,370 @@
+use wgpu::util::DeviceExt;
+
+// room for the most drops a config can ask for
+const MAX_DROPS: u32 = 16384;
+// matches SPLASHES in weather.wgsl
+const SPLASHES: u32 = 1024;
+// how long a rain streak is, in seconds of its fall
+const STREAK: f32 = 0.04;
+// a dropped frame shouldn't throw every drop through the floor
+const MAX_DT: f32 = 0.1;
+// seconds of rain to soak the ground, and of anything else to dry it
+const SOAK_SECONDS: f32 = 20.0;
+const DRY_SECONDS: f32 = 45.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Rain,
+    Snow,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct Precipitation {
+    pub count: u32,
+    pub speed: f32,
+    pub size: f32,
+}
+
+// what res/weather.ron holds
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct WeatherConfig {
+    pub wind: (f32, f32, f32),
+    pub radius: f32,
+    pub height: f32,
+    pub rain: Precipitation,
+    pub snow: Precipitation,
+}
+
+impl WeatherConfig {
+    pub fn parse(ron: &str) -> anyhow::Result<Self> {
+        let config: WeatherConfig = ron::from_str(ron)?;
+        for (name, p) in [("rain", &config.rain), ("snow", &config.snow)] {
+            if p.count > MAX_DROPS {
+                anyhow::bail!("{} asks for {} drops, at most {}", name, p.count, MAX_DROPS);
+            }
+            if p.speed <= 0.0 || p.size <= 0.0 {
+                anyhow::bail!("{} needs a positive speed and size", name);
+            }
+        }
+        if config.radius <= 0.0 || config.height <= 0.0 {
+            anyhow::bail!("the weather box needs a positive radius and height");
+        }
+        Ok(config)
+    }
+
+    fn precipitation(&self, kind: Kind) -> &Precipitation {
+        match kind {
+            Kind::Rain => &self.rain,
+            Kind::Snow => &self.snow,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct Particle {
+    position: [f32; 4],
+    velocity: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SimUniform {
+    // w is the time step
+    eye: [f32; 4],
+    // w is how fast drops fall
+    wind: [f32; 4],
+    // radius, height, floor y, time
+    volume: [f32; 4],
+    // drop size, streak length
+    drop: [f32; 4],
+    // drops, kind, frame
+    counts: [u32; 4],
+}
+
+// Rain or snow falling through a box that follows the camera, simulated on
+// the GPU like the cloth. Drops that hit the floor hand a few splash
+// particles to a second buffer, which the same pass moves on. There's no
+// depth buffer, so everything is drawn over the scene in front of it.
+pub struct Weather {
+    pub config: WeatherConfig,
+    pub kind: Option<Kind>,
+    // how wet the ground is, 0 to 1, creeping up while it rains and back
+    // down afterwards
+    pub wetness: f32,
+    // how much of the configured count falls, turned down when frames run long
+    pub density: f32,
+    drops: wgpu::Buffer,
+    splashes: wgpu::Buffer,
+    sim_buffer: wgpu::Buffer,
+    compute_bind_group: wgpu::BindGroup,
+    drops_pipeline: wgpu::ComputePipeline,
+    splashes_pipeline: wgpu::ComputePipeline,
+
+    draw_bind_group: wgpu::BindGroup,
+    drop_draw_pipeline: wgpu::RenderPipeline,
+    splash_draw_pipeline: wgpu::RenderPipeline,
+    // session clock at the last step
+    last: Option<f32>,
+    frame: u32,
+}
+
+impl Weather {
+    pub fn new(
+        device: &wgpu::Device,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        noise: &crate::noise::Noise,
+    ) -> anyhow::Result<Self> {
+        let config = WeatherConfig::parse(include_str!("../res/weather.ron"))?;
+        let particle_buffer = |label, count: u32| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: count as u64 * std::mem::size_of::<Particle>() as u64,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let drops = particle_buffer("weather drop buffer", MAX_DROPS);
+        let splashes = particle_buffer("weather splash buffer", SPLASHES);
+        let next_splash = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("weather next splash buffer"),
+            contents: bytemuck::cast_slice(&[0u32]),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let sim_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("weather sim buffer"),
+            size: std::mem::size_of::<SimUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        ///// simulation
+        let compute_bind_group_layout = crate::reflect::bind_group_layout(
+            device,
+            "weather compute bind group layout",
+            &[("weather.wgsl", 0)],
+        )
+        .unwrap();
+        let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &compute_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: sim_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: drops.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: splashes.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: next_splash.as_entire_binding(),
+                },
+            ],
+            label: Some("weather compute bind group"),
+        });
+        let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("weather compute shader"),
+            source: wgpu::ShaderSource::Wgsl(noise.shader("weather.wgsl")?.into()),
+        });
+        let compute_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("weather compute pipeline layout"),
+                bind_group_layouts: &[&compute_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let compute_pipeline = |label, entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&compute_pipeline_layout),
+                module: &compute_shader,
+                entry_point,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: crate::pipeline_cache::current().as_deref(),
+            })
+        };
+        let drops_pipeline = compute_pipeline("weather drops pipeline", "update_drops");
+        let splashes_pipeline = compute_pipeline("weather splashes pipeline", "update_splashes");
+
+        ///// drawing
+        let draw_bind_group_layout = crate::reflect::bind_group_layout(
+            device,
+            "weather draw bind group layout",
+            &[("weather_draw.wgsl", 1)],
+        )
+        .unwrap();
+        let draw_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &draw_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: sim_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: drops.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: splashes.as_entire_binding(),
+                },
+            ],
+            label: Some("weather draw bind group"),
+        });
+        let draw_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("weather draw shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                crate::shaders::load("weather_draw.wgsl", &[])
+                    .unwrap()
+                    .into(),
+            ),
+        });
+        let draw_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("weather draw pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &draw_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let draw_pipeline = |label, entry_point| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&draw_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &draw_shader,
+                    entry_point,
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &draw_shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                // blended, so hidden behind things but not hiding them
+                depth_stencil: Some(crate::texture::Texture::depth_state(
+                    false,
+                    wgpu::CompareFunction::Less,
+                )),
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+                cache: crate::pipeline_cache::current().as_deref(),
+            })
+        };
+        let drop_draw_pipeline = draw_pipeline("weather drop pipeline", "vs_drop");
+        let splash_draw_pipeline = draw_pipeline("weather splash pipeline", "vs_splash");
+
+        Ok(Self {
+            config,
+            kind: None,
+            wetness: 0.0,
+            drops,
+            splashes,
+            sim_buffer,
+            compute_bind_group,
+            drops_pipeline,
+            splashes_pipeline,
+            draw_bind_group,
+            drop_draw_pipeline,
+            splash_draw_pipeline,
+            density: 1.0,
+            last: None,
+            frame: 0,
+        })
+    }
+
+    // starts rain or snow over from nothing, or stops it
+    pub fn set(&mut self, queue: &wgpu::Queue, kind: Option<Kind>) {
+        let zeros = |count: u32| vec![0u8; count as usize * std::mem::size_of::<Particle>()];
+        queue.write_buffer(&self.drops, 0, &zeros(MAX_DROPS));
+        queue.write_buffer(&self.splashes, 0, &zeros(SPLASHES));
+        self.kind = kind;
+    }
+
+    fn count(&self, kind: Kind) -> u32 {
+        (self.config.precipitation(kind).count as f32 * self.density.clamp(0.0, 1.0)) as u32
+    }
+
+    // moves everything on to `now` on the session clock, around the eye
+    pub fn step(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        queue: &wgpu::Queue,
+        eye: crate::math::Point3,
+        now: f32,
+    ) {
+        let dt = self
+            .last
+            .map_or(0.0, |last| (now - last).clamp(0.0, MAX_DT));
+        self.last = Some(now);
+        self.wetness = if self.kind == Some(Kind::Rain) {
+            (self.wetness + dt / SOAK_SECONDS).min(1.0)
+        } else {
+            (self.wetness - dt / DRY_SECONDS).max(0.0)
+        };
+        let Some(kind) = self.kind else {
+            return;
+        };
+        self.frame = self.frame.wrapping_add(1);
+
+        let p = self.config.precipitation(kind);
+        let (x, y, z) = self.config.wind;
+        let sim = SimUniform {
+            eye: [eye.x, eye.y, eye.z, dt],
+            wind: [x, y, z, p.speed],
+            volume: [
+                self.config.radius,
+                self.config.height,
+                crate::floor::FLOOR_Y,
+                now,
+            ],
+            drop: [p.size, STREAK, 0.0, 0.0],
+            counts: [self.count(kind), kind as u32, self.frame, 0],
+        };
+        queue.write_buffer(&self.sim_buffer, 0, bytemuck::cast_slice(&[sim]));
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("weather pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_bind_group(0, &self.compute_bind_group, &[]);
+        compute_pass.set_pipeline(&self.drops_pipeline);
+        compute_pass.dispatch_workgroups(self.count(kind).div_ceil(64), 1, 1);
+        // splashes from this step already move a little
+        compute_pass.set_pipeline(&self.splashes_pipeline);
+        compute_pass.dispatch_workgroups(SPLASHES.div_ceil(64), 1, 1);
+    }
+
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass, camera_bind_group: &wgpu::BindGroup) {
+        let Some(kind) = self.kind else {
+            return;
+        };
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.draw_bind_group, &[]);
+        render_pass.set_pipeline(&self.splash_draw_pipeline);
+        render_pass.draw(0..6, 0..SPLASHES);
+        render_pass.set_pipeline(&self.drop_draw_pipeline);
+        render_pass.draw(0..6, 0..self.count(kind));
+    }
+}
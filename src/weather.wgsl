@@ -0,0 +1,143 @@
+// Rain and snow, one invocation per drop. Drops fall through a box around
+// the eye and wrap around it sideways, so the box follows the camera without
+// anything respawning. A drop that reaches the floor leaves splashes behind
+// and starts again at the top. Gusts push them about through noise.wgsl, a
+// field drops near each other share so they move together.
+
+#include "noise.wgsl"
+
+struct Particle {
+    // w is 0 until the drop has been placed, for splashes it's the age
+    position: vec4<f32>,
+    // w is how long a splash lasts, 0 once it's gone
+    velocity: vec4<f32>,
+}
+
+struct Sim {
+    // w is the time step
+    eye: vec4<f32>,
+    // w is how fast drops fall
+    wind: vec4<f32>,
+    // radius, height, floor y, time
+    volume: vec4<f32>,
+    // drop size, streak length, unused
+    drop: vec4<f32>,
+    // drops, kind (0 rain, 1 snow), frame, unused
+    counts: vec4<u32>,
+}
+@group(0) @binding(0)
+var<uniform> sim: Sim;
+@group(0) @binding(1)
+var<storage, read_write> drops: array<Particle>;
+@group(0) @binding(2)
+var<storage, read_write> splashes: array<Particle>;
+// the next splash slot, the ring wraps around on its own
+@group(0) @binding(3)
+var<storage, read_write> next_splash: atomic<u32>;
+
+const SPLASHES: u32 = 1024u;
+const GRAVITY: f32 = 9.8;
+const RAIN_SPLASH_LIFE: f32 = 0.35;
+const SNOW_SETTLE_LIFE: f32 = 2.0;
+// how hard the gusts blow rain and snow about, snow's lighter
+const RAIN_TURBULENCE: f32 = 0.8;
+const SNOW_TURBULENCE: f32 = 1.5;
+
+// pcg hash of the drop and whatever it's being asked for
+fn random(i: u32, n: u32) -> f32 {
+    var h = i * 747796405u + n * 2891336453u + sim.counts.z * 1664525u;
+    h = ((h >> ((h >> 28u) + 4u)) ^ h) * 277803737u;
+    h = (h >> 22u) ^ h;
+    return f32(h) / 4294967295.0;
+}
+
+fn bottom() -> f32 {
+    return max(sim.volume.z, sim.eye.y - sim.volume.y * 0.5);
+}
+
+// somewhere in the footprint of the box at height `y`
+fn place(i: u32, y: f32) -> vec3<f32> {
+    let offset = vec2<f32>(random(i, 0u), random(i, 1u)) * 2.0 - 1.0;
+    let xz = sim.eye.xz + offset * sim.volume.x;
+    return vec3<f32>(xz.x, y, xz.y);
+}
+
+fn splash(i: u32, position: vec3<f32>) {
+    let ground = vec3<f32>(position.x, sim.volume.z, position.z);
+    if sim.counts.y == 1u {
+        // flakes settle and melt
+        let slot = atomicAdd(&next_splash, 1u) % SPLASHES;
+        splashes[slot] = Particle(vec4<f32>(ground, 0.0), vec4<f32>(0.0, 0.0, 0.0, SNOW_SETTLE_LIFE));
+        return;
+    }
+    let first = atomicAdd(&next_splash, 3u);
+    for (var k = 0u; k < 3u; k++) {
+        let side = vec2<f32>(random(i, 4u + k), random(i, 8u + k)) * 2.0 - 1.0;
+        let velocity = vec3<f32>(side.x, 1.2 + random(i, 12u + k), side.y) * 0.8;
+        splashes[(first + k) % SPLASHES] = Particle(vec4<f32>(ground, 0.0), vec4<f32>(velocity, RAIN_SPLASH_LIFE));
+    }
+}
+
+@compute @workgroup_size(64)
+fn update_drops(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= sim.counts.x {
+        return;
+    }
+    let dt = sim.eye.w;
+    let top = bottom() + sim.volume.y;
+    var position = drops[i].position.xyz;
+    if drops[i].position.w == 0.0 {
+        // first frame, spread through the whole box rather than all at the top
+        position = place(i, mix(bottom(), top, random(i, 2u)));
+    }
+
+    var velocity = sim.wind.xyz - vec3<f32>(0.0, sim.wind.w, 0.0);
+    let t = sim.volume.w;
+    let at = position.xz * 0.08 + vec2<f32>(t * 0.4, t * 0.25);
+    let gust = vec2<f32>(noise_fbm(at, 3u), noise_fbm(at + vec2<f32>(31.7, 17.3), 3u));
+    let snow = sim.counts.y == 1u;
+    velocity += vec3<f32>(gust.x, 0.0, gust.y) * select(RAIN_TURBULENCE, SNOW_TURBULENCE, snow);
+    if snow {
+        // flakes sway about as they fall as well, each one out of step with the rest
+        let sway = noise_simplex(vec2<f32>(t * 1.2, f32(i) * 0.618));
+        velocity += vec3<f32>(sway, 0.0, noise_simplex(vec2<f32>(f32(i) * 0.618, t * 1.1))) * 0.4;
+    }
+    position += velocity * dt;
+
+    let offset = position.xz - sim.eye.xz;
+    let width = sim.volume.x * 2.0;
+    let wrapped = sim.eye.xz + offset - width * round(offset / width);
+    position = vec3<f32>(wrapped.x, position.y, wrapped.y);
+
+    if position.y < sim.volume.z {
+        splash(i, position);
+        position = place(i, top);
+    } else if position.y < bottom() || position.y > top {
+        // the camera moved up or down past it
+        position = place(i, select(top, bottom(), position.y > top));
+    }
+    drops[i] = Particle(vec4<f32>(position, 1.0), vec4<f32>(velocity, 0.0));
+}
+
+@compute @workgroup_size(64)
+fn update_splashes(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= SPLASHES || splashes[i].velocity.w == 0.0 {
+        return;
+    }
+    let dt = sim.eye.w;
+    var s = splashes[i];
+    s.position.w += dt;
+    if s.position.w > s.velocity.w {
+        splashes[i].velocity.w = 0.0;
+        return;
+    }
+    s.velocity.y -= GRAVITY * dt;
+    s.position = vec4<f32>(s.position.xyz + s.velocity.xyz * dt, s.position.w);
+    if s.position.y < sim.volume.z {
+        s.position.y = sim.volume.z;
+        s.velocity = vec4<f32>(0.0, 0.0, 0.0, s.velocity.w);
+    }
+    splashes[i] = s;
+}
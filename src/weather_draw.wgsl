@@ -0,0 +1,130 @@
+#define CAMERA_GROUP 0
+#include "common.wgsl"
+
+// Draws the drops weather.wgsl moves, rain as streaks along their velocity
+// and snow as round flakes, plus the splashes they leave on the floor.
+
+struct Particle {
+    position: vec4<f32>,
+    velocity: vec4<f32>,
+}
+
+struct Sim {
+    eye: vec4<f32>,
+    wind: vec4<f32>,
+    volume: vec4<f32>,
+    drop: vec4<f32>,
+    counts: vec4<u32>,
+}
+@group(1) @binding(0)
+var<uniform> sim: Sim;
+@group(1) @binding(1)
+var<storage, read> drops: array<Particle>;
+@group(1) @binding(2)
+var<storage, read> splashes: array<Particle>;
+
+const SPLASH_SIZE: f32 = 0.02;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // -1 to 1 across the quad
+    @location(0) uv: vec2<f32>,
+    @location(1) alpha: f32,
+    // 0 rain streak, 1 snow flake, 2 splash
+    @location(2) @interpolate(flat) kind: u32,
+}
+
+fn corner(index: u32) -> vec2<f32> {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, 1.0), vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, 1.0),
+        vec2<f32>(1.0, 1.0), vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, -1.0),
+    );
+    return corners[index];
+}
+
+// a quad through `position` along `axis`, turned about it to face the eye
+fn quad(position: vec3<f32>, axis: vec3<f32>, uv: vec2<f32>, width: f32, half_length: f32) -> vec3<f32> {
+    let to_eye = normalize(camera.view_pos.xyz - position);
+    var side = cross(axis, to_eye);
+    if length(side) < 0.001 {
+        side = vec3<f32>(1.0, 0.0, 0.0);
+    }
+    side = normalize(side);
+    return position + side * uv.x * width + axis * uv.y * half_length;
+}
+
+fn billboard(position: vec3<f32>, uv: vec2<f32>, size: f32) -> vec3<f32> {
+    let to_eye = normalize(camera.view_pos.xyz - position);
+    var right = cross(vec3<f32>(0.0, 1.0, 0.0), to_eye);
+    if length(right) < 0.001 {
+        right = vec3<f32>(1.0, 0.0, 0.0);
+    }
+    right = normalize(right);
+    let up = cross(to_eye, right);
+    return position + (right * uv.x + up * uv.y) * size;
+}
+
+@vertex
+fn vs_drop(@builtin(vertex_index) index: u32, @builtin(instance_index) instance: u32) -> VertexOutput {
+    let drop = drops[instance];
+    let uv = corner(index);
+    var out: VertexOutput;
+    out.uv = uv;
+    out.kind = sim.counts.y;
+    out.alpha = 1.0;
+    var world: vec3<f32>;
+    if sim.counts.y == 0u {
+        // from the drop back along where it came from
+        let axis = -normalize(drop.velocity.xyz);
+        let streak = sim.drop.y * length(drop.velocity.xyz);
+        world = quad(drop.position.xyz + axis * streak * 0.5, axis, uv, sim.drop.x, streak * 0.5);
+    } else {
+        world = billboard(drop.position.xyz, uv, sim.drop.x);
+    }
+    // nothing that hasn't been placed yet
+    if drop.position.w == 0.0 {
+        out.alpha = 0.0;
+    }
+    out.clip_position = camera.view_proj * vec4<f32>(world, 1.0);
+    return out;
+}
+
+@vertex
+fn vs_splash(@builtin(vertex_index) index: u32, @builtin(instance_index) instance: u32) -> VertexOutput {
+    let splash = splashes[instance];
+    let uv = corner(index);
+    var out: VertexOutput;
+    out.uv = uv;
+    out.kind = 2u;
+    if splash.velocity.w == 0.0 {
+        // gone, collapsed to nothing
+        out.clip_position = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+        out.alpha = 0.0;
+        return out;
+    }
+    out.alpha = 1.0 - splash.position.w / splash.velocity.w;
+    // settled snow is flake sized
+    let size = select(SPLASH_SIZE, sim.drop.x, sim.counts.y == 1u);
+    let world = billboard(splash.position.xyz, uv, size);
+    out.clip_position = camera.view_proj * vec4<f32>(world, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var alpha = in.alpha;
+    var color = vec3<f32>(0.7, 0.75, 0.82);
+    if in.kind == 0u {
+        // thin at the edges, fading towards the tail
+        alpha *= (1.0 - abs(in.uv.x)) * (0.5 - 0.5 * in.uv.y) * 0.6;
+    } else {
+        alpha *= smoothstep(1.0, 0.4, length(in.uv));
+        if sim.counts.y == 1u {
+            color = vec3<f32>(0.95, 0.97, 1.0);
+        }
+    }
+    if alpha <= 0.0 {
+        discard;
+    }
+    return vec4<f32>(color, alpha);
+}
@@ -0,0 +1,154 @@
+// `#[derive(Vertex)]` for praxis' vertex and instance structs. Every field
+// needs a `#[vertex(location = N)]`, the format comes from the field's type
+// unless given with `format = Name` (one of wgpu::VertexFormat's variants).
+// Offsets come from `offset_of!`, so fields can be reordered or changed
+// without touching the layout. `#[vertex(instance)]` on the struct makes it
+// step per instance. Matrices take one location per column.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Ident, LitInt, Type};
+
+#[proc_macro_derive(Vertex, attributes(vertex))]
+pub fn derive_vertex(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let mut instance = false;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("vertex")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("instance") {
+                instance = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `instance`"))
+            }
+        })?;
+    }
+
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            input,
+            "Vertex can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new_spanned(input, "Vertex needs named fields"));
+    };
+
+    let mut attributes = Vec::new();
+    for field in &fields.named {
+        let field_name = field.ident.as_ref().unwrap();
+        let mut location = None;
+        let mut format = None;
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("vertex")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("location") {
+                    location = Some(meta.value()?.parse::<LitInt>()?.base10_parse::<u32>()?);
+                    Ok(())
+                } else if meta.path.is_ident("format") {
+                    format = Some(meta.value()?.parse::<Ident>()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `location` or `format`"))
+                }
+            })?;
+        }
+        let Some(location) = location else {
+            return Err(Error::new_spanned(field, "missing #[vertex(location = N)]"));
+        };
+
+        // matrices are split into one attribute per column
+        let (format, columns) = match format {
+            Some(format) => (format, 1),
+            None => format_for(&field.ty).ok_or_else(|| {
+                Error::new_spanned(
+                    &field.ty,
+                    "can't tell the vertex format of this type, add `format = ...`",
+                )
+            })?,
+        };
+        for column in 0..columns {
+            let shader_location = location + column;
+            attributes.push(quote! {
+                wgpu::VertexAttribute {
+                    offset: (std::mem::offset_of!(#name, #field_name)
+                        + #column as usize * wgpu::VertexFormat::#format.size() as usize)
+                        as wgpu::BufferAddress,
+                    shader_location: #shader_location,
+                    format: wgpu::VertexFormat::#format,
+                }
+            });
+        }
+    }
+
+    let step_mode = if instance {
+        quote!(wgpu::VertexStepMode::Instance)
+    } else {
+        quote!(wgpu::VertexStepMode::Vertex)
+    };
+    Ok(quote! {
+        impl crate::vertex::Vertex for #name {
+            fn desc() -> wgpu::VertexBufferLayout<'static> {
+                const ATTRIBUTES: &[wgpu::VertexAttribute] = &[#(#attributes),*];
+                wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<#name>() as wgpu::BufferAddress,
+                    step_mode: #step_mode,
+                    attributes: ATTRIBUTES,
+                }
+            }
+        }
+    })
+}
+
+// (format, how many locations it takes) for the plain types with an obvious format
+fn format_for(ty: &Type) -> Option<(Ident, u32)> {
+    let scalar = |ty: &Type| match ty {
+        Type::Path(path) => path
+            .path
+            .get_ident()
+            .and_then(|i| match i.to_string().as_str() {
+                "f32" => Some("Float32"),
+                "u32" => Some("Uint32"),
+                "i32" => Some("Sint32"),
+                _ => None,
+            }),
+        _ => None,
+    };
+    let length = |len: &syn::Expr| match len {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(n),
+            ..
+        }) => n.base10_parse::<u32>().ok(),
+        _ => None,
+    };
+    let ident = |name: String| format_ident!("{}", name, span = Span::call_site());
+
+    if let Some(scalar) = scalar(ty) {
+        return Some((ident(scalar.to_string()), 1));
+    }
+    let Type::Array(array) = ty else {
+        return None;
+    };
+    let n = length(&array.len)?;
+    if let Some(scalar) = scalar(&array.elem) {
+        return (2..=4)
+            .contains(&n)
+            .then(|| (ident(format!("{}x{}", scalar, n)), 1));
+    }
+    // [[f32; rows]; columns]
+    let Type::Array(column) = &*array.elem else {
+        return None;
+    };
+    let rows = length(&column.len)?;
+    let scalar = scalar(&column.elem)?;
+    ((2..=4).contains(&rows) && (2..=4).contains(&n))
+        .then(|| (ident(format!("{}x{}", scalar, rows)), n))
+}